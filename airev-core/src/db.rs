@@ -3,7 +3,64 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use rusqlite::OptionalExtension;
 use tokio_rusqlite::Connection;
 
-use crate::types::Session;
+use crate::types::{Comment, Session};
+
+/// Most recently used ranges kept per repository by [`push_recent_diff_args`].
+pub const RECENT_DIFF_ARGS_MAX: usize = 10;
+
+/// Failure from [`open_db`], tagged with the step that failed.
+///
+/// Each variant wraps the underlying error (`rusqlite::Error` from opening the
+/// file, `tokio_rusqlite::Error` from the later steps); [`DbError::hint`]
+/// suggests what the user can do about it.
+#[derive(Debug)]
+pub enum DbError {
+    /// The database file could not be opened or created.
+    Open(rusqlite::Error),
+    /// Setting the WAL pragmas or busy timeout failed.
+    WalConfig(tokio_rusqlite::Error),
+    /// Checkpointing the WAL left by a previous run failed.
+    Checkpoint(tokio_rusqlite::Error),
+    /// Applying schema migrations failed.
+    Migrate(tokio_rusqlite::Error),
+}
+
+impl DbError {
+    /// Returns a one-line suggestion for resolving the failure.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            DbError::Open(_) => "Check that the directory is writable and the disk is not full.",
+            DbError::WalConfig(_) | DbError::Checkpoint(_) => {
+                "Another process may hold the database lock, or the directory is read-only."
+            }
+            DbError::Migrate(_) => {
+                "The database may be corrupt or from a newer airev; move it aside to start fresh."
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::Open(e) => write!(f, "cannot open database: {e}"),
+            DbError::WalConfig(e) => write!(f, "cannot configure WAL mode: {e}"),
+            DbError::Checkpoint(e) => write!(f, "cannot checkpoint the WAL: {e}"),
+            DbError::Migrate(e) => write!(f, "cannot migrate the schema: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Open(e) => Some(e),
+            DbError::WalConfig(e)
+            | DbError::Checkpoint(e)
+            | DbError::Migrate(e) => Some(e),
+        }
+    }
+}
 
 /// Opens (or creates) the SQLite database at `path`, configures WAL mode,
 /// and applies schema migrations via the `schema_version` table.
@@ -14,10 +71,10 @@ use crate::types::Session;
 ///
 /// # Errors
 ///
-/// Returns `tokio_rusqlite::Error` if the file cannot be opened, WAL configuration
-/// fails, or schema DDL fails.
-pub async fn open_db(path: &str) -> Result<Connection, tokio_rusqlite::Error> {
-    let conn = Connection::open(path).await?;
+/// Returns a [`DbError`] naming the step that failed: opening the file, WAL
+/// configuration, the startup checkpoint, or schema migration.
+pub async fn open_db(path: &str) -> Result<Connection, DbError> {
+    let conn = Connection::open(path).await.map_err(DbError::Open)?;
 
     // Step 1: WAL pragmas — connection-level settings re-applied on every open.
     conn.call(|db| {
@@ -30,7 +87,8 @@ pub async fn open_db(path: &str) -> Result<Connection, tokio_rusqlite::Error> {
         db.busy_timeout(Duration::from_secs(5))?;
         Ok(())
     })
-    .await?;
+    .await
+    .map_err(DbError::WalConfig)?;
 
     // Step 2: Checkpoint any leftover WAL from a previous run.
     // Called from the TUI process only — airev-mcp must not call this.
@@ -38,14 +96,16 @@ pub async fn open_db(path: &str) -> Result<Connection, tokio_rusqlite::Error> {
         db.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
         Ok(())
     })
-    .await?;
+    .await
+    .map_err(DbError::Checkpoint)?;
 
     // Step 3: Apply schema migrations via schema_version versioning system.
     conn.call(|db| {
         crate::schema::migrate(db)?;
         Ok(())
     })
-    .await?;
+    .await
+    .map_err(DbError::Migrate)?;
 
     Ok(conn)
 }
@@ -82,7 +142,8 @@ pub async fn detect_or_create_session(
     conn.call(move |db| {
         let existing: Option<Session> = db
             .query_row(
-                "SELECT id, repo_path, diff_mode, diff_args, created_at, updated_at
+                "SELECT id, repo_path, diff_mode, diff_args, created_at, updated_at,
+                        comment_filter, head_sha, diff_only, context_lines
                  FROM sessions
                  WHERE repo_path = ?1 AND diff_mode = ?2 AND diff_args = ?3
                  ORDER BY updated_at DESC
@@ -96,6 +157,10 @@ pub async fn detect_or_create_session(
                         diff_args: r.get(3)?,
                         created_at: r.get(4)?,
                         updated_at: r.get(5)?,
+                        comment_filter: r.get(6)?,
+                        head_sha: r.get(7)?,
+                        diff_only: r.get(8)?,
+                        context_lines: r.get(9)?,
                     })
                 },
             )
@@ -127,6 +192,10 @@ pub async fn detect_or_create_session(
                 diff_args,
                 created_at: now,
                 updated_at: now,
+                comment_filter: "all".to_owned(),
+                head_sha: String::new(),
+                diff_only: false,
+                context_lines: None,
             })
         }
     })
@@ -211,6 +280,162 @@ pub async fn toggle_file_reviewed(
     .await
 }
 
+/// Sets the reviewed flag for every path in `file_paths` within `session_id`.
+///
+/// All upserts run in a single `BEGIN IMMEDIATE` transaction, so either every
+/// file is updated or none is. `reviewed_at` is set to now when marking reviewed
+/// and cleared otherwise. Returns the number of files written.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if any upsert or the commit fails.
+pub async fn batch_set_reviewed(
+    conn: &Connection,
+    session_id: &str,
+    file_paths: Vec<String>,
+    reviewed: bool,
+) -> Result<usize, tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+
+    conn.call(move |db| {
+        let reviewed_at: Option<i64> = if reviewed { Some(now_secs()) } else { None };
+
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO file_review_state (session_id, file_path, reviewed, reviewed_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(session_id, file_path)
+                 DO UPDATE SET reviewed = excluded.reviewed,
+                               reviewed_at = excluded.reviewed_at",
+            )?;
+            for file_path in &file_paths {
+                stmt.execute(rusqlite::params![&session_id, file_path, reviewed, reviewed_at])?;
+            }
+        }
+        tx.commit()?;
+        Ok(file_paths.len())
+    })
+    .await
+}
+
+/// Loads every comment in `session_id`, ordered by file, line, then creation time.
+///
+/// File-level comments (`line_number IS NULL`) sort before line comments within
+/// the same file.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the query fails.
+pub async fn load_comments(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Vec<Comment>, tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+
+    conn.call(move |db| {
+        let mut stmt = db.prepare(
+            "SELECT id, session_id, file_path, line_number, hunk_offset, comment_type,
+                    severity, body, created_at, resolved_at, thread_id
+             FROM comments
+             WHERE session_id = ?1
+             ORDER BY file_path, line_number, created_at",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![&session_id], comment_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    })
+    .await
+}
+
+/// Maps a row selected with the full `comments` column list (in schema order)
+/// to a `Comment`.
+fn comment_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: r.get(0)?,
+        session_id: r.get(1)?,
+        file_path: r.get(2)?,
+        line_number: r.get(3)?,
+        hunk_offset: r.get(4)?,
+        comment_type: r.get(5)?,
+        severity: r.get(6)?,
+        body: r.get(7)?,
+        created_at: r.get(8)?,
+        resolved_at: r.get(9)?,
+        thread_id: r.get(10)?,
+    })
+}
+
+/// Renders every comment in `session_id` as a Markdown report, in
+/// [`load_comments`] order.
+///
+/// Each run of comments on one file becomes a section rendered by
+/// [`markdown_file_section`](crate::types::markdown_file_section).
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the query fails.
+pub async fn export_comments_markdown(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<String, tokio_rusqlite::Error> {
+    let comments = load_comments(conn, session_id).await?;
+    let mut out = "# Review comments\n".to_owned();
+    if comments.is_empty() {
+        out.push_str("\nNo comments.\n");
+    }
+    for file in comments.chunk_by(|a, b| a.file_path == b.file_path) {
+        out.push_str(&crate::types::markdown_file_section(&file[0].file_path, file));
+    }
+    Ok(out)
+}
+
+/// Inserts a new comment and returns it with its generated `id` and `created_at`.
+///
+/// Any `id` or `created_at` already set on `comment` is overwritten: a fresh
+/// UUID v4 is generated and the timestamp is taken at insert time. The insert
+/// runs inside `BEGIN IMMEDIATE`. `comment_type` and `severity` must satisfy the
+/// table's `CHECK` constraints.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the insert fails (including a `CHECK`
+/// violation or an unknown `session_id`).
+pub async fn insert_comment(
+    conn: &Connection,
+    mut comment: Comment,
+) -> Result<Comment, tokio_rusqlite::Error> {
+    conn.call(move |db| {
+        comment.id = uuid::Uuid::new_v4().to_string();
+        comment.created_at = now_secs();
+
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO comments (id, session_id, file_path, line_number, hunk_offset,
+                                   comment_type, severity, body, created_at, resolved_at,
+                                   thread_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![
+                &comment.id,
+                &comment.session_id,
+                &comment.file_path,
+                comment.line_number,
+                comment.hunk_offset,
+                &comment.comment_type,
+                &comment.severity,
+                &comment.body,
+                comment.created_at,
+                comment.resolved_at,
+                &comment.thread_id,
+            ],
+        )?;
+        tx.commit()?;
+        Ok(comment)
+    })
+    .await
+}
+
 /// Updates the `updated_at` timestamp for `session_id` to the current time.
 ///
 /// Called on quit or after significant user actions to keep the session's
@@ -237,3 +462,360 @@ pub async fn update_session_timestamp(
     })
     .await
 }
+
+/// Records `head_sha` as the HEAD commit `session_id` started from.
+///
+/// Called for sessions whose `head_sha` is still empty (new, or created before
+/// schema v3); later HEAD changes are compared against this value.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_session_head(
+    conn: &Connection,
+    session_id: &str,
+    head_sha: &str,
+) -> Result<(), tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+    let head_sha = head_sha.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "UPDATE sessions SET head_sha = ?1 WHERE id = ?2",
+            rusqlite::params![&head_sha, &session_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Marks comment `comment_id` resolved now (`resolved_at` = current time), or
+/// clears `resolved_at` when `resolved` is false.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_comment_resolved(
+    conn: &Connection,
+    comment_id: &str,
+    resolved: bool,
+) -> Result<(), tokio_rusqlite::Error> {
+    let comment_id = comment_id.to_owned();
+
+    conn.call(move |db| {
+        let resolved_at = resolved.then(now_secs);
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "UPDATE comments SET resolved_at = ?1 WHERE id = ?2",
+            rusqlite::params![resolved_at, &comment_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Stores the comments-panel severity filter for `session_id`.
+///
+/// `filter` must be a `CommentFilter::as_str()` value (`all`, `major`, or
+/// `critical`); anything else violates the column's `CHECK` constraint.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_comment_filter(
+    conn: &Connection,
+    session_id: &str,
+    filter: &str,
+) -> Result<(), tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+    let filter = filter.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "UPDATE sessions SET comment_filter = ?1 WHERE id = ?2",
+            rusqlite::params![&filter, &session_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Stores whether `session_id` shows only the diff panel (`--diff-only` / `F`).
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_session_diff_only(
+    conn: &Connection,
+    session_id: &str,
+    diff_only: bool,
+) -> Result<(), tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "UPDATE sessions SET diff_only = ?1 WHERE id = ?2",
+            rusqlite::params![diff_only, &session_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Stores the diff context line count chosen for `session_id` (`-` / `+`).
+///
+/// `lines` must be at most 15; larger counts violate the column's `CHECK`
+/// constraint.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_session_context_lines(
+    conn: &Connection,
+    session_id: &str,
+    lines: u32,
+) -> Result<(), tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "UPDATE sessions SET context_lines = ?1 WHERE id = ?2",
+            rusqlite::params![lines, &session_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the diff mode last shown for `repo_path`, or `None` if none was saved.
+///
+/// Unlike a session's `diff_mode`, this is keyed by repository alone, so it
+/// survives new sessions being created.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the query fails.
+pub async fn get_last_diff_mode(
+    conn: &Connection,
+    repo_path: &str,
+) -> Result<Option<String>, tokio_rusqlite::Error> {
+    let repo_path = repo_path.to_owned();
+
+    conn.call(move |db| {
+        let mode = db
+            .query_row(
+                "SELECT last_diff_mode FROM repo_state WHERE repo_path = ?1",
+                rusqlite::params![&repo_path],
+                |r| r.get(0),
+            )
+            .optional()?;
+        Ok(mode)
+    })
+    .await
+}
+
+/// Records `mode` as the diff mode last shown for `repo_path`.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_last_diff_mode(
+    conn: &Connection,
+    repo_path: &str,
+    mode: &str,
+) -> Result<(), tokio_rusqlite::Error> {
+    let repo_path = repo_path.to_owned();
+    let mode = mode.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO repo_state (repo_path, last_diff_mode) VALUES (?1, ?2)
+             ON CONFLICT(repo_path) DO UPDATE SET last_diff_mode = excluded.last_diff_mode",
+            rusqlite::params![&repo_path, &mode],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the `FROM..TO` range last shown for `repo_path`, or `None` when the
+/// last-shown mode is not a range.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the query fails.
+pub async fn get_last_diff_range(
+    conn: &Connection,
+    repo_path: &str,
+) -> Result<Option<String>, tokio_rusqlite::Error> {
+    let repo_path = repo_path.to_owned();
+
+    conn.call(move |db| {
+        let range = db
+            .query_row(
+                "SELECT last_diff_args FROM repo_state
+                 WHERE repo_path = ?1 AND last_diff_mode = 'range'",
+                rusqlite::params![&repo_path],
+                |r| r.get(0),
+            )
+            .optional()?;
+        Ok(range)
+    })
+    .await
+}
+
+/// Records the `FROM..TO` range as the diff last shown for `repo_path`, with
+/// `range` as its last diff mode.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn set_last_diff_range(
+    conn: &Connection,
+    repo_path: &str,
+    range: &str,
+) -> Result<(), tokio_rusqlite::Error> {
+    let repo_path = repo_path.to_owned();
+    let range = range.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO repo_state (repo_path, last_diff_mode, last_diff_args)
+             VALUES (?1, 'range', ?2)
+             ON CONFLICT(repo_path) DO UPDATE
+             SET last_diff_mode = 'range', last_diff_args = excluded.last_diff_args",
+            rusqlite::params![&repo_path, &range],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the `FROM..TO` ranges recently shown for `repo_path`, most recent
+/// first, at most [`RECENT_DIFF_ARGS_MAX`].
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the query fails.
+pub async fn get_recent_diff_args(
+    conn: &Connection,
+    repo_path: &str,
+) -> Result<Vec<String>, tokio_rusqlite::Error> {
+    let repo_path = repo_path.to_owned();
+
+    conn.call(move |db| {
+        let mut stmt = db.prepare(
+            "SELECT diff_args FROM recent_diff_args
+             WHERE repo_path = ?1 ORDER BY used_seq DESC LIMIT ?2",
+        )?;
+        let args = stmt
+            .query_map(rusqlite::params![&repo_path, RECENT_DIFF_ARGS_MAX as i64], |r| r.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(args)
+    })
+    .await
+}
+
+/// Moves `args` to the front of the recent ranges for `repo_path`, adding it
+/// if new, and drops the oldest beyond [`RECENT_DIFF_ARGS_MAX`].
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn push_recent_diff_args(
+    conn: &Connection,
+    repo_path: &str,
+    args: &str,
+) -> Result<(), tokio_rusqlite::Error> {
+    let repo_path = repo_path.to_owned();
+    let args = args.to_owned();
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO recent_diff_args (repo_path, diff_args, used_seq)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(used_seq), 0) + 1
+                              FROM recent_diff_args WHERE repo_path = ?1))
+             ON CONFLICT(repo_path, diff_args) DO UPDATE SET used_seq = excluded.used_seq",
+            rusqlite::params![&repo_path, &args],
+        )?;
+        tx.execute(
+            "DELETE FROM recent_diff_args
+             WHERE repo_path = ?1 AND diff_args NOT IN (
+                 SELECT diff_args FROM recent_diff_args
+                 WHERE repo_path = ?1 ORDER BY used_seq DESC LIMIT ?2)",
+            rusqlite::params![&repo_path, RECENT_DIFF_ARGS_MAX as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the `[left, center, right]` panel percentages saved for
+/// `session_id`, or `None` if it has none.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the query fails.
+pub async fn load_layout(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Option<[u16; 3]>, tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+
+    conn.call(move |db| {
+        let layout = db
+            .query_row(
+                "SELECT left_pct, center_pct, right_pct FROM session_layout WHERE session_id = ?1",
+                rusqlite::params![&session_id],
+                |r| Ok([r.get(0)?, r.get(1)?, r.get(2)?]),
+            )
+            .optional()?;
+        Ok(layout)
+    })
+    .await
+}
+
+/// Saves `[left, center, right]` panel percentages for `session_id`, replacing
+/// any saved before.
+///
+/// # Errors
+///
+/// Returns `tokio_rusqlite::Error` if the `BEGIN IMMEDIATE` transaction fails.
+pub async fn save_layout(
+    conn: &Connection,
+    session_id: &str,
+    layout: [u16; 3],
+) -> Result<(), tokio_rusqlite::Error> {
+    let session_id = session_id.to_owned();
+    let [left, center, right] = layout;
+
+    conn.call(move |db| {
+        let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO session_layout (session_id, left_pct, center_pct, right_pct)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(session_id) DO UPDATE
+             SET left_pct = excluded.left_pct, center_pct = excluded.center_pct,
+                 right_pct = excluded.right_pct",
+            rusqlite::params![&session_id, left, center, right],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
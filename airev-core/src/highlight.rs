@@ -0,0 +1,423 @@
+//! Syntax- and word-diff highlighting of unified diff text.
+//!
+//! This module is independent of git2 and any live repository: it takes the text
+//! of a unified diff (as produced by `git diff` or stored in a `.patch` file) and
+//! returns styled ratatui lines. The git worker serializes each file's hunks to
+//! unified text and calls [`highlight_unified_diff`]; tests and patch-file
+//! consumers can call it directly.
+
+use std::sync::LazyLock;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use similar::{ChangeTag, TextDiff};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+static PS: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static TS: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// Name of the default syntect theme for syntax highlighting (config
+/// `syntax_theme`).
+pub const SYNTECT_THEME: &str = "base16-ocean.dark";
+
+/// Granularity of the inline emphasis on paired removed/added lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WordDiff {
+    /// Emphasize changed words (`similar::TextDiff::from_words`).
+    #[default]
+    Word,
+    /// Emphasize changed characters (`similar::TextDiff::from_chars`).
+    Char,
+    /// No inline emphasis; removed and added lines keep their syntax colors.
+    Off,
+}
+
+impl WordDiff {
+    /// Returns the config name: `"word"`, `"char"`, or `"off"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WordDiff::Word => "word",
+            WordDiff::Char => "char",
+            WordDiff::Off => "off",
+        }
+    }
+
+    /// Parses a config name produced by [`WordDiff::as_str`].
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "word" => Some(WordDiff::Word),
+            "char" => Some(WordDiff::Char),
+            "off" => Some(WordDiff::Off),
+            _ => None,
+        }
+    }
+}
+
+/// A hunk parsed from unified diff text: its header and `(origin, code)` lines.
+///
+/// `code` has the origin character and trailing newline stripped.
+struct ParsedHunk<'a> {
+    header: &'a str,
+    lines: Vec<(char, &'a str)>,
+}
+
+/// Full text of the old and new sides of one file, for
+/// [`highlight_unified_diff_with_sources`]. A side that is `None` (added or
+/// deleted files, unreadable blobs) is highlighted hunk by hunk instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSources<'a> {
+    /// The file before the change.
+    pub old: Option<&'a str>,
+    /// The file after the change.
+    pub new: Option<&'a str>,
+}
+
+/// Forces the syntax and theme sets to load.
+///
+/// Loading takes tens of milliseconds; callers on a background thread can call
+/// this at startup so the first highlight request does not pay for it.
+pub fn preload_syntax_assets() {
+    let _ = &*PS;
+    let _ = &*TS;
+}
+
+/// Names of the bundled syntect themes [`highlight_unified_diff`] accepts, sorted.
+pub fn syntect_theme_names() -> Vec<&'static str> {
+    TS.themes.keys().map(String::as_str).collect()
+}
+
+/// Returns the name of the syntax [`highlight_unified_diff`] picks for extension
+/// `ext`, e.g. `Rust`, or `plain` when it falls back to plain text.
+pub fn syntax_name(ext: &str) -> &'static str {
+    PS.find_syntax_by_extension(ext).map_or("plain", |syntax| syntax.name.as_str())
+}
+
+/// Highlights the hunks of a unified diff using the syntax for extension `ext`.
+///
+/// Everything before the first `@@` header (`diff --git`, `index`, `---`/`+++`
+/// lines) is skipped, as are file headers between files in a multi-file patch;
+/// all hunks are highlighted with the same syntax. Consecutive `-`/`+` line pairs
+/// get inline diff emphasis at the granularity chosen by `word_diff`, or none
+/// when it is `WordDiff::Off`. `\ No newline at end of file` markers are kept as
+/// dimmed lines.
+///
+/// Code is colored with the bundled syntect theme named `syntect_theme` (see
+/// [`syntect_theme_names`]); an unknown name falls back to [`SYNTECT_THEME`],
+/// then to the first bundled theme.
+///
+/// Returns the highlighted lines and, for each hunk, the index of its header
+/// line in the returned vec. Unknown extensions fall back to plain text.
+pub fn highlight_unified_diff(
+    diff_text: &str,
+    ext: &str,
+    word_diff: WordDiff,
+    syntect_theme: &str,
+) -> (Vec<Line<'static>>, Vec<usize>) {
+    highlight_unified_diff_with_sources(
+        diff_text,
+        ext,
+        word_diff,
+        syntect_theme,
+        FileSources::default(),
+    )
+}
+
+/// Like [`highlight_unified_diff`], but takes code colors from `sources`, the
+/// whole file highlighted top to bottom, so constructs that start outside a hunk
+/// (block comments, multi-line strings) are colored correctly inside it.
+///
+/// Removed lines are looked up in the old side and added and context lines in
+/// the new side, by the line numbers in the hunk headers. A line whose source
+/// text does not match the diff (e.g. a diff taken ignoring whitespace) falls
+/// back to the per-hunk highlighter.
+pub fn highlight_unified_diff_with_sources(
+    diff_text: &str,
+    ext: &str,
+    word_diff: WordDiff,
+    syntect_theme: &str,
+    sources: FileSources<'_>,
+) -> (Vec<Line<'static>>, Vec<usize>) {
+    let theme = TS
+        .themes
+        .get(syntect_theme)
+        .or_else(|| TS.themes.get(SYNTECT_THEME))
+        .or_else(|| TS.themes.values().next());
+    let syntax = PS.find_syntax_by_extension(ext).unwrap_or_else(|| PS.find_syntax_plain_text());
+
+    let highlight_side = |text: Option<&str>| match (text, theme) {
+        (Some(text), Some(theme)) => highlight_source(text, syntax, theme),
+        _ => Vec::new(),
+    };
+    let old_source = highlight_side(sources.old);
+    let new_source = highlight_side(sources.new);
+
+    let mut highlighted_lines: Vec<Line<'static>> = Vec::new();
+    let mut hunk_offsets: Vec<usize> = Vec::new();
+
+    for hunk in parse_hunks(diff_text) {
+        // Record hunk header position and emit a styled header line.
+        hunk_offsets.push(highlighted_lines.len());
+        let header_span =
+            Span::styled(hunk.header.trim_end().to_owned(), Style::default().fg(Color::Cyan));
+        highlighted_lines.push(Line::from(vec![header_span]));
+
+        // Fresh highlighter per hunk for simplicity (safe, predictable state).
+        let mut h = match theme {
+            Some(t) => HighlightLines::new(syntax, t),
+            None => {
+                emit_plain_hunk_lines(&hunk.lines, &mut highlighted_lines);
+                continue;
+            }
+        };
+
+        let mut pending_removed: Option<(&str, Vec<Span<'static>>)> = None;
+        let (mut old_no, mut new_no) = hunk_starts(hunk.header);
+
+        for &(origin, code) in &hunk.lines {
+            let source = match origin {
+                '-' => source_spans(&old_source, &mut old_no, code),
+                '+' => source_spans(&new_source, &mut new_no, code),
+                ' ' => {
+                    old_no += 1;
+                    source_spans(&new_source, &mut new_no, code)
+                }
+                _ => None,
+            };
+            let base_spans =
+                source.unwrap_or_else(|| build_syntect_spans(code, &mut h, &PS));
+
+            match origin {
+                '-' => {
+                    if let Some((_, spans)) = pending_removed.take() {
+                        highlighted_lines.push(Line::from(spans));
+                    }
+                    let mut s = vec![Span::styled("- ", Style::default().fg(Color::Red))];
+                    s.extend(base_spans);
+                    if word_diff == WordDiff::Off {
+                        // Nothing to pair with; emit right away.
+                        highlighted_lines.push(Line::from(s));
+                    } else {
+                        pending_removed = Some((code, s));
+                    }
+                }
+                '+' => {
+                    if let Some((old_code, _)) = pending_removed.take() {
+                        let (old_word, new_word) = word_diff_spans(old_code, code, word_diff);
+                        let mut old_s = vec![Span::styled("- ", Style::default().fg(Color::Red))];
+                        old_s.extend(old_word);
+                        highlighted_lines.push(Line::from(old_s));
+                        let mut new_s =
+                            vec![Span::styled("+ ", Style::default().fg(Color::Green))];
+                        new_s.extend(new_word);
+                        highlighted_lines.push(Line::from(new_s));
+                    } else {
+                        let mut s =
+                            vec![Span::styled("+ ", Style::default().fg(Color::Green))];
+                        s.extend(base_spans);
+                        highlighted_lines.push(Line::from(s));
+                    }
+                }
+                _ => {
+                    if let Some((_, spans)) = pending_removed.take() {
+                        highlighted_lines.push(Line::from(spans));
+                    }
+                    let prefix = format!("{origin} ");
+                    let mut s = vec![Span::styled(prefix, Style::default().fg(Color::DarkGray))];
+                    s.extend(base_spans);
+                    highlighted_lines.push(Line::from(s));
+                }
+            }
+        }
+        // Flush any trailing unpaired removed line.
+        if let Some((_, spans)) = pending_removed.take() {
+            highlighted_lines.push(Line::from(spans));
+        }
+    }
+
+    (highlighted_lines, hunk_offsets)
+}
+
+/// Splits unified diff text into hunks, dropping file headers.
+///
+/// A line starting with `@@` opens a new hunk. Within a hunk, lines starting with
+/// `+`, `-`, space, or `\` are hunk lines; an empty line is treated as blank
+/// context (some tools strip the trailing space). Any other line — e.g. the
+/// `diff --git` header of the next file — ends the current hunk.
+fn parse_hunks(diff_text: &str) -> Vec<ParsedHunk<'_>> {
+    let mut hunks: Vec<ParsedHunk<'_>> = Vec::new();
+    let mut in_hunk = false;
+
+    for raw in diff_text.lines() {
+        if raw.starts_with("@@") {
+            hunks.push(ParsedHunk { header: raw, lines: Vec::new() });
+            in_hunk = true;
+            continue;
+        }
+        if !in_hunk {
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else { continue };
+        match raw.chars().next() {
+            Some(origin @ ('+' | '-' | ' ')) => hunk.lines.push((origin, &raw[1..])),
+            Some('\\') => hunk.lines.push(('\\', raw[1..].trim_start())),
+            None => hunk.lines.push((' ', "")),
+            Some(_) => in_hunk = false,
+        }
+    }
+
+    hunks
+}
+
+/// The first old and new line numbers of a hunk, read from its
+/// `@@ -a,b +c,d @@` header; `(0, 0)` if the header is malformed.
+fn hunk_starts(header: &str) -> (usize, usize) {
+    let start = |sign: char| {
+        header
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix(sign))
+            .and_then(|range| range.split(',').next()?.parse().ok())
+            .unwrap_or(0)
+    };
+    (start('-'), start('+'))
+}
+
+/// Highlights `text` line by line with one highlighter, so syntax state carries
+/// across lines. Returns each line's spans without its line ending.
+fn highlight_source(
+    text: &str,
+    syntax: &SyntaxReference,
+    theme: &syntect::highlighting::Theme,
+) -> Vec<Vec<Span<'static>>> {
+    let mut h = HighlightLines::new(syntax, theme);
+    LinesWithEndings::from(text)
+        .map(|line| {
+            let ranges = h.highlight_line(line, &PS).unwrap_or_default();
+            ranges
+                .into_iter()
+                .map(|(style, text)| (style, text.trim_end_matches(['\n', '\r'])))
+                .filter(|(_, text)| !text.is_empty())
+                .map(|(style, text)| syntect_to_span(style, text))
+                .collect()
+        })
+        .collect()
+}
+
+/// The spans of 1-based line `*line_no` of a highlighted source, advancing
+/// `*line_no`; `None` when the line is missing or its text is not `code`.
+fn source_spans(
+    source: &[Vec<Span<'static>>],
+    line_no: &mut usize,
+    code: &str,
+) -> Option<Vec<Span<'static>>> {
+    let index = line_no.checked_sub(1)?;
+    *line_no += 1;
+    let spans = source.get(index)?;
+    let text: String = spans.iter().map(|s| s.content.as_ref()).collect();
+    if text != code {
+        return None;
+    }
+    Some(if spans.is_empty() { vec![Span::raw(String::new())] } else { spans.clone() })
+}
+
+/// Converts a syntect (Style, &str) pair to an owned ratatui Span.
+///
+/// Rebuilds color and modifier fields from syntect types into ratatui types to
+/// avoid the type mismatch between ratatui::style::Style and ratatui::prelude::Style
+/// that arises from syntect-tui using a different ratatui crate split.
+fn syntect_to_span(style: syntect::highlighting::Style, content: &str) -> Span<'static> {
+    use syntect::highlighting::Color as SC;
+    let to_color = |c: SC| -> Option<Color> {
+        if c.a > 0 { Some(Color::Rgb(c.r, c.g, c.b)) } else { None }
+    };
+    let mut ratatui_style = Style::default();
+    if let Some(fg) = to_color(style.foreground) {
+        ratatui_style = ratatui_style.fg(fg);
+    }
+    if let Some(bg) = to_color(style.background) {
+        ratatui_style = ratatui_style.bg(bg);
+    }
+    if style.font_style.contains(syntect::highlighting::FontStyle::BOLD) {
+        ratatui_style = ratatui_style.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(syntect::highlighting::FontStyle::ITALIC) {
+        ratatui_style = ratatui_style.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(syntect::highlighting::FontStyle::UNDERLINE) {
+        ratatui_style = ratatui_style.add_modifier(Modifier::UNDERLINED);
+    }
+    Span::styled(content.to_owned(), ratatui_style)
+}
+
+/// Builds syntect-highlighted spans for a single line of code.
+///
+/// Returns owned `Vec<Span<'static>>`. Falls back to a plain unstyled span on error.
+fn build_syntect_spans(
+    code: &str,
+    h: &mut HighlightLines,
+    ps: &SyntaxSet,
+) -> Vec<Span<'static>> {
+    let ranges = h.highlight_line(code, ps).unwrap_or_default();
+    let spans: Vec<Span<'static>> =
+        ranges.into_iter().map(|(style, text)| syntect_to_span(style, text)).collect();
+    if spans.is_empty() {
+        vec![Span::raw(code.to_owned())]
+    } else {
+        spans
+    }
+}
+
+/// Computes inline diff spans for a removed/added line pair.
+///
+/// Returns two parallel Vecs of spans: old_line spans and new_line spans.
+/// Changed words (or characters, for `WordDiff::Char`) are rendered bold;
+/// unchanged text uses the base diff color.
+fn word_diff_spans(
+    old_line: &str,
+    new_line: &str,
+    granularity: WordDiff,
+) -> (Vec<Span<'static>>, Vec<Span<'static>>) {
+    let diff = match granularity {
+        WordDiff::Char => TextDiff::from_chars(old_line, new_line),
+        WordDiff::Word | WordDiff::Off => TextDiff::from_words(old_line, new_line),
+    };
+    let mut old_spans: Vec<Span<'static>> = Vec::new();
+    let mut new_spans: Vec<Span<'static>> = Vec::new();
+
+    for change in diff.iter_all_changes() {
+        let text = change.value().to_owned();
+        match change.tag() {
+            ChangeTag::Delete => {
+                let style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+                old_spans.push(Span::styled(text, style));
+            }
+            ChangeTag::Insert => {
+                let style = Style::default().fg(Color::Green).add_modifier(Modifier::BOLD);
+                new_spans.push(Span::styled(text, style));
+            }
+            ChangeTag::Equal => {
+                let span = Span::styled(text, Style::default().fg(Color::DarkGray));
+                old_spans.push(span.clone());
+                new_spans.push(span);
+            }
+        }
+    }
+    (old_spans, new_spans)
+}
+
+/// Emits plain (non-syntect) lines for a hunk when no theme is available.
+///
+/// Fallback path used when ThemeSet contains no themes (unusual but possible).
+fn emit_plain_hunk_lines(lines: &[(char, &str)], out: &mut Vec<Line<'static>>) {
+    for &(origin, code) in lines {
+        let color = match origin {
+            '+' => Color::Green,
+            '-' => Color::Red,
+            _ => Color::DarkGray,
+        };
+        let text = format!("{origin} {code}");
+        out.push(Line::from(vec![Span::styled(text, Style::default().fg(color))]));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod db;
+pub mod highlight;
 pub mod schema;
 pub mod types;
+
+pub use db::DbError;
@@ -62,6 +62,101 @@ pub const SCHEMA_V1_SQL: &str = "
     ) STRICT;
 ";
 
+/// DDL for the v2 schema: per-session comment severity filter.
+///
+/// Adds `sessions.comment_filter`, one of `all`, `major` (critical + major), or
+/// `critical`. Existing sessions default to `all`.
+pub const SCHEMA_V2_SQL: &str = "
+    ALTER TABLE sessions ADD COLUMN comment_filter TEXT NOT NULL DEFAULT 'all'
+        CHECK(comment_filter IN ('all', 'major', 'critical'));
+";
+
+/// DDL for the v3 schema: the HEAD commit a session started from.
+///
+/// Adds `sessions.head_sha`, the full SHA of `HEAD` when the session was first
+/// opened, used to warn that comment anchors may be stale after HEAD moves.
+/// Existing sessions start empty (unknown) and adopt the next HEAD they see.
+pub const SCHEMA_V3_SQL: &str = "
+    ALTER TABLE sessions ADD COLUMN head_sha TEXT NOT NULL DEFAULT '';
+";
+
+/// DDL for the v4 schema: per-repository state that outlives sessions.
+///
+/// `repo_state.last_diff_mode` is the diff mode last shown for `repo_path`
+/// (`unstaged`, `staged`, or `branch`), used as the startup mode next time.
+pub const SCHEMA_V4_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS repo_state (
+        repo_path      TEXT PRIMARY KEY,
+        last_diff_mode TEXT NOT NULL
+    ) STRICT;
+";
+
+/// DDL for the v5 schema: per-session diff-only layout.
+///
+/// Adds `sessions.diff_only`, `1` when the session hides the side panels and
+/// shows only the diff. Existing sessions default to `0` (all panels).
+pub const SCHEMA_V5_SQL: &str = "
+    ALTER TABLE sessions ADD COLUMN diff_only INTEGER NOT NULL DEFAULT 0
+        CHECK(diff_only IN (0, 1));
+";
+
+/// DDL for the v6 schema: the commit range last shown per repository.
+///
+/// `repo_state.last_diff_args` holds `FROM..TO` when `last_diff_mode` is
+/// `range`, and is empty otherwise.
+pub const SCHEMA_V6_SQL: &str = "
+    ALTER TABLE repo_state ADD COLUMN last_diff_args TEXT NOT NULL DEFAULT '';
+";
+
+/// DDL for the v7 schema: recently used commit ranges per repository.
+///
+/// Each row is a `FROM..TO` range shown for `repo_path`; `used_seq` grows with
+/// every use, so the highest is the most recent.
+pub const SCHEMA_V7_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS recent_diff_args (
+        repo_path TEXT NOT NULL,
+        diff_args TEXT NOT NULL,
+        used_seq  INTEGER NOT NULL,
+        PRIMARY KEY (repo_path, diff_args)
+    ) STRICT;
+";
+
+/// DDL for the v8 schema: per-session panel widths.
+///
+/// One row per session that has saved its `left_pct` / `center_pct` /
+/// `right_pct` split; a session without a row uses the configured widths.
+pub const SCHEMA_V8_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS session_layout (
+        session_id TEXT    PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+        left_pct   INTEGER NOT NULL,
+        center_pct INTEGER NOT NULL,
+        right_pct  INTEGER NOT NULL
+    ) STRICT;
+";
+
+/// DDL for the v9 schema: per-session diff context.
+///
+/// Adds `sessions.context_lines`, the context line count chosen with `-` / `+`.
+/// `NULL` (the default for existing sessions) uses the configured count.
+pub const SCHEMA_V9_SQL: &str = "
+    ALTER TABLE sessions ADD COLUMN context_lines INTEGER
+        CHECK(context_lines BETWEEN 0 AND 15);
+";
+
+/// Every schema version with the DDL that brings the previous one up to it, in
+/// ascending order. A new version is one more entry here.
+const MIGRATIONS: &[(i64, &str)] = &[
+    (1, SCHEMA_V1_SQL),
+    (2, SCHEMA_V2_SQL),
+    (3, SCHEMA_V3_SQL),
+    (4, SCHEMA_V4_SQL),
+    (5, SCHEMA_V5_SQL),
+    (6, SCHEMA_V6_SQL),
+    (7, SCHEMA_V7_SQL),
+    (8, SCHEMA_V8_SQL),
+    (9, SCHEMA_V9_SQL),
+];
+
 /// Runs forward-only schema migration to migrate the DB to the latest version.
 ///
 /// This function is idempotent: safe to call on every startup regardless of
@@ -71,8 +166,9 @@ pub const SCHEMA_V1_SQL: &str = "
 ///
 /// 1. Creates the `schema_version` table if it does not exist.
 /// 2. Reads the current version (`0` if the table is empty).
-/// 3. If the version is below 1, applies `SCHEMA_V1_SQL` inside a
-///    `BEGIN IMMEDIATE` transaction and records `version = 1`.
+/// 3. Applies each [`MIGRATIONS`] entry above that version in its own
+///    `BEGIN IMMEDIATE` transaction, recording its version as it commits. The
+///    v1 step first drops the unversioned Phase 1 tables.
 ///
 /// # Errors
 ///
@@ -88,18 +184,20 @@ pub fn migrate(db: &mut rusqlite::Connection) -> rusqlite::Result<()> {
         )
         .unwrap_or(0);
 
-    if version < 1 {
+    for &(target, sql) in MIGRATIONS.iter().filter(|(target, _)| *target > version) {
         let tx = db.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
-        // Drop legacy tables from Phase 1 that have incompatible columns.
-        // Safe: only fires when schema_version is 0 (no versioned data exists).
-        tx.execute_batch(
-            "DROP TABLE IF EXISTS comments;
-             DROP TABLE IF EXISTS file_review_state;
-             DROP TABLE IF EXISTS threads;
-             DROP TABLE IF EXISTS sessions;",
-        )?;
-        tx.execute_batch(SCHEMA_V1_SQL)?;
-        tx.execute("INSERT INTO schema_version (version) VALUES (1)", [])?;
+        if target == 1 {
+            // Drop legacy tables from Phase 1 that have incompatible columns.
+            // Safe: only fires when schema_version is 0 (no versioned data exists).
+            tx.execute_batch(
+                "DROP TABLE IF EXISTS comments;
+                 DROP TABLE IF EXISTS file_review_state;
+                 DROP TABLE IF EXISTS threads;
+                 DROP TABLE IF EXISTS sessions;",
+            )?;
+        }
+        tx.execute_batch(sql)?;
+        tx.execute("INSERT INTO schema_version (version) VALUES (?1)", [target])?;
         tx.commit()?;
     }
 
@@ -11,6 +11,10 @@ pub struct Session {
     pub diff_args: String,
     pub created_at: i64,      // Unix timestamp seconds
     pub updated_at: i64,      // Unix timestamp seconds
+    pub comment_filter: String, // CommentFilter::as_str(), "all" by default
+    pub head_sha: String,     // HEAD at session start; "" when unknown
+    pub diff_only: bool,      // side panels hidden for this session
+    pub context_lines: Option<u32>, // diff context chosen with -/+; None = config
 }
 
 /// A single comment attached to a hunk or line within a session.
@@ -33,6 +37,179 @@ pub struct Comment {
     pub thread_id: Option<String>,
 }
 
+/// Renders `comments` as the Markdown section for the file at `path`: a
+/// `## path` heading and one bullet per comment, e.g.
+/// `- **[MAJOR] concern** (line 12): body`.
+///
+/// File and hunk comments say `(file)` and `(hunk)`; a comment with
+/// `resolved_at` set reads `(line 12, resolved)`. Continuation lines of a body
+/// are indented under the bullet. Shared by the `E` export and the `:note` report.
+pub fn markdown_file_section<'a>(
+    path: &str,
+    comments: impl IntoIterator<Item = &'a Comment>,
+) -> String {
+    let mut out = format!("\n## {path}\n\n");
+    for c in comments {
+        let mut location = match (c.line_number, c.hunk_offset) {
+            (Some(n), _) => format!("line {n}"),
+            (None, Some(_)) => "hunk".to_owned(),
+            (None, None) => "file".to_owned(),
+        };
+        if c.resolved_at.is_some() {
+            location.push_str(", resolved");
+        }
+        let severity = c.severity.to_uppercase();
+        out.push_str(&format!("- **[{severity}] {}** ({location})", c.comment_type));
+        let body = c.body.trim_end();
+        if !body.is_empty() {
+            out.push_str(&format!(": {}", body.replace('\n', "\n  ")));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The kind of review remark a comment represents.
+///
+/// Mirrors the `CHECK(comment_type IN (...))` constraint on the `comments` table.
+/// The default is `Concern`, which is what the comment composer pre-selects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommentType {
+    Question,
+    #[default]
+    Concern,
+    Til,
+    Suggestion,
+    Praise,
+    Nitpick,
+}
+
+impl CommentType {
+    /// All variants in display order (used by the composer's type picker).
+    pub const ALL: [CommentType; 6] = [
+        CommentType::Question,
+        CommentType::Concern,
+        CommentType::Til,
+        CommentType::Suggestion,
+        CommentType::Praise,
+        CommentType::Nitpick,
+    ];
+
+    /// Returns the lowercase string stored in the `comment_type` column.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CommentType::Question => "question",
+            CommentType::Concern => "concern",
+            CommentType::Til => "til",
+            CommentType::Suggestion => "suggestion",
+            CommentType::Praise => "praise",
+            CommentType::Nitpick => "nitpick",
+        }
+    }
+
+    /// Parses a `comment_type` column value. Returns `None` for unknown strings.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == s)
+    }
+}
+
+/// Valid `severity` column values, most severe first.
+///
+/// Index order matches the composer's `1`–`4` quick-pick keys.
+pub const SEVERITIES: [&str; 4] = ["critical", "major", "minor", "info"];
+
+/// A comment severity, mirroring the `CHECK(severity IN (...))` constraint on
+/// the `comments` table. Variants are ordered most severe first, like
+/// [`SEVERITIES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Critical,
+    Major,
+    Minor,
+    Info,
+}
+
+impl Severity {
+    /// All variants, most severe first.
+    pub const ALL: [Severity; 4] =
+        [Severity::Critical, Severity::Major, Severity::Minor, Severity::Info];
+
+    /// Returns the lowercase string stored in the `severity` column.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Critical => "critical",
+            Severity::Major => "major",
+            Severity::Minor => "minor",
+            Severity::Info => "info",
+        }
+    }
+
+    /// Parses a `severity` column value. Returns `None` for unknown strings.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sev| sev.as_str() == s)
+    }
+
+    /// Returns the next less severe variant, or `None` after `Info`.
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Severity::Critical => Some(Severity::Major),
+            Severity::Major => Some(Severity::Minor),
+            Severity::Minor => Some(Severity::Info),
+            Severity::Info => None,
+        }
+    }
+}
+
+/// Which comment severities the comments panel shows.
+///
+/// Cycled with `f` in the TUI and persisted per session in the
+/// `sessions.comment_filter` column. The default shows every comment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommentFilter {
+    #[default]
+    All,
+    /// `critical` and `major` only.
+    MajorAndAbove,
+    /// `critical` only.
+    CriticalOnly,
+}
+
+impl CommentFilter {
+    /// Returns the lowercase string stored in the `comment_filter` column.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CommentFilter::All => "all",
+            CommentFilter::MajorAndAbove => "major",
+            CommentFilter::CriticalOnly => "critical",
+        }
+    }
+
+    /// Parses a `comment_filter` column value. Returns `None` for unknown strings.
+    pub fn parse(s: &str) -> Option<Self> {
+        [CommentFilter::All, CommentFilter::MajorAndAbove, CommentFilter::CriticalOnly]
+            .into_iter()
+            .find(|f| f.as_str() == s)
+    }
+
+    /// Returns the next filter in the cycle `all → critical+major → critical → all`.
+    pub fn next(self) -> Self {
+        match self {
+            CommentFilter::All => CommentFilter::MajorAndAbove,
+            CommentFilter::MajorAndAbove => CommentFilter::CriticalOnly,
+            CommentFilter::CriticalOnly => CommentFilter::All,
+        }
+    }
+
+    /// Returns whether a comment with `severity` passes this filter.
+    pub fn allows(self, severity: &str) -> bool {
+        match self {
+            CommentFilter::All => true,
+            CommentFilter::MajorAndAbove => matches!(severity, "critical" | "major"),
+            CommentFilter::CriticalOnly => severity == "critical",
+        }
+    }
+}
+
 /// Per-file reviewed state within a session.
 ///
 /// Toggled by the user via the `r` keybinding in the file list panel.
@@ -1,9 +1,16 @@
 //! Integration test for Phase 4 DB lifecycle.
 //!
 //! Exercises: open_db, migrate, detect_or_create_session,
-//! load_file_review_state, toggle_file_reviewed, update_session_timestamp.
+//! load_file_review_state, toggle_file_reviewed, update_session_timestamp,
+//! insert_comment, load_comments, set_comment_filter, set_session_head,
+//! get_last_diff_mode, set_last_diff_mode, set_session_diff_only,
+//! set_session_context_lines,
+//! get_last_diff_range, set_last_diff_range, get_recent_diff_args,
+//! push_recent_diff_args, load_layout, save_layout, export_comments_markdown,
+//! set_comment_resolved.
 
 use airev_core::db;
+use airev_core::types::{Comment, CommentFilter, CommentType, SEVERITIES};
 
 fn temp_db_path() -> String {
     let dir = tempfile::TempDir::new().unwrap();
@@ -16,25 +23,23 @@ async fn full_session_lifecycle() {
     let path = temp_db_path();
     let conn = db::open_db(&path).await.unwrap();
 
-    // Verify schema_version = 1
+    // Verify schema_version = 9
     let version: i64 = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(db.query_row(
+            db.query_row(
                 "SELECT MAX(version) FROM schema_version",
                 [],
                 |r| r.get(0),
-            )?)
+            )
         })
         .await
         .unwrap();
-    assert_eq!(version, 1, "schema_version should be 1");
+    assert_eq!(version, 9, "schema_version should be 9");
 
     // Verify WAL mode
     let journal: String = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(
-                db.query_row("PRAGMA journal_mode", [], |r| r.get(0))?,
-            )
+            db.query_row("PRAGMA journal_mode", [], |r| r.get(0))
         })
         .await
         .unwrap();
@@ -43,9 +48,7 @@ async fn full_session_lifecycle() {
     // Verify threads table exists (empty)
     let thread_count: i64 = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(
-                db.query_row("SELECT COUNT(*) FROM threads", [], |r| r.get(0))?,
-            )
+            db.query_row("SELECT COUNT(*) FROM threads", [], |r| r.get(0))
         })
         .await
         .unwrap();
@@ -54,11 +57,11 @@ async fn full_session_lifecycle() {
     // Verify sessions table has TEXT primary key
     let session_pk_type: String = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(db.query_row(
+            db.query_row(
                 "SELECT type FROM pragma_table_info('sessions') WHERE name = 'id'",
                 [],
                 |r| r.get(0),
-            )?)
+            )
         })
         .await
         .unwrap();
@@ -67,11 +70,11 @@ async fn full_session_lifecycle() {
     // Verify file_review_state composite PK
     let frs_pk_count: i64 = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(db.query_row(
+            db.query_row(
                 "SELECT COUNT(*) FROM pragma_table_info('file_review_state') WHERE pk > 0",
                 [],
                 |r| r.get(0),
-            )?)
+            )
         })
         .await
         .unwrap();
@@ -106,9 +109,7 @@ async fn full_session_lifecycle() {
     // Session count should be 2
     let count: i64 = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(
-                db.query_row("SELECT COUNT(*) FROM sessions", [], |r| r.get(0))?,
-            )
+            db.query_row("SELECT COUNT(*) FROM sessions", [], |r| r.get(0))
         })
         .await
         .unwrap();
@@ -198,11 +199,438 @@ async fn migration_handles_legacy_db() {
     // The old data should be gone (dropped during migration)
     let count: i64 = conn
         .call(|db| {
-            Ok::<_, rusqlite::Error>(
-                db.query_row("SELECT COUNT(*) FROM sessions", [], |r| r.get(0))?,
-            )
+            db.query_row("SELECT COUNT(*) FROM sessions", [], |r| r.get(0))
         })
         .await
         .unwrap();
     assert_eq!(count, 1, "only the new session should exist");
 }
+
+#[tokio::test]
+async fn migration_keeps_v1_sessions_and_comments() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("v1.db").to_string_lossy().to_string();
+
+    // A populated database as the first versioned release left it.
+    {
+        let db = rusqlite::Connection::open(&path).unwrap();
+        db.execute_batch(airev_core::schema::SCHEMA_VERSION_DDL).unwrap();
+        db.execute_batch(airev_core::schema::SCHEMA_V1_SQL).unwrap();
+        db.execute_batch(
+            "INSERT INTO schema_version (version) VALUES (1);
+             INSERT INTO sessions (id, repo_path, diff_mode, diff_args, created_at, updated_at)
+                 VALUES ('s1', '/repo', 'staged', '', 10, 10);
+             INSERT INTO comments (id, session_id, file_path, line_number, comment_type,
+                                   severity, body, created_at)
+                 VALUES ('c1', 's1', 'src/lib.rs', 3, 'concern', 'major', 'kept', 11);
+             INSERT INTO file_review_state (session_id, file_path, reviewed)
+                 VALUES ('s1', 'src/lib.rs', 1);",
+        )
+        .unwrap();
+    }
+
+    let conn = db::open_db(&path).await.unwrap();
+    let version: i64 = conn
+        .call(|db| db.query_row("SELECT MAX(version) FROM schema_version", [], |r| r.get(0)))
+        .await
+        .unwrap();
+    assert_eq!(version, 9);
+
+    let session = db::detect_or_create_session(&conn, "/repo", "staged", "").await.unwrap();
+    assert_eq!(session.id, "s1", "the v1 session is resumed");
+    assert_eq!((session.comment_filter.as_str(), session.head_sha.as_str()), ("all", ""));
+    assert_eq!((session.diff_only, session.context_lines), (false, None));
+
+    let comments = db::load_comments(&conn, "s1").await.unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!((comments[0].id.as_str(), comments[0].body.as_str()), ("c1", "kept"));
+    let states = db::load_file_review_state(&conn, "s1").await.unwrap();
+    assert_eq!(states, vec![("src/lib.rs".to_owned(), true)]);
+}
+
+#[tokio::test]
+async fn open_db_reports_the_failing_step() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("missing").join("reviews.db");
+    let err = db::open_db(&path.to_string_lossy()).await.unwrap_err();
+    assert!(matches!(err, db::DbError::Open(_)), "got {err:?}");
+    assert!(err.to_string().starts_with("cannot open database: "));
+}
+
+#[tokio::test]
+async fn insert_comment_persists_severity_and_type() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+
+    let draft = Comment {
+        id: String::new(),
+        session_id: session.id.clone(),
+        file_path: "src/main.rs".to_owned(),
+        line_number: None,
+        hunk_offset: None,
+        comment_type: CommentType::Nitpick.as_str().to_owned(),
+        severity: SEVERITIES[0].to_owned(),
+        body: "rename this".to_owned(),
+        created_at: 0,
+        resolved_at: None,
+        thread_id: None,
+    };
+    let saved = db::insert_comment(&conn, draft).await.unwrap();
+    assert!(!saved.id.is_empty(), "insert should generate a UUID");
+    assert!(saved.created_at > 0, "insert should stamp created_at");
+
+    let (comment_type, severity): (String, String) = conn
+        .call(move |db| {
+            db.query_row(
+                "SELECT comment_type, severity FROM comments WHERE id = ?1",
+                [&saved.id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+        })
+        .await
+        .unwrap();
+    assert_eq!(comment_type, "nitpick");
+    assert_eq!(severity, "critical");
+
+    // CHECK constraint rejects unknown severities.
+    let bad = Comment {
+        id: String::new(),
+        session_id: session.id.clone(),
+        file_path: "src/main.rs".to_owned(),
+        line_number: None,
+        hunk_offset: None,
+        comment_type: "concern".to_owned(),
+        severity: "blocker".to_owned(),
+        body: "x".to_owned(),
+        created_at: 0,
+        resolved_at: None,
+        thread_id: None,
+    };
+    assert!(db::insert_comment(&conn, bad).await.is_err());
+}
+
+#[tokio::test]
+async fn hunk_level_comment_round_trips() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+
+    let draft = Comment {
+        id: String::new(),
+        session_id: session.id.clone(),
+        file_path: "src/lib.rs".to_owned(),
+        line_number: None,
+        hunk_offset: Some(12),
+        comment_type: CommentType::Question.as_str().to_owned(),
+        severity: "info".to_owned(),
+        body: "why split this hunk?".to_owned(),
+        created_at: 0,
+        resolved_at: None,
+        thread_id: None,
+    };
+    let saved = db::insert_comment(&conn, draft).await.unwrap();
+
+    let (file_path, line_number, hunk_offset): (String, Option<i64>, Option<i64>) = conn
+        .call(move |db| {
+            db.query_row(
+                "SELECT file_path, line_number, hunk_offset FROM comments WHERE id = ?1",
+                [&saved.id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+        })
+        .await
+        .unwrap();
+    assert_eq!(line_number, None, "hunk comments have no line");
+    assert_eq!(hunk_offset, Some(12));
+    assert_eq!(file_path, "src/lib.rs");
+}
+
+#[tokio::test]
+async fn comment_filter_persists_per_session() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(session.comment_filter, "all", "new sessions show every comment");
+
+    db::set_comment_filter(&conn, &session.id, CommentFilter::CriticalOnly.as_str())
+        .await
+        .unwrap();
+    let resumed = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(CommentFilter::parse(&resumed.comment_filter), Some(CommentFilter::CriticalOnly));
+
+    // Other sessions keep their own filter.
+    let other = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    assert_eq!(other.comment_filter, "all");
+
+    // CHECK constraint rejects unknown filters.
+    assert!(db::set_comment_filter(&conn, &session.id, "minor").await.is_err());
+}
+
+#[tokio::test]
+async fn diff_only_persists_per_session() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert!(!session.diff_only, "new sessions show every panel");
+
+    db::set_session_diff_only(&conn, &session.id, true).await.unwrap();
+    let resumed = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert!(resumed.diff_only);
+
+    let other = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    assert!(!other.diff_only);
+}
+
+#[tokio::test]
+async fn context_lines_persist_per_session() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(session.context_lines, None, "new sessions use the configured count");
+
+    db::set_session_context_lines(&conn, &session.id, 0).await.unwrap();
+    let resumed = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(resumed.context_lines, Some(0));
+    assert!(db::set_session_context_lines(&conn, &session.id, 16).await.is_err());
+}
+
+#[tokio::test]
+async fn session_head_is_recorded_and_resumed() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(session.head_sha, "", "new sessions have no recorded HEAD");
+
+    db::set_session_head(&conn, &session.id, "0123abcd").await.unwrap();
+    let resumed = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(resumed.head_sha, "0123abcd");
+}
+
+#[tokio::test]
+async fn last_diff_mode_is_kept_per_repo() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    assert_eq!(db::get_last_diff_mode(&conn, "/tmp/test-repo").await.unwrap(), None);
+
+    db::set_last_diff_mode(&conn, "/tmp/test-repo", "staged").await.unwrap();
+    db::set_last_diff_mode(&conn, "/tmp/test-repo", "branch").await.unwrap();
+    db::set_last_diff_mode(&conn, "/tmp/other-repo", "unstaged").await.unwrap();
+
+    let mode = db::get_last_diff_mode(&conn, "/tmp/test-repo").await.unwrap();
+    assert_eq!(mode.as_deref(), Some("branch"));
+    let other = db::get_last_diff_mode(&conn, "/tmp/other-repo").await.unwrap();
+    assert_eq!(other.as_deref(), Some("unstaged"));
+}
+
+#[tokio::test]
+async fn last_diff_range_is_cleared_by_a_plain_mode() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    db::set_last_diff_range(&conn, "/tmp/test-repo", "v1.0..HEAD").await.unwrap();
+
+    let mode = db::get_last_diff_mode(&conn, "/tmp/test-repo").await.unwrap();
+    assert_eq!(mode.as_deref(), Some("range"));
+    let range = db::get_last_diff_range(&conn, "/tmp/test-repo").await.unwrap();
+    assert_eq!(range.as_deref(), Some("v1.0..HEAD"));
+
+    db::set_last_diff_mode(&conn, "/tmp/test-repo", "staged").await.unwrap();
+    assert_eq!(db::get_last_diff_range(&conn, "/tmp/test-repo").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn recent_diff_args_are_most_recent_first_and_capped() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    for i in 0..12 {
+        db::push_recent_diff_args(&conn, "/tmp/test-repo", &format!("v{i}..HEAD")).await.unwrap();
+    }
+    db::push_recent_diff_args(&conn, "/tmp/test-repo", "v5..HEAD").await.unwrap();
+    db::push_recent_diff_args(&conn, "/tmp/other-repo", "main..HEAD").await.unwrap();
+
+    let recent = db::get_recent_diff_args(&conn, "/tmp/test-repo").await.unwrap();
+    assert_eq!(recent.len(), db::RECENT_DIFF_ARGS_MAX);
+    assert_eq!(recent[..3], ["v5..HEAD", "v11..HEAD", "v10..HEAD"]);
+    assert!(!recent.iter().any(|r| r == "v0..HEAD" || r == "v1..HEAD"));
+
+    let other = db::get_recent_diff_args(&conn, "/tmp/other-repo").await.unwrap();
+    assert_eq!(other, ["main..HEAD"]);
+}
+
+#[tokio::test]
+async fn layout_is_saved_per_session() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    let other = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+    assert_eq!(db::load_layout(&conn, &session.id).await.unwrap(), None);
+
+    db::save_layout(&conn, &session.id, [30, 45, 25]).await.unwrap();
+    db::save_layout(&conn, &session.id, [25, 50, 25]).await.unwrap();
+    assert_eq!(db::load_layout(&conn, &session.id).await.unwrap(), Some([25, 50, 25]));
+    assert_eq!(db::load_layout(&conn, &other.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn batch_set_reviewed_marks_every_path() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    db::toggle_file_reviewed(&conn, &session.id, "src/a.rs").await.unwrap();
+
+    let paths = vec!["src/a.rs".to_owned(), "src/b.rs".to_owned()];
+    let written = db::batch_set_reviewed(&conn, &session.id, paths, true).await.unwrap();
+    assert_eq!(written, 2);
+
+    let mut states = db::load_file_review_state(&conn, &session.id).await.unwrap();
+    states.sort();
+    assert_eq!(
+        states,
+        vec![("src/a.rs".to_owned(), true), ("src/b.rs".to_owned(), true)]
+    );
+}
+
+#[tokio::test]
+async fn load_comments_orders_by_file_then_line() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "staged", "")
+        .await
+        .unwrap();
+
+    let anchors = [("src/b.rs", Some(3)), ("src/a.rs", Some(9)), ("src/a.rs", None)];
+    for (file_path, line_number) in anchors {
+        let draft = Comment {
+            id: String::new(),
+            session_id: session.id.clone(),
+            file_path: file_path.to_owned(),
+            line_number,
+            hunk_offset: None,
+            comment_type: "concern".to_owned(),
+            severity: "minor".to_owned(),
+            body: format!("{file_path}:{line_number:?}"),
+            created_at: 0,
+            resolved_at: None,
+            thread_id: None,
+        };
+        db::insert_comment(&conn, draft).await.unwrap();
+    }
+
+    let loaded = db::load_comments(&conn, &session.id).await.unwrap();
+    let order: Vec<(&str, Option<i64>)> =
+        loaded.iter().map(|c| (c.file_path.as_str(), c.line_number)).collect();
+    assert_eq!(order, vec![("src/a.rs", None), ("src/a.rs", Some(9)), ("src/b.rs", Some(3))]);
+
+    // Other sessions see none of them.
+    let other = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    assert!(db::load_comments(&conn, &other.id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn comments_export_as_markdown_grouped_by_file() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    assert_eq!(
+        db::export_comments_markdown(&conn, &session.id).await.unwrap(),
+        "# Review comments\n\nNo comments.\n"
+    );
+
+    let comment = |file_path: &str, line_number, severity: &str, body: &str| Comment {
+        id: String::new(),
+        session_id: session.id.clone(),
+        file_path: file_path.to_owned(),
+        line_number,
+        hunk_offset: None,
+        comment_type: CommentType::Concern.as_str().to_owned(),
+        severity: severity.to_owned(),
+        body: body.to_owned(),
+        created_at: 0,
+        resolved_at: None,
+        thread_id: None,
+    };
+    db::insert_comment(&conn, comment("src/b.rs", Some(40), "minor", "two\nlines"))
+        .await
+        .unwrap();
+    db::insert_comment(&conn, comment("src/a.rs", Some(7), "major", "off by one"))
+        .await
+        .unwrap();
+    let resolved = Comment { resolved_at: Some(1), ..comment("src/a.rs", None, "info", "ok") };
+    db::insert_comment(&conn, resolved).await.unwrap();
+
+    let report = db::export_comments_markdown(&conn, &session.id).await.unwrap();
+    assert_eq!(
+        report,
+        "# Review comments\n\
+         \n## src/a.rs\n\n\
+         - **[INFO] concern** (file, resolved): ok\n\
+         - **[MAJOR] concern** (line 7): off by one\n\
+         \n## src/b.rs\n\n\
+         - **[MINOR] concern** (line 40): two\n  lines\n"
+    );
+}
+
+#[tokio::test]
+async fn comment_resolution_is_set_and_cleared() {
+    let path = temp_db_path();
+    let conn = db::open_db(&path).await.unwrap();
+    let session = db::detect_or_create_session(&conn, "/tmp/test-repo", "unstaged", "")
+        .await
+        .unwrap();
+    let draft = Comment {
+        id: String::new(),
+        session_id: session.id.clone(),
+        file_path: "src/main.rs".to_owned(),
+        line_number: Some(3),
+        hunk_offset: None,
+        comment_type: CommentType::Concern.as_str().to_owned(),
+        severity: "minor".to_owned(),
+        body: "unwrap".to_owned(),
+        created_at: 0,
+        resolved_at: None,
+        thread_id: None,
+    };
+    let saved = db::insert_comment(&conn, draft).await.unwrap();
+
+    db::set_comment_resolved(&conn, &saved.id, true).await.unwrap();
+    let loaded = db::load_comments(&conn, &session.id).await.unwrap();
+    assert!(loaded[0].resolved_at.is_some_and(|t| t >= saved.created_at));
+
+    db::set_comment_resolved(&conn, &saved.id, false).await.unwrap();
+    let loaded = db::load_comments(&conn, &session.id).await.unwrap();
+    assert_eq!(loaded[0].resolved_at, None);
+}
@@ -0,0 +1,143 @@
+//! Tests for highlighting unified diff text without a repository.
+
+use airev_core::highlight::{
+    highlight_unified_diff, highlight_unified_diff_with_sources, syntect_theme_names, FileSources,
+    WordDiff, SYNTECT_THEME,
+};
+use ratatui::style::{Color, Modifier};
+use ratatui::text::Line;
+
+const PATCH: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@ fn main
+ fn main() {
+-    let x = 1;
++    let x = 2;
+ }
+@@ -10,2 +10,3 @@
+ // tail
++// added
+\\ No newline at end of file
+";
+
+fn text(line: &Line<'_>) -> String {
+    line.spans.iter().map(|s| s.content.as_ref()).collect()
+}
+
+#[test]
+fn skips_file_headers_and_records_hunk_offsets() {
+    let (lines, offsets) = highlight_unified_diff(PATCH, "rs", WordDiff::Word, SYNTECT_THEME);
+
+    assert_eq!(offsets, vec![0, 5]);
+    assert_eq!(text(&lines[0]), "@@ -1,3 +1,3 @@ fn main");
+    assert_eq!(text(&lines[5]), "@@ -10,2 +10,3 @@");
+    assert_eq!(lines.len(), 9);
+}
+
+#[test]
+fn strips_origin_once_and_keeps_indentation() {
+    let (lines, _) = highlight_unified_diff(PATCH, "rs", WordDiff::Word, SYNTECT_THEME);
+
+    assert_eq!(text(&lines[1]), "  fn main() {");
+    assert_eq!(text(&lines[2]), "-     let x = 1;");
+    assert_eq!(text(&lines[3]), "+     let x = 2;");
+    assert_eq!(text(&lines[7]), "+ // added");
+    assert_eq!(text(&lines[8]), "\\ No newline at end of file");
+}
+
+#[test]
+fn empty_input_yields_nothing() {
+    let (lines, offsets) = highlight_unified_diff("", "rs", WordDiff::Word, SYNTECT_THEME);
+    assert!(lines.is_empty());
+    assert!(offsets.is_empty());
+}
+
+const PAIR: &str = "\
+@@ -1 +1 @@
+-let total = 10;
++let total = 12;
+";
+
+/// Bold (emphasized) span texts of a line, skipping the origin prefix.
+fn emphasized(line: &Line<'_>) -> Vec<String> {
+    line.spans[1..]
+        .iter()
+        .filter(|s| s.style.add_modifier.contains(Modifier::BOLD))
+        .map(|s| s.content.to_string())
+        .collect()
+}
+
+#[test]
+fn word_mode_emphasizes_changed_words() {
+    let (lines, _) = highlight_unified_diff(PAIR, "txt", WordDiff::Word, SYNTECT_THEME);
+    assert_eq!(emphasized(&lines[1]), vec!["10;"]);
+    assert_eq!(emphasized(&lines[2]), vec!["12;"]);
+}
+
+#[test]
+fn char_mode_emphasizes_changed_characters() {
+    let (lines, _) = highlight_unified_diff(PAIR, "txt", WordDiff::Char, SYNTECT_THEME);
+    assert_eq!(emphasized(&lines[1]), vec!["0"]);
+    assert_eq!(emphasized(&lines[2]), vec!["2"]);
+}
+
+#[test]
+fn off_mode_keeps_whole_lines_unemphasized() {
+    let (lines, _) = highlight_unified_diff(PAIR, "txt", WordDiff::Off, SYNTECT_THEME);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(text(&lines[1]), "- let total = 10;");
+    assert_eq!(text(&lines[2]), "+ let total = 12;");
+    assert!(emphasized(&lines[1]).is_empty());
+    assert!(emphasized(&lines[2]).is_empty());
+}
+
+#[test]
+fn syntect_theme_is_picked_by_name_with_default_fallback() {
+    assert!(syntect_theme_names().contains(&SYNTECT_THEME));
+    assert!(syntect_theme_names().contains(&"InspiredGitHub"));
+    let colors = |theme: &str| {
+        let (lines, _) = highlight_unified_diff(PATCH, "rs", WordDiff::Off, theme);
+        lines[1].spans.iter().map(|s| s.style.fg).collect::<Vec<_>>()
+    };
+
+    assert_ne!(colors("InspiredGitHub"), colors(SYNTECT_THEME));
+    assert_eq!(colors("no-such-theme"), colors(SYNTECT_THEME));
+}
+
+/// A hunk inside a block comment that opens two lines above it.
+const IN_COMMENT: &str = "\
+@@ -3,2 +3,2 @@
+ let x = 1;
+-let y = 2;
++let y = 3;
+";
+
+#[test]
+fn full_file_sources_carry_syntax_state_into_the_hunk() {
+    let old = "/*\nnotes\nlet x = 1;\nlet y = 2;\n*/\n";
+    let new = "/*\nnotes\nlet x = 1;\nlet y = 3;\n*/\n";
+    let colors = |sources: FileSources<'_>| -> Vec<Vec<Option<Color>>> {
+        let (lines, _) = highlight_unified_diff_with_sources(
+            IN_COMMENT,
+            "rs",
+            WordDiff::Off,
+            SYNTECT_THEME,
+            sources,
+        );
+        assert_eq!(text(&lines[1]), "  let x = 1;");
+        assert_eq!(text(&lines[3]), "+ let y = 3;");
+        lines[1..].iter().map(|l| l.spans[1..].iter().map(|s| s.style.fg).collect()).collect()
+    };
+    let hunk_only = colors(FileSources::default());
+    let full = colors(FileSources { old: Some(old), new: Some(new) });
+
+    assert_ne!(full, hunk_only);
+    // Inside the comment every span has the comment color.
+    assert!(full.iter().flatten().all(|fg| *fg == full[0][0]));
+    // A source that does not match the diff is ignored.
+    let stale = colors(FileSources { old: Some("a\nb\nc\nd\n"), new: Some("a\nb\nc\nd\n") });
+    assert_eq!(stale, hunk_only);
+}
@@ -5,14 +5,24 @@
 //! unsaved-comment guard flag. No ratatui rendering logic lives here — `app.rs` is
 //! pure state that is read by the render module and mutated by the keybinding dispatcher.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::git::types::{DiffMode, FileSummary, GitRequest};
+use airev_core::types::{Comment, CommentFilter, CommentType, Severity};
+
+use crate::git::types::{
+    hunk_key, BlameTag, DiffMode, DiffStatus, DiffTiming, FileSummary, GitRequest, LineRef,
+    MAX_CONTEXT_LINES,
+};
+use crate::config::Config;
+use crate::keymap::KeyMap;
+use crate::theme::{Theme, BUILTIN_THEMES};
+use crate::ui::layout::{LayoutOrientation, StatusSegment};
 
 /// Editor mode controlling which keybinding set is active.
 ///
@@ -26,8 +36,45 @@ pub enum Mode {
     Insert,
     /// Full-screen help overlay is shown above all panels.
     HelpOverlay,
+    /// Debug overlay with the git worker's timing (`F12`); any key closes it.
+    DebugOverlay,
     /// Quit-confirmation dialog shown when unsaved comments exist.
     ConfirmQuit,
+    /// Severity/type quick-pick overlay shown after a comment body is typed.
+    CommentTag,
+    /// Startup error overlay shown when no git repository or review database
+    /// could be opened.
+    StartupError,
+    /// A `:` command is being typed in the status bar.
+    Command,
+    /// The diff-mode picker (`m`) is open, possibly prompting for refs.
+    DiffModePicker,
+    /// A `/` search of the diff is being typed in the status bar.
+    Search,
+    /// A filter of the file list (`f` in the file list) is being typed.
+    FileFilter,
+}
+
+/// Side of a `-`/`+` line pair kept readable by the `o` toggle; the other side
+/// is dimmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairSide {
+    /// Keep the removed (`-`) lines.
+    Old,
+    /// Keep the added (`+`) lines.
+    New,
+}
+
+/// A startup failure airev can continue past, shown in `Mode::StartupError`.
+/// Also used for a config file that fails to reload on `SIGHUP`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupError {
+    /// Overlay title, e.g. `Cannot open repository`.
+    pub title: String,
+    /// What failed and what to do about it; may span several lines.
+    pub message: String,
+    /// What continuing means, shown next to the `Enter / c` key.
+    pub continue_label: &'static str,
 }
 
 /// Which panel currently has keyboard focus.
@@ -69,6 +116,118 @@ impl PanelFocus {
     }
 }
 
+/// An entry of the diff-mode picker (`m`).
+///
+/// Range, commit, and stash all load as `DiffMode::CommitRange`: a commit `C`
+/// is the range `C^..C`, and a stash is a commit whose first parent is the
+/// `HEAD` it was made on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModePick {
+    /// Working tree vs index.
+    Unstaged,
+    /// Index vs `HEAD`.
+    Staged,
+    /// `HEAD` vs the main branch.
+    Branch,
+    /// `FROM..TO`, typed in the picker.
+    Range,
+    /// One commit, typed in the picker.
+    Commit,
+    /// One stash entry, typed in the picker.
+    Stash,
+}
+
+impl ModePick {
+    /// All entries, in picker order.
+    pub const ALL: [ModePick; 6] = [
+        ModePick::Unstaged,
+        ModePick::Staged,
+        ModePick::Branch,
+        ModePick::Range,
+        ModePick::Commit,
+        ModePick::Stash,
+    ];
+
+    /// Name and description shown in the picker.
+    pub fn label(self) -> (&'static str, &'static str) {
+        match self {
+            ModePick::Unstaged => ("Unstaged", "working tree vs index"),
+            ModePick::Staged => ("Staged", "index vs HEAD"),
+            ModePick::Branch => ("Branch", "HEAD vs base branch"),
+            ModePick::Range => ("Range", "FROM..TO"),
+            ModePick::Commit => ("Commit", "one commit"),
+            ModePick::Stash => ("Stash", "one stash entry"),
+        }
+    }
+
+    /// The mode loaded directly, or `None` for the entries that prompt for refs.
+    pub fn diff_mode(self) -> Option<DiffMode> {
+        match self {
+            ModePick::Unstaged => Some(DiffMode::Unstaged),
+            ModePick::Staged => Some(DiffMode::Staged),
+            ModePick::Branch => Some(DiffMode::BranchComparison),
+            ModePick::Range | ModePick::Commit | ModePick::Stash => None,
+        }
+    }
+
+    /// The entry a `from..to` range is shown under: `C^..C` is a commit, or a
+    /// stash when `C` names one; anything else is a range.
+    pub fn of_range(from: &str, to: &str) -> ModePick {
+        match from.strip_suffix('^') {
+            Some(parent) if parent == to && to.starts_with("stash") => ModePick::Stash,
+            Some(parent) if parent == to => ModePick::Commit,
+            _ => ModePick::Range,
+        }
+    }
+
+    /// The ref prompt text for `from..to`: the range itself, or the `C` of a
+    /// commit or stash.
+    fn prompt_refs(self, from: &str, to: &str) -> String {
+        match self {
+            ModePick::Range => format!("{from}..{to}"),
+            _ => to.to_owned(),
+        }
+    }
+
+    /// Text the ref prompt starts with.
+    fn default_refs(self) -> &'static str {
+        match self {
+            ModePick::Commit => "HEAD",
+            ModePick::Stash => "stash@{0}",
+            _ => "",
+        }
+    }
+}
+
+/// Default severity index for new comments (`minor` in `SEVERITIES`).
+pub const DEFAULT_SEVERITY: usize = 2;
+
+/// How long a notification stays visible.
+const NOTIFICATION_DURATION: Duration = Duration::from_secs(3);
+
+/// Most notifications kept at once; pushing another drops the oldest.
+const MAX_NOTIFICATIONS: usize = 4;
+
+/// Highlighting slower than this shows a warning toast when the diff arrives.
+pub const SLOW_HIGHLIGHT_MS: u64 = 500;
+
+/// Consecutive failed diff requests before the repository is reported unavailable.
+/// Fewer failures are treated as transient (e.g. a briefly held `index.lock`).
+const REPO_UNAVAILABLE_AFTER: u32 = 3;
+
+/// Percentage points moved from the (left, right) panels into the diff panel by
+/// one `>` press. `<` moves exactly the same amounts back, so the two are inverses.
+const RESIZE_STEP: (u16, u16) = (2, 3);
+/// Narrowest the diff panel may be, in percent.
+const MIN_CENTER_PCT: u16 = 20;
+/// Widest the diff panel may be grown to with `>`, in percent.
+const MAX_CENTER_PCT: u16 = 80;
+/// Narrowest either side panel may be, in percent.
+const MIN_SIDE_PCT: u16 = 5;
+/// Largest count a motion can be given (`9999j`); more digits are ignored.
+/// Small enough to scroll by as a `u16` row count.
+const MAX_COUNT: usize = 9999;
+
 /// All mutable UI state passed through every render cycle.
 ///
 /// Scroll state, focus, mode, and panel geometry are bundled here so the render
@@ -80,12 +239,24 @@ pub struct AppState {
     /// Which panel currently receives keyboard scroll/navigation events.
     pub focus: PanelFocus,
 
-    /// Stateful list widget backing the file-list panel (left).
+    /// Stateful list widget backing the file-list panel (left). Its selection and
+    /// offset are rows of the list, which differ from `file_summaries` indices
+    /// while a filter is set (see [`AppState::file_at_row`]).
     pub file_list_state: ListState,
+    /// Query typed after `f` in the file list. The list shows only the files whose
+    /// path contains its characters in order, ignoring case; empty shows them all.
+    pub file_filter: String,
+    /// Indices into `file_summaries` of the files matching `file_filter`, in
+    /// order. Unused while the filter is empty.
+    pub filtered_indices: Vec<usize>,
 
     /// Vertical scroll offset for the diff panel (centre panel).
     /// usize supports >65535 line diffs; clamped by the renderer to visible range.
     pub diff_scroll: usize,
+    /// Side shown by the `o` pair toggle, with the `diff_scroll` and `diff_cursor`
+    /// it was set at. Any scroll or cursor move makes it lapse (see
+    /// [`AppState::active_pair_view`]).
+    pub pair_view: Option<(PairSide, usize, usize)>,
     /// Vertical scroll offset for the comments `Paragraph` widget (right panel).
     pub comments_scroll: u16,
     /// Vertical scroll offset for the help overlay Paragraph. Reset to 0 when entering help mode.
@@ -105,6 +276,91 @@ pub struct AppState {
     pub center_pct: u16,
     /// Width percentage allocated to the right (comments) panel. Default: 25.
     pub right_pct: u16,
+    /// Text typed after `:` while in `Mode::Command`.
+    pub command_line: String,
+    /// Text typed after `/` while in `Mode::Search`, kept afterwards for `n` / `N`.
+    pub search_query: String,
+    /// Highlighted row of the diff-mode picker, an index into `ModePick::ALL`.
+    pub mode_pick: usize,
+    /// Refs being typed for the highlighted picker entry; `None` while choosing.
+    pub mode_pick_refs: Option<String>,
+    /// `(from, to)` of the `CommitRange` diff picked with `m`, re-sent on refresh.
+    pub diff_range: Option<(String, String)>,
+    /// Refs submitted at the picker prompt, waiting for the worker to resolve them.
+    pub pending_range: Option<(String, String)>,
+    /// Why the refs last submitted at the picker prompt were rejected, shown
+    /// under the prompt until they are edited.
+    pub mode_pick_error: Option<String>,
+    /// Branch and tag names offered at the picker prompt, listed by the worker
+    /// the first time the prompt opens. `Some` but empty while the list is on
+    /// its way; kept for the rest of the run, since refs rarely change mid-review.
+    pub ref_names: Option<Vec<String>>,
+    /// While `Tab` cycles through ambiguous completions: the prefix that was
+    /// typed and the index of the completion filled in. Cleared by any edit.
+    pub ref_tab: Option<(String, usize)>,
+    /// Ranges recently shown for the repository, most recent first, capped at
+    /// `RECENT_DIFF_ARGS_MAX`. Loaded with the session and saved to the DB.
+    pub recent_ranges: Vec<(String, String)>,
+    /// While `Up` / `Down` step through `recent_ranges` at the picker prompt:
+    /// the index into [`AppState::ref_history`] shown and the text typed before.
+    pub ref_history_pos: Option<(usize, String)>,
+    /// Active color theme, read by every renderer in `ui/`. Replaced live by `:theme`
+    /// and `T`.
+    pub theme: Theme,
+    /// Canonical name of `theme` (one of `BUILTIN_THEMES` or a custom theme), or
+    /// `None` when it was picked from the terminal background.
+    pub theme_name: Option<String>,
+    /// Custom themes from the config's `[themes.NAME]` tables, offered by
+    /// `:theme` and `T` after the built-ins.
+    pub custom_themes: Vec<(String, Theme)>,
+    /// Set once `:theme` or `T` changes the theme; the choice is then written
+    /// back to the config file on quit.
+    pub theme_changed: bool,
+    /// Whether the terminal shows 24-bit color (`COLORTERM`, or config
+    /// `force_truecolor`); without it themes pass through `Theme::for_terminal`.
+    pub truecolor: bool,
+    /// Cap on the diff panel's content width in the 3-panel layout (config
+    /// `max_diff_width`). 0 means unlimited.
+    pub max_diff_width: u16,
+    /// Editor command template for `e` (config `editor_command`); `None` uses
+    /// `$VISUAL` / `$EDITOR`. See `editor`.
+    pub editor_command: Option<String>,
+    /// `--editor-command`, which wins over `editor_command` and survives reloads.
+    pub editor_override: Option<String>,
+    /// Suspend the TUI while a template editor runs (config `editor_suspend`).
+    pub editor_suspend: bool,
+    /// Web URL of the `origin` remote when it is on GitHub, for permalinks (`Y`).
+    pub web_url: Option<String>,
+    /// Print [`AppState::quit_summary`] after quitting (config `quit_summary`).
+    pub show_quit_summary: bool,
+    /// Shade the focused panel's current row with `Theme::cursor_line` (config
+    /// `cursor_line`).
+    pub cursor_line: bool,
+    /// Notes reference `:note` writes to (config `note_ref`).
+    pub note_ref: String,
+    /// `:note` replaces a note already on `HEAD` (config `note_replace`).
+    pub note_replace: bool,
+    /// Normal-mode key bindings (config `[keybindings]`).
+    pub keymap: KeyMap,
+    /// File (and optional new-file line) from `--file` / `--line`, jumped to once
+    /// the session and the first diff have loaded (see
+    /// [`AppState::apply_pending_jump`]).
+    pub pending_jump: Option<(String, Option<u32>)>,
+    /// Hide both side panels regardless of terminal width (`--diff-only` / `F`).
+    /// Saved per session; focus stays on the diff while it is set.
+    pub diff_only: bool,
+    /// Panel orientation forced by config `layout`; `None` picks it from the
+    /// terminal shape on every frame. The percentages above become heights when
+    /// the panels are stacked vertically.
+    pub layout_orientation: Option<LayoutOrientation>,
+    /// Status bar segments in display order (config `status_segments`).
+    pub status_segments: Vec<StatusSegment>,
+
+    /// Rows scrolled per mouse-wheel notch (config `scroll_lines`, default 3).
+    /// Keyboard scroll amounts are independent of this setting.
+    pub wheel_scroll_lines: u16,
+    /// Inverts the mouse-wheel direction (config `natural_scroll`).
+    pub natural_scroll: bool,
 
     /// Set to `true` when the user has typed a comment that has not been saved.
     /// Guards the quit path — if `true`, a confirmation dialog is shown first.
@@ -116,30 +372,114 @@ pub struct AppState {
     pub diff_lines: Vec<ratatui::text::Line<'static>>,
     /// File summaries from the most recent git diff (for the file-list panel).
     pub file_summaries: Vec<FileSummary>,
+    /// Lines `(added, removed)` across `file_summaries`, summed by
+    /// [`AppState::apply_git_result`] for the status bar's `files` segment.
+    pub diff_totals: (usize, usize),
     /// Currently active diff mode (Unstaged by default).
     pub diff_mode: DiffMode,
     /// True while the background thread is computing a diff (shows spinner in status bar).
     pub diff_loading: bool,
+    /// Outcome of the last diff request; selects the empty-panel message.
+    pub diff_status: DiffStatus,
     /// Line indices of @@ hunk header lines within diff_lines (for [/] hunk navigation).
     pub hunk_offsets: Vec<usize>,
     /// Index of the currently selected file in file_summaries (for file-list → diff jump).
     pub selected_file_index: usize,
     /// Current hunk offset cursor index (index into hunk_offsets, not line number).
     pub hunk_cursor: usize,
+    /// Index into `diff_lines` of the cursor line (set by click, file jump, and
+    /// hunk navigation). Rendered highlighted in the diff panel.
+    pub diff_cursor: usize,
     /// Per-file starting line offsets in diff_lines, for file-list jump navigation.
     ///
     /// `file_line_offsets[i]` is the line index in `diff_lines` where file `i`'s
     /// first hunk header appears. Populated from `GitResultPayload.file_line_offsets`.
     pub file_line_offsets: Vec<usize>,
+    /// Source file, hunk, and line numbers of each line, parallel to `diff_lines`.
+    pub line_refs: Vec<LineRef>,
+    /// `(added, removed)` line counts per hunk, indexed like `hunk_offsets`.
+    /// Derived from `line_refs` and shown after each hunk header.
+    pub hunk_line_counts: Vec<(usize, usize)>,
+    /// [`hunk_key`] of each hunk, indexed like `hunk_offsets`. Lets a refresh find
+    /// the hunk under the cursor again after files or hunks move.
+    pub hunk_keys: Vec<String>,
+    /// Body rows of the hunks folded to their header (`za`, or Enter on a header),
+    /// by hunk index: the lines and their refs, taken out of `diff_lines` and
+    /// `line_refs` until the hunk is unfolded. A refresh folds the hunks listed in
+    /// `folded_hunks` again.
+    pub collapsed_hunks: HashMap<usize, (Vec<ratatui::text::Line<'static>>, Vec<LineRef>)>,
+    /// [`hunk_key`]s of the folded hunks, by file path. Outlives the hunk indices
+    /// in `collapsed_hunks`, so a file that drops out of the diff and comes back
+    /// gets its folds back. A generated file's hunks are all added the first time
+    /// it appears (see [`AppState::fold_new_generated_files`]). Cleared when the
+    /// diff mode changes.
+    pub folded_hunks: HashMap<String, HashSet<String>>,
+    /// Whether the line-number gutter (`#`) is shown in the diff panel.
+    pub show_line_numbers: bool,
+    /// Digits in the largest line number of the diff, so the gutter lines up
+    /// across files. Derived from `line_refs`.
+    pub line_number_width: usize,
+    /// Whether whitespace changes are left out of the diff (`w`), shown as `[ws]`
+    /// in the status bar. Mirrored in the worker by `GitRequest::SetIgnoreWhitespace`.
+    pub ignore_whitespace: bool,
+    /// Unchanged lines shown around each change (`-` / `+`, config
+    /// `context_lines`). Mirrored in the worker by `GitRequest::SetContextLines`.
+    pub context_lines: u32,
+    /// Branch a branch comparison is taken against (config `base_branch`), shown in
+    /// the status bar. Replaced by the base the worker actually resolved, which
+    /// may be a fallback.
+    pub base_branch: String,
+    /// Whether the blame column (`a`) is shown in the diff panel.
+    pub blame_visible: bool,
+    /// Blame of old-side lines, by path and then old line number. `None` while
+    /// the worker is blaming the line, or when git could not. Cleared with every
+    /// new diff, since its old side may have changed.
+    pub blame: HashMap<String, HashMap<u32, Option<BlameTag>>>,
+    /// Worker timing for the current diff, shown in the debug overlay (`F12`).
+    pub diff_timing: DiffTiming,
 
     /// Channel sender to the git background thread, for keybinding-driven requests.
     ///
     /// `None` when no git repository was detected at startup (graceful no-repo mode).
     pub git_tx: Option<Sender<GitRequest>>,
+    /// Set when the git worker thread has stopped (panicked or exited); carries
+    /// the reason. Cleared when the worker is restarted with `R`.
+    pub git_worker_error: Option<String>,
+    /// Banner text shown when HEAD moved since the session started; `x` clears it
+    /// and makes the current HEAD the session's.
+    pub drift_warning: Option<String>,
+    /// Number of diff requests in a row that came back as `DiffStatus::Error`.
+    /// Reset by any successful result.
+    pub git_failures: u32,
+    /// Persistent banner text set once `git_failures` reaches
+    /// `REPO_UNAVAILABLE_AFTER`. Pauses auto-refresh until a diff loads again.
+    pub repo_unavailable: Option<String>,
+    /// Startup failures still to be shown by the `Mode::StartupError` overlay,
+    /// first one on screen. Each `Enter` dismisses one.
+    pub startup_errors: Vec<StartupError>,
+    /// Generation of the live git worker. Results from any other generation come
+    /// from a replaced worker and are dropped by `apply_git_result`.
+    pub git_generation: u64,
+    /// Seconds between automatic diff refreshes (config `auto_refresh_secs`).
+    /// 0 disables auto-refresh.
+    pub auto_refresh_secs: u64,
+    /// When the last diff request was sent to the worker. The auto-refresh
+    /// interval counts from here, so any refresh postpones the next one.
+    pub last_diff_request: Option<Instant>,
+    /// Set while a `refresh_diff` request is in flight; its result shows a toast.
+    pub refresh_pending: bool,
+    /// Transient messages ("comment saved", errors), oldest first, each with the
+    /// time it was pushed. The newest is shown in the status bar and the others
+    /// stack in a box above it; entries expire on the Tick after
+    /// `NOTIFICATION_DURATION`.
+    pub notifications: VecDeque<(String, Instant)>,
 
     /// Panel Rects [left, center, right] cached after compute_layout for mouse hit-testing.
     /// Updated every render frame.
     pub panel_rects: [Rect; 3],
+    /// Time and screen position `(column, row)` of the previous left click, used
+    /// to detect double-clicks in the file list.
+    pub last_click: Option<(Instant, u16, u16)>,
 
     // Phase 4: Persistence Layer fields
 
@@ -148,6 +488,12 @@ pub struct AppState {
     /// `None` if the database failed to open at startup. Clone is cheap
     /// (just an Arc increment) — clone into tokio::spawn closures freely.
     pub db_conn: Option<tokio_rusqlite::Connection>,
+    /// Set while the startup session load is in flight. Normal-mode keys other
+    /// than quit and help are held back until it completes.
+    pub session_loading: bool,
+    /// Milliseconds from process start to the first drawn frame, shown in the
+    /// debug overlay (`F12`).
+    pub first_frame_ms: Option<u64>,
 
     /// The current review session loaded/created at startup.
     ///
@@ -167,6 +513,56 @@ pub struct AppState {
     /// Follows the same pattern as `git_tx` — stored in AppState so
     /// keybindings.rs can access it without extra parameters to handle_key().
     pub event_tx: Option<UnboundedSender<crate::event::AppEvent>>,
+
+    // Phase 5: Comment fields
+
+    /// Body text of the comment currently being composed in Insert mode.
+    pub comment_draft: String,
+    /// Index into `airev_core::types::SEVERITIES` chosen in the tag picker.
+    /// Defaults to `minor` (index 2).
+    pub draft_severity: usize,
+    /// Comment type chosen in the tag picker. Defaults to `concern`.
+    pub draft_type: CommentType,
+    /// File path and hunk offset when the draft is a hunk-level comment; `None`
+    /// for a comment on the whole selected file. See [`AppState::cursor_hunk_anchor`].
+    pub draft_hunk: Option<(String, i64)>,
+    /// File path and line number when the draft is a line comment (`i`); takes
+    /// precedence over `draft_hunk`. See [`AppState::cursor_line_anchor`].
+    pub draft_line: Option<(String, i64)>,
+    /// Set while the drafted comment's insert is in flight. The draft is kept
+    /// until `CommentSaved` arrives, so a failed save loses nothing.
+    pub comment_saving: bool,
+    /// Error from the last failed save, shown in the composer until the draft
+    /// is saved or discarded.
+    pub comment_error: Option<String>,
+    /// Comments persisted for the current session, appended as saves complete.
+    pub comments: Vec<Comment>,
+    /// Index into `comments` of the selected comment (j / k in the comments
+    /// panel, or a click).
+    pub selected_comment: usize,
+    /// Set when the selection changes; the comments renderer then scrolls the
+    /// selection into view and clears the flag. Manual scrolling never sets it,
+    /// so the user can scroll away from the selection freely.
+    pub comments_follow_selection: bool,
+    /// `(first rendered row, index into comments)` for each comment drawn in the
+    /// wrapped comments panel, cached by the renderer for scroll-to-selection and
+    /// click hit-testing. Comments hidden by `comment_filter` have no entry.
+    pub comment_row_offsets: Vec<(usize, usize)>,
+    /// Severity filter for the comments panel and its `j` / `k` selection (`f`
+    /// cycles it). Loaded from and saved to the current session.
+    pub comment_filter: CommentFilter,
+    /// Hide resolved comments from the comments panel and its selection (`X`).
+    pub hide_resolved: bool,
+    /// Show only comments of this exact severity (`!` cycles it), on top of
+    /// `comment_filter`. Their lines are also marked in the diff. Not saved.
+    pub severity_filter: Option<Severity>,
+
+    /// First key of a pending two-key Normal-mode sequence (`g` in `gg`, `z` in
+    /// `za`).
+    pub pending_key: Option<char>,
+    /// Count typed before a Normal-mode motion (`20j`, `5]`), taken by the motion;
+    /// any other key drops it.
+    pub pending_count: Option<usize>,
 }
 
 impl Default for AppState {
@@ -179,6 +575,8 @@ impl Default for AppState {
             mode: Mode::default(),
             focus: PanelFocus::default(),
             file_list_state: ListState::default(),
+            file_filter: String::new(),
+            filtered_indices: Vec::new(),
             diff_scroll: 0,
             comments_scroll: 0,
             help_scroll: 0,
@@ -186,35 +584,296 @@ impl Default for AppState {
             comments_viewport_height: 0,
             file_list_viewport_height: 0,
             left_pct: 20,
+            command_line: String::new(),
+            search_query: String::new(),
+            mode_pick: 0,
+            mode_pick_refs: None,
+            diff_range: None,
+            pending_range: None,
+            mode_pick_error: None,
+            ref_names: None,
+            ref_tab: None,
+            recent_ranges: Vec::new(),
+            ref_history_pos: None,
+            theme: Theme::dark(),
+            theme_name: None,
+            custom_themes: Vec::new(),
+            theme_changed: false,
+            truecolor: true,
             center_pct: 55,
             right_pct: 25,
+            max_diff_width: 0,
+            editor_command: None,
+            editor_override: None,
+            editor_suspend: false,
+            web_url: None,
+            show_quit_summary: true,
+            cursor_line: true,
+            note_ref: "refs/notes/airev".to_owned(),
+            note_replace: false,
+            keymap: KeyMap::default(),
+            pending_jump: None,
+            diff_only: false,
+            layout_orientation: None,
+            status_segments: StatusSegment::DEFAULT.to_vec(),
+            wheel_scroll_lines: 3,
+            natural_scroll: false,
             has_unsaved_comments: false,
             diff_lines: Vec::new(),
             file_summaries: Vec::new(),
+            diff_totals: (0, 0),
             diff_mode: DiffMode::default(),
             diff_loading: false,
+            diff_status: DiffStatus::default(),
             hunk_offsets: Vec::new(),
             selected_file_index: 0,
             hunk_cursor: 0,
+            diff_cursor: 0,
+            pair_view: None,
             file_line_offsets: Vec::new(),
+            line_refs: Vec::new(),
+            show_line_numbers: false,
+            line_number_width: 1,
+            ignore_whitespace: false,
+            context_lines: 3,
+            base_branch: "main".to_owned(),
+            blame_visible: false,
+            blame: HashMap::new(),
+            hunk_line_counts: Vec::new(),
+            hunk_keys: Vec::new(),
+            collapsed_hunks: HashMap::new(),
+            folded_hunks: HashMap::new(),
+            diff_timing: DiffTiming::default(),
             git_tx: None,
+            git_worker_error: None,
+            startup_errors: Vec::new(),
+            drift_warning: None,
+            git_failures: 0,
+            repo_unavailable: None,
+            git_generation: 0,
+            auto_refresh_secs: 0,
+            last_diff_request: None,
+            refresh_pending: false,
+            notifications: VecDeque::new(),
             panel_rects: [Rect::default(); 3],
+            last_click: None,
             db_conn: None,
+            session_loading: false,
+            first_frame_ms: None,
             session: None,
             file_review_states: HashMap::new(),
             event_tx: None,
+            comment_draft: String::new(),
+            draft_severity: DEFAULT_SEVERITY,
+            draft_type: CommentType::default(),
+            draft_hunk: None,
+            draft_line: None,
+            comment_saving: false,
+            comment_error: None,
+            comments: Vec::new(),
+            selected_comment: 0,
+            comments_follow_selection: false,
+            comment_row_offsets: Vec::new(),
+            comment_filter: CommentFilter::default(),
+            hide_resolved: false,
+            severity_filter: None,
+            pending_key: None,
+            pending_count: None,
+        }
+    }
+}
+
+/// Content-based position of the diff cursor, captured before a refresh so the
+/// same line can be found again if files or hunks were reordered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiffAnchor {
+    /// Path of the file under the cursor.
+    path: String,
+    /// [`hunk_key`] of the hunk under the cursor.
+    hunk: String,
+    /// Cursor line relative to the hunk header.
+    line: usize,
+    /// Rows between the top of the viewport and the cursor.
+    cursor_row: usize,
+}
+
+/// Builds the drift banner text for a session recorded at `recorded` that now
+/// sees `current` as HEAD. Both SHAs are shortened to 7 characters.
+///
+/// The key hint is left to the banner, which reads it from the keymap.
+pub fn drift_message(recorded: &str, current: &str) -> String {
+    let short = |sha: &str| sha.get(..7).unwrap_or(sha).to_owned();
+    format!(
+        "HEAD moved since this session started ({} -> {}); comment anchors may be stale",
+        short(recorded),
+        short(current)
+    )
+}
+
+/// Counts the `+` and `-` lines of each hunk in `line_refs`, indexed by
+/// `LineRef::hunk_index`.
+pub fn hunk_line_counts(line_refs: &[LineRef]) -> Vec<(usize, usize)> {
+    let hunks = line_refs.iter().map(|r| r.hunk_index + 1).max().unwrap_or(0);
+    let mut counts = vec![(0, 0); hunks];
+    for r in line_refs {
+        match r.origin {
+            '+' => counts[r.hunk_index].0 += 1,
+            '-' => counts[r.hunk_index].1 += 1,
+            _ => {}
         }
     }
+    counts
+}
+
+/// Digits needed for the largest old or new line number in `line_refs`, at
+/// least 1.
+pub fn line_number_width(line_refs: &[LineRef]) -> usize {
+    let max = line_refs.iter().flat_map(|r| r.old_lineno.max(r.new_lineno)).max().unwrap_or(0);
+    max.to_string().len()
+}
+
+/// Returns the text of `line` with its styling dropped, for matching.
+pub fn line_text(line: &ratatui::text::Line<'_>) -> String {
+    line.spans.iter().map(|span| span.content.as_ref()).collect()
+}
+
+/// Whether the characters of `query` appear in `text` in order, not necessarily
+/// next to each other: `"srapp"` matches `"airev/src/app.rs"`, `"ppa"` does not.
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut rest = text.chars();
+    query.chars().all(|q| rest.any(|c| c == q))
+}
+
+/// Splits picker refs into the text before the ref being typed and that ref:
+/// `"main...feat"` gives `("main...", "feat")`, and refs without a `..` or
+/// `...` separator are all one ref.
+fn split_ref_word(refs: &str) -> (&str, &str) {
+    refs.split_at(refs.rfind("..").map_or(0, |i| i + 2))
 }
 
 impl AppState {
-    /// Scrolls the focused panel down by `lines` rows.
+    /// Queues `error` for the startup error overlay and switches to it.
+    pub fn push_startup_error(&mut self, error: StartupError) {
+        self.startup_errors.push(error);
+        self.mode = Mode::StartupError;
+    }
+
+    /// Dismisses the startup error on screen; returns to Normal mode after the last.
+    pub fn dismiss_startup_error(&mut self) {
+        if !self.startup_errors.is_empty() {
+            self.startup_errors.remove(0);
+        }
+        if self.startup_errors.is_empty() {
+            self.mode = Mode::Normal;
+        }
+    }
+
+    /// Returns the panels drawn in the last frame, in focus-cycle order.
+    ///
+    /// Derived from the sizes in `panel_rects`: the side panels collapse to zero
+    /// width (or zero height when stacked vertically) at the medium and narrow
+    /// breakpoints. Before the first layout (or while the terminal is too small)
+    /// every panel counts as visible.
+    pub fn visible_panels(&self) -> Vec<PanelFocus> {
+        let [left, center, right] = self.panel_rects;
+        if center.is_empty() {
+            return vec![PanelFocus::FileList, PanelFocus::Diff, PanelFocus::Comments];
+        }
+        [(PanelFocus::FileList, left), (PanelFocus::Diff, center), (PanelFocus::Comments, right)]
+            .into_iter()
+            .filter(|(_, rect)| !rect.is_empty())
+            .map(|(panel, _)| panel)
+            .collect()
+    }
+
+    /// Moves focus to the next visible panel (`L`), skipping collapsed ones.
+    pub fn focus_next(&mut self) {
+        self.focus = self.step_focus(PanelFocus::next);
+    }
+
+    /// Moves focus to the previous visible panel (`H`), skipping collapsed ones.
+    pub fn focus_prev(&mut self) {
+        self.focus = self.step_focus(PanelFocus::prev);
+    }
+
+    /// Caches the panel rects from the latest layout and reacts to the comments
+    /// panel appearing or disappearing at a breakpoint (or a `Resize`).
+    ///
+    /// When it is hidden, its row offsets and scroll are dropped, since they were
+    /// computed for a width that no longer exists; when it comes back, the
+    /// selected comment is scrolled into view at the new width. Focus is then
+    /// clamped to a visible panel (see [`AppState::clamp_focus_to_visible`]).
+    pub fn apply_panel_rects(&mut self, rects: [Rect; 3]) {
+        let comments_were_shown = !self.panel_rects[2].is_empty();
+        let comments_shown = !rects[2].is_empty();
+        self.panel_rects = rects;
+        if comments_were_shown && !comments_shown {
+            self.comment_row_offsets.clear();
+            self.comments_scroll = 0;
+        } else if !comments_were_shown && comments_shown {
+            self.comments_follow_selection = true;
+        }
+        self.clamp_focus_to_visible();
+    }
+
+    /// Moves focus to the diff panel if the focused panel is no longer visible.
+    ///
+    /// Called after each layout so a resize past a breakpoint never leaves
+    /// keyboard scrolling aimed at a hidden panel.
+    pub fn clamp_focus_to_visible(&mut self) {
+        if !self.visible_panels().contains(&self.focus) {
+            self.focus = PanelFocus::Diff;
+        }
+    }
+
+    /// Applies `step` from the current focus until it lands on a visible panel.
+    fn step_focus(&self, step: fn(PanelFocus) -> PanelFocus) -> PanelFocus {
+        let visible = self.visible_panels();
+        let mut panel = step(self.focus);
+        // At most three panels, so two extra steps always reach a visible one.
+        for _ in 0..2 {
+            if visible.contains(&panel) {
+                break;
+            }
+            panel = step(panel);
+        }
+        panel
+    }
+
+    /// Adds a digit typed in Normal mode to `pending_count`, up to [`MAX_COUNT`].
+    pub fn push_count_digit(&mut self, digit: u32) {
+        let count = self.pending_count.unwrap_or(0) * 10 + digit as usize;
+        self.pending_count = Some(count.min(MAX_COUNT));
+    }
+
+    /// Takes `pending_count` for a motion: the number of times to repeat it.
+    fn take_count(&mut self) -> usize {
+        self.pending_count.take().unwrap_or(1)
+    }
+
+    /// Scrolls the focused panel down by `lines` rows, times a pending count.
+    ///
+    /// See [`AppState::scroll_panel_down`] for per-panel behaviour.
+    pub fn scroll_down(&mut self, lines: u16) {
+        let lines = lines.saturating_mul(self.take_count() as u16);
+        self.scroll_panel_down(self.focus, lines);
+    }
+
+    /// Scrolls the focused panel up by `lines` rows, times a pending count.
+    ///
+    /// See [`AppState::scroll_panel_up`] for per-panel behaviour.
+    pub fn scroll_up(&mut self, lines: u16) {
+        let lines = lines.saturating_mul(self.take_count() as u16);
+        self.scroll_panel_up(self.focus, lines);
+    }
+
+    /// Scrolls `panel` down by `lines` rows without changing focus.
     ///
     /// For `FileList`: advances the `ListState` selection by `lines` items.
     /// For `Diff`: adds `lines` to the usize scroll offset (saturating).
     /// For `Comments`: adds `lines` to the u16 scroll offset (saturating).
-    pub fn scroll_down(&mut self, lines: u16) {
-        match self.focus {
+    pub fn scroll_panel_down(&mut self, panel: PanelFocus, lines: u16) {
+        match panel {
             PanelFocus::FileList => {
                 self.file_list_state.scroll_down_by(lines);
             }
@@ -227,13 +886,13 @@ impl AppState {
         }
     }
 
-    /// Scrolls the focused panel up by `lines` rows.
+    /// Scrolls `panel` up by `lines` rows without changing focus.
     ///
     /// For `FileList`: moves the `ListState` selection up by `lines` items.
     /// For `Diff`: subtracts `lines` from the usize scroll offset (saturating).
     /// For `Comments`: subtracts `lines` from the u16 scroll offset (saturating).
-    pub fn scroll_up(&mut self, lines: u16) {
-        match self.focus {
+    pub fn scroll_panel_up(&mut self, panel: PanelFocus, lines: u16) {
+        match panel {
             PanelFocus::FileList => {
                 self.file_list_state.scroll_up_by(lines);
             }
@@ -330,143 +989,3297 @@ impl AppState {
         self.scroll_up(full.max(1));
     }
 
-    /// Moves the file-list selection to the previous file (up one row).
+    /// Returns the range of file-list rows shown in `height` rows.
+    ///
+    /// Does what `List` does with every item materialized, so the renderer only
+    /// builds the visible window: the selection is clamped to the last file, and
+    /// the scroll offset moves just far enough to keep it in view. The offset is
+    /// stored back in `file_list_state` for mouse hit-testing.
+    pub fn file_list_window(&mut self, height: usize) -> std::ops::Range<usize> {
+        let len = self.file_list_len();
+        let selected = self.file_list_state.selected().map(|s| s.min(len.saturating_sub(1)));
+        self.file_list_state.select(selected.filter(|_| len > 0));
+
+        let mut offset = self.file_list_state.offset().min(len.saturating_sub(height));
+        if let (Some(sel), true) = (selected, height > 0) {
+            if sel < offset {
+                offset = sel;
+            } else if sel >= offset + height {
+                offset = sel + 1 - height;
+            }
+        }
+        *self.file_list_state.offset_mut() = offset;
+        offset..(offset + height).min(len)
+    }
+
+    /// Moves the file-list selection to the previous file (up one row, or as many
+    /// as a pending count).
     ///
     /// Equivalent to pressing `k` while focused on the file list, regardless
     /// of which panel actually has focus.
     pub fn prev_file(&mut self) {
-        self.file_list_state.scroll_up_by(1);
+        let count = self.take_count() as u16;
+        self.file_list_state.scroll_up_by(count);
     }
 
-    /// Moves the file-list selection to the next file (down one row).
+    /// Moves the file-list selection to the next file (down one row, or as many
+    /// as a pending count).
     ///
     /// Equivalent to pressing `j` while focused on the file list, regardless
     /// of which panel actually has focus.
     pub fn next_file(&mut self) {
-        self.file_list_state.scroll_down_by(1);
+        let count = self.take_count() as u16;
+        self.file_list_state.scroll_down_by(count);
     }
 
-    /// Jumps diff_scroll to the previous hunk header ([ keybinding).
+    /// Jumps diff_scroll to the previous hunk header ([ keybinding), or as many
+    /// hunks back as a pending count (`5[`).
     ///
-    /// Decrements hunk_cursor. If already at the first hunk, stays there.
+    /// Decrements hunk_cursor and moves the diff cursor onto the header line.
+    /// If already at the first hunk, stays there.
     pub fn prev_hunk(&mut self) {
+        let count = self.take_count();
         if self.hunk_offsets.is_empty() {
             return;
         }
-        self.hunk_cursor = self.hunk_cursor.saturating_sub(1);
+        self.hunk_cursor = self.hunk_cursor.saturating_sub(count);
         self.diff_scroll = self.hunk_offsets[self.hunk_cursor];
+        self.diff_cursor = self.diff_scroll;
     }
 
-    /// Jumps diff_scroll to the next hunk header (] keybinding).
+    /// Jumps diff_scroll to the next hunk header (] keybinding), or as many hunks
+    /// on as a pending count (`5]`).
     ///
-    /// Advances hunk_cursor. If already at the last hunk, stays there.
+    /// Advances hunk_cursor and moves the diff cursor onto the header line.
+    /// If already at the last hunk, stays there.
     pub fn next_hunk(&mut self) {
+        let count = self.take_count();
         if self.hunk_offsets.is_empty() {
             return;
         }
-        self.hunk_cursor = (self.hunk_cursor + 1).min(self.hunk_offsets.len() - 1);
+        self.hunk_cursor = (self.hunk_cursor + count).min(self.hunk_offsets.len() - 1);
         self.diff_scroll = self.hunk_offsets[self.hunk_cursor];
+        self.diff_cursor = self.diff_scroll;
     }
 
     /// Applies the received GitResultPayload to AppState.
     ///
     /// Called from the AppEvent::GitResult arm in main.rs. Replaces diff content,
     /// clears the loading flag, and resets scroll to top on mode change.
+    ///
+    /// The file-list selection follows the previously selected file by path, so a
+    /// refresh that adds, removes, or reorders files does not move the highlight to
+    /// a different file. If that file is gone, the old index is clamped to the new
+    /// list instead.
+    ///
+    /// Payloads from a worker generation other than `git_generation` are in-flight
+    /// results from a replaced worker and are ignored.
+    ///
+    /// A failed request for the mode already on screen keeps the previous diff and
+    /// only counts the failure; after `REPO_UNAVAILABLE_AFTER` in a row the
+    /// `repo_unavailable` banner is raised. The next success clears both.
+    ///
+    /// On a refresh of the same mode the diff cursor is re-anchored by file path
+    /// and [`hunk_key`] rather than line index (see [`DiffAnchor`]), so it stays
+    /// on the same hunk when files are added, removed, or reordered.
     pub fn apply_git_result(&mut self, payload: crate::git::types::GitResultPayload) {
+        if payload.generation != self.git_generation {
+            return;
+        }
+        if let DiffStatus::Error(msg) = &payload.status {
+            self.git_failures += 1;
+            if self.git_failures >= REPO_UNAVAILABLE_AFTER {
+                self.repo_unavailable = Some(format!(
+                    "repository path unavailable ({msg}); auto-refresh paused. \
+                     R to retry, q to quit"
+                ));
+            }
+            // Keep the last good diff of this mode on screen instead of blanking it.
+            if self.diff_status == DiffStatus::Loaded && self.diff_mode == payload.mode {
+                self.diff_loading = false;
+                self.refresh_pending = false;
+                return;
+            }
+        } else {
+            self.git_failures = 0;
+            self.repo_unavailable = None;
+        }
         let mode_changed = self.diff_mode != payload.mode;
+        let anchor = if mode_changed { None } else { self.diff_anchor() };
+        let previous_path = self.current_file_path().map(str::to_owned);
+        let previous_index = self.file_list_state.selected();
         self.diff_mode = payload.mode;
+        self.diff_status = payload.status;
+        if let Some(base) = payload.base {
+            self.base_branch = base;
+        }
         self.file_summaries = payload.files;
+        self.diff_totals = self
+            .file_summaries
+            .iter()
+            .fold((0, 0), |(added, removed), f| (added + f.added, removed + f.removed));
+        self.filtered_indices = self.matching_files();
+        self.restore_file_selection(previous_path.as_deref(), previous_index);
+        self.collapsed_hunks.clear();
+        if mode_changed {
+            self.folded_hunks.clear();
+        }
         self.diff_lines = payload.highlighted_lines;
         self.hunk_offsets = payload.hunk_offsets;
         self.file_line_offsets = payload.file_line_offsets;
+        self.line_refs = payload.line_refs;
+        self.hunk_line_counts = hunk_line_counts(&self.line_refs);
+        self.line_number_width = line_number_width(&self.line_refs);
+        self.hunk_keys = payload.hunks.iter().map(hunk_key).collect();
+        self.blame.clear();
+        self.diff_timing = payload.timing;
         self.diff_loading = false;
+        let refreshed = std::mem::take(&mut self.refresh_pending);
+        if payload.timing.highlight_ms > SLOW_HIGHLIGHT_MS {
+            self.push_notification(format!("slow highlight: {} ms", payload.timing.highlight_ms));
+        } else if refreshed {
+            self.push_notification("refreshed".to_owned());
+        }
         if mode_changed {
             self.diff_scroll = 0;
             self.hunk_cursor = 0;
+            self.diff_cursor = 0;
+        } else if let Some(anchor) = anchor {
+            self.restore_diff_anchor(&anchor);
+        }
+        self.fold_new_generated_files();
+        for hunk in 0..self.hunk_keys.len() {
+            let folded = self.hunk_path(hunk).and_then(|path| self.folded_hunks.get(path));
+            if folded.is_some_and(|keys| keys.contains(&self.hunk_keys[hunk])) {
+                self.collapse_hunk(hunk);
+            }
         }
+        self.diff_cursor = self.diff_cursor.min(self.diff_lines.len().saturating_sub(1));
+        self.apply_pending_jump();
     }
 
-    /// Jumps diff view to the selected file's first hunk (Enter or l on file list).
+    /// The commit the old side of the diff is blamed at (see `git::blame`), or
+    /// `None` for a `--files` review.
+    pub fn blame_rev(&self) -> Option<String> {
+        match (self.diff_mode, &self.diff_range) {
+            (DiffMode::Unstaged | DiffMode::Staged, _) => Some("HEAD".to_owned()),
+            (DiffMode::BranchComparison, _) => Some(self.base_branch.clone()),
+            (DiffMode::CommitRange, Some((from, _))) => Some(from.clone()),
+            (DiffMode::Stash, _) => Some("stash@{0}^".to_owned()),
+            (DiffMode::CommitRange, None) | (DiffMode::Files, _) => None,
+        }
+    }
+
+    /// Folds the hunk under the diff cursor down to its header, or unfolds it
+    /// (`za`, or Enter on a hunk header). Shows a toast when the cursor is not in
+    /// a hunk or the hunk has no lines to hide.
+    pub fn toggle_hunk_fold(&mut self) {
+        let Some(hunk) = self.line_refs.get(self.diff_cursor).map(|r| r.hunk_index) else {
+            self.push_notification("no hunk at the cursor".to_owned());
+            return;
+        };
+        self.pair_view = None;
+        let key = self.hunk_keys.get(hunk).cloned().unwrap_or_default();
+        let path = self.hunk_path(hunk).unwrap_or_default().to_owned();
+        if self.collapsed_hunks.contains_key(&hunk) {
+            self.expand_hunk(hunk);
+            if let Some(keys) = self.folded_hunks.get_mut(&path) {
+                keys.remove(&key);
+            }
+        } else if self.collapse_hunk(hunk) {
+            self.folded_hunks.entry(path).or_default().insert(key);
+        } else {
+            self.push_notification("nothing to fold in this hunk".to_owned());
+        }
+    }
+
+    /// Folds every hunk of a generated file the first time it shows up, by adding
+    /// them to `folded_hunks`. A file that already has an entry keeps it, so a
+    /// generated hunk unfolded with Enter or `za` stays open across refreshes.
+    fn fold_new_generated_files(&mut self) {
+        let mut seeded: HashMap<String, HashSet<String>> = HashMap::new();
+        for (hunk, key) in self.hunk_keys.iter().enumerate() {
+            let file = self
+                .hunk_offsets
+                .get(hunk)
+                .and_then(|&header| self.line_refs.get(header))
+                .and_then(|r| self.file_summaries.get(r.file_index));
+            if let Some(file) =
+                file.filter(|f| f.generated && !self.folded_hunks.contains_key(&f.path))
+            {
+                seeded.entry(file.path.clone()).or_default().insert(key.clone());
+            }
+        }
+        self.folded_hunks.extend(seeded);
+    }
+
+    /// Path of the file `hunk` belongs to, read from the ref of its header line.
+    fn hunk_path(&self, hunk: usize) -> Option<&str> {
+        let header = self.line_refs.get(*self.hunk_offsets.get(hunk)?)?;
+        self.file_summaries.get(header.file_index).map(|f| f.path.as_str())
+    }
+
+    /// Takes the body rows of `hunk` out of `diff_lines` and `line_refs` into
+    /// `collapsed_hunks`, leaving its header. Offsets, the cursor, and the scroll
+    /// position past the body move up; inside it, they move to the header.
     ///
-    /// Updates selected_file_index and sets diff_scroll to the file's first hunk
-    /// header line using `file_line_offsets`. Falls back to 0 if the index is
-    /// out of range (e.g. no diff loaded yet).
-    pub fn jump_to_selected_file(&mut self) {
-        if let Some(idx) = self.file_list_state.selected() {
-            self.selected_file_index = idx;
-            self.diff_scroll = self.file_line_offsets.get(idx).copied().unwrap_or(0);
-            self.hunk_cursor = 0;
-            self.focus = PanelFocus::Diff;
+    /// Returns `false` when the hunk has no body rows (or does not exist).
+    fn collapse_hunk(&mut self, hunk: usize) -> bool {
+        let Some(&header) = self.hunk_offsets.get(hunk) else {
+            return false;
+        };
+        let start = header + 1;
+        let hidden = self.line_refs.get(start..).map_or(0, |rest| {
+            rest.iter().take_while(|r| r.hunk_index == hunk && r.origin != 'H').count()
+        });
+        if hidden == 0 {
+            return false;
+        }
+        let end = start + hidden;
+        let lines = self.diff_lines.drain(start..end).collect();
+        let refs = self.line_refs.drain(start..end).collect();
+        self.collapsed_hunks.insert(hunk, (lines, refs));
+        self.move_rows(|row| match row {
+            _ if row >= end => row - hidden,
+            _ if row >= start => header,
+            _ => row,
+        });
+        true
+    }
+
+    /// Puts the body rows of a folded `hunk` back after its header, moving the
+    /// offsets, cursor, and scroll position past the header down.
+    fn expand_hunk(&mut self, hunk: usize) {
+        let (Some(&header), Some((lines, refs))) =
+            (self.hunk_offsets.get(hunk), self.collapsed_hunks.remove(&hunk))
+        else {
+            return;
+        };
+        let start = header + 1;
+        let shown = lines.len();
+        self.diff_lines.splice(start..start, lines);
+        self.line_refs.splice(start..start, refs);
+        self.move_rows(|row| if row >= start { row + shown } else { row });
+    }
+
+    /// Maps every stored `diff_lines` row (hunk and file offsets, the diff cursor,
+    /// and the scroll position) through `to` after rows were added or removed.
+    fn move_rows(&mut self, to: impl Fn(usize) -> usize) {
+        for offset in self.hunk_offsets.iter_mut().chain(self.file_line_offsets.iter_mut()) {
+            *offset = to(*offset);
         }
+        self.diff_cursor = to(self.diff_cursor);
+        self.diff_scroll = to(self.diff_scroll);
+    }
+
+    /// Shows or hides the line-number gutter (`#`).
+    pub fn toggle_line_numbers(&mut self) {
+        self.show_line_numbers = !self.show_line_numbers;
     }
 
-    /// Shrinks the diff (centre) panel by transferring 5% to each side panel.
+    /// Starts or stops ignoring whitespace changes (`w`) and reloads the diff.
     ///
-    /// The centre panel will not shrink below 20%. The 5% is split evenly: 2% to
-    /// the left panel and 3% to the right panel (or nearest even split).
-    pub fn shrink_diff_panel(&mut self) {
-        const MIN_CENTER: u16 = 20;
-        const STEP: u16 = 5;
-        if self.center_pct <= MIN_CENTER {
+    /// Loose-file reviews are not diffed by the worker, so they are left alone.
+    pub fn toggle_ignore_whitespace(&mut self) {
+        if self.diff_mode == DiffMode::Files {
+            let message = "ignoring whitespace needs a diff from the repository";
+            self.push_notification(message.to_owned());
             return;
         }
-        let transfer = STEP.min(self.center_pct - MIN_CENTER);
-        self.center_pct -= transfer;
-        // Distribute transfer: half to left, half to right (round down to left).
-        let left_gain = transfer / 2;
-        let right_gain = transfer - left_gain;
-        self.left_pct = self.left_pct.saturating_add(left_gain);
-        self.right_pct = self.right_pct.saturating_add(right_gain);
+        let Some(tx) = self.git_tx.as_ref() else {
+            return;
+        };
+        self.ignore_whitespace = !self.ignore_whitespace;
+        let _ = tx.send(GitRequest::SetIgnoreWhitespace(self.ignore_whitespace));
+        self.request_diff(self.diff_request(self.diff_mode));
     }
 
-    /// Grows the diff (centre) panel by pulling 5% from each side panel equally.
+    /// Shows `delta` more (or fewer) context lines, clamped to
+    /// `0..=MAX_CONTEXT_LINES`, and reloads the diff (`-` / `+`).
     ///
-    /// The centre panel will not grow above 80%. Side panels each give 2–3% until
-    /// either hits its minimum or the centre reaches its maximum.
-    pub fn grow_diff_panel(&mut self) {
-        const MAX_CENTER: u16 = 80;
-        const MIN_SIDE: u16 = 5;
-        const STEP: u16 = 5;
-        if self.center_pct >= MAX_CENTER {
+    /// Returns whether the count changed, so the caller can save it for the
+    /// session. Loose-file reviews are not diffed by the worker and keep theirs.
+    pub fn change_context_lines(&mut self, delta: i32) -> bool {
+        if self.diff_mode == DiffMode::Files {
+            self.push_notification("context lines need a diff from the repository".to_owned());
+            return false;
+        }
+        let lines = self.context_lines.saturating_add_signed(delta).min(MAX_CONTEXT_LINES);
+        if lines == self.context_lines || self.git_tx.is_none() {
+            return false;
+        }
+        self.set_context_lines(lines);
+        self.request_diff(self.diff_request(self.diff_mode));
+        self.push_notification(format!("context: {lines} lines"));
+        true
+    }
+
+    /// Sets `context_lines` and tells the worker, without reloading the diff.
+    fn set_context_lines(&mut self, lines: u32) {
+        self.context_lines = lines;
+        if let Some(tx) = self.git_tx.as_ref() {
+            let _ = tx.send(GitRequest::SetContextLines(lines));
+        }
+    }
+
+    /// Shows or hides the blame column (`a`).
+    pub fn toggle_blame(&mut self) {
+        if !self.blame_visible && self.blame_rev().is_none() {
+            self.push_notification("blame needs a diff from the repository".to_owned());
             return;
         }
-        let room = MAX_CENTER - self.center_pct;
-        let transfer = STEP.min(room);
-        let left_give = (transfer / 2).min(self.left_pct.saturating_sub(MIN_SIDE));
-        let right_give = (transfer - transfer / 2).min(self.right_pct.saturating_sub(MIN_SIDE));
-        let actual = left_give + right_give;
-        self.left_pct -= left_give;
-        self.right_pct -= right_give;
-        self.center_pct += actual;
+        self.blame_visible = !self.blame_visible;
     }
 
-    /// Applies a DbResultPayload to AppState.
+    /// Asks the worker to blame the old-side lines in view that have not been
+    /// blamed yet: one request per file, from its first such line to its last.
     ///
-    /// Called from the AppEvent::DbResult arm in main.rs. Updates session
-    /// and file review state based on the payload variant.
-    pub fn apply_db_result(&mut self, payload: crate::event::DbResultPayload) {
-        match payload {
-            crate::event::DbResultPayload::SessionLoaded(session)
-            | crate::event::DbResultPayload::SessionCreated(session) => {
-                self.session = Some(session);
+    /// Called after every draw, so the lines in view are known; a no-op while the
+    /// blame column is hidden or everything in view is cached.
+    pub fn request_visible_blame(&mut self) {
+        let (true, Some(rev), Some(tx)) = (self.blame_visible, self.blame_rev(), &self.git_tx)
+        else {
+            return;
+        };
+        let end = (self.diff_scroll + self.diff_viewport_height as usize).min(self.line_refs.len());
+        let mut wanted: Vec<(usize, u32, u32)> = Vec::new();
+        for r in self.line_refs.get(self.diff_scroll..end).unwrap_or_default() {
+            let (Some(line), Some(file)) = (r.old_lineno, self.file_summaries.get(r.file_index))
+            else {
+                continue;
+            };
+            if self.blame.get(&file.path).is_some_and(|lines| lines.contains_key(&line)) {
+                continue;
             }
-            crate::event::DbResultPayload::FileReviewStateLoaded(states) => {
-                self.file_review_states = states.into_iter().collect();
+            match wanted.iter_mut().find(|(index, ..)| *index == r.file_index) {
+                Some((_, first, last)) => (*first, *last) = ((*first).min(line), (*last).max(line)),
+                None => wanted.push((r.file_index, line, line)),
             }
-            crate::event::DbResultPayload::ReviewToggled { file_path, reviewed } => {
-                self.file_review_states.insert(file_path, reviewed);
+        }
+        for (index, first, last) in wanted {
+            let path = self.file_summaries[index].path.clone();
+            let lines = self.blame.entry(path.clone()).or_default();
+            for line in first..=last {
+                lines.entry(line).or_insert(None);
+            }
+            let request = GitRequest::Blame { rev: rev.clone(), path, first, last };
+            if tx.send(request).is_err() {
+                self.mark_git_worker_stopped("worker thread exited".to_owned());
+                return;
             }
         }
     }
 
-    /// Returns the repo-relative path of the currently selected file, if any.
+    /// Caches the worker's blame of `path` (`AppEvent::BlameLoaded`).
     ///
-    /// Looks up `selected_file_index` in `file_summaries`. Returns `None` if
-    /// no files are loaded or the index is out of bounds.
-    pub fn current_file_path(&self) -> Option<&str> {
-        self.file_list_state
-            .selected()
-            .and_then(|idx| self.file_summaries.get(idx))
-            .map(|f| f.path.as_str())
+    /// Answers for a replaced worker, another old side, or lines no longer
+    /// waiting (the diff was reloaded) are dropped. A failure is shown as a toast
+    /// and its lines stay blank.
+    pub fn apply_blame(
+        &mut self,
+        generation: u64,
+        rev: &str,
+        path: &str,
+        result: Result<Vec<(u32, BlameTag)>, String>,
+    ) {
+        if generation != self.git_generation || self.blame_rev().as_deref() != Some(rev) {
+            return;
+        }
+        let Some(lines) = self.blame.get_mut(path) else {
+            return;
+        };
+        match result {
+            Ok(tags) => {
+                for (line, tag) in tags {
+                    if let Some(slot) = lines.get_mut(&line) {
+                        slot.get_or_insert(tag);
+                    }
+                }
+            }
+            Err(message) => self.push_notification(format!("blame {path}: {message}")),
+        }
+    }
+
+    /// The blame of diff line `index`, when it is an old-side line that has been
+    /// blamed.
+    pub fn blame_tag(&self, index: usize) -> Option<&BlameTag> {
+        let r = self.line_refs.get(index)?;
+        let path = &self.file_summaries.get(r.file_index)?.path;
+        self.blame.get(path)?.get(&r.old_lineno?)?.as_ref()
+    }
+
+    /// Jumps to the `--file` (and `--line`) target once there is a diff to find it
+    /// in.
+    ///
+    /// Waits while the session is loading, since it may switch diff modes, and
+    /// while a diff is in flight. A file missing from the diff gets a toast and
+    /// the first file stays selected. The cursor goes to the first line of the
+    /// file whose new-file number is at least `--line`, or the file's first line
+    /// when there is none.
+    pub fn apply_pending_jump(&mut self) {
+        if self.session_loading || self.diff_loading || self.diff_status != DiffStatus::Loaded {
+            return;
+        }
+        let Some((path, line)) = self.pending_jump.take() else {
+            return;
+        };
+        let wanted = path.strip_prefix("./").unwrap_or(&path);
+        let Some(idx) = self.file_summaries.iter().position(|f| f.path == wanted) else {
+            self.push_notification(format!("not in the diff: {path}"));
+            return;
+        };
+        // A filter hiding the file would leave nothing to jump to.
+        if self.row_of_file(idx).is_none() {
+            self.file_filter.clear();
+        }
+        self.file_list_state.select(self.row_of_file(idx));
+        self.jump_to_selected_file();
+        let target = line.and_then(|line| {
+            self.line_refs.iter().position(|r| {
+                r.file_index == idx && r.new_lineno.is_some_and(|n| n >= line)
+            })
+        });
+        if let Some(row) = target {
+            self.diff_cursor = row;
+            self.diff_scroll = row;
+            self.hunk_cursor = self.line_refs[row].hunk_index;
+        }
+    }
+
+    /// Captures the diff cursor's position by content, or `None` when the cursor
+    /// is not on a diff line.
+    fn diff_anchor(&self) -> Option<DiffAnchor> {
+        let at = self.line_refs.get(self.diff_cursor)?;
+        Some(DiffAnchor {
+            path: self.file_summaries.get(at.file_index)?.path.clone(),
+            hunk: self.hunk_keys.get(at.hunk_index)?.clone(),
+            line: self.diff_cursor - self.hunk_offsets.get(at.hunk_index)?,
+            cursor_row: self.diff_cursor.saturating_sub(self.diff_scroll),
+        })
+    }
+
+    /// Moves the diff cursor back to `anchor` in the freshly applied diff.
+    ///
+    /// Finds the hunk with the same key in the same file and keeps the cursor's
+    /// line within it and its row on screen. If that hunk is gone but the file is
+    /// not, lands on the file's first line. Otherwise the cursor stays where it is.
+    fn restore_diff_anchor(&mut self, anchor: &DiffAnchor) {
+        let file_index = self.file_summaries.iter().position(|f| f.path == anchor.path);
+        let hunk = self.hunk_keys.iter().enumerate().position(|(i, key)| {
+            *key == anchor.hunk
+                && self
+                    .hunk_offsets
+                    .get(i)
+                    .and_then(|&offset| self.line_refs.get(offset))
+                    .is_some_and(|r| Some(r.file_index) == file_index)
+        });
+        match (hunk, file_index) {
+            (Some(h), _) => {
+                let end = self.hunk_offsets.get(h + 1).copied().unwrap_or(self.diff_lines.len());
+                self.hunk_cursor = h;
+                self.diff_cursor = (self.hunk_offsets[h] + anchor.line).min(end.saturating_sub(1));
+                self.diff_scroll = self.diff_cursor.saturating_sub(anchor.cursor_row);
+            }
+            (None, Some(f)) => {
+                let Some(&offset) = self.file_line_offsets.get(f) else {
+                    return;
+                };
+                self.hunk_cursor = self.line_refs.get(offset).map_or(0, |r| r.hunk_index);
+                self.diff_cursor = offset;
+                self.diff_scroll = offset;
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Sends `request` to the git worker and marks the diff as loading.
+    ///
+    /// Does nothing when there is no worker (no repository). If the send fails
+    /// because the worker thread has exited, the worker is marked stopped instead
+    /// of leaving the diff stuck on `Computing diff...`.
+    pub fn request_diff(&mut self, request: GitRequest) {
+        let Some(tx) = self.git_tx.as_ref() else {
+            return;
+        };
+        self.diff_loading = true;
+        self.last_diff_request = Some(Instant::now());
+        if tx.send(request).is_err() {
+            self.mark_git_worker_stopped("worker thread exited".to_owned());
+        }
+    }
+
+    /// Re-issues the current diff request in the background.
+    ///
+    /// Shared by the auto-refresh timer and `AppEvent::FileChanged` so the two
+    /// coalesce: nothing is sent while a request is already in flight, and each
+    /// refresh restarts the auto-refresh interval. The result shows a toast.
+    /// Paused while the repository is reported unavailable, so a vanished work
+    /// tree does not turn the timer into an error loop.
+    pub fn refresh_diff(&mut self) {
+        if self.git_tx.is_none()
+            || self.git_worker_error.is_some()
+            || self.repo_unavailable.is_some()
+            || self.diff_loading
+        {
+            return;
+        }
+        self.refresh_pending = true;
+        self.request_diff(self.diff_request(self.diff_mode));
+    }
+
+    /// The worker request that loads `mode`. A `CommitRange` picked with `m` is
+    /// sent with its refs; without them the worker reports an error.
+    pub fn diff_request(&self, mode: DiffMode) -> GitRequest {
+        match (mode, &self.diff_range) {
+            (DiffMode::CommitRange, Some((from, to))) => {
+                GitRequest::LoadDiffRange { from: from.clone(), to: to.clone() }
+            }
+            _ => GitRequest::LoadDiff(mode),
+        }
+    }
+
+    /// Shows `mode` from the top and requests its diff (`Tab`, `m`).
+    pub fn switch_diff_mode(&mut self, mode: DiffMode) {
+        self.diff_mode = mode;
+        self.diff_scroll = 0;
+        self.request_diff(self.diff_request(mode));
+    }
+
+    /// Cycles the pair toggle (`o`) for the change block at the diff cursor:
+    /// old only, then new only, then both.
+    ///
+    /// A toggle that lapsed because the view scrolled starts over at old. Shows
+    /// a toast when the cursor is not on a block with both removed and added lines.
+    pub fn toggle_pair_view(&mut self) {
+        if self.change_block_at_cursor().is_none() {
+            self.pair_view = None;
+            self.push_notification("no -/+ line pair at the cursor".to_owned());
+            return;
+        }
+        let next = match self.active_pair_view().map(|(side, _)| side) {
+            None => Some(PairSide::Old),
+            Some(PairSide::Old) => Some(PairSide::New),
+            Some(PairSide::New) => None,
+        };
+        self.pair_view = next.map(|side| (side, self.diff_scroll, self.diff_cursor));
+    }
+
+    /// Returns the pair toggle's side and the `diff_lines` range it applies to,
+    /// or `None` when it is off or has lapsed after a scroll or cursor move.
+    pub fn active_pair_view(&self) -> Option<(PairSide, std::ops::Range<usize>)> {
+        let (side, scroll, cursor) = self.pair_view?;
+        if scroll != self.diff_scroll || cursor != self.diff_cursor {
+            return None;
+        }
+        Some((side, self.change_block_at_cursor()?))
+    }
+
+    /// Returns the run of `-`/`+` lines in the cursor's hunk that contains the
+    /// cursor, found through `line_refs`. `None` unless the run has both kinds.
+    fn change_block_at_cursor(&self) -> Option<std::ops::Range<usize>> {
+        let at = self.line_refs.get(self.diff_cursor)?;
+        let in_block = |i: usize| {
+            self.line_refs
+                .get(i)
+                .is_some_and(|r| r.hunk_index == at.hunk_index && matches!(r.origin, '-' | '+'))
+        };
+        if !in_block(self.diff_cursor) {
+            return None;
+        }
+        let mut start = self.diff_cursor;
+        while start > 0 && in_block(start - 1) {
+            start -= 1;
+        }
+        let mut end = self.diff_cursor + 1;
+        while in_block(end) {
+            end += 1;
+        }
+        let origins: Vec<char> = self.line_refs[start..end].iter().map(|r| r.origin).collect();
+        (origins.contains(&'-') && origins.contains(&'+')).then_some(start..end)
+    }
+
+    /// Adds `message` to `notifications` until it expires on a later tick,
+    /// dropping the oldest beyond `MAX_NOTIFICATIONS`.
+    pub fn push_notification(&mut self, message: String) {
+        self.notifications.push_back((message, Instant::now()));
+        if self.notifications.len() > MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+    }
+
+    /// The newest notification's text, if any.
+    pub fn latest_notification(&self) -> Option<&str> {
+        self.notifications.back().map(|(message, _)| message.as_str())
+    }
+
+    /// Handles `AppEvent::Tick`: expires old notifications and runs a due
+    /// auto-refresh.
+    pub fn on_tick(&mut self, now: Instant) {
+        self.notifications
+            .retain(|(_, shown)| now.saturating_duration_since(*shown) < NOTIFICATION_DURATION);
+        if self.auto_refresh_secs == 0 {
+            return;
+        }
+        let interval = Duration::from_secs(self.auto_refresh_secs);
+        let due = |sent: Instant| now.saturating_duration_since(sent) >= interval;
+        if self.last_diff_request.is_none_or(due) {
+            self.refresh_diff();
+        }
+    }
+
+    /// Switches to a newly spawned (or respawned) git worker.
+    ///
+    /// Stores its sender and generation, clears any stopped-worker notice, and
+    /// re-issues the current diff request so the panel refreshes from it. A new
+    /// worker starts out diffing whitespace with the configured context, so
+    /// `ignore_whitespace` and `context_lines` are sent first.
+    pub fn attach_git_worker(&mut self, git: &crate::git::AsyncGit) {
+        self.git_tx = Some(git.request_tx.clone());
+        self.git_generation = git.generation;
+        self.git_worker_error = None;
+        if self.ignore_whitespace {
+            let _ = git.request_tx.send(GitRequest::SetIgnoreWhitespace(true));
+        }
+        let _ = git.request_tx.send(GitRequest::SetContextLines(self.context_lines));
+        self.request_diff(self.diff_request(self.diff_mode));
+    }
+
+    /// Records that the git worker has stopped and clears the loading flag.
+    ///
+    /// Called when a send to the worker fails and on `AppEvent::GitWorkerStopped`.
+    pub fn mark_git_worker_stopped(&mut self, reason: String) {
+        self.diff_loading = false;
+        self.git_worker_error = Some(reason);
+    }
+
+    /// Re-selects `path` in the file list, or clamps the row `index` if it is gone.
+    ///
+    /// Clears the selection when the list is empty; leaves it unset when nothing
+    /// was selected before.
+    fn restore_file_selection(&mut self, path: Option<&str>, index: Option<usize>) {
+        let len = self.file_list_len();
+        if len == 0 {
+            self.file_list_state.select(None);
+            return;
+        }
+        let by_path = path
+            .and_then(|p| self.file_summaries.iter().position(|f| f.path == p))
+            .and_then(|idx| self.row_of_file(idx));
+        let restored = by_path.or_else(|| index.map(|i| i.min(len - 1)));
+        self.file_list_state.select(restored);
+        if let Some(idx) = restored.and_then(|row| self.file_at_row(row)) {
+            self.selected_file_index = idx;
+        }
+    }
+
+    /// Number of rows in the file list: the files matching the filter, or every
+    /// file without one.
+    pub fn file_list_len(&self) -> usize {
+        if self.file_filter.is_empty() {
+            self.file_summaries.len()
+        } else {
+            self.filtered_indices.len()
+        }
+    }
+
+    /// The `file_summaries` index of the file shown at file-list row `row`.
+    pub fn file_at_row(&self, row: usize) -> Option<usize> {
+        if self.file_filter.is_empty() {
+            (row < self.file_summaries.len()).then_some(row)
+        } else {
+            self.filtered_indices.get(row).copied()
+        }
+    }
+
+    /// The file-list row showing `file_summaries[idx]`, or `None` when the filter
+    /// hides it.
+    fn row_of_file(&self, idx: usize) -> Option<usize> {
+        if self.file_filter.is_empty() {
+            (idx < self.file_summaries.len()).then_some(idx)
+        } else {
+            self.filtered_indices.iter().position(|&i| i == idx)
+        }
+    }
+
+    /// The `file_summaries` index of the file selected in the file list.
+    pub fn selected_file(&self) -> Option<usize> {
+        self.file_list_state.selected().and_then(|row| self.file_at_row(row))
+    }
+
+    /// Indices of the files whose path matches `file_filter` (see
+    /// [`fuzzy_match`]); empty while the filter is.
+    fn matching_files(&self) -> Vec<usize> {
+        if self.file_filter.is_empty() {
+            return Vec::new();
+        }
+        let query = self.file_filter.to_lowercase();
+        self.file_summaries
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| fuzzy_match(&query, &f.path.to_lowercase()))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Opens the file filter prompt (`f` in the file list), keeping the query of
+    /// a filter already set.
+    pub fn begin_file_filter(&mut self) {
+        self.mode = Mode::FileFilter;
+    }
+
+    /// Appends `ch` to the file filter, or with `None` removes its last character,
+    /// and filters the list again. The selected file stays selected if it still
+    /// matches; otherwise the first match is.
+    pub fn edit_file_filter(&mut self, ch: Option<char>) {
+        let previous = self.selected_file();
+        match ch {
+            Some(ch) => self.file_filter.push(ch),
+            None => {
+                self.file_filter.pop();
+            }
+        }
+        self.filtered_indices = self.matching_files();
+        let row = previous
+            .and_then(|idx| self.row_of_file(idx))
+            .or_else(|| (self.file_list_len() > 0).then_some(0));
+        self.file_list_state.select(row);
+    }
+
+    /// Closes the file filter prompt, keeping the filter (`Enter`).
+    pub fn accept_file_filter(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    /// Clears the file filter and closes its prompt (`Esc`): the full list comes
+    /// back with the same file selected.
+    pub fn clear_file_filter(&mut self) {
+        let previous = self.selected_file();
+        self.file_filter.clear();
+        self.filtered_indices.clear();
+        self.file_list_state.select(previous);
+        self.mode = Mode::Normal;
+    }
+
+    /// Selects the file shown at `row` rows below the top of the file list's inner
+    /// area (mouse click), accounting for the list's scroll offset.
+    ///
+    /// Returns `true` if a file was selected; clicks below the last file are ignored.
+    pub fn click_file_row(&mut self, row: u16) -> bool {
+        let row = self.file_list_state.offset() + row as usize;
+        if row < self.file_list_len() {
+            self.file_list_state.select(Some(row));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the diff cursor to the line shown at `row` rows below the top of the
+    /// diff panel's inner area (mouse click).
+    ///
+    /// `row` is translated through `diff_scroll` the same way the renderer picks
+    /// its visible window. Clicks below the last line, or outside the cached
+    /// viewport height, are ignored.
+    pub fn click_diff_row(&mut self, row: u16) {
+        if row >= self.diff_viewport_height || self.diff_lines.is_empty() {
+            return;
+        }
+        let visible_start = self.diff_scroll.min(self.diff_lines.len() - 1);
+        let line = visible_start + row as usize;
+        if line < self.diff_lines.len() {
+            self.diff_cursor = line;
+        }
+    }
+
+    /// Jumps diff view to the selected file's first hunk (Enter or l on file list).
+    ///
+    /// Updates selected_file_index and sets diff_scroll to the file's first hunk
+    /// header line using `file_line_offsets`, clamped to the last file's offset if
+    /// the index is past the end, or 0 with no diff loaded yet. `hunk_cursor`
+    /// moves to the first hunk at or after that line. The comments panel follows:
+    /// its selection moves to the file's first comment passing the filter, if any.
+    ///
+    /// With the file list filtered, the selected row is mapped back to its file.
+    pub fn jump_to_selected_file(&mut self) {
+        if let Some(idx) = self.selected_file() {
+            self.selected_file_index = idx;
+            let offset = self.file_line_offsets.get(idx).or(self.file_line_offsets.last());
+            self.diff_scroll = offset.copied().unwrap_or(0);
+            self.diff_cursor = self.diff_scroll;
+            self.hunk_cursor = self
+                .hunk_offsets
+                .iter()
+                .position(|&line| line >= self.diff_scroll)
+                .unwrap_or(self.hunk_offsets.len().saturating_sub(1));
+            self.focus = PanelFocus::Diff;
+            self.select_file_comments(idx);
+        }
+    }
+
+    /// Selects the first comment on file `idx` that passes the filter, without
+    /// moving the diff (unlike [`AppState::select_comment`]).
+    fn select_file_comments(&mut self, idx: usize) {
+        let Some(path) = self.file_summaries.get(idx).map(|f| f.path.as_str()) else {
+            return;
+        };
+        let first = self
+            .visible_comment_indices()
+            .into_iter()
+            .find(|&i| self.comments[i].file_path == path);
+        if let Some(first) = first {
+            self.selected_comment = first;
+            self.comments_follow_selection = true;
+        }
+    }
+
+    /// Shrinks the diff (centre) panel by handing `RESIZE_STEP` back to the side
+    /// panels.
+    ///
+    /// Only whole steps are taken: if the centre would drop below
+    /// `MIN_CENTER_PCT`, nothing changes. That keeps `<` the exact inverse of `>`,
+    /// so shrinking and growing again always returns to the same widths.
+    pub fn shrink_diff_panel(&mut self) {
+        let (left, right) = RESIZE_STEP;
+        if self.center_pct < MIN_CENTER_PCT + left + right {
+            return;
+        }
+        self.center_pct -= left + right;
+        self.left_pct += left;
+        self.right_pct += right;
+    }
+
+    /// Grows the diff (centre) panel by taking `RESIZE_STEP` from the side panels.
+    ///
+    /// Only whole steps are taken: if the centre would exceed `MAX_CENTER_PCT` or
+    /// either side would drop below `MIN_SIDE_PCT`, nothing changes.
+    pub fn grow_diff_panel(&mut self) {
+        let (left, right) = RESIZE_STEP;
+        if self.center_pct + left + right > MAX_CENTER_PCT
+            || self.left_pct < MIN_SIDE_PCT + left
+            || self.right_pct < MIN_SIDE_PCT + right
+        {
+            return;
+        }
+        self.center_pct += left + right;
+        self.left_pct -= left;
+        self.right_pct -= right;
+    }
+
+    /// Sets the three panel percentages directly (`:layout L C R`).
+    ///
+    /// Rejects, with a message suitable for a toast, a split that does not sum to
+    /// 100, a diff panel below `MIN_CENTER_PCT`, or a side panel below
+    /// `MIN_SIDE_PCT`. The widths are left unchanged on error.
+    pub fn set_panel_percentages(
+        &mut self,
+        left: u16,
+        center: u16,
+        right: u16,
+    ) -> Result<(), String> {
+        let sum = u32::from(left) + u32::from(center) + u32::from(right);
+        if sum != 100 {
+            return Err(format!("layout must sum to 100 (got {sum})"));
+        }
+        if center < MIN_CENTER_PCT {
+            return Err(format!("diff panel must be at least {MIN_CENTER_PCT}%"));
+        }
+        if left < MIN_SIDE_PCT || right < MIN_SIDE_PCT {
+            return Err(format!("side panels must be at least {MIN_SIDE_PCT}%"));
+        }
+        (self.left_pct, self.center_pct, self.right_pct) = (left, center, right);
+        Ok(())
+    }
+
+    /// Shows or hides both side panels (`F`). Hiding them moves focus to the
+    /// diff.
+    pub fn toggle_diff_only(&mut self) {
+        self.diff_only = !self.diff_only;
+        if self.diff_only {
+            self.focus = PanelFocus::Diff;
+        }
+    }
+
+    /// Opens the `:` command line in the status bar.
+    pub fn begin_command(&mut self) {
+        self.command_line.clear();
+        self.mode = Mode::Command;
+    }
+
+    /// Opens the `/` search prompt in the status bar with an empty query.
+    pub fn begin_search(&mut self) {
+        self.search_query.clear();
+        self.mode = Mode::Search;
+    }
+
+    /// Leaves `Mode::Search`, discarding the typed query.
+    pub fn cancel_search(&mut self) {
+        self.search_query.clear();
+        self.mode = Mode::Normal;
+    }
+
+    /// Runs the typed search and returns to Normal mode: the diff cursor moves
+    /// to the next line after it containing the query (see
+    /// [`AppState::search_next`]). An empty query does nothing.
+    pub fn run_search(&mut self) {
+        self.mode = Mode::Normal;
+        if !self.search_query.is_empty() {
+            self.search_next(true);
+        }
+    }
+
+    /// Moves the diff cursor to the next (`n`) or, with `forward` unset, the
+    /// previous (`N`) line of `diff_lines` containing `search_query`, ignoring
+    /// case and wrapping around the ends.
+    ///
+    /// The match is scrolled to the top of the diff panel, which takes focus.
+    /// A toast reports a wrap or no match.
+    pub fn search_next(&mut self, forward: bool) {
+        if self.search_query.is_empty() {
+            self.push_notification("no search: type / first".to_owned());
+            return;
+        }
+        let query = self.search_query.to_lowercase();
+        let matches =
+            |row: &usize| line_text(&self.diff_lines[*row]).to_lowercase().contains(&query);
+        let (len, cursor) = (self.diff_lines.len(), self.diff_cursor);
+        // Every row once, starting past the cursor and ending on it.
+        let found = if forward {
+            (cursor + 1..len).chain(0..(cursor + 1).min(len)).find(matches)
+        } else {
+            (0..cursor.min(len)).rev().chain((cursor.min(len)..len).rev()).find(matches)
+        };
+        let Some(row) = found else {
+            self.push_notification(format!("not found: {}", self.search_query));
+            return;
+        };
+        if forward && row <= cursor {
+            self.push_notification("search hit bottom, continuing at top".to_owned());
+        } else if !forward && row >= cursor {
+            self.push_notification("search hit top, continuing at bottom".to_owned());
+        }
+        self.diff_cursor = row;
+        self.diff_scroll = row;
+        if let Some(at) = self.line_refs.get(row) {
+            self.hunk_cursor = at.hunk_index;
+        }
+        self.focus = PanelFocus::Diff;
+    }
+
+    /// Opens the diff-mode picker (`m`) with the current mode highlighted.
+    pub fn begin_mode_picker(&mut self) {
+        let current = self.current_mode_pick();
+        self.mode_pick = ModePick::ALL.iter().position(|&p| p == current).unwrap_or(0);
+        self.cancel_mode_refs();
+        self.mode = Mode::DiffModePicker;
+    }
+
+    /// The picker entry for the diff on screen (see [`ModePick::of_range`]).
+    pub fn current_mode_pick(&self) -> ModePick {
+        match (self.diff_mode, &self.diff_range) {
+            (DiffMode::Staged, _) => ModePick::Staged,
+            (DiffMode::BranchComparison, _) => ModePick::Branch,
+            (DiffMode::CommitRange, Some((from, to))) => ModePick::of_range(from, to),
+            (DiffMode::CommitRange, None) => ModePick::Range,
+            (DiffMode::Stash, _) => ModePick::Stash,
+            (DiffMode::Unstaged | DiffMode::Files, _) => ModePick::Unstaged,
+        }
+    }
+
+    /// Moves the picker highlight by `step` rows, wrapping around.
+    pub fn move_mode_pick(&mut self, step: isize) {
+        let len = ModePick::ALL.len() as isize;
+        self.mode_pick = (self.mode_pick as isize + step).rem_euclid(len) as usize;
+    }
+
+    /// Picks the highlighted entry (`Enter`).
+    ///
+    /// A plain mode is switched to and returned, so the caller can remember it for
+    /// the repository. Range, commit, and stash open the ref prompt instead,
+    /// prefilled with the current refs when the diff on screen is of that kind.
+    pub fn choose_mode_pick(&mut self) -> Option<DiffMode> {
+        let pick = ModePick::ALL[self.mode_pick];
+        if let Some(mode) = pick.diff_mode() {
+            self.mode = Mode::Normal;
+            self.switch_diff_mode(mode);
+            return Some(mode);
+        }
+        let refs = match (&self.diff_range, pick == self.current_mode_pick()) {
+            (Some((from, to)), true) => pick.prompt_refs(from, to),
+            _ => pick.default_refs().to_owned(),
+        };
+        self.cancel_mode_refs();
+        self.mode_pick_refs = Some(refs);
+        self.request_ref_names();
+        None
+    }
+
+    /// Asks the worker for the repository's ref names, once per run.
+    fn request_ref_names(&mut self) {
+        if self.ref_names.is_some() {
+            return;
+        }
+        let Some(tx) = self.git_tx.as_ref() else {
+            return;
+        };
+        if tx.send(GitRequest::ListRefs).is_err() {
+            self.mark_git_worker_stopped("worker thread exited".to_owned());
+            return;
+        }
+        self.ref_names = Some(Vec::new());
+    }
+
+    /// Stores the ref names listed by the worker (`AppEvent::RefsListed`).
+    pub fn apply_ref_names(&mut self, generation: u64, names: Vec<String>) {
+        if generation == self.git_generation {
+            self.ref_names = Some(names);
+        }
+    }
+
+    /// Returns the ref names that complete the ref being typed at the picker
+    /// prompt — the text after the last `..` or `...`, or all of it.
+    ///
+    /// While `Tab` is cycling, the completions of the prefix that was typed.
+    pub fn ref_completions(&self) -> Vec<&str> {
+        let (Some(refs), Some(names)) = (&self.mode_pick_refs, &self.ref_names) else {
+            return Vec::new();
+        };
+        let prefix = match &self.ref_tab {
+            Some((prefix, _)) => prefix.as_str(),
+            None => split_ref_word(refs).1,
+        };
+        names.iter().map(String::as_str).filter(|name| name.starts_with(prefix)).collect()
+    }
+
+    /// Completes the ref being typed at the picker prompt (`Tab`).
+    ///
+    /// A single match is filled in. Several matches are first extended to their
+    /// longest common prefix; once that is typed, each `Tab` fills in the next
+    /// match, wrapping around.
+    pub fn complete_ref(&mut self) {
+        let completions = self.ref_completions();
+        let Some(refs) = self.mode_pick_refs.as_deref() else {
+            return;
+        };
+        let (head, word) = split_ref_word(refs);
+        let (fill, tab) = match (&self.ref_tab, completions.as_slice()) {
+            (_, []) => return,
+            (Some((prefix, i)), _) => {
+                let next = (i + 1) % completions.len();
+                (completions[next], Some((prefix.clone(), next)))
+            }
+            (None, [only]) => (*only, None),
+            (None, [first, rest @ ..]) => {
+                let common = rest.iter().fold(*first, |common, name| {
+                    let len = common
+                        .char_indices()
+                        .zip(name.chars())
+                        .find(|((_, a), b)| a != b)
+                        .map_or(common.len().min(name.len()), |((i, _), _)| i);
+                    &common[..len]
+                });
+                if common.len() > word.len() {
+                    (common, None)
+                } else {
+                    (*first, Some((word.to_owned(), 0)))
+                }
+            }
+        };
+        let completed = format!("{head}{fill}");
+        self.mode_pick_refs = Some(completed);
+        self.ref_tab = tab;
+        self.ref_history_pos = None;
+        self.mode_pick_error = None;
+    }
+
+    /// Submits the refs typed at the picker prompt (`Enter`) for resolving.
+    ///
+    /// A range must be `FROM..TO`; a commit or stash `C` is the range `C^..C`.
+    /// The worker checks that both ends name commits and answers with
+    /// `AppEvent::RangeResolved` (see [`AppState::apply_range_resolved`]); until
+    /// then the prompt stays open. Problems are shown under the prompt.
+    pub fn submit_mode_refs(&mut self) {
+        let Some(refs) = self.mode_pick_refs.as_deref().map(str::trim) else {
+            return;
+        };
+        let (range, expected) = match ModePick::ALL[self.mode_pick] {
+            ModePick::Range => (
+                refs.split_once("..")
+                    .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+                    .filter(|(_, to)| !to.starts_with('.'))
+                    .map(|(from, to)| (from.to_owned(), to.to_owned())),
+                "expected FROM..TO",
+            ),
+            _ => (
+                (!refs.is_empty()).then(|| (format!("{refs}^"), refs.to_owned())),
+                "expected a commit",
+            ),
+        };
+        let Some((from, to)) = range else {
+            self.mode_pick_error = Some(expected.to_owned());
+            return;
+        };
+        let Some(tx) = self.git_tx.as_ref() else {
+            self.mode_pick_error = Some("no repository to resolve refs in".to_owned());
+            return;
+        };
+        if tx.send(GitRequest::ResolveRange { from: from.clone(), to: to.clone() }).is_err() {
+            self.mark_git_worker_stopped("worker thread exited".to_owned());
+            return;
+        }
+        self.pending_range = Some((from, to));
+        self.mode_pick_error = None;
+    }
+
+    /// Leaves the picker prompt for the list (`Esc`), dropping the typed refs and
+    /// any answer still on its way.
+    pub fn cancel_mode_refs(&mut self) {
+        self.mode_pick_refs = None;
+        self.pending_range = None;
+        self.mode_pick_error = None;
+        self.ref_tab = None;
+        self.ref_history_pos = None;
+    }
+
+    /// Types `ch` at the picker prompt, or deletes the last character for `None`.
+    pub fn edit_mode_refs(&mut self, ch: Option<char>) {
+        let Some(refs) = self.mode_pick_refs.as_mut() else {
+            return;
+        };
+        match ch {
+            Some(ch) => refs.push(ch),
+            None => {
+                refs.pop();
+            }
+        }
+        self.mode_pick_error = None;
+        self.ref_tab = None;
+        self.ref_history_pos = None;
+    }
+
+    /// The recent ranges of the highlighted picker entry's kind, as prompt text,
+    /// most recent first.
+    pub fn ref_history(&self) -> Vec<String> {
+        let pick = ModePick::ALL[self.mode_pick];
+        self.recent_ranges
+            .iter()
+            .filter(|(from, to)| ModePick::of_range(from, to) == pick)
+            .map(|(from, to)| pick.prompt_refs(from, to))
+            .collect()
+    }
+
+    /// Steps through the prompt history: `1` (`Up`) to an older entry, `-1`
+    /// (`Down`) to a newer one. Stepping past the newest restores the text that
+    /// was typed before.
+    pub fn step_ref_history(&mut self, step: isize) {
+        let history = self.ref_history();
+        if history.is_empty() || self.mode_pick_refs.is_none() {
+            return;
+        }
+        let pos = self.ref_history_pos.as_ref().map_or(-1, |(i, _)| *i as isize);
+        let next = (pos + step).clamp(-1, history.len() as isize - 1);
+        let typed = match self.ref_history_pos.take() {
+            Some((_, typed)) => typed,
+            None => self.mode_pick_refs.clone().unwrap_or_default(),
+        };
+        if next < 0 {
+            self.mode_pick_refs = Some(typed);
+        } else {
+            self.mode_pick_refs = Some(history[next as usize].clone());
+            self.ref_history_pos = Some((next as usize, typed));
+        }
+        self.mode_pick_error = None;
+        self.ref_tab = None;
+    }
+
+    /// Applies the worker's answer to a submitted range.
+    ///
+    /// When both refs resolved, the range becomes `diff_range` and the newest of
+    /// `recent_ranges`, the picker closes, and the diff is requested; returns
+    /// `true` so the caller can remember the range. Otherwise the error is shown
+    /// under the prompt. Answers for refs that are no longer pending, or from a
+    /// replaced worker, are ignored.
+    pub fn apply_range_resolved(
+        &mut self,
+        generation: u64,
+        range: (String, String),
+        result: Result<(), String>,
+    ) -> bool {
+        if generation != self.git_generation || self.pending_range.as_ref() != Some(&range) {
+            return false;
+        }
+        self.pending_range = None;
+        if let Err(message) = result {
+            self.mode_pick_error = Some(message);
+            return false;
+        }
+        self.recent_ranges.retain(|recent| *recent != range);
+        self.recent_ranges.insert(0, range.clone());
+        self.recent_ranges.truncate(airev_core::db::RECENT_DIFF_ARGS_MAX);
+        self.diff_range = Some(range);
+        self.cancel_mode_refs();
+        if self.mode == Mode::DiffModePicker {
+            self.mode = Mode::Normal;
+        }
+        self.switch_diff_mode(DiffMode::CommitRange);
+        true
+    }
+
+    /// Opens the picker's empty `FROM..TO` prompt (`Tab` into a commit range
+    /// before one was entered), so no diff is requested without refs.
+    pub fn begin_range_prompt(&mut self) {
+        self.begin_mode_picker();
+        self.mode_pick = ModePick::ALL.iter().position(|&p| p == ModePick::Range).unwrap_or(0);
+        self.choose_mode_pick();
+    }
+
+    /// Opens the picker prompt on `spec` and submits it (`:range A..B`).
+    pub fn begin_range(&mut self, spec: &str) {
+        self.begin_mode_picker();
+        self.mode_pick = ModePick::ALL.iter().position(|&p| p == ModePick::Range).unwrap_or(0);
+        self.mode_pick_refs = Some(spec.to_owned());
+        self.submit_mode_refs();
+    }
+
+    /// Leaves `Mode::Command`, discarding the typed text.
+    pub fn cancel_command(&mut self) {
+        self.command_line.clear();
+        self.mode = Mode::Normal;
+    }
+
+    /// Switches to the custom or built-in theme `name` and marks it for saving
+    /// on quit.
+    ///
+    /// Returns an error listing the known themes for an unknown name.
+    pub fn set_theme(&mut self, name: &str) -> Result<(), String> {
+        let (name, theme) = Theme::find(name, &self.custom_themes).ok_or_else(|| {
+            format!("unknown theme: {name} (try {})", self.theme_names().join(", "))
+        })?;
+        self.theme = theme.for_terminal(self.truecolor);
+        self.push_notification(format!("theme: {name}"));
+        self.theme_name = Some(name);
+        self.theme_changed = true;
+        Ok(())
+    }
+
+    /// Names `:theme` accepts, in `T` order: the built-ins, then the custom
+    /// themes not shadowing one.
+    pub fn theme_names(&self) -> Vec<&str> {
+        let custom = self.custom_themes.iter().map(|(name, _)| name.as_str());
+        let mut names: Vec<&str> = BUILTIN_THEMES.to_vec();
+        names.extend(custom.filter(|name| !BUILTIN_THEMES.contains(name)));
+        names
+    }
+
+    /// Switches to the next theme of [`AppState::theme_names`] (`T`), wrapping
+    /// around.
+    pub fn cycle_theme(&mut self) {
+        let names = self.theme_names();
+        let current = self.theme_name.as_deref().and_then(|n| names.iter().position(|&t| t == n));
+        let next = names[current.map_or(0, |i| (i + 1) % names.len())].to_owned();
+        // Every listed name is a known theme.
+        let _ = self.set_theme(&next);
+    }
+
+    /// Applies a config re-read on `SIGHUP`.
+    ///
+    /// Settings that only matter at startup (`default_diff_mode`, `alt_screen`,
+    /// and the git worker's options) wait for the next launch, as do
+    /// `panel_widths`, so a `:layout` or resize is never undone; a theme
+    /// picked with `:theme` / `T` likewise wins over the file until quit saves it.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.custom_themes = config.custom_themes.clone();
+        self.truecolor = config.force_truecolor || crate::theme::supports_truecolor();
+        if !self.theme_changed {
+            let found = config.theme.as_deref().and_then(|n| Theme::find(n, &self.custom_themes));
+            let (name, theme) = match found {
+                Some((name, theme)) => (Some(name), theme),
+                None => {
+                    let colorfgbg = std::env::var("COLORFGBG").ok();
+                    (None, Theme::for_background(colorfgbg.as_deref()))
+                }
+            };
+            self.theme_name = name;
+            self.theme = theme.for_terminal(self.truecolor);
+        }
+        self.wheel_scroll_lines = config.scroll_lines;
+        self.natural_scroll = config.natural_scroll;
+        self.max_diff_width = config.max_diff_width;
+        self.layout_orientation = config.layout;
+        self.status_segments = config.status_segments.clone();
+        self.auto_refresh_secs = config.auto_refresh_secs;
+        self.editor_command = config.editor_command.clone();
+        self.editor_suspend = config.editor_suspend;
+        self.show_quit_summary = config.quit_summary;
+        self.cursor_line = config.cursor_line;
+        self.note_ref = config.note_ref.clone();
+        self.note_replace = config.note_replace;
+        self.keymap = config.keybindings.clone();
+        self.push_notification("config reloaded".to_owned());
+    }
+
+    /// Runs the typed `:` command and returns to Normal mode.
+    ///
+    /// Supported: `layout L C R`, `theme NAME`, `range A..B` (which continues in
+    /// the diff-mode picker), and `note` (see [`AppState::write_review_note`]).
+    /// Errors and unknown commands are shown as a toast.
+    pub fn run_command(&mut self) {
+        let line = std::mem::take(&mut self.command_line);
+        self.mode = Mode::Normal;
+        let mut words = line.split_whitespace();
+        let result = match words.next() {
+            None => Ok(()),
+            Some("layout") => {
+                let pcts: Result<Vec<u16>, _> = words.map(str::parse).collect();
+                match pcts.as_deref() {
+                    Ok(&[left, center, right]) => self.set_panel_percentages(left, center, right),
+                    _ => Err("usage: :layout LEFT CENTER RIGHT".to_owned()),
+                }
+            }
+            Some("theme") => match (words.next(), words.next()) {
+                (Some(name), None) => self.set_theme(name),
+                _ => Err(format!("usage: :theme NAME ({})", self.theme_names().join(", "))),
+            },
+            Some("range") => match (words.next(), words.next()) {
+                (Some(spec), None) => {
+                    self.begin_range(spec);
+                    Ok(())
+                }
+                _ => Err("usage: :range FROM..TO".to_owned()),
+            },
+            Some("note") => match words.next() {
+                None => self.write_review_note(),
+                Some(_) => Err("usage: :note".to_owned()),
+            },
+            Some(other) => Err(format!("unknown command: {other}")),
+        };
+        if let Err(msg) = result {
+            self.push_notification(msg);
+        }
+    }
+
+    /// Applies a DbResultPayload to AppState.
+    ///
+    /// Called from the AppEvent::DbResult arm in main.rs. Updates file review
+    /// state based on the payload variant.
+    pub fn apply_db_result(&mut self, payload: crate::event::DbResultPayload) {
+        match payload {
+            crate::event::DbResultPayload::ReviewToggled { file_path, reviewed } => {
+                self.file_review_states.insert(file_path, reviewed);
+            }
+            crate::event::DbResultPayload::FilesMarkedReviewed(file_paths) => {
+                for file_path in file_paths {
+                    self.file_review_states.insert(file_path, true);
+                }
+            }
+            crate::event::DbResultPayload::ReviewToggleFailed { file_path, reviewed, message } => {
+                // Undo the optimistic toggle.
+                self.push_notification(format!("cannot toggle {file_path} reviewed: {message}"));
+                self.file_review_states.insert(file_path, reviewed);
+            }
+            crate::event::DbResultPayload::MarkReviewedFailed { file_paths, message } => {
+                // Undo the optimistic mark. `false` rather than no state, so a
+                // generated file is not auto-reviewed (and retried) on every refresh.
+                for file_path in &file_paths {
+                    self.file_review_states.insert(file_path.clone(), false);
+                }
+                let noun = if file_paths.len() == 1 { "file" } else { "files" };
+                self.push_notification(format!(
+                    "cannot mark {} {noun} reviewed: {message}",
+                    file_paths.len()
+                ));
+            }
+            crate::event::DbResultPayload::CommentSaved(comment) => {
+                self.comment_saving = false;
+                self.clear_comment_draft();
+                self.comments.push(comment);
+                self.push_notification("comment saved".to_owned());
+            }
+            crate::event::DbResultPayload::SaveFailed(message) => {
+                self.push_notification(message);
+            }
+            crate::event::DbResultPayload::CommentSaveFailed(message) => {
+                // Back to Insert with the draft intact so it can be retried.
+                self.comment_saving = false;
+                self.comment_error = Some(message);
+                self.mode = Mode::Insert;
+            }
+            crate::event::DbResultPayload::SessionOpened(opened) => {
+                self.apply_opened_session(*opened);
+            }
+            crate::event::DbResultPayload::SessionFailed(message) => {
+                self.session_loading = false;
+                self.push_startup_error(StartupError {
+                    title: "Cannot load review session".to_owned(),
+                    message: format!(".airev/reviews.db: {message}"),
+                    continue_label: "continue without saving comments or review state",
+                });
+                self.apply_pending_jump();
+            }
+            crate::event::DbResultPayload::CommentsExported(result) => {
+                self.push_notification(match result {
+                    Ok(path) => format!("comments exported to {path}"),
+                    Err(message) => message,
+                });
+            }
+        }
+    }
+
+    /// Applies the session loaded at startup and ends `session_loading`.
+    ///
+    /// Replaces comments, review states, and the comment filter with the
+    /// session's, restores its saved panel widths (over config `panel_widths`),
+    /// and hides the side panels if the session was left diff-only
+    /// (`--diff-only` keeps them hidden either way). When the session belongs to
+    /// a different diff mode than the one requested at startup (the repository's
+    /// remembered mode), switches to it and requests that diff, with its refs for
+    /// a remembered commit range. A context line count saved with the session
+    /// replaces the configured one and reloads the diff if it differs.
+    fn apply_opened_session(&mut self, opened: crate::session::OpenedSession) {
+        self.session_loading = false;
+        self.comments = opened.comments;
+        self.comment_filter =
+            CommentFilter::parse(&opened.session.comment_filter).unwrap_or_default();
+        self.file_review_states = opened.review_states.into_iter().collect();
+        self.diff_only |= opened.session.diff_only;
+        if let Some([left, center, right]) = opened.panel_widths {
+            // A split saved under other limits is dropped rather than clamped.
+            let _ = self.set_panel_percentages(left, center, right);
+        }
+        self.drift_warning = opened.drift_warning;
+        self.session = Some(opened.session);
+        if opened.diff_range.is_some() {
+            self.diff_range = opened.diff_range;
+        }
+        self.recent_ranges = opened.recent_ranges;
+        let saved_context = self.session.as_ref().and_then(|s| s.context_lines);
+        let saved_context = saved_context.filter(|&lines| lines != self.context_lines);
+        if let Some(lines) = saved_context {
+            self.set_context_lines(lines.min(MAX_CONTEXT_LINES));
+        }
+        if opened.diff_mode != self.diff_mode {
+            self.diff_mode = opened.diff_mode;
+            self.diff_scroll = 0;
+            self.diff_cursor = 0;
+            self.hunk_cursor = 0;
+            self.request_diff(self.diff_request(opened.diff_mode));
+        } else if saved_context.is_some() {
+            self.request_diff(self.diff_request(self.diff_mode));
+        }
+        self.apply_pending_jump();
+    }
+
+    /// Enters Insert mode with an empty comment draft for the selected file.
+    ///
+    /// Resets the tag picker to the `concern` / `minor` defaults. Does nothing
+    /// if no file is selected, since every comment must be anchored to a file.
+    pub fn begin_comment(&mut self) {
+        if self.current_file_path().is_none() {
+            return;
+        }
+        self.start_draft(None);
+    }
+
+    /// Enters Insert mode with an empty draft anchored to the hunk under the diff
+    /// cursor (`C`, or `c` on a hunk header).
+    ///
+    /// Does nothing if the cursor is not inside a hunk.
+    pub fn begin_hunk_comment(&mut self) {
+        let Some(anchor) = self.cursor_hunk_anchor() else {
+            return;
+        };
+        self.start_draft(Some(anchor));
+    }
+
+    /// Enters Insert mode with an empty draft anchored to the diff cursor's line
+    /// (`i` in the diff panel).
+    ///
+    /// Does nothing on a hunk header or file header, which have no line number.
+    pub fn begin_line_comment(&mut self) {
+        let Some(anchor) = self.cursor_line_anchor() else {
+            return;
+        };
+        self.start_draft(None);
+        self.draft_line = Some(anchor);
+    }
+
+    /// Resets the draft and tag picker defaults and enters Insert mode.
+    fn start_draft(&mut self, hunk: Option<(String, i64)>) {
+        self.comment_draft.clear();
+        self.has_unsaved_comments = false;
+        self.draft_severity = DEFAULT_SEVERITY;
+        self.draft_type = CommentType::default();
+        self.draft_hunk = hunk;
+        self.draft_line = None;
+        self.comment_error = None;
+        self.mode = Mode::Insert;
+    }
+
+    /// Returns the file path and new-side line number of the diff line under the
+    /// cursor.
+    ///
+    /// Comments store only a line number, so it is always on the new side. A
+    /// removed line has none and anchors to the first new-side line after it in
+    /// its hunk, or the last one before it at the end of a hunk. `None` on a
+    /// header or in a hunk with no new side, as in a deleted file.
+    pub fn cursor_line_anchor(&self) -> Option<(String, i64)> {
+        let index = self.diff_cursor;
+        let line_ref = self.line_refs.get(index)?;
+        if line_ref.origin == 'H' {
+            return None;
+        }
+        let in_hunk = |r: &&LineRef| r.hunk_index == line_ref.hunk_index && r.origin != 'H';
+        let after = || self.line_refs[index..].iter().take_while(in_hunk).find_map(|r| r.new_lineno);
+        let before =
+            || self.line_refs[..index].iter().rev().take_while(in_hunk).find_map(|r| r.new_lineno);
+        let line = line_ref.new_lineno.or_else(after).or_else(before)?;
+        let path = self.file_summaries.get(line_ref.file_index)?.path.clone();
+        Some((path, i64::from(line)))
+    }
+
+    /// Returns whether the diff cursor sits on a hunk header line.
+    pub fn cursor_on_hunk_header(&self) -> bool {
+        self.line_refs.get(self.diff_cursor).is_some_and(|r| r.origin == 'H')
+    }
+
+    /// Returns the file path and hunk offset of the hunk under the diff cursor.
+    ///
+    /// The offset is the hunk header's display line relative to the first line
+    /// of its file in `diff_lines`, so it stays valid when other files in the
+    /// diff change.
+    pub fn cursor_hunk_anchor(&self) -> Option<(String, i64)> {
+        let line_ref = self.line_refs.get(self.diff_cursor)?;
+        let header = *self.hunk_offsets.get(line_ref.hunk_index)?;
+        let file_start = *self.file_line_offsets.get(line_ref.file_index)?;
+        let path = self.file_summaries.get(line_ref.file_index)?.path.clone();
+        Some((path, header.checked_sub(file_start)? as i64))
+    }
+
+    /// Appends `ch` to the draft (Insert mode typing).
+    pub fn push_draft_char(&mut self, ch: char) {
+        self.comment_draft.push(ch);
+        self.sync_unsaved_flag();
+    }
+
+    /// Removes the last character of the draft (Insert mode `Backspace`).
+    pub fn pop_draft_char(&mut self) {
+        self.comment_draft.pop();
+        self.sync_unsaved_flag();
+    }
+
+    /// Sets `has_unsaved_comments` only while the draft has non-blank text, so
+    /// erasing everything typed does not leave the quit prompt armed.
+    fn sync_unsaved_flag(&mut self) {
+        self.has_unsaved_comments = !self.comment_draft.trim().is_empty();
+    }
+
+    /// Clears the current draft and returns to Normal mode.
+    ///
+    /// Used both when a draft is discarded (`Esc`) and once the DB task reports
+    /// it saved.
+    pub fn clear_comment_draft(&mut self) {
+        self.comment_draft.clear();
+        self.draft_hunk = None;
+        self.draft_line = None;
+        self.comment_error = None;
+        self.has_unsaved_comments = false;
+        self.mode = Mode::Normal;
+    }
+
+    /// Selects the comment at `idx` (clamped) and requests that it be scrolled into view.
+    ///
+    /// A hunk-level comment (`line_number` NULL, `hunk_offset` set) also scrolls the
+    /// diff to its hunk.
+    pub fn select_comment(&mut self, idx: usize) {
+        if self.comments.is_empty() {
+            return;
+        }
+        self.selected_comment = idx.min(self.comments.len() - 1);
+        self.comments_follow_selection = true;
+        self.scroll_to_comment_hunk();
+    }
+
+    /// Scrolls the diff to the hunk of the selected comment, if it is a hunk-level
+    /// comment on a file in the current diff.
+    fn scroll_to_comment_hunk(&mut self) {
+        let Some(comment) = self.comments.get(self.selected_comment) else {
+            return;
+        };
+        let (None, Some(offset)) = (comment.line_number, comment.hunk_offset) else {
+            return;
+        };
+        let Some(file_idx) = self.file_summaries.iter().position(|f| f.path == comment.file_path)
+        else {
+            return;
+        };
+        let Some(&file_start) = self.file_line_offsets.get(file_idx) else {
+            return;
+        };
+        let line = file_start + offset.max(0) as usize;
+        if line >= self.diff_lines.len() {
+            return;
+        }
+        self.diff_scroll = line;
+        self.diff_cursor = line;
+        if let Some(hunk) = self.hunk_offsets.iter().position(|&h| h == line) {
+            self.hunk_cursor = hunk;
+        }
+    }
+
+    /// Selects the next comment passing the filter (`gn`, or `j` in the comments
+    /// panel). Stays put at the end.
+    pub fn next_comment(&mut self) {
+        let next = self.visible_comment_indices().into_iter().find(|&i| i > self.selected_comment);
+        if let Some(idx) = next {
+            self.select_comment(idx);
+        }
+    }
+
+    /// Selects the previous comment passing the filter (`gp`, or `k` in the
+    /// comments panel). Stays put at the start.
+    pub fn prev_comment(&mut self) {
+        let prev =
+            self.visible_comment_indices().into_iter().rev().find(|&i| i < self.selected_comment);
+        if let Some(idx) = prev {
+            self.select_comment(idx);
+        }
+    }
+
+    /// Returns the indices into `comments` of the comments passing `comment_filter`,
+    /// without the resolved ones while `hide_resolved` is set.
+    pub fn visible_comment_indices(&self) -> Vec<usize> {
+        self.comments
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| self.comment_filter.allows(&c.severity))
+            .filter(|(_, c)| !(self.hide_resolved && c.resolved_at.is_some()))
+            .filter(|(_, c)| self.severity_filter.is_none_or(|s| s.as_str() == c.severity))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Steps `severity_filter` through all → critical → major → minor → info →
+    /// all (`!`), moving a hidden selection like [`AppState::cycle_comment_filter`].
+    pub fn cycle_severity_filter(&mut self) {
+        self.severity_filter = match self.severity_filter {
+            None => Some(Severity::Critical),
+            Some(severity) => severity.next(),
+        };
+        self.keep_selection_visible();
+        let shown = self.severity_filter.map_or("all", Severity::as_str);
+        self.push_notification(format!("showing {shown} comments"));
+    }
+
+    /// Whether diff line `index` carries a line comment shown in the comments
+    /// panel, for the diff's severity marks while `severity_filter` is set. Lines
+    /// are matched by new-side number, which [`AppState::cursor_line_anchor`]
+    /// stores, so removed lines are never marked.
+    pub fn line_has_visible_comment(&self, index: usize) -> bool {
+        let Some(line_ref) = self.line_refs.get(index) else {
+            return false;
+        };
+        let line = line_ref.new_lineno;
+        let (Some(file), Some(line)) = (self.file_summaries.get(line_ref.file_index), line) else {
+            return false;
+        };
+        self.visible_comment_indices().into_iter().any(|i| {
+            let c = &self.comments[i];
+            c.file_path == file.path && c.line_number == Some(i64::from(line))
+        })
+    }
+
+    /// Advances `comment_filter` to the next setting (`f`).
+    ///
+    /// If the selected comment is hidden by the new filter, the selection moves
+    /// to the next visible comment (or the last one if none follow). Persisting
+    /// the filter to the session is left to the caller.
+    pub fn cycle_comment_filter(&mut self) {
+        self.comment_filter = self.comment_filter.next();
+        self.keep_selection_visible();
+    }
+
+    /// Shows or hides resolved comments (`X`), moving a hidden selection like
+    /// [`AppState::cycle_comment_filter`].
+    pub fn toggle_hide_resolved(&mut self) {
+        self.hide_resolved = !self.hide_resolved;
+        self.keep_selection_visible();
+        let shown = if self.hide_resolved { "hidden" } else { "shown" };
+        self.push_notification(format!("resolved comments {shown}"));
+    }
+
+    /// Resolves the selected comment at `now` (Unix seconds), or reopens it if
+    /// it was resolved (`x` in the comments panel).
+    ///
+    /// Returns the comment's id and new state for the caller to persist, or
+    /// `None` when no visible comment is selected. While `hide_resolved` is
+    /// set, the resolved comment disappears and the selection moves on.
+    pub fn toggle_comment_resolved(&mut self, now: i64) -> Option<(String, bool)> {
+        if !self.visible_comment_indices().contains(&self.selected_comment) {
+            return None;
+        }
+        let comment = &mut self.comments[self.selected_comment];
+        comment.resolved_at = match comment.resolved_at {
+            Some(_) => None,
+            None => Some(now),
+        };
+        let toggled = (comment.id.clone(), comment.resolved_at.is_some());
+        self.keep_selection_visible();
+        Some(toggled)
+    }
+
+    /// Moves the selection to the next visible comment (or the last one if none
+    /// follow) when the selected comment is hidden. Nothing changes while every
+    /// comment is hidden.
+    fn keep_selection_visible(&mut self) {
+        let visible = self.visible_comment_indices();
+        if visible.contains(&self.selected_comment) {
+            return;
+        }
+        let replacement = visible
+            .iter()
+            .copied()
+            .find(|&i| i > self.selected_comment)
+            .or(visible.last().copied());
+        if let Some(idx) = replacement {
+            self.select_comment(idx);
+        }
+    }
+
+    /// Cycles the draft comment type forward (`delta = 1`) or backward (`delta = -1`).
+    pub fn cycle_draft_type(&mut self, delta: isize) {
+        let all = CommentType::ALL;
+        let idx = all.iter().position(|t| *t == self.draft_type).unwrap_or(0) as isize;
+        let next = (idx + delta).rem_euclid(all.len() as isize) as usize;
+        self.draft_type = all[next];
+    }
+
+    /// Returns the summary of the file the diff cursor is in, if any.
+    pub fn cursor_file(&self) -> Option<&FileSummary> {
+        let line_ref = self.line_refs.get(self.diff_cursor)?;
+        self.file_summaries.get(line_ref.file_index)
+    }
+
+    /// Returns the repo-relative path of the currently selected file, if any.
+    ///
+    /// Looks up the file-list selection in `file_summaries`. Returns `None` if
+    /// no files are loaded or the selection is out of bounds.
+    pub fn current_file_path(&self) -> Option<&str> {
+        self.selected_file().and_then(|idx| self.file_summaries.get(idx)).map(|f| f.path.as_str())
+    }
+
+    /// Number of files in the current diff marked reviewed.
+    pub fn reviewed_count(&self) -> usize {
+        self.file_summaries
+            .iter()
+            .filter(|f| self.file_review_states.get(&f.path).copied().unwrap_or(false))
+            .count()
+    }
+
+    /// The line printed after a normal quit, e.g. "Reviewed 9/12 files,
+    /// 5 comments (2 critical) in session <id>." `None` without a session.
+    pub fn quit_summary(&self) -> Option<String> {
+        let session = self.session.as_ref()?;
+        let comments = self.comments.len();
+        let critical = self.comments.iter().filter(|c| c.severity == "critical").count();
+        let plural = if comments == 1 { "" } else { "s" };
+        let critical = if critical > 0 { format!(" ({critical} critical)") } else { String::new() };
+        Some(format!(
+            "Reviewed {}/{} files, {comments} comment{plural}{critical} in session {}.",
+            self.reviewed_count(),
+            self.file_summaries.len(),
+            session.id
+        ))
+    }
+
+    /// The review as markdown, for `:note`: the [`AppState::quit_summary`] line,
+    /// the comments grouped by file in file-list order, formatted like the `E`
+    /// export by [`airev_core::types::markdown_file_section`], and the files not
+    /// yet reviewed. `None` without a session.
+    pub fn review_markdown(&self) -> Option<String> {
+        let mut out = format!("# airev review\n\n{}\n", self.quit_summary()?);
+        let mut paths: Vec<&str> = self.file_summaries.iter().map(|f| f.path.as_str()).collect();
+        for comment in &self.comments {
+            if !paths.contains(&comment.file_path.as_str()) {
+                paths.push(&comment.file_path);
+            }
+        }
+        for path in paths {
+            let mut comments = self.comments.iter().filter(|c| c.file_path == path).peekable();
+            if comments.peek().is_none() {
+                continue;
+            }
+            out.push_str(&airev_core::types::markdown_file_section(path, comments));
+        }
+        let unreviewed: Vec<&str> = self
+            .file_summaries
+            .iter()
+            .filter(|f| !self.file_review_states.get(&f.path).copied().unwrap_or(false))
+            .map(|f| f.path.as_str())
+            .collect();
+        if !unreviewed.is_empty() {
+            out.push_str("\n## Not reviewed\n\n");
+            for path in unreviewed {
+                out.push_str(&format!("- {path}\n"));
+            }
+        }
+        Some(out)
+    }
+
+    /// Asks the worker to save [`AppState::review_markdown`] as the git note on
+    /// `HEAD` (`:note`). The outcome arrives as `AppEvent::NoteWritten`.
+    pub fn write_review_note(&mut self) -> Result<(), String> {
+        let text = self.review_markdown().ok_or("no review session to save")?;
+        let tx = self.git_tx.as_ref().ok_or("git notes need a repository")?;
+        let notes_ref = self.note_ref.clone();
+        let request = GitRequest::WriteNote { notes_ref, text, replace: self.note_replace };
+        if tx.send(request).is_err() {
+            self.mark_git_worker_stopped("worker thread exited".to_owned());
+            return Err("git worker stopped".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Reports the outcome of `:note` (`AppEvent::NoteWritten`) as a toast,
+    /// unless it comes from a replaced worker.
+    pub fn apply_note_written(
+        &mut self,
+        generation: u64,
+        notes_ref: &str,
+        result: Result<String, String>,
+    ) {
+        if generation != self.git_generation {
+            return;
+        }
+        let message = match result {
+            Ok(sha) => {
+                let short = sha.get(..7).unwrap_or(&sha);
+                format!("review saved as a note on {short} ({notes_ref})")
+            }
+            Err(e) => format!("cannot write note: {e}"),
+        };
+        self.push_notification(message);
+    }
+
+    /// Returns the cursor's file and new-file line, as opened by `e` and copied by
+    /// `y` / `Y`.
+    ///
+    /// That is the diff cursor's file, or the selected file when the file list has
+    /// focus. The line is the first new-file line at or after the cursor (the
+    /// file's first hunk from the file list) within that file, or 1 when there is
+    /// none, as in a deleted file.
+    pub fn editor_target(&self) -> Option<(&str, u32)> {
+        let row = match self.focus {
+            PanelFocus::FileList => {
+                *self.file_line_offsets.get(self.selected_file()?)?
+            }
+            PanelFocus::Diff | PanelFocus::Comments => self.diff_cursor,
+        };
+        let file_index = self.line_refs.get(row)?.file_index;
+        let line = self.line_refs[row..]
+            .iter()
+            .take_while(|r| r.file_index == file_index)
+            .find_map(|r| r.new_lineno)
+            .unwrap_or(1);
+        Some((self.file_summaries.get(file_index)?.path.as_str(), line))
+    }
+
+    /// Returns the selected file's directory and the unreviewed files under it.
+    ///
+    /// The directory is the selected path up to and including its last `/`; every
+    /// file below it matches, including nested subdirectories. A root-level file
+    /// (no `/`) matches only the other root-level files, since "everything under
+    /// the root" would be the whole diff. The directory is `""` in that case.
+    pub fn unreviewed_files_in_selected_dir(&self) -> Option<(String, Vec<String>)> {
+        let selected = self.current_file_path()?;
+        let dir = selected.rfind('/').map_or("", |i| &selected[..=i]);
+        let paths = self
+            .file_summaries
+            .iter()
+            .map(|f| f.path.as_str())
+            .filter(|p| match dir {
+                "" => !p.contains('/'),
+                _ => p.starts_with(dir),
+            })
+            .filter(|p| !self.file_review_states.get(*p).copied().unwrap_or(false))
+            .map(str::to_owned)
+            .collect();
+        Some((dir.to_owned(), paths))
+    }
+
+    /// Marks generated files that have never been reviewed or unreviewed as
+    /// reviewed, and returns their paths so the caller can persist them.
+    ///
+    /// Files with any recorded state are left alone, so un-marking a generated
+    /// file sticks across refreshes and sessions.
+    pub fn auto_review_generated(&mut self) -> Vec<String> {
+        let paths: Vec<String> = self
+            .file_summaries
+            .iter()
+            .filter(|f| f.generated && !self.file_review_states.contains_key(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        for path in &paths {
+            self.file_review_states.insert(path.clone(), true);
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::types::{GitResultPayload, OwnedDiffHunk};
+    use ratatui::text::Line;
+
+    fn file(path: &str) -> FileSummary {
+        FileSummary {
+            path: path.to_owned(),
+            status: 'M',
+            added: 1,
+            removed: 0,
+            generated: false,
+            binary: false,
+            language: "plain",
+        }
+    }
+
+    fn payload(paths: &[&str]) -> GitResultPayload {
+        GitResultPayload {
+            generation: 0,
+            mode: DiffMode::Unstaged,
+            status: DiffStatus::Loaded,
+            hunks: Vec::new(),
+            files: paths.iter().map(|p| file(p)).collect(),
+            highlighted_lines: Vec::new(),
+            hunk_offsets: Vec::new(),
+            file_line_offsets: Vec::new(),
+            line_refs: Vec::new(),
+            timing: DiffTiming::default(),
+            base: None,
+        }
+    }
+
+    #[test]
+    fn diff_totals_follow_each_result() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs", "b.rs"]);
+        (result.files[1].added, result.files[1].removed) = (3, 2);
+        state.apply_git_result(result);
+        assert_eq!(state.diff_totals, (4, 2));
+
+        state.apply_git_result(payload(&["a.rs"]));
+        assert_eq!(state.diff_totals, (1, 0));
+    }
+
+    #[test]
+    fn dropped_worker_clears_loading_flag() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        drop(rx);
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+
+        state.request_diff(GitRequest::LoadDiff(DiffMode::Staged));
+
+        assert!(!state.diff_loading);
+        assert!(state.git_worker_error.is_some());
+    }
+
+    #[test]
+    fn directory_selection_matches_nested_files_or_root_files() {
+        let mut state = AppState::default();
+        let paths = ["README.md", "src/git/a.rs", "src/git/sub/b.rs", "src/main.rs", "Cargo.toml"];
+        state.apply_git_result(payload(&paths));
+        state.file_review_states.insert("src/git/sub/b.rs".to_owned(), true);
+
+        state.file_list_state.select(Some(1));
+        let (dir, paths) = state.unreviewed_files_in_selected_dir().unwrap();
+        assert_eq!(dir, "src/git/");
+        assert_eq!(paths, vec!["src/git/a.rs"]);
+
+        state.file_list_state.select(Some(0));
+        let (dir, paths) = state.unreviewed_files_in_selected_dir().unwrap();
+        assert_eq!(dir, "");
+        assert_eq!(paths, vec!["README.md", "Cargo.toml"]);
+    }
+
+    #[test]
+    fn repeated_git_failures_raise_banner_and_pause_refresh() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        state.apply_git_result(payload(&["a.rs"]));
+        let failed = || GitResultPayload {
+            status: DiffStatus::Error("could not find repository".to_owned()),
+            ..payload(&[])
+        };
+
+        // Transient failures keep the last good diff and stay quiet.
+        for _ in 1..REPO_UNAVAILABLE_AFTER {
+            state.apply_git_result(failed());
+        }
+        assert_eq!(state.file_summaries.len(), 1);
+        assert!(state.repo_unavailable.is_none());
+
+        state.apply_git_result(failed());
+        assert!(state.repo_unavailable.is_some());
+        assert_eq!(state.file_summaries.len(), 1);
+        state.refresh_diff();
+        assert!(rx.try_recv().is_err());
+
+        state.apply_git_result(payload(&["a.rs"]));
+        assert_eq!((state.git_failures, state.repo_unavailable.as_deref()), (0, None));
+    }
+
+    #[test]
+    fn shrink_then_grow_restores_panel_widths() {
+        let mut state = AppState::default();
+        let start = (state.left_pct, state.center_pct, state.right_pct);
+        for _ in 0..3 {
+            state.shrink_diff_panel();
+        }
+        for _ in 0..3 {
+            state.grow_diff_panel();
+        }
+        assert_eq!((state.left_pct, state.center_pct, state.right_pct), start);
+
+        // At the limit a step is skipped rather than taken partially.
+        state.set_panel_percentages(30, 22, 48).unwrap();
+        state.shrink_diff_panel();
+        assert_eq!(state.center_pct, 22);
+        state.grow_diff_panel();
+        state.shrink_diff_panel();
+        assert_eq!((state.left_pct, state.center_pct, state.right_pct), (30, 22, 48));
+    }
+
+    #[test]
+    fn layout_command_validates_percentages() {
+        let mut state =
+            AppState { command_line: "layout 25 50 25".to_owned(), ..AppState::default() };
+        state.run_command();
+        assert_eq!((state.left_pct, state.center_pct, state.right_pct), (25, 50, 25));
+        assert_eq!(state.mode, Mode::Normal);
+
+        for bad in ["layout 20 60 30", "layout 40 10 50", "layout 2 60 38", "layout 20 60", "lay"] {
+            state.command_line = bad.to_owned();
+            state.run_command();
+            assert_eq!((state.left_pct, state.center_pct, state.right_pct), (25, 50, 25));
+            assert!(state.latest_notification().is_some(), "{bad}");
+            state.notifications.clear();
+        }
+    }
+
+    #[test]
+    fn hunk_line_counts_tally_each_hunk() {
+        let line = |hunk_index, origin| LineRef {
+            file_index: 0,
+            hunk_index,
+            origin,
+            old_lineno: None,
+            new_lineno: None,
+        };
+        let refs = [
+            line(0, 'H'),
+            line(0, '-'),
+            line(0, '+'),
+            line(0, '+'),
+            line(1, 'H'),
+            line(1, ' '),
+            line(1, '-'),
+        ];
+        assert_eq!(hunk_line_counts(&refs), vec![(2, 1), (0, 1)]);
+        assert!(hunk_line_counts(&[]).is_empty());
+    }
+
+    #[test]
+    fn line_number_width_fits_the_largest_line_on_either_side() {
+        let line = |old_lineno, new_lineno| LineRef {
+            file_index: 0,
+            hunk_index: 0,
+            origin: ' ',
+            old_lineno,
+            new_lineno,
+        };
+        let refs = [line(None, None), line(Some(1204), None), line(Some(98), Some(99))];
+        assert_eq!(line_number_width(&refs), 4);
+        assert_eq!(line_number_width(&[]), 1);
+    }
+
+    /// Builds a payload whose files each have the given hunk headers, every hunk
+    /// rendered as a header, a context line, and an added line.
+    fn hunk_payload(files: &[(&str, &[&str])]) -> GitResultPayload {
+        let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+        let mut p = payload(&paths);
+        for (file_index, (_, headers)) in files.iter().enumerate() {
+            p.file_line_offsets.push(p.line_refs.len());
+            for header in *headers {
+                let hunk_index = p.hunks.len();
+                p.hunk_offsets.push(p.line_refs.len());
+                p.hunks.push(OwnedDiffHunk {
+                    header: (*header).to_owned(),
+                    old_start: 1,
+                    new_start: 1,
+                    lines: Vec::new(),
+                });
+                for origin in ['H', ' ', '+'] {
+                    p.line_refs.push(LineRef {
+                        file_index,
+                        hunk_index,
+                        origin,
+                        old_lineno: None,
+                        new_lineno: None,
+                    });
+                    p.highlighted_lines.push(Line::raw(origin.to_string()));
+                }
+            }
+        }
+        p
+    }
+
+    #[test]
+    fn folded_hunk_hides_its_body_and_survives_a_refresh() {
+        let files: [(&str, &[&str]); 2] =
+            [("a.rs", &["@@ -1 +1 @@ a"]), ("b.rs", &["@@ -1 +1 @@ b1", "@@ -9 +9 @@ b2"])];
+        let mut state = AppState::default();
+        state.apply_git_result(hunk_payload(&files));
+        (state.diff_cursor, state.diff_scroll, state.hunk_cursor) = (4, 4, 1);
+
+        state.toggle_hunk_fold();
+        assert_eq!(state.diff_lines.len(), 7);
+        assert_eq!(state.hunk_offsets, vec![0, 3, 4]);
+        assert_eq!(state.file_line_offsets, vec![0, 3]);
+        assert_eq!((state.diff_cursor, state.diff_scroll), (3, 3), "cursor moves to the header");
+        assert_eq!(state.collapsed_hunks[&1].0.len(), 2);
+
+        state.next_hunk();
+        assert_eq!(state.diff_cursor, 4);
+        assert_eq!(state.line_refs[4].hunk_index, 2);
+
+        state.apply_git_result(hunk_payload(&files));
+        assert_eq!(state.hunk_offsets, vec![0, 3, 4], "b1 is folded again");
+        assert_eq!(state.diff_cursor, 4);
+
+        state.diff_cursor = 3;
+        state.toggle_hunk_fold();
+        assert_eq!((state.diff_lines.len(), state.line_refs.len()), (9, 9));
+        assert_eq!(state.hunk_offsets, vec![0, 3, 6]);
+        assert!(state.collapsed_hunks.is_empty());
+    }
+
+    #[test]
+    fn hunk_folds_are_kept_per_file_until_the_mode_changes() {
+        let a: (&str, &[&str]) = ("a.rs", &["@@ -1 +1 @@ a"]);
+        let b: (&str, &[&str]) = ("b.rs", &["@@ -1 +1 @@ b"]);
+        let mut state = AppState::default();
+        state.apply_git_result(hunk_payload(&[a, b]));
+        state.toggle_hunk_fold();
+        assert_eq!(state.collapsed_hunks.keys().collect::<Vec<_>>(), [&0]);
+
+        state.file_list_state.select(Some(1));
+        state.jump_to_selected_file();
+        state.apply_git_result(hunk_payload(&[b]));
+        assert!(state.collapsed_hunks.is_empty(), "b.rs has the same hunk key but is not folded");
+        state.apply_git_result(hunk_payload(&[a, b]));
+        state.file_list_state.select(Some(0));
+        state.jump_to_selected_file();
+        assert_eq!(state.collapsed_hunks.keys().collect::<Vec<_>>(), [&0], "a.rs is folded again");
+        assert_eq!(state.diff_lines.len(), 4);
+
+        let mut staged = hunk_payload(&[a, b]);
+        staged.mode = DiffMode::Staged;
+        state.apply_git_result(staged);
+        assert!(state.collapsed_hunks.is_empty() && state.folded_hunks.is_empty());
+    }
+
+    #[test]
+    fn generated_files_start_folded_until_unfolded() {
+        let files: [(&str, &[&str]); 2] =
+            [("a.rs", &["@@ -1 +1 @@ a"]), ("gen.rs", &["@@ -1 +1 @@ g"])];
+        let generated = || {
+            let mut p = hunk_payload(&files);
+            p.files[1].generated = true;
+            p
+        };
+        let mut state = AppState::default();
+        state.apply_git_result(generated());
+        assert_eq!(state.collapsed_hunks.keys().collect::<Vec<_>>(), [&1]);
+        assert_eq!(state.diff_lines.len(), 4);
+
+        state.diff_cursor = 3;
+        state.toggle_hunk_fold();
+        assert_eq!(state.diff_lines.len(), 6);
+        state.apply_git_result(generated());
+        assert!(state.collapsed_hunks.is_empty(), "an unfolded generated hunk stays open");
+    }
+
+    #[test]
+    fn pending_count_repeats_motions_once() {
+        let mut state = AppState::default();
+        state.apply_git_result(hunk_payload(&[
+            ("a.rs", &["@@ -1 +1 @@ a1", "@@ -5 +5 @@ a2"]),
+            ("b.rs", &["@@ -1 +1 @@ b1", "@@ -9 +9 @@ b2"]),
+        ]));
+        state.focus = PanelFocus::Diff;
+
+        for digit in [1, 2] {
+            state.push_count_digit(digit);
+        }
+        state.scroll_down(1);
+        assert_eq!((state.diff_scroll, state.pending_count), (12, None));
+        state.scroll_down(1);
+        assert_eq!(state.diff_scroll, 13, "the count is used up");
+
+        state.push_count_digit(3);
+        state.next_hunk();
+        assert_eq!((state.hunk_cursor, state.diff_cursor), (3, 9));
+        state.push_count_digit(9);
+        state.prev_hunk();
+        assert_eq!(state.hunk_cursor, 0, "stops at the first hunk");
+
+        for _ in 0..6 {
+            state.push_count_digit(9);
+        }
+        assert_eq!(state.pending_count, Some(MAX_COUNT));
+    }
+
+    #[test]
+    fn refresh_keeps_cursor_on_same_hunk_after_reorder() {
+        let mut state = AppState::default();
+        state.apply_git_result(hunk_payload(&[
+            ("a.rs", &["@@ -1 +1 @@ a"]),
+            ("b.rs", &["@@ -1 +1 @@ b1", "@@ -9 +9 @@ b2"]),
+        ]));
+        state.file_review_states.insert("b.rs".to_owned(), true);
+        // Cursor on b2's added line, one row below the top of the viewport.
+        (state.diff_cursor, state.diff_scroll, state.hunk_cursor) = (8, 7, 2);
+
+        // A new file sorts first, b1 disappears, and a.rs moves to the end.
+        state.apply_git_result(hunk_payload(&[
+            ("new.rs", &["@@ -1 +1 @@ n"]),
+            ("b.rs", &["@@ -9 +9 @@ b2"]),
+            ("a.rs", &["@@ -1 +1 @@ a"]),
+        ]));
+        assert_eq!((state.diff_cursor, state.diff_scroll, state.hunk_cursor), (5, 4, 1));
+        let at = state.line_refs[state.diff_cursor];
+        assert_eq!(state.file_summaries[at.file_index].path, "b.rs");
+        assert_eq!(state.file_review_states.get("b.rs"), Some(&true));
+
+        // The hunk is gone but its file remains: land on the file's first line.
+        state.apply_git_result(hunk_payload(&[
+            ("b.rs", &["@@ -20 +20 @@ b3"]),
+            ("a.rs", &["@@ -1 +1 @@ a"]),
+        ]));
+        assert_eq!((state.diff_cursor, state.diff_scroll), (0, 0));
+    }
+
+    #[test]
+    fn pending_jump_waits_for_session_then_selects_file_and_line() {
+        let numbered = || {
+            let mut p =
+                hunk_payload(&[("a.rs", &["@@ a @@"]), ("b.rs", &["@@ b1 @@", "@@ b2 @@"])]);
+            for (row, r) in p.line_refs.iter_mut().enumerate() {
+                r.new_lineno = (r.origin != 'H').then_some(row as u32);
+            }
+            p
+        };
+        let mut state = AppState {
+            session_loading: true,
+            pending_jump: Some(("./b.rs".to_owned(), Some(7))),
+            ..AppState::default()
+        };
+        state.apply_git_result(numbered());
+        assert!(state.pending_jump.is_some());
+        assert_eq!(state.file_list_state.selected(), None, "no jump before the session loads");
+
+        state.session_loading = false;
+        state.apply_pending_jump();
+        assert!(state.pending_jump.is_none());
+        assert_eq!(state.file_list_state.selected(), Some(1));
+        assert_eq!((state.diff_cursor, state.hunk_cursor), (7, 2));
+
+        state.pending_jump = Some(("missing.rs".to_owned(), None));
+        state.file_list_state.select(Some(0));
+        state.apply_git_result(numbered());
+        assert_eq!(state.file_list_state.selected(), Some(0));
+        assert!(state.latest_notification().is_some_and(|msg| msg.contains("missing.rs")));
+    }
+
+    #[test]
+    fn editor_target_follows_cursor_or_selected_file() {
+        let mut p = hunk_payload(&[("a.rs", &["@@ a @@"]), ("b.rs", &["@@ b @@"])]);
+        for (row, r) in p.line_refs.iter_mut().enumerate() {
+            r.new_lineno = (r.origin != 'H').then_some(10 + row as u32);
+        }
+        let mut state = AppState::default();
+        state.apply_git_result(p);
+
+        state.focus = PanelFocus::Diff;
+        state.diff_cursor = 3;
+        assert_eq!(state.editor_target(), Some(("b.rs", 14)));
+
+        state.focus = PanelFocus::FileList;
+        state.file_list_state.select(Some(0));
+        assert_eq!(state.editor_target(), Some(("a.rs", 11)));
+    }
+
+    fn session() -> airev_core::types::Session {
+        airev_core::types::Session {
+            id: "0123456789abcdef".to_owned(),
+            repo_path: "/tmp/repo".to_owned(),
+            diff_mode: "Staged".to_owned(),
+            diff_args: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            comment_filter: "all".to_owned(),
+            head_sha: String::new(),
+            diff_only: false,
+            context_lines: None,
+        }
+    }
+
+    #[test]
+    fn opened_session_ends_loading_and_switches_to_its_mode() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), session_loading: true, ..AppState::default() };
+        let session = airev_core::types::Session {
+            comment_filter: "critical".to_owned(),
+            diff_only: true,
+            context_lines: Some(3),
+            ..session()
+        };
+        state.apply_db_result(crate::event::DbResultPayload::SessionOpened(Box::new(
+            crate::session::OpenedSession {
+                session,
+                diff_mode: DiffMode::Staged,
+                diff_range: None,
+                recent_ranges: Vec::new(),
+                review_states: vec![("a.rs".to_owned(), true)],
+                comments: Vec::new(),
+                drift_warning: None,
+                panel_widths: Some([30, 45, 25]),
+            },
+        )));
+
+        assert!(!state.session_loading);
+        assert_eq!(state.diff_mode, DiffMode::Staged);
+        assert_eq!(state.comment_filter, CommentFilter::CriticalOnly);
+        assert!(state.diff_only);
+        assert_eq!((state.left_pct, state.center_pct, state.right_pct), (30, 45, 25));
+        assert_eq!(state.file_review_states.get("a.rs"), Some(&true));
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::LoadDiff(DiffMode::Staged))));
+    }
+
+    #[test]
+    fn quit_summary_counts_reviewed_files_and_critical_comments() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        assert_eq!(state.quit_summary(), None, "nothing to summarize without a session");
+
+        state.session = Some(session());
+        state.apply_git_result(payload(&["a.rs", "b.rs", "c.rs"]));
+        state.file_review_states.insert("a.rs".to_owned(), true);
+        state.comments = vec![comment("critical"), comment("minor")];
+        assert_eq!(
+            state.quit_summary().as_deref(),
+            Some("Reviewed 1/3 files, 2 comments (1 critical) in session 0123456789abcdef.")
+        );
+
+        state.comments[0].line_number = Some(7);
+        state.comments[0].body = "off by one\nin the loop".to_owned();
+        state.comments[1].file_path = "gone.rs".to_owned();
+        state.comments[1].resolved_at = Some(1);
+        assert_eq!(
+            state.review_markdown().unwrap(),
+            "# airev review\n\n\
+             Reviewed 1/3 files, 2 comments (1 critical) in session 0123456789abcdef.\n\n\
+             ## a.rs\n\n\
+             - **[CRITICAL] nitpick** (line 7): off by one\n  in the loop\n\n\
+             ## gone.rs\n\n\
+             - **[MINOR] nitpick** (file, resolved)\n\n\
+             ## Not reviewed\n\n\
+             - b.rs\n\
+             - c.rs\n"
+        );
+        state.command_line = "note".to_owned();
+        state.run_command();
+        assert!(matches!(
+            rx.try_iter().last(),
+            Some(GitRequest::WriteNote { notes_ref, replace: false, .. })
+                if notes_ref == "refs/notes/airev"
+        ));
+    }
+
+    #[test]
+    fn mode_picker_loads_plain_modes_and_typed_refs() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+
+        state.begin_mode_picker();
+        assert_eq!((state.mode, state.mode_pick), (Mode::DiffModePicker, 0));
+        state.move_mode_pick(-1);
+        assert_eq!(ModePick::ALL[state.mode_pick], ModePick::Stash);
+        state.move_mode_pick(-4);
+        assert_eq!(state.choose_mode_pick(), Some(DiffMode::Staged));
+        assert_eq!(state.mode, Mode::Normal);
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::LoadDiff(DiffMode::Staged))));
+
+        let requested = |request: Option<GitRequest>| match request {
+            Some(
+                GitRequest::LoadDiffRange { from, to } | GitRequest::ResolveRange { from, to },
+            ) => (from, to),
+            other => panic!("expected a range request, got {other:?}"),
+        };
+        let range = |from: &str, to: &str| (from.to_owned(), to.to_owned());
+
+        state.begin_mode_picker();
+        state.move_mode_pick(2);
+        assert_eq!(state.choose_mode_pick(), None);
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::ListRefs)));
+        state.mode_pick_refs = Some("v1.0".to_owned());
+        state.submit_mode_refs();
+        assert_eq!(state.mode_pick_error.as_deref(), Some("expected FROM..TO"));
+        state.mode_pick_refs = Some("v1.0..HEAD".to_owned());
+        state.submit_mode_refs();
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::ResolveRange { .. })));
+        assert_eq!(state.mode, Mode::DiffModePicker);
+
+        // A failed resolve stays in the prompt; a stale answer is ignored.
+        let failed = Err("v1.0: revspec 'v1.0' not found".to_owned());
+        assert!(!state.apply_range_resolved(0, range("v1.0", "HEAD"), failed));
+        assert!(state.mode_pick_error.as_deref().is_some_and(|e| e.starts_with("v1.0:")));
+        assert!(!state.apply_range_resolved(0, range("v1.0", "HEAD"), Ok(())));
+
+        state.command_line = "range v1.0..v2.0".to_owned();
+        state.run_command();
+        assert_eq!(requested(rx.try_recv().ok()), range("v1.0", "v2.0"));
+        assert!(state.apply_range_resolved(0, range("v1.0", "v2.0"), Ok(())));
+        assert_eq!((state.mode, state.diff_mode), (Mode::Normal, DiffMode::CommitRange));
+        assert_eq!(requested(rx.try_recv().ok()), range("v1.0", "v2.0"));
+
+        state.begin_mode_picker();
+        state.move_mode_pick(2);
+        state.choose_mode_pick();
+        assert_eq!(state.mode_pick_refs.as_deref(), Some("stash@{0}"));
+        state.submit_mode_refs();
+        assert!(state.apply_range_resolved(0, requested(rx.try_recv().ok()), Ok(())));
+        assert_eq!(state.current_mode_pick(), ModePick::Stash);
+        state.diff_loading = false;
+        state.refresh_diff();
+        assert_eq!(requested(rx.try_iter().last()), range("stash@{0}^", "stash@{0}"));
+    }
+
+    #[test]
+    fn range_prompt_opens_empty_without_requesting_a_diff() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        state.begin_range_prompt();
+        assert_eq!(state.mode, Mode::DiffModePicker);
+        assert_eq!(ModePick::ALL[state.mode_pick], ModePick::Range);
+        assert_eq!(state.mode_pick_refs.as_deref(), Some(""));
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::ListRefs)));
+        assert!(rx.try_recv().is_err());
+        assert!(!state.diff_loading);
+    }
+
+    #[test]
+    fn tab_completes_refs_after_the_range_separator() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        state.begin_mode_picker();
+        state.move_mode_pick(3);
+        state.choose_mode_pick();
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::ListRefs)));
+        let names = ["HEAD", "main", "origin/feature-a", "origin/feature-b", "v1.0"];
+        state.apply_ref_names(0, names.iter().map(|&n| n.to_owned()).collect());
+
+        let tab = |state: &mut AppState| {
+            state.complete_ref();
+            state.mode_pick_refs.clone().unwrap()
+        };
+        state.mode_pick_refs = Some("ori".to_owned());
+        assert_eq!(tab(&mut state), "origin/feature-");
+        assert_eq!(state.ref_completions(), ["origin/feature-a", "origin/feature-b"]);
+        assert_eq!(tab(&mut state), "origin/feature-a");
+        assert_eq!(tab(&mut state), "origin/feature-b");
+        assert_eq!(tab(&mut state), "origin/feature-a");
+
+        state.ref_tab = None;
+        state.mode_pick_refs = Some("origin/feature-a...m".to_owned());
+        assert_eq!(tab(&mut state), "origin/feature-a...main");
+        state.mode_pick_refs = Some("v1.0..x".to_owned());
+        assert_eq!(tab(&mut state), "v1.0..x");
+
+        // The list is kept for the rest of the run.
+        state.cancel_mode_refs();
+        state.choose_mode_pick();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn up_and_down_step_through_recent_ranges_of_the_picked_kind() {
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        let range = |from: &str, to: &str| (from.to_owned(), to.to_owned());
+        state.recent_ranges = vec![range("abc1234^", "abc1234"), range("main", "HEAD")];
+        state.begin_range("v1.0..HEAD");
+        assert!(state.apply_range_resolved(0, range("v1.0", "HEAD"), Ok(())));
+        assert_eq!(state.recent_ranges[0], range("v1.0", "HEAD"));
+
+        state.begin_mode_picker();
+        state.mode_pick = 3;
+        state.choose_mode_pick();
+        state.mode_pick_refs = Some("dev..".to_owned());
+        assert_eq!(state.ref_history(), ["v1.0..HEAD", "main..HEAD"]);
+        state.step_ref_history(1);
+        state.step_ref_history(1);
+        state.step_ref_history(1);
+        assert_eq!(state.mode_pick_refs.as_deref(), Some("main..HEAD"));
+        state.step_ref_history(-1);
+        assert_eq!(state.mode_pick_refs.as_deref(), Some("v1.0..HEAD"));
+        state.step_ref_history(-1);
+        assert_eq!(state.mode_pick_refs.as_deref(), Some("dev.."));
+        assert_eq!(state.ref_history_pos, None);
+
+        // Commits are offered at the commit prompt, by their commit.
+        state.cancel_mode_refs();
+        state.mode_pick = 4;
+        state.choose_mode_pick();
+        state.step_ref_history(1);
+        assert_eq!(state.mode_pick_refs.as_deref(), Some("abc1234"));
+    }
+
+    #[test]
+    fn search_finds_lines_ignoring_case_and_wraps() {
+        use ratatui::text::{Line, Span};
+        let diff_lines = ["@@ -1,3 +1,3 @@", "  fn parse_args() {", "- let x = 1;", "+ Parse(x)"]
+            .into_iter()
+            .map(|text| Line::from(vec![Span::raw(&text[..2]), Span::raw(&text[2..])]))
+            .collect();
+        let mut state = AppState { diff_lines, ..AppState::default() };
+        assert_eq!(line_text(&state.diff_lines[1]), "  fn parse_args() {");
+
+        state.begin_search();
+        for ch in "PARSE".chars() {
+            state.search_query.push(ch);
+        }
+        state.run_search();
+        assert_eq!((state.mode, state.diff_cursor, state.diff_scroll), (Mode::Normal, 1, 1));
+        state.search_next(true);
+        assert_eq!(state.diff_cursor, 3);
+        state.search_next(true);
+        assert_eq!(state.diff_cursor, 1);
+        assert!(state.latest_notification().is_some_and(|msg| msg.contains("continuing at top")));
+        state.search_next(false);
+        assert_eq!(state.diff_cursor, 3);
+
+        state.search_query = "missing".to_owned();
+        state.search_next(true);
+        assert_eq!(state.diff_cursor, 3);
+        assert_eq!(state.latest_notification(), Some("not found: missing"));
+    }
+
+    #[test]
+    fn theme_command_and_cycle_switch_live_theme() {
+        let mut state = AppState { command_line: "theme light".to_owned(), ..AppState::default() };
+        state.run_command();
+        assert_eq!((state.theme_name.as_deref(), state.theme_changed), (Some("light"), true));
+        assert_eq!(state.theme.status_bar_bg, Theme::light().status_bar_bg);
+
+        state.command_line = "theme solarized".to_owned();
+        state.run_command();
+        assert_eq!(state.theme_name.as_deref(), Some("light"));
+        assert!(state.latest_notification().is_some_and(|msg| msg.starts_with("unknown theme")));
+
+        state.cycle_theme();
+        assert_eq!(state.theme_name.as_deref(), Some(BUILTIN_THEMES[0]));
+
+        // Custom themes come after the built-ins.
+        let mut gruvbox = Theme::dark();
+        gruvbox.border_active = ratatui::style::Color::Rgb(0x83, 0xa5, 0x98);
+        state.custom_themes = vec![("gruvbox".to_owned(), gruvbox.clone())];
+        state.command_line = "theme gruvbox".to_owned();
+        state.run_command();
+        assert_eq!(state.theme, gruvbox);
+        state.cycle_theme();
+        assert_eq!(state.theme_name.as_deref(), Some(BUILTIN_THEMES[0]));
+        state.theme_name = Some("light".to_owned());
+        state.cycle_theme();
+        assert_eq!(state.theme_name.as_deref(), Some("gruvbox"));
+    }
+
+    #[test]
+    fn notifications_queue_up_and_expire_on_tick() {
+        let mut state = AppState::default();
+        for i in 0..=MAX_NOTIFICATIONS {
+            state.push_notification(format!("n{i}"));
+        }
+        assert_eq!(state.notifications.len(), MAX_NOTIFICATIONS, "the oldest is dropped");
+        assert_eq!(state.notifications.front().map(|(msg, _)| msg.as_str()), Some("n1"));
+        assert_eq!(state.latest_notification(), Some(format!("n{MAX_NOTIFICATIONS}").as_str()));
+
+        let pushed = state.notifications[0].1;
+        state.notifications[0].1 = pushed - NOTIFICATION_DURATION;
+        state.on_tick(pushed);
+        assert_eq!(state.notifications.len(), MAX_NOTIFICATIONS - 1);
+        state.on_tick(pushed + NOTIFICATION_DURATION * 2);
+        assert!(state.notifications.is_empty());
+    }
+
+    #[test]
+    fn slow_highlight_warns_above_the_threshold_and_ends_the_refresh() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs"]);
+        result.timing.highlight_ms = SLOW_HIGHLIGHT_MS;
+        state.refresh_pending = true;
+        state.apply_git_result(result);
+        assert_eq!(state.latest_notification(), Some("refreshed"));
+
+        let mut result = payload(&["a.rs"]);
+        result.timing.highlight_ms = SLOW_HIGHLIGHT_MS + 1;
+        state.refresh_pending = true;
+        state.apply_git_result(result);
+        let slow = format!("slow highlight: {} ms", SLOW_HIGHLIGHT_MS + 1);
+        assert_eq!(state.latest_notification(), Some(slow.as_str()));
+        assert!(!state.refresh_pending, "a slow refresh still completes");
+    }
+
+    #[test]
+    fn review_states_follow_db_results() {
+        use crate::event::DbResultPayload;
+
+        let mut state = AppState::default();
+        state.apply_git_result(payload(&["a.rs", "b.rs"]));
+        state.apply_db_result(DbResultPayload::ReviewToggled {
+            file_path: "a.rs".to_owned(),
+            reviewed: true,
+        });
+        assert_eq!(state.reviewed_count(), 1);
+
+        state.apply_db_result(DbResultPayload::ReviewToggled {
+            file_path: "b.rs".to_owned(),
+            reviewed: true,
+        });
+        assert_eq!(state.reviewed_count(), 2);
+    }
+
+    #[test]
+    fn generated_files_are_auto_reviewed_once() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs", "gen.rs", "old_gen.rs"]);
+        result.files[1].generated = true;
+        result.files[2].generated = true;
+        state.apply_git_result(result);
+        state.file_review_states.insert("old_gen.rs".to_owned(), false);
+
+        assert_eq!(state.auto_review_generated(), vec!["gen.rs"]);
+        assert_eq!(state.file_review_states.get("gen.rs"), Some(&true));
+        assert_eq!(state.file_review_states.get("old_gen.rs"), Some(&false));
+        assert!(state.auto_review_generated().is_empty());
+    }
+
+    #[test]
+    fn failed_review_mark_is_rolled_back() {
+        use crate::event::DbResultPayload;
+
+        let mut state = AppState::default();
+        let mut result = payload(&["src/a.rs", "src/b.rs", "gen.rs"]);
+        result.files[2].generated = true;
+        state.apply_git_result(result);
+        state.file_list_state.select(Some(0));
+        let (_, paths) = state.unreviewed_files_in_selected_dir().unwrap();
+        for path in &paths {
+            state.file_review_states.insert(path.clone(), true);
+        }
+        let generated = state.auto_review_generated();
+        assert_eq!(state.reviewed_count(), 3);
+
+        for file_paths in [paths, generated] {
+            let message = "database is locked".to_owned();
+            state.apply_db_result(DbResultPayload::MarkReviewedFailed { file_paths, message });
+        }
+        assert_eq!(state.reviewed_count(), 0);
+        assert!(state.auto_review_generated().is_empty(), "no retry on every refresh");
+        let toast = state.latest_notification().unwrap_or_default();
+        assert_eq!(toast, "cannot mark 1 file reviewed: database is locked");
+    }
+
+    #[test]
+    fn failed_background_writes_are_notified() {
+        use crate::event::DbResultPayload;
+
+        let mut state = AppState::default();
+        state.file_review_states.insert("src/a.rs".to_owned(), true);
+        state.apply_db_result(DbResultPayload::ReviewToggleFailed {
+            file_path: "src/a.rs".to_owned(),
+            reviewed: false,
+            message: "disk I/O error".to_owned(),
+        });
+        assert_eq!(state.file_review_states.get("src/a.rs"), Some(&false));
+        let toast = state.latest_notification().unwrap_or_default();
+        assert_eq!(toast, "cannot toggle src/a.rs reviewed: disk I/O error");
+
+        let message = "cannot save diff mode: database is locked".to_owned();
+        state.apply_db_result(DbResultPayload::SaveFailed(message.clone()));
+        assert_eq!(state.latest_notification(), Some(message.as_str()));
+    }
+
+    #[test]
+    fn whitespace_toggle_tells_the_worker_then_reloads() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        state.toggle_ignore_whitespace();
+        assert!(state.ignore_whitespace && state.diff_loading);
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::SetIgnoreWhitespace(true))));
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::LoadDiff(DiffMode::Unstaged))));
+
+        state.diff_mode = DiffMode::Files;
+        state.toggle_ignore_whitespace();
+        assert!(state.ignore_whitespace, "loose-file reviews cannot ignore whitespace");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn context_lines_clamp_and_reload_the_diff() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), context_lines: 1, ..AppState::default() };
+        assert!(state.change_context_lines(-1));
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::SetContextLines(0))));
+        assert!(matches!(rx.try_recv(), Ok(GitRequest::LoadDiff(DiffMode::Unstaged))));
+        assert!(!state.change_context_lines(-1), "already at zero");
+        assert!(rx.try_recv().is_err());
+
+        state.context_lines = MAX_CONTEXT_LINES;
+        assert!(!state.change_context_lines(1));
+        assert_eq!(state.context_lines, MAX_CONTEXT_LINES);
+    }
+
+    #[test]
+    fn auto_refresh_waits_for_interval_and_in_flight_request() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state =
+            AppState { git_tx: Some(tx), auto_refresh_secs: 5, ..AppState::default() };
+        state.request_diff(GitRequest::LoadDiff(DiffMode::Unstaged));
+        let sent = state.last_diff_request.unwrap();
+        rx.try_recv().unwrap();
+
+        // In flight: even a due tick sends nothing.
+        state.on_tick(sent + Duration::from_secs(6));
+        assert!(rx.try_recv().is_err());
+
+        state.apply_git_result(payload(&["a.rs"]));
+        state.on_tick(sent + Duration::from_secs(1));
+        assert!(rx.try_recv().is_err());
+        state.on_tick(sent + Duration::from_secs(5));
+        assert!(rx.try_recv().is_ok());
+
+        state.apply_git_result(payload(&["a.rs"]));
+        assert_eq!(state.latest_notification(), Some("refreshed"));
+    }
+
+    #[test]
+    fn result_from_replaced_worker_is_ignored() {
+        let mut state = AppState::default();
+        state.apply_git_result(payload(&["a.rs"]));
+        state.git_generation = 1;
+        state.diff_loading = true;
+
+        state.apply_git_result(payload(&["stale.rs"]));
+
+        assert!(state.diff_loading);
+        assert_eq!(state.file_summaries[0].path, "a.rs");
+    }
+
+    #[test]
+    fn focus_cycle_skips_hidden_comments_panel() {
+        let mut state = AppState {
+            panel_rects: [Rect::new(0, 0, 25, 30), Rect::new(25, 0, 75, 30), Rect::default()],
+            focus: PanelFocus::Diff,
+            ..AppState::default()
+        };
+
+        state.focus_next();
+        assert_eq!(state.focus, PanelFocus::FileList);
+
+        state.focus_prev();
+        assert_eq!(state.focus, PanelFocus::Diff);
+
+        state.focus = PanelFocus::Comments;
+        state.clamp_focus_to_visible();
+        assert_eq!(state.focus, PanelFocus::Diff);
+    }
+
+    #[test]
+    fn file_list_window_follows_selection_through_5000_files() {
+        let mut state = AppState {
+            file_summaries: (0..5000).map(|i| file(&format!("src/f{i}.rs"))).collect(),
+            ..AppState::default()
+        };
+        state.file_list_state.select(Some(0));
+        assert_eq!(state.file_list_window(40), 0..40);
+
+        for _ in 0..5000 {
+            state.next_file();
+            let window = state.file_list_window(40);
+            assert!(window.contains(&state.file_list_state.selected().unwrap()));
+        }
+        assert_eq!(state.file_list_state.selected(), Some(4999), "clamped to the last file");
+        assert_eq!(state.file_list_window(40), 4960..5000);
+
+        state.file_list_state.select(Some(10));
+        assert_eq!(state.file_list_window(40), 10..50);
+        assert!(state.click_file_row(2));
+        assert_eq!(state.current_file_path(), Some("src/f12.rs"));
+    }
+
+    /// Timing for the walk above; wall-clock numbers are too noisy to assert on
+    /// CI, so run it by hand: `cargo test -p airev -- --ignored bench_`.
+    #[test]
+    #[ignore = "benchmark; prints timings"]
+    fn bench_file_list_window_through_5000_files() {
+        let mut state = AppState {
+            file_summaries: (0..5000).map(|i| file(&format!("src/f{i}.rs"))).collect(),
+            ..AppState::default()
+        };
+        state.file_list_state.select(Some(0));
+        let started = Instant::now();
+        for _ in 0..5000 {
+            state.next_file();
+            std::hint::black_box(state.file_list_window(40));
+        }
+        let elapsed = started.elapsed();
+        println!("5000 selections: {elapsed:?} total, {:?} each", elapsed / 5000);
+    }
+
+    #[test]
+    fn resize_below_120_columns_drops_comments_panel_state() {
+        let layout = |state: &AppState, width: u16| {
+            let [left, center, right, _] =
+                crate::ui::layout::compute_layout(Rect::new(0, 0, width, 40), state);
+            [left, center, right]
+        };
+        let mut state = AppState::default();
+        let wide = layout(&state, 130);
+        state.apply_panel_rects(wide);
+        assert!(state.comments_follow_selection);
+        state.comments_follow_selection = false;
+        state.focus = PanelFocus::Comments;
+        state.comments_scroll = 7;
+        state.comment_row_offsets = vec![(0, 0), (4, 1)];
+
+        let medium = layout(&state, 100);
+        state.apply_panel_rects(medium);
+        assert_eq!(state.focus, PanelFocus::Diff);
+        assert_eq!(state.comments_scroll, 0);
+        assert!(state.comment_row_offsets.is_empty());
+        assert!(!state.visible_panels().contains(&PanelFocus::Comments));
+
+        state.apply_panel_rects(wide);
+        assert!(state.comments_follow_selection);
+        assert_eq!(state.visible_panels().len(), 3);
+    }
+
+    fn comment(severity: &str) -> Comment {
+        Comment {
+            id: String::new(),
+            session_id: String::new(),
+            file_path: "a.rs".to_owned(),
+            line_number: None,
+            hunk_offset: None,
+            comment_type: "nitpick".to_owned(),
+            severity: severity.to_owned(),
+            body: String::new(),
+            created_at: 0,
+            resolved_at: None,
+            thread_id: None,
+        }
+    }
+
+    #[test]
+    fn comment_navigation_skips_filtered_severities() {
+        let mut state = AppState {
+            comments: ["minor", "critical", "info", "major", "critical"]
+                .into_iter()
+                .map(comment)
+                .collect(),
+            ..AppState::default()
+        };
+
+        state.cycle_comment_filter();
+        assert_eq!(state.comment_filter, CommentFilter::MajorAndAbove);
+        assert_eq!(state.selected_comment, 1, "hidden selection moves to the next visible");
+
+        state.next_comment();
+        assert_eq!(state.selected_comment, 3);
+
+        state.cycle_comment_filter();
+        assert_eq!(state.selected_comment, 4);
+        state.prev_comment();
+        assert_eq!(state.selected_comment, 1);
+        state.prev_comment();
+        assert_eq!(state.selected_comment, 1);
+    }
+
+    #[test]
+    fn hiding_resolved_comments_keeps_the_selection_visible() {
+        let mut state = AppState {
+            comments: ["minor", "major", "info"].into_iter().map(comment).collect(),
+            ..AppState::default()
+        };
+        state.toggle_hide_resolved();
+
+        state.select_comment(2);
+        assert_eq!(state.toggle_comment_resolved(100), Some((String::new(), true)));
+        assert_eq!(state.comments[2].resolved_at, Some(100));
+        assert_eq!(state.selected_comment, 1, "no later comment, so the last visible one");
+
+        assert!(state.toggle_comment_resolved(101).is_some());
+        state.select_comment(0);
+        assert!(state.toggle_comment_resolved(102).is_some());
+        assert!(state.visible_comment_indices().is_empty());
+        assert!(state.selected_comment < state.comments.len());
+        assert_eq!(state.toggle_comment_resolved(103), None, "nothing visible to resolve");
+
+        state.toggle_hide_resolved();
+        assert_eq!(state.visible_comment_indices(), [0, 1, 2]);
+        assert_eq!(state.toggle_comment_resolved(104), Some((String::new(), false)));
+        assert_eq!(state.comments[state.selected_comment].resolved_at, None);
+    }
+
+    #[test]
+    fn severity_filter_cycles_through_each_severity_and_back_to_all() {
+        let mut state = AppState {
+            comments: ["minor", "critical", "info"].into_iter().map(comment).collect(),
+            ..AppState::default()
+        };
+        state.cycle_severity_filter();
+        assert_eq!(state.severity_filter, Some(Severity::Critical));
+        assert_eq!(state.visible_comment_indices(), [1]);
+        assert_eq!(state.selected_comment, 1);
+
+        state.cycle_severity_filter();
+        state.cycle_severity_filter();
+        assert_eq!(state.severity_filter, Some(Severity::Minor));
+        assert_eq!(state.selected_comment, 0, "no later minor comment, so the last one");
+
+        state.cycle_severity_filter();
+        state.cycle_severity_filter();
+        assert_eq!(state.severity_filter, None);
+        assert_eq!(state.visible_comment_indices(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn jumping_to_a_file_selects_its_first_visible_comment() {
+        let mut state = AppState::default();
+        state.apply_git_result(payload(&["a.rs", "b.rs", "c.rs"]));
+        state.comments = ["major", "info", "critical"].into_iter().map(comment).collect();
+        state.comments[1].file_path = "b.rs".to_owned();
+        state.comments[2].file_path = "b.rs".to_owned();
+        state.comment_filter = CommentFilter::MajorAndAbove;
+
+        state.file_list_state.select(Some(1));
+        state.jump_to_selected_file();
+        assert_eq!(state.selected_comment, 2, "the info comment is filtered out");
+        assert!(state.comments_follow_selection);
+
+        state.file_list_state.select(Some(2));
+        state.jump_to_selected_file();
+        assert_eq!(state.selected_comment, 2, "a file without comments keeps the selection");
+    }
+
+    #[test]
+    fn hunk_comment_anchors_relative_to_file_and_scrolls_back() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs", "b.rs"]);
+        result.highlighted_lines = vec![Line::default(); 8];
+        result.hunk_offsets = vec![0, 4, 6];
+        result.file_line_offsets = vec![0, 4];
+        result.line_refs = (0..8)
+            .map(|i| LineRef {
+                file_index: usize::from(i >= 4),
+                hunk_index: if i < 4 { 0 } else if i < 6 { 1 } else { 2 },
+                origin: if matches!(i, 0 | 4 | 6) { 'H' } else { ' ' },
+                old_lineno: None,
+                new_lineno: None,
+            })
+            .collect();
+        state.apply_git_result(result);
+
+        state.diff_cursor = 7;
+        assert!(!state.cursor_on_hunk_header());
+        assert_eq!(state.cursor_hunk_anchor(), Some(("b.rs".to_owned(), 2)));
+
+        state.comments = vec![comment("minor"), comment("major")];
+        state.comments[1].file_path = "b.rs".to_owned();
+        state.comments[1].hunk_offset = Some(2);
+        state.diff_scroll = 0;
+        state.next_comment();
+        assert_eq!((state.diff_scroll, state.diff_cursor, state.hunk_cursor), (6, 6, 2));
+    }
+
+    #[test]
+    fn file_filter_narrows_the_list_and_maps_rows_back() {
+        let mut state = AppState::default();
+        let paths = ["README.md", "src/ui/diff_view.rs", "src/app.rs", "src/ui/mod.rs"];
+        let mut result = payload(&paths);
+        result.file_line_offsets = vec![0, 10, 20, 30];
+        state.apply_git_result(result);
+        state.file_list_state.select(Some(2));
+
+        state.begin_file_filter();
+        for ch in "UIRS".chars() {
+            state.edit_file_filter(Some(ch));
+        }
+        assert_eq!(state.filtered_indices, vec![1, 3]);
+        assert_eq!(state.file_list_len(), 2);
+        assert_eq!(state.current_file_path(), Some("src/ui/diff_view.rs"), "first match");
+
+        state.next_file();
+        state.accept_file_filter();
+        assert_eq!(state.mode, Mode::Normal);
+        state.jump_to_selected_file();
+        assert_eq!((state.selected_file_index, state.diff_scroll), (3, 30));
+
+        // A new diff keeps the filter and the selected file.
+        let mut result = payload(&["src/ui/mod.rs", "src/ui/help.rs"]);
+        result.file_line_offsets = vec![0, 5];
+        state.apply_git_result(result);
+        assert_eq!(state.filtered_indices, vec![0, 1]);
+        assert_eq!(state.current_file_path(), Some("src/ui/mod.rs"));
+
+        state.edit_file_filter(Some('x'));
+        assert_eq!((state.file_list_len(), state.current_file_path()), (0, None));
+        state.edit_file_filter(None);
+        state.file_list_state.select(Some(1));
+        state.clear_file_filter();
+        assert_eq!(state.file_list_len(), 2);
+        assert_eq!(state.current_file_path(), Some("src/ui/help.rs"));
+    }
+
+    #[test]
+    fn jumping_to_a_file_moves_the_hunk_cursor_to_its_first_hunk() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs", "b.rs", "c.rs"]);
+        result.hunk_offsets = vec![0, 4, 9, 12];
+        result.file_line_offsets = vec![0, 9, 12];
+        state.apply_git_result(result);
+
+        state.file_list_state.select(Some(1));
+        state.jump_to_selected_file();
+        assert_eq!((state.diff_scroll, state.diff_cursor, state.hunk_cursor), (9, 9, 2));
+
+        // An offset list shorter than the file list clamps to its last file.
+        state.file_line_offsets.truncate(2);
+        state.file_list_state.select(Some(2));
+        state.jump_to_selected_file();
+        assert_eq!((state.diff_scroll, state.hunk_cursor), (9, 2));
+    }
+
+    #[test]
+    fn line_comment_anchors_to_the_cursor_line_and_marks_unsaved() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs"]);
+        result.highlighted_lines = vec![Line::default(); 5];
+        result.line_refs = [
+            ('H', None, None),
+            (' ', Some(9), Some(10)),
+            ('-', Some(10), None),
+            (' ', Some(11), Some(11)),
+            ('-', Some(12), None),
+        ]
+        .into_iter()
+        .map(|(origin, old_lineno, new_lineno)| LineRef {
+            file_index: 0,
+            hunk_index: 0,
+            origin,
+            old_lineno,
+            new_lineno,
+        })
+        .collect();
+        state.apply_git_result(result);
+
+        state.begin_line_comment();
+        assert_eq!(state.mode, Mode::Normal, "a hunk header has no line to anchor to");
+
+        state.diff_cursor = 2;
+        state.begin_line_comment();
+        assert_eq!(state.mode, Mode::Insert);
+        let anchor = Some(("a.rs".to_owned(), 11));
+        assert_eq!(state.draft_line, anchor, "removed lines anchor to the next new-side line");
+        state.diff_cursor = 4;
+        state.begin_line_comment();
+        assert_eq!(state.draft_line, anchor, "or the previous one at the end of the hunk");
+        state.push_draft_char('x');
+        assert!(state.has_unsaved_comments);
+
+        state.clear_comment_draft();
+        assert_eq!((state.draft_line.clone(), state.has_unsaved_comments), (None, false));
+    }
+
+    #[test]
+    fn pair_toggle_cycles_and_lapses_on_scroll() {
+        let mut state = AppState::default();
+        let mut result = payload(&["a.rs"]);
+        result.highlighted_lines = vec![Line::default(); 6];
+        result.line_refs = ['H', ' ', '-', '-', '+', ' ']
+            .into_iter()
+            .map(|origin| LineRef {
+                file_index: 0,
+                hunk_index: 0,
+                origin,
+                old_lineno: None,
+                new_lineno: None,
+            })
+            .collect();
+        state.apply_git_result(result);
+
+        state.diff_cursor = 1;
+        state.toggle_pair_view();
+        assert_eq!(state.active_pair_view(), None, "context lines have no pair");
+
+        state.diff_cursor = 4;
+        state.toggle_pair_view();
+        assert_eq!(state.active_pair_view(), Some((PairSide::Old, 2..5)));
+        state.toggle_pair_view();
+        assert_eq!(state.active_pair_view(), Some((PairSide::New, 2..5)));
+        state.toggle_pair_view();
+        assert_eq!(state.active_pair_view(), None);
+
+        state.toggle_pair_view();
+        state.scroll_panel_down(PanelFocus::Diff, 1);
+        assert_eq!(state.active_pair_view(), None, "scrolling resets the toggle");
+        state.toggle_pair_view();
+        assert_eq!(state.active_pair_view(), Some((PairSide::Old, 2..5)));
+    }
+
+    #[test]
+    fn drift_message_shortens_both_shas() {
+        let msg = drift_message("0123456789abcdef", "fedcba9876543210");
+        assert!(msg.contains("(0123456 -> fedcba9)"), "{msg}");
+        assert!(drift_message("abc", "def").contains("(abc -> def)"));
+    }
+
+    #[test]
+    fn unsaved_flag_follows_draft_contents() {
+        let mut state = AppState::default();
+        state.apply_git_result(payload(&["a.rs"]));
+        state.file_list_state.select(Some(0));
+
+        state.begin_comment();
+        state.push_draft_char(' ');
+        assert!(!state.has_unsaved_comments, "whitespace alone is not worth a prompt");
+        state.push_draft_char('x');
+        assert!(state.has_unsaved_comments);
+        state.pop_draft_char();
+        assert!(!state.has_unsaved_comments, "erasing the text disarms the prompt");
+
+        state.push_draft_char('y');
+        state.clear_comment_draft();
+        assert!(!state.has_unsaved_comments, "save/discard clears the flag");
+        assert_eq!(state.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn refresh_keeps_selected_file_when_reordered() {
+        let mut state = AppState::default();
+        state.apply_git_result(payload(&["a.rs", "b.rs", "c.rs"]));
+        state.file_list_state.select(Some(1));
+
+        state.apply_git_result(payload(&["new.rs", "c.rs", "a.rs", "b.rs"]));
+
+        assert_eq!(state.file_list_state.selected(), Some(3));
+        assert_eq!(state.current_file_path(), Some("b.rs"));
+    }
+
+    #[test]
+    fn refresh_clamps_selection_when_file_removed() {
+        let mut state = AppState::default();
+        state.apply_git_result(payload(&["a.rs", "b.rs", "c.rs"]));
+        state.file_list_state.select(Some(2));
+
+        state.apply_git_result(payload(&["a.rs", "b.rs"]));
+
+        assert_eq!(state.file_list_state.selected(), Some(1));
+
+        state.apply_git_result(payload(&[]));
+        assert_eq!(state.file_list_state.selected(), None);
+    }
+
+    #[test]
+    fn failed_comment_save_keeps_the_draft() {
+        use crate::event::DbResultPayload;
+
+        let mut state = AppState::default();
+        state.comment_draft.push('x');
+        state.has_unsaved_comments = true;
+        state.mode = Mode::CommentTag;
+        state.comment_saving = true;
+
+        state.apply_db_result(DbResultPayload::CommentSaveFailed("disk full".to_owned()));
+        assert_eq!((state.comment_draft.as_str(), state.mode), ("x", Mode::Insert));
+        assert!(state.has_unsaved_comments && !state.comment_saving);
+        assert_eq!(state.comment_error.as_deref(), Some("disk full"));
+
+        state.comment_saving = true;
+        state.apply_db_result(DbResultPayload::CommentSaved(Comment {
+            id: "c1".to_owned(),
+            session_id: "s".to_owned(),
+            file_path: "a.rs".to_owned(),
+            line_number: None,
+            hunk_offset: None,
+            comment_type: "concern".to_owned(),
+            severity: "minor".to_owned(),
+            body: "x".to_owned(),
+            created_at: 1,
+            resolved_at: None,
+            thread_id: None,
+        }));
+        assert_eq!((state.comment_draft.as_str(), state.mode), ("", Mode::Normal));
+        assert!(state.comment_error.is_none());
+        assert_eq!(state.comments.len(), 1);
+    }
+
+    #[test]
+    fn blame_is_requested_for_old_side_lines_in_view_and_cached() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut state = AppState { git_tx: Some(tx), ..AppState::default() };
+        let mut p = payload(&["a.rs", "b.rs"]);
+        // a.rs: header, context 10, removed 11, added (new 11); b.rs: header, context 3.
+        let lines = [(0, 'H', None), (0, ' ', Some(10)), (0, '-', Some(11)), (0, '+', None)];
+        let lines = lines.into_iter().chain([(1, 'H', None), (1, ' ', Some(3))]);
+        p.line_refs = lines
+            .map(|(file_index, origin, old_lineno)| LineRef {
+                file_index,
+                hunk_index: file_index,
+                origin,
+                old_lineno,
+                new_lineno: None,
+            })
+            .collect();
+        state.apply_git_result(p);
+        state.diff_viewport_height = 4;
+
+        state.request_visible_blame();
+        assert!(rx.try_recv().is_err(), "hidden column blames nothing");
+        state.toggle_blame();
+        state.request_visible_blame();
+        let requests: Vec<_> = rx.try_iter().collect();
+        assert!(matches!(
+            requests.as_slice(),
+            [GitRequest::Blame { rev, path, first: 10, last: 11 }]
+                if rev == "HEAD" && path == "a.rs"
+        ));
+        state.request_visible_blame();
+        assert!(rx.try_recv().is_err(), "lines in flight are not asked for again");
+
+        let tag = BlameTag { sha: "abc1234".to_owned(), initials: "JD".to_owned() };
+        state.apply_blame(0, "main", "a.rs", Ok(vec![(10, tag.clone())]));
+        assert_eq!(state.blame_tag(1), None, "answer for another old side");
+        state.apply_blame(0, "HEAD", "a.rs", Ok(vec![(10, tag.clone()), (11, tag.clone())]));
+        assert_eq!(state.blame_tag(1), Some(&tag));
+        assert_eq!(state.blame_tag(2), Some(&tag));
+        assert_eq!(state.blame_tag(3), None);
+
+        state.diff_scroll = 2;
+        state.request_visible_blame();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GitRequest::Blame { path, first: 3, last: 3, .. }) if path == "b.rs"
+        ));
     }
 }
@@ -0,0 +1,322 @@
+//! Command-line arguments for the `airev` binary.
+//!
+//! With no arguments airev starts the TUI on the current repository.
+//! `airev --files OLD NEW` starts it on a diff of two loose files instead, and
+//! `airev --range FROM..TO` on the diff between two revisions.
+//! `--no-alt-screen` may be given anywhere to render the TUI inline,
+//! `--diff-only` to start with the side panels hidden, and `--file PATH`
+//! (optionally with `--line N`) to open on one file of the diff.
+//! `--editor-command TEMPLATE` sets the command `e` opens files with (see
+//! `editor`).
+//! `airev json` prints the current diff as JSON on stdout (see `git::report`
+//! for the schema) and exits without touching the terminal.
+
+use std::path::PathBuf;
+
+use crate::git::types::{DiffMode, GitRequest};
+
+/// Usage text printed for `--help` and after argument errors.
+pub const USAGE: &str = "\
+Usage: airev [--files OLD NEW | --range FROM..TO] [--no-alt-screen] [--diff-only]
+             [--file PATH [--line N]] [--editor-command TEMPLATE]
+       airev json [--unstaged | --staged | --branch | --range FROM..TO]
+
+Options:
+  --files OLD NEW   Review a diff of two files, no repository needed
+  --range FROM..TO  Review the diff between two revisions, e.g. main..HEAD
+  --no-alt-screen   Render inline on the normal screen, keeping scrollback
+  --diff-only       Show only the diff panel (F toggles the side panels)
+  --file PATH       Open on PATH once the diff loads (repository-relative)
+  --line N          With --file, put the cursor on new-file line N
+  --editor-command TEMPLATE
+                    Command for e, e.g. 'code --goto {file}:{line}'
+
+Commands:
+  json        Print the diff's files and hunk headers as JSON and exit
+
+Diff mode (json only, default --unstaged):
+  --unstaged        Working tree vs index
+  --staged          Index vs HEAD
+  --branch          main vs HEAD
+  --range FROM..TO  Between two revisions";
+
+/// Parsed command line.
+#[derive(Debug)]
+pub struct Args {
+    /// What to run.
+    pub command: Command,
+    /// `--no-alt-screen`: render the TUI in an inline viewport.
+    pub no_alt_screen: bool,
+    /// `--diff-only`: start with both side panels hidden.
+    pub diff_only: bool,
+    /// `--file PATH`: the file to select once the first diff arrives.
+    pub file: Option<String>,
+    /// `--line N`: the new-file line to put the cursor on within `file`.
+    pub line: Option<u32>,
+    /// `--editor-command TEMPLATE`: overrides config `editor_command`.
+    pub editor_command: Option<String>,
+}
+
+/// What the binary was asked to do.
+#[derive(Debug)]
+pub enum Command {
+    /// Start the interactive TUI.
+    Tui,
+    /// Start the TUI on a diff of two loose files.
+    Files {
+        /// The file shown as the old side.
+        old: PathBuf,
+        /// The file shown as the new side.
+        new: PathBuf,
+    },
+    /// Start the TUI on the diff between two revisions.
+    Range {
+        /// The starting ref.
+        from: String,
+        /// The ending ref.
+        to: String,
+    },
+    /// Print the diff for the request as JSON and exit.
+    Json(GitRequest),
+    /// Print usage and exit.
+    Help,
+}
+
+/// Parses the arguments after the program name.
+///
+/// `--no-alt-screen`, `--diff-only`, `--file PATH`, `--line N`, and
+/// `--editor-command TEMPLATE` are accepted in any position; everything else is
+/// parsed in order by [`parse_command`].
+///
+/// # Errors
+///
+/// Returns a message describing the first unrecognized or malformed argument,
+/// or `--line` without `--file`.
+pub fn parse_args<I>(args: I) -> Result<Args, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let (mut no_alt_screen, mut diff_only, mut file, mut line) = (false, false, None, None);
+    let mut editor_command = None;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-alt-screen" => no_alt_screen = true,
+            "--diff-only" => diff_only = true,
+            "--file" => file = Some(args.next().ok_or("--file needs a path")?),
+            "--editor-command" => {
+                editor_command = Some(args.next().ok_or("--editor-command needs a command")?);
+            }
+            "--line" => {
+                let n = args.next().ok_or("--line needs a line number")?;
+                match n.parse::<u32>() {
+                    Ok(n) if n > 0 => line = Some(n),
+                    _ => return Err(format!("invalid line number '{n}'")),
+                }
+            }
+            _ => rest.push(arg),
+        }
+    }
+    if line.is_some() && file.is_none() {
+        return Err("--line needs --file".to_owned());
+    }
+    Ok(Args {
+        command: parse_command(rest.into_iter())?,
+        no_alt_screen,
+        diff_only,
+        file,
+        line,
+        editor_command,
+    })
+}
+
+/// Parses the command and its arguments.
+fn parse_command(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    match args.next().as_deref() {
+        None => Ok(Command::Tui),
+        Some("-h" | "--help") => Ok(Command::Help),
+        Some("json") => parse_json_args(args).map(Command::Json),
+        Some("--files") => match (args.next(), args.next(), args.next()) {
+            (Some(old), Some(new), None) => {
+                Ok(Command::Files { old: PathBuf::from(old), new: PathBuf::from(new) })
+            }
+            _ => Err("--files needs exactly two paths: OLD NEW".to_owned()),
+        },
+        Some("--range") => {
+            let (from, to) = parse_range(args.next())?;
+            match args.next() {
+                None => Ok(Command::Range { from, to }),
+                Some(other) => Err(format!("unexpected argument '{other}' after --range")),
+            }
+        }
+        Some(other) => Err(format!("unknown argument '{other}'")),
+    }
+}
+
+/// Parses the diff mode flags accepted by `airev json`.
+///
+/// The last mode flag wins, matching how git treats repeated options.
+fn parse_json_args(mut args: impl Iterator<Item = String>) -> Result<GitRequest, String> {
+    let mut request = GitRequest::LoadDiff(DiffMode::Unstaged);
+    while let Some(arg) = args.next() {
+        request = match arg.as_str() {
+            "--unstaged" => GitRequest::LoadDiff(DiffMode::Unstaged),
+            "--staged" => GitRequest::LoadDiff(DiffMode::Staged),
+            "--branch" => GitRequest::LoadDiff(DiffMode::BranchComparison),
+            "--range" => {
+                let (from, to) = parse_range(args.next())?;
+                GitRequest::LoadDiffRange { from, to }
+            }
+            other => return Err(format!("unknown json option '{other}'")),
+        };
+    }
+    Ok(request)
+}
+
+/// Splits the `FROM..TO` argument of `--range` into its two non-empty refs.
+///
+/// Git's three-dot `FROM...TO` (diff against the merge base) is rejected rather
+/// than read as `FROM..` and `.TO`.
+fn parse_range(arg: Option<String>) -> Result<(String, String), String> {
+    let range = arg.ok_or("--range needs a FROM..TO argument")?;
+    if range.contains("...") {
+        return Err(format!("'{range}': three-dot ranges are not supported, use FROM..TO"));
+    }
+    match range.split_once("..") {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_owned(), to.to_owned()))
+        }
+        _ => Err(format!("invalid range '{range}', expected FROM..TO")),
+    }
+}
+
+/// Runs `airev json`: computes the diff and prints it to stdout. `--branch`
+/// compares against `base_branch` (config `base_branch`).
+///
+/// Returns the process exit code: `0` on success, `1` when there is no
+/// repository or git fails to compute the diff.
+pub fn run_json(request: GitRequest, base_branch: &str) -> i32 {
+    let Ok(path) = crate::git::discover_repo_path() else {
+        eprintln!("airev: not inside a git repository");
+        return 1;
+    };
+    let report = match crate::git::worker::diff_report(&path, request, base_branch) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("airev: {}", e.message());
+            return 1;
+        }
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => {
+            println!("{json}");
+            0
+        }
+        Err(e) => {
+            eprintln!("airev: {e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|a| a.to_string())).map(|a| a.command)
+    }
+
+    #[test]
+    fn json_mode_flags_select_the_request() {
+        assert!(matches!(parse(&[]), Ok(Command::Tui)));
+        assert!(matches!(
+            parse(&["json"]),
+            Ok(Command::Json(GitRequest::LoadDiff(DiffMode::Unstaged)))
+        ));
+        assert!(matches!(
+            parse(&["json", "--staged"]),
+            Ok(Command::Json(GitRequest::LoadDiff(DiffMode::Staged)))
+        ));
+        match parse(&["json", "--range", "v1.0..HEAD"]) {
+            Ok(Command::Json(GitRequest::LoadDiffRange { from, to })) => {
+                assert_eq!((from.as_str(), to.as_str()), ("v1.0", "HEAD"));
+            }
+            other => panic!("unexpected parse result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn range_starts_the_tui_on_two_revisions() {
+        let args = ["--range", "main..HEAD", "--diff-only"].map(str::to_owned);
+        let parsed = parse_args(args).unwrap();
+        assert!(parsed.diff_only);
+        match parsed.command {
+            Command::Range { from, to } => {
+                assert_eq!((from.as_str(), to.as_str()), ("main", "HEAD"));
+            }
+            other => panic!("unexpected parse result: {other:?}"),
+        }
+        assert!(parse(&["--range"]).is_err());
+        assert!(parse(&["--range", "main"]).is_err());
+        assert!(parse(&["--range", "..HEAD"]).is_err());
+        let three_dot = parse(&["--range", "main...HEAD"]).unwrap_err();
+        assert!(three_dot.contains("three-dot"), "{three_dot}");
+        assert!(parse(&["--range", "main..HEAD", "extra"]).is_err());
+    }
+
+    #[test]
+    fn files_takes_exactly_two_paths() {
+        match parse(&["--files", "a.txt", "b.txt"]) {
+            Ok(Command::Files { old, new }) => {
+                assert_eq!((old, new), (PathBuf::from("a.txt"), PathBuf::from("b.txt")));
+            }
+            other => panic!("unexpected parse result: {other:?}"),
+        }
+        assert!(parse(&["--files", "a.txt"]).is_err());
+        assert!(parse(&["--files", "a.txt", "b.txt", "c.txt"]).is_err());
+    }
+
+    #[test]
+    fn no_alt_screen_is_accepted_anywhere() {
+        let args = ["--files", "a.txt", "--no-alt-screen", "b.txt"];
+        let parsed = parse_args(args.iter().map(|a| a.to_string())).unwrap();
+        assert!(parsed.no_alt_screen);
+        assert!(matches!(parsed.command, Command::Files { .. }));
+
+        let parsed = parse_args(std::iter::empty()).unwrap();
+        assert!(!parsed.no_alt_screen);
+        assert!(!parsed.diff_only);
+
+        let parsed = parse_args(["--diff-only".to_owned()]).unwrap();
+        assert!(parsed.diff_only && !parsed.no_alt_screen);
+        assert!(matches!(parsed.command, Command::Tui));
+    }
+
+    #[test]
+    fn file_and_line_take_values() {
+        let args = ["--line", "42", "--file", "src/app.rs"].map(str::to_owned);
+        let parsed = parse_args(args).unwrap();
+        assert_eq!(parsed.file.as_deref(), Some("src/app.rs"));
+        assert_eq!(parsed.line, Some(42));
+        assert!(matches!(parsed.command, Command::Tui));
+
+        assert!(parse_args(["--file".to_owned()]).is_err());
+        assert!(parse_args(["--line", "7"].map(str::to_owned)).is_err());
+        let zero = ["--file", "a.rs", "--line", "0"].map(str::to_owned);
+        assert!(parse_args(zero).is_err());
+
+        let template = ["--editor-command", "code --goto {file}:{line}"].map(str::to_owned);
+        let parsed = parse_args(template).unwrap();
+        assert_eq!(parsed.editor_command.as_deref(), Some("code --goto {file}:{line}"));
+    }
+
+    #[test]
+    fn malformed_arguments_are_rejected() {
+        assert!(parse(&["--staged"]).is_err());
+        assert!(parse(&["json", "--range"]).is_err());
+        assert!(parse(&["json", "--range", "HEAD"]).is_err());
+        assert!(parse(&["json", "--bogus"]).is_err());
+    }
+}
@@ -0,0 +1,995 @@
+//! User configuration for airev.
+//!
+//! Settings are read at startup from `$XDG_CONFIG_HOME/airev/config.toml`
+//! (falling back to `~/.config/airev/config.toml`). Every key is optional: a
+//! missing file, a parse error, or an invalid value never prevents startup — the
+//! affected setting keeps its default and a note is printed to stderr. On first
+//! run the file is created from [`DEFAULT_CONFIG`] so every key is discoverable.
+//!
+//! A repo-local [`LOCAL_CONFIG_PATH`] (`.airev/config.toml`, next to the review
+//! database) is layered on top, key by key: a key it sets wins over the global
+//! file, and every other key keeps the global value. `[themes.NAME]` tables are
+//! merged per theme. The local file is never created, and a malformed one is
+//! noted on stderr and skipped like the global file.
+//!
+//! `SIGHUP` re-reads both files while airev runs (see [`reload_config`]). A
+//! reload is all-or-nothing: any problem is reported in the error overlay and
+//! the running settings are kept.
+//!
+//! ```toml
+//! theme = "catppuccin-mocha" # or "dark" / "light"; omit to follow COLORFGBG
+//! force_truecolor = false # keep RGB theme colors even if COLORTERM lacks truecolor
+//! scroll_lines = 3        # rows per mouse-wheel notch
+//! natural_scroll = false  # invert the wheel direction
+//! highlight_cache = false # cache highlighted diffs in .airev/ across runs
+//! highlight_cache_max_entries = 500
+//! default_diff_mode = "unstaged" # or "staged" / "branch"
+//! base_branch = "main"    # what "branch" compares HEAD with; falls back to main, master
+//! max_diff_width = 0      # cap on diff content columns; 0 = unlimited
+//! auto_refresh_secs = 0   # re-read the diff every N seconds; 0 = off
+//! render_ms = 33          # redraw interval, clamped to 16..=200
+//! tick_ms = 250           # toast / auto-refresh tick interval, clamped to 50..=2000
+//! word_diff = "word"      # inline emphasis: "word" / "char" / "off"
+//! context_lines = 3       # unchanged lines around each change, 0..=15
+//! syntax_theme = "base16-ocean.dark" # syntect theme for code, independent of `theme`
+//! alt_screen = true       # false renders inline, keeping shell scrollback
+//! layout = "auto"         # or "horizontal" / "vertical" (stacked panels)
+//! panel_widths = [20, 55, 25] # file list / diff / comments, in percent
+//! status_segments = ["mode", "diff_mode", "files", "session", "status"]
+//!                         # also "reviewed" and "clock"; dropped from the right when narrow
+//! detect_generated = true # fold and auto-review linguist-generated files
+//! color_moved = false     # mark blocks moved within a file (see `git::moved`)
+//! full_file_highlight = false # highlight whole files so multi-line syntax is right
+//! editor_command = "code --goto {file}:{line}" # for `e`; omit to use $EDITOR
+//! editor_suspend = false  # hand the terminal to the editor_command
+//! quit_summary = true     # print files reviewed and comments on quit
+//! cursor_line = true      # shade the current row of the focused panel
+//! note_ref = "refs/notes/airev" # where `:note` saves the review summary
+//! note_replace = false    # true replaces an existing note instead of appending
+//!
+//! [themes.gruvbox]        # a custom theme, selected with theme = "gruvbox"
+//! border_active = "#83a598" # any `Theme` field as "#rrggbb"; the rest stay dark
+//!
+//! [keybindings]           # Normal-mode keys by action name (see `keymap`)
+//! next_hunk = ["]", "ctrl-n"]
+//! ```
+
+use airev_core::highlight::{syntect_theme_names, WordDiff, SYNTECT_THEME};
+
+use crate::git::types::{DiffMode, MAX_CONTEXT_LINES};
+use crate::keymap::KeyMap;
+use crate::theme::Theme;
+use crate::ui::layout::{LayoutOrientation, StatusSegment};
+
+/// The commented config written on first run.
+///
+/// Every active line holds the built-in default, so the file parses to
+/// `Config::default()` until the user edits it.
+pub const DEFAULT_CONFIG: &str = r##"# airev configuration. Every key is optional; the values below are the defaults.
+# Send airev SIGHUP (kill -HUP <pid>) to apply edits without restarting.
+
+# Color theme: "catppuccin-mocha", "dark", "light", or a custom theme defined
+# below. When unset, airev picks dark or light from the terminal background
+# (COLORFGBG).
+# theme = "catppuccin-mocha"
+
+# RGB theme colors are approximated with the 256-color palette unless COLORTERM
+# is "truecolor" or "24bit". Set to true if your terminal supports 24-bit color
+# without saying so.
+force_truecolor = false
+
+# Rows scrolled per mouse-wheel notch, and whether to invert the wheel.
+scroll_lines = 3
+natural_scroll = false
+
+# Cache highlighted diffs in .airev/ across runs, and how many files to keep.
+highlight_cache = false
+highlight_cache_max_entries = 500
+
+# Diff shown at startup: "unstaged", "staged", or "branch". Repositories opened
+# before start in the mode they were last shown in.
+default_diff_mode = "unstaged"
+
+# Branch the "branch" mode compares HEAD with. When it does not resolve, main
+# and then master are tried.
+base_branch = "main"
+
+# Cap on diff content columns in the 3-panel layout; 0 = unlimited.
+max_diff_width = 0
+
+# Re-read the diff every N seconds; 0 = off.
+auto_refresh_secs = 0
+
+# Milliseconds between redraws (16 to 200; 33 is about 30 FPS), and between the
+# ticks that expire toasts and run auto_refresh_secs (50 to 2000). Raise them to
+# save power on battery, or lower render_ms on a high-refresh display. Values
+# outside the range are clamped. Applies from the next launch.
+render_ms = 33
+tick_ms = 250
+
+# Inline emphasis on changed line pairs: "word", "char", or "off".
+word_diff = "word"
+
+# Unchanged lines shown around each change, 0 to 15. - and + change it for the
+# current review session, which remembers its own count.
+context_lines = 3
+
+# Syntax coloring of code in the diff, independent of the UI theme above. One
+# of "base16-ocean.dark", "base16-eighties.dark", "base16-mocha.dark",
+# "base16-ocean.light", "InspiredGitHub", "Solarized (dark)", or
+# "Solarized (light)". Applies from the next launch.
+syntax_theme = "base16-ocean.dark"
+
+# Use the alternate screen; false renders inline and keeps shell scrollback.
+alt_screen = true
+
+# Panel orientation: "auto", "horizontal", or "vertical" (stacked panels).
+layout = "auto"
+
+# Starting widths of the file list, diff, and comments panels, in percent;
+# they must sum to 100. Like :layout, but applied at startup only. A resumed
+# review session reopens with the widths it was quit with instead.
+# panel_widths = [20, 55, 25]
+
+# Status bar segments in order; also available: "reviewed", "clock".
+status_segments = ["mode", "diff_mode", "files", "session", "status"]
+
+# Fold files marked linguist-generated in .gitattributes, dim them in the file
+# list, and mark them reviewed.
+detect_generated = true
+
+# Draw blocks of lines moved within a file with blue -/+ markers, like
+# git diff --color-moved. A line-matching heuristic: re-indented code also
+# counts as moved, and moves between files are not detected.
+color_moved = false
+
+# Highlight the whole old and new file rather than each hunk on its own, so a
+# block comment or multi-line string that opens above a hunk is colored right
+# inside it. Slower on large files.
+full_file_highlight = false
+
+# Command `e` opens the cursor's file with. {file} and {line} are filled in.
+# When unset, $VISUAL or $EDITOR is run as `EDITOR +LINE FILE` on the terminal.
+# A command set here is started in the background for GUI editors, unless
+# editor_suspend is true (for a terminal editor that needs its own command).
+# editor_command = "code --goto {file}:{line}"
+editor_suspend = false
+
+# After quitting, print a line like "Reviewed 9/12 files, 5 comments
+# (2 critical) in session <id>." Not printed on SIGTERM.
+quit_summary = true
+
+# Shade the current row of the focused panel (file, diff line, or comment)
+# with the theme's cursor_line background instead of reversing it, so syntax
+# colors stay readable. Unfocused panels keep their usual marker.
+cursor_line = true
+
+# :note saves the review summary as a git note on HEAD under this ref. Share
+# it with `git push origin refs/notes/airev`; read it with
+# `git log --notes=airev`. A note already on HEAD is appended to, unless
+# note_replace is true.
+note_ref = "refs/notes/airev"
+note_replace = false
+
+# Custom themes, one [themes.NAME] table each, selected with theme = "NAME".
+# Keys are the theme's color fields as "#rrggbb": border_active,
+# border_inactive, diff_added, diff_removed, diff_context, diff_hunk_header,
+# cursor_line, file_added, file_removed, file_modified, badge_critical,
+# badge_major, badge_minor, badge_info, status_bar_bg, status_bar_fg,
+# status_mode_normal, status_mode_insert, background. Fields left out keep the
+# "dark" theme's color.
+# [themes.gruvbox]
+# border_active = "#83a598"
+# diff_added = "#b8bb26"
+# diff_removed = "#fb4934"
+
+# Normal-mode keys, one action per line, each a key or a list of keys, e.g.
+# "j", "G", "ctrl-d", "alt-x", "enter", "esc", "tab", "space", "up", "pageup",
+# or "f1". The keys replace the action's defaults, and are taken from any other
+# action that has them by default; [] unbinds the action. Count digits and the
+# g and z prefixes are fixed. Actions: scroll_down, scroll_up, scroll_bottom,
+# half_page_down, half_page_up, page_down, page_up, jump_to_file,
+# toggle_reviewed, filter_files, mark_directory_reviewed, cycle_diff_mode,
+# focus_left, focus_right, prev_file, next_file, prev_hunk, next_hunk,
+# toggle_pair_view, toggle_blame, toggle_whitespace, fewer_context,
+# more_context, toggle_line_numbers, toggle_fold, toggle_resolved,
+# hide_resolved, dismiss_banner,
+# restart_worker, comment, comment_hunk, comment_line, export_comments,
+# cycle_comment_filter, cycle_severity_filter, toggle_diff_only, shrink_diff,
+# grow_diff, command, search, search_next, search_prev, open_editor,
+# copy_reference, copy_permalink, pick_mode, cycle_theme, help, debug_overlay,
+# quit. The help overlay (?) lists the active keys.
+# [keybindings]
+# scroll_down = ["j", "down"]
+# next_hunk = "ctrl-n"
+"##;
+
+/// Parsed configuration with defaults applied for absent or invalid keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Theme name passed to `Theme::from_name`. `None` when the key is absent, in
+    /// which case the theme is picked from the terminal background (`COLORFGBG`).
+    pub theme: Option<String>,
+    /// Custom themes from the `[themes.NAME]` tables, in file order. Their names
+    /// are looked up before the built-ins. Default: none.
+    pub custom_themes: Vec<(String, Theme)>,
+    /// Show RGB theme colors as-is without checking `COLORTERM` for truecolor
+    /// support (see `theme::supports_truecolor`). Default: `false`.
+    pub force_truecolor: bool,
+    /// Rows scrolled per mouse-wheel notch. Default: 3. Must be at least 1.
+    pub scroll_lines: u16,
+    /// When `true`, wheel-up scrolls content down (trackpad "natural" scrolling).
+    pub natural_scroll: bool,
+    /// Persist syntax-highlighted diff lines in `.airev/highlight-cache.json`
+    /// so unchanged files are not re-highlighted on the next launch. Default: off.
+    pub highlight_cache: bool,
+    /// Maximum number of files kept in the highlight cache. Default: 500.
+    pub highlight_cache_max_entries: usize,
+    /// Diff mode shown at startup: `"unstaged"`, `"staged"`, or `"branch"`.
+    /// A repository reopened later starts in its last-used mode instead (stored
+    /// in the review database). Default: `Unstaged`.
+    pub default_diff_mode: DiffMode,
+    /// Ref the branch comparison diffs `HEAD` against. When it does not resolve,
+    /// `main` and then `master` are tried (see `git::worker`). Default: `"main"`.
+    pub base_branch: String,
+    /// Maximum width of the diff panel's content, in columns, in the 3-panel
+    /// layout. Excess width goes to the side panels. Default: 0 (unlimited).
+    pub max_diff_width: u16,
+    /// Seconds between automatic diff refreshes. Default: 0 (off).
+    pub auto_refresh_secs: u64,
+    /// Milliseconds between redraws, clamped to `16..=200`. Applied at startup
+    /// (see `event::spawn_event_task`). Default: 33 (about 30 FPS).
+    pub render_ms: u64,
+    /// Milliseconds between `Tick` events, which expire toasts and run the auto
+    /// refresh, clamped to `50..=2000`. Applied at startup. Default: 250.
+    pub tick_ms: u64,
+    /// Inline emphasis on changed line pairs: `"word"`, `"char"`, or `"off"`.
+    /// Default: `Word`.
+    pub word_diff: WordDiff,
+    /// Unchanged lines around each change, `0..=MAX_CONTEXT_LINES`. Applied at
+    /// startup; a session that changed it with `-` / `+` keeps its own count.
+    /// Default: 3.
+    pub context_lines: u32,
+    /// Bundled syntect theme for code in the diff, chosen independently of the
+    /// UI `theme`. Default: `SYNTECT_THEME` (`base16-ocean.dark`).
+    pub syntax_theme: String,
+    /// Run on the terminal's alternate screen. `false` renders inline instead,
+    /// preserving earlier scrollback but pushing redraws into it (see `tui.rs`).
+    /// `--no-alt-screen` overrides this. Default: `true`.
+    pub alt_screen: bool,
+    /// Panel orientation: `"horizontal"`, `"vertical"`, or `"auto"` (`None`),
+    /// which stacks the panels on terminals with fewer columns than rows.
+    /// Default: `None`.
+    pub layout: Option<LayoutOrientation>,
+    /// Starting `[left, center, right]` panel percentages, as for `:layout`.
+    /// Applied at startup only, so a resize is never undone by a reload, and
+    /// replaced by a resumed session's saved widths. Default: `None` (the
+    /// built-in 20 / 55 / 25).
+    pub panel_widths: Option<[u16; 3]>,
+    /// Status bar segments in display order. Unknown names are skipped with a
+    /// warning. Default: `StatusSegment::DEFAULT`.
+    pub status_segments: Vec<StatusSegment>,
+    /// Honour `linguist-generated` in `.gitattributes`: such files are dimmed in
+    /// the file list, auto-marked reviewed, and their hunks folded to headers.
+    /// Default: `true`.
+    pub detect_generated: bool,
+    /// Mark runs of removed lines that reappear as added lines elsewhere in the
+    /// same file as moved (an approximation of `git diff --color-moved`; see
+    /// `git::moved` for its limits). Default: `false`.
+    pub color_moved: bool,
+    /// Highlight each changed file in full, old and new side, and take the diff's
+    /// code colors from that, instead of highlighting every hunk from a fresh
+    /// state. Applied at startup. Default: `false`.
+    pub full_file_highlight: bool,
+    /// Command template for opening the cursor's file (`e`), with `{file}` and
+    /// `{line}` placeholders. `None` runs `$VISUAL` / `$EDITOR`. See `editor`.
+    pub editor_command: Option<String>,
+    /// Suspend the TUI while `editor_command` runs instead of spawning it in the
+    /// background. Default: `false`.
+    pub editor_suspend: bool,
+    /// Print the files reviewed and comments left to stdout after a normal quit.
+    /// Default: `true`.
+    pub quit_summary: bool,
+    /// Shade the focused panel's current row with `Theme::cursor_line` instead
+    /// of reversing it. Default: `true`.
+    pub cursor_line: bool,
+    /// Notes reference `:note` writes to. Must be under `refs/notes/`.
+    /// Default: `refs/notes/airev`.
+    pub note_ref: String,
+    /// Replace a note already on `HEAD` instead of appending to it.
+    /// Default: `false`.
+    pub note_replace: bool,
+    /// Normal-mode keys from the `[keybindings]` table, which replaces the
+    /// defaults per action (see [`KeyMap::from_table`]). Default: the built-in
+    /// keys.
+    pub keybindings: KeyMap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            custom_themes: Vec::new(),
+            force_truecolor: false,
+            scroll_lines: 3,
+            natural_scroll: false,
+            highlight_cache: false,
+            highlight_cache_max_entries: 500,
+            default_diff_mode: DiffMode::Unstaged,
+            base_branch: "main".to_owned(),
+            max_diff_width: 0,
+            auto_refresh_secs: 0,
+            render_ms: 33,
+            tick_ms: 250,
+            word_diff: WordDiff::Word,
+            context_lines: 3,
+            syntax_theme: SYNTECT_THEME.to_owned(),
+            alt_screen: true,
+            layout: None,
+            panel_widths: None,
+            status_segments: StatusSegment::DEFAULT.to_vec(),
+            detect_generated: true,
+            color_moved: false,
+            full_file_highlight: false,
+            editor_command: None,
+            editor_suspend: false,
+            quit_summary: true,
+            cursor_line: true,
+            note_ref: "refs/notes/airev".to_owned(),
+            note_replace: false,
+            keybindings: KeyMap::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Builds a `Config` from a parsed TOML table, keeping defaults for absent keys.
+    ///
+    /// Keys with the wrong type or an out-of-range value are reported to stderr
+    /// and ignored.
+    pub fn from_table(table: &toml::Table) -> Self {
+        let (config, warnings) = Self::parse_table(table);
+        for warning in warnings {
+            eprintln!("airev: {warning}");
+        }
+        config
+    }
+
+    /// Like [`Config::from_table`], but returns the notes about ignored keys
+    /// instead of printing them.
+    pub fn parse_table(table: &toml::Table) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let w = &mut Vec::new();
+        read_opt_string(table, "theme", &mut config.theme, w);
+        read_custom_themes(table, "themes", &mut config.custom_themes, w);
+        read_bool(table, "force_truecolor", &mut config.force_truecolor, w);
+        read_int(table, "scroll_lines", 1, u16::MAX as i64, &mut config.scroll_lines, w);
+        read_bool(table, "natural_scroll", &mut config.natural_scroll, w);
+        read_bool(table, "highlight_cache", &mut config.highlight_cache, w);
+        read_int(
+            table,
+            "highlight_cache_max_entries",
+            1,
+            1_000_000,
+            &mut config.highlight_cache_max_entries,
+            w,
+        );
+        read_diff_mode(table, "default_diff_mode", &mut config.default_diff_mode, w);
+        read_base_branch(table, "base_branch", &mut config.base_branch, w);
+        read_int(table, "max_diff_width", 0, u16::MAX as i64, &mut config.max_diff_width, w);
+        read_int(table, "auto_refresh_secs", 0, 86_400, &mut config.auto_refresh_secs, w);
+        read_millis(table, "render_ms", 16, 200, &mut config.render_ms, w);
+        read_millis(table, "tick_ms", 50, 2000, &mut config.tick_ms, w);
+        read_word_diff(table, "word_diff", &mut config.word_diff, w);
+        let max_context = i64::from(MAX_CONTEXT_LINES);
+        read_int(table, "context_lines", 0, max_context, &mut config.context_lines, w);
+        read_syntax_theme(table, "syntax_theme", &mut config.syntax_theme, w);
+        read_bool(table, "alt_screen", &mut config.alt_screen, w);
+        read_layout(table, "layout", &mut config.layout, w);
+        read_panel_widths(table, "panel_widths", &mut config.panel_widths, w);
+        read_status_segments(table, "status_segments", &mut config.status_segments, w);
+        read_bool(table, "detect_generated", &mut config.detect_generated, w);
+        read_bool(table, "color_moved", &mut config.color_moved, w);
+        read_bool(table, "full_file_highlight", &mut config.full_file_highlight, w);
+        read_opt_string(table, "editor_command", &mut config.editor_command, w);
+        read_bool(table, "editor_suspend", &mut config.editor_suspend, w);
+        read_bool(table, "quit_summary", &mut config.quit_summary, w);
+        read_bool(table, "cursor_line", &mut config.cursor_line, w);
+        read_note_ref(table, "note_ref", &mut config.note_ref, w);
+        read_bool(table, "note_replace", &mut config.note_replace, w);
+        read_keybindings(table, "keybindings", &mut config.keybindings, w);
+        (config, std::mem::take(w))
+    }
+}
+
+/// Returns the path to the airev config file.
+///
+/// Prefers `$XDG_CONFIG_HOME/airev/config.toml`; falls back to
+/// `~/.config/airev/config.toml` when the env var is absent.
+pub fn config_path() -> std::path::PathBuf {
+    let base = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|h| std::path::PathBuf::from(h).join(".config"))
+        })
+        .unwrap_or_else(|| std::path::PathBuf::from(".config"));
+    base.join("airev").join("config.toml")
+}
+
+/// Path of the repo-local config layered over the global one, relative to the
+/// working directory like the review database.
+pub const LOCAL_CONFIG_PATH: &str = ".airev/config.toml";
+
+/// Loads the config from [`config_path`], with [`LOCAL_CONFIG_PATH`] layered on
+/// top (see [`merge_tables`]).
+///
+/// A file that does not exist or cannot be parsed contributes no keys, so with
+/// neither the result is `Config::default()`. A missing global file is first
+/// created from [`DEFAULT_CONFIG`] (see [`write_default_config`]). Never panics —
+/// config errors are soft failures printed to stderr.
+pub fn load_config() -> Config {
+    let path = config_path();
+    let mut table = match std::fs::read_to_string(&path) {
+        Ok(raw) => parse_or_note(&path, &raw),
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                match write_default_config(&path) {
+                    Ok(()) => eprintln!("airev: wrote default config to {}", path.display()),
+                    Err(e) => eprintln!("airev: could not write {}: {e}", path.display()),
+                }
+            }
+            toml::Table::new()
+        }
+    };
+    let local = std::path::Path::new(LOCAL_CONFIG_PATH);
+    if let Ok(raw) = std::fs::read_to_string(local) {
+        merge_tables(&mut table, parse_or_note(local, &raw));
+    }
+    Config::from_table(&table)
+}
+
+/// Parses a config file's text, printing a note and returning an empty table
+/// when it is not valid TOML.
+fn parse_or_note(path: &std::path::Path, raw: &str) -> toml::Table {
+    toml::from_str(raw).unwrap_or_else(|e| {
+        eprintln!("airev: config parse error in {:?}: {}", path, e);
+        toml::Table::new()
+    })
+}
+
+/// Layers `overlay` onto `base` key by key: each key in `overlay` replaces the
+/// one in `base`, except that two tables (such as `[themes]`) are merged the
+/// same way.
+pub fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Re-reads the config at `path`, with the repo-local config at `local` layered
+/// on top, for a `SIGHUP` reload.
+///
+/// Unlike [`load_config`], nothing is printed (stderr is the TUI) and nothing
+/// falls back to a default: a missing or unparseable global file, an unparseable
+/// local file, an ignored key, or an unknown theme name is an error, so the
+/// caller can keep the running settings. A missing local file is fine.
+///
+/// # Errors
+///
+/// Returns a message describing every problem found, one per line.
+pub fn reload_config(path: &std::path::Path, local: &std::path::Path) -> Result<Config, String> {
+    let parse = |path: &std::path::Path, raw: &str| {
+        toml::from_str::<toml::Table>(raw)
+            .map_err(|e| format!("parse error in {}: {e}", path.display()))
+    };
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let mut table = parse(path, &raw)?;
+    match std::fs::read_to_string(local) {
+        Ok(raw) => merge_tables(&mut table, parse(local, &raw)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("cannot read {}: {e}", local.display())),
+    }
+    let (config, mut warnings) = Config::parse_table(&table);
+    if let Some(name) = config.theme.as_deref() {
+        if Theme::find(name, &config.custom_themes).is_none() {
+            warnings.push(format!("unknown theme '{name}'"));
+        }
+    }
+    if warnings.is_empty() {
+        Ok(config)
+    } else {
+        Err(warnings.join("\n"))
+    }
+}
+
+/// Records a soft-failure note for an invalid config value.
+fn warn_invalid(warnings: &mut Vec<String>, key: &str, expected: &str) {
+    warnings.push(format!("ignoring config key '{key}': {expected}"));
+}
+
+/// Reads a string key into `out` as `Some`; leaves `out` unchanged if absent or mistyped.
+fn read_opt_string(
+    table: &toml::Table,
+    key: &str,
+    out: &mut Option<String>,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        match v.as_str() {
+            Some(s) => *out = Some(s.to_owned()),
+            None => warn_invalid(warnings, key, "expected a string"),
+        }
+    }
+}
+
+/// Reads a boolean key into `out`; leaves `out` unchanged if absent or mistyped.
+fn read_bool(table: &toml::Table, key: &str, out: &mut bool, warnings: &mut Vec<String>) {
+    if let Some(v) = table.get(key) {
+        match v.as_bool() {
+            Some(b) => *out = b,
+            None => warn_invalid(warnings, key, "expected true or false"),
+        }
+    }
+}
+
+/// Reads an integer key in `min..=max` into `out`; leaves `out` unchanged if
+/// absent, mistyped, or out of range.
+fn read_int<T: TryFrom<i64>>(
+    table: &toml::Table,
+    key: &str,
+    min: i64,
+    max: i64,
+    out: &mut T,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        let value = v.as_integer().filter(|n| (min..=max).contains(n));
+        match value.and_then(|n| T::try_from(n).ok()) {
+            Some(n) => *out = n,
+            None => {
+                warn_invalid(warnings, key, &format!("expected an integer in {min}..={max}"))
+            }
+        }
+    }
+}
+
+/// Reads a millisecond interval into `out`, clamped to `min..=max` with a
+/// note when it was outside; leaves `out` unchanged if absent or mistyped.
+fn read_millis(
+    table: &toml::Table,
+    key: &str,
+    min: u64,
+    max: u64,
+    out: &mut u64,
+    warnings: &mut Vec<String>,
+) {
+    let Some(v) = table.get(key) else { return };
+    match v.as_integer().and_then(|n| u64::try_from(n).ok()) {
+        Some(n) => {
+            *out = n.clamp(min, max);
+            if *out != n {
+                warnings.push(format!("config key '{key}' clamped to {}", *out));
+            }
+        }
+        None => warn_invalid(warnings, key, "expected a number of milliseconds"),
+    }
+}
+
+/// Reads a startup diff mode name into `out`; leaves `out` unchanged if absent,
+/// mistyped, or not one of `unstaged` / `staged` / `branch`.
+fn read_diff_mode(
+    table: &toml::Table,
+    key: &str,
+    out: &mut DiffMode,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        match v.as_str().and_then(DiffMode::from_config_name) {
+            Some(m) => *out = m,
+            None => {
+                warn_invalid(warnings, key, "expected \"unstaged\", \"staged\", or \"branch\"")
+            }
+        }
+    }
+}
+
+/// Reads a panel orientation into `out`; leaves `out` unchanged if absent,
+/// mistyped, or not one of `auto` / `horizontal` / `vertical`.
+fn read_layout(
+    table: &toml::Table,
+    key: &str,
+    out: &mut Option<LayoutOrientation>,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        let layout = match v.as_str() {
+            Some("auto") => Some(None),
+            s => s.and_then(LayoutOrientation::parse).map(Some),
+        };
+        match layout {
+            Some(l) => *out = l,
+            None => warn_invalid(
+                warnings,
+                key,
+                "expected \"auto\", \"horizontal\", or \"vertical\"",
+            ),
+        }
+    }
+}
+
+/// Reads three panel percentages into `out`; leaves `out` unchanged if absent,
+/// mistyped, or not summing to 100. Minimum widths are checked when they are
+/// applied (see `AppState::set_panel_percentages`).
+fn read_panel_widths(
+    table: &toml::Table,
+    key: &str,
+    out: &mut Option<[u16; 3]>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(v) = table.get(key) else {
+        return;
+    };
+    let pcts: Option<Vec<u16>> = v
+        .as_array()
+        .and_then(|a| a.iter().map(|n| n.as_integer().and_then(|n| n.try_into().ok())).collect());
+    let sum = |pcts: &[u16]| pcts.iter().copied().map(u32::from).sum::<u32>();
+    match pcts.as_deref() {
+        Some(&[left, center, right]) if sum(&[left, center, right]) == 100 => {
+            *out = Some([left, center, right]);
+        }
+        _ => warn_invalid(warnings, key, "expected three percentages summing to 100"),
+    }
+}
+
+/// Reads a list of status bar segment names into `out`, skipping unknown names
+/// with a warning; leaves `out` unchanged if absent or not an array of strings.
+fn read_status_segments(
+    table: &toml::Table,
+    key: &str,
+    out: &mut Vec<StatusSegment>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(v) = table.get(key) else {
+        return;
+    };
+    let names: Option<Vec<&str>> =
+        v.as_array().and_then(|a| a.iter().map(|n| n.as_str()).collect());
+    let Some(names) = names else {
+        warn_invalid(warnings, key, "expected an array of segment names");
+        return;
+    };
+    *out = names
+        .into_iter()
+        .filter_map(|name| {
+            let segment = StatusSegment::parse(name);
+            if segment.is_none() {
+                warnings.push(format!("ignoring unknown status segment '{name}'"));
+            }
+            segment
+        })
+        .collect();
+}
+
+/// Reads a word-diff granularity into `out`; leaves `out` unchanged if absent,
+/// mistyped, or not one of `word` / `char` / `off`.
+fn read_word_diff(
+    table: &toml::Table,
+    key: &str,
+    out: &mut WordDiff,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        match v.as_str().and_then(WordDiff::parse) {
+            Some(w) => *out = w,
+            None => warn_invalid(warnings, key, "expected \"word\", \"char\", or \"off\""),
+        }
+    }
+}
+
+/// Reads a syntect theme name into `out`; leaves `out` unchanged if absent,
+/// mistyped, or not one of the bundled themes.
+fn read_syntax_theme(
+    table: &toml::Table,
+    key: &str,
+    out: &mut String,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        let names = syntect_theme_names();
+        match v.as_str().filter(|s| names.contains(s)) {
+            Some(s) => *out = s.to_owned(),
+            None => warn_invalid(warnings, key, &format!("expected one of {}", names.join(", "))),
+        }
+    }
+}
+
+/// Reads a notes reference into `out`; leaves `out` unchanged if absent,
+/// mistyped, or not under `refs/notes/`.
+fn read_note_ref(table: &toml::Table, key: &str, out: &mut String, warnings: &mut Vec<String>) {
+    if let Some(v) = table.get(key) {
+        match v.as_str().filter(|s| s.len() > "refs/notes/".len() && s.starts_with("refs/notes/")) {
+            Some(s) => *out = s.to_owned(),
+            None => warn_invalid(warnings, key, "expected a ref under refs/notes/"),
+        }
+    }
+}
+
+/// Reads a ref name into `out`; leaves `out` unchanged if absent, mistyped,
+/// empty, or containing whitespace.
+fn read_base_branch(
+    table: &toml::Table,
+    key: &str,
+    out: &mut String,
+    warnings: &mut Vec<String>,
+) {
+    if let Some(v) = table.get(key) {
+        match v.as_str().filter(|s| !s.is_empty() && !s.contains(char::is_whitespace)) {
+            Some(s) => *out = s.to_owned(),
+            None => warn_invalid(warnings, key, "expected a branch name"),
+        }
+    }
+}
+
+/// Reads the `[themes.NAME]` tables into `out` (see [`Theme::from_table`]).
+///
+/// A `themes` key that is not a table, and entries in it that are not tables,
+/// are skipped with a warning.
+fn read_custom_themes(
+    table: &toml::Table,
+    key: &str,
+    out: &mut Vec<(String, Theme)>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(v) = table.get(key) else { return };
+    let Some(themes) = v.as_table() else {
+        warn_invalid(warnings, key, "expected [themes.NAME] tables");
+        return;
+    };
+    for (name, colors) in themes {
+        match colors.as_table() {
+            Some(colors) => out.push((name.clone(), Theme::from_table(name, colors, warnings))),
+            None => warn_invalid(warnings, &format!("{key}.{name}"), "expected a table"),
+        }
+    }
+}
+
+/// Reads the `[keybindings]` table into `out` (see [`KeyMap::from_table`]);
+/// leaves `out` unchanged if absent or not a table.
+fn read_keybindings(
+    table: &toml::Table,
+    key: &str,
+    out: &mut KeyMap,
+    warnings: &mut Vec<String>,
+) {
+    let Some(v) = table.get(key) else { return };
+    match v.as_table() {
+        Some(bindings) => *out = KeyMap::from_table(bindings, warnings),
+        None => warn_invalid(warnings, key, "expected a [keybindings] table"),
+    }
+}
+
+/// Writes [`DEFAULT_CONFIG`] to `path`, creating its parent directories.
+///
+/// Never overwrites: fails with `AlreadyExists` if the file is already there.
+///
+/// # Errors
+///
+/// Returns the I/O error if the directory or file cannot be created, e.g. in an
+/// unwritable config directory.
+pub fn write_default_config(path: &std::path::Path) -> std::io::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(DEFAULT_CONFIG.as_bytes())
+}
+
+/// Returns `config` with its `theme` key set to `name`, keeping every other line
+/// (comments included) as written.
+///
+/// The first `theme = ...` line, commented out or not, is replaced; without one,
+/// the key is prepended so it stays at the top level rather than landing in a
+/// table.
+pub fn with_theme(config: &str, name: &str) -> String {
+    let setting = format!("theme = \"{name}\"");
+    let is_theme_line = |line: &str| {
+        let key = line.trim_start().trim_start_matches('#').trim_start();
+        key.strip_prefix("theme").is_some_and(|rest| rest.trim_start().starts_with('='))
+    };
+    let mut replaced = false;
+    let mut out: Vec<&str> = Vec::new();
+    for line in config.lines() {
+        if !replaced && is_theme_line(line) {
+            out.push(&setting);
+            replaced = true;
+        } else {
+            out.push(line);
+        }
+    }
+    if !replaced {
+        out.insert(0, &setting);
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Saves `name` as the `theme` in the config file at `path` (see [`with_theme`]).
+///
+/// A missing file is started from [`DEFAULT_CONFIG`].
+///
+/// # Errors
+///
+/// Returns the I/O error if the file cannot be read or written.
+pub fn save_theme(path: &std::path::Path, name: &str) -> std::io::Result<()> {
+    let current = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => DEFAULT_CONFIG.to_owned(),
+        Err(e) => return Err(e),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, with_theme(&current, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_file_parses_to_defaults() {
+        let table: toml::Table = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(Config::from_table(&table), Config::default());
+    }
+
+    #[test]
+    fn theme_is_written_in_place() {
+        let updated = with_theme(DEFAULT_CONFIG, "light");
+        let table: toml::Table = toml::from_str(&updated).unwrap();
+        assert_eq!(Config::from_table(&table).theme.as_deref(), Some("light"));
+        assert_eq!(updated.lines().count(), DEFAULT_CONFIG.lines().count());
+        assert!(updated.contains("# Color theme:"));
+
+        let again: toml::Table = toml::from_str(&with_theme(&updated, "dark")).unwrap();
+        assert_eq!(Config::from_table(&again).theme.as_deref(), Some("dark"));
+
+        let prepended = with_theme("[extra]\nx = 1\n", "dark");
+        assert!(prepended.starts_with("theme = \"dark\"\n[extra]"));
+    }
+
+    #[test]
+    fn custom_themes_fall_back_per_field_and_note_bad_colors() {
+        let table: toml::Table = toml::from_str(
+            "theme = \"gruvbox\"\n\
+             [themes.gruvbox]\n\
+             border_active = \"#83a598\"\n\
+             diff_added = \"green\"\n\
+             sparkle = \"#ffffff\"\n",
+        )
+        .unwrap();
+        let (config, warnings) = Config::parse_table(&table);
+        let [(name, theme)] = &config.custom_themes[..] else {
+            panic!("expected one custom theme: {:?}", config.custom_themes);
+        };
+        assert_eq!(name, "gruvbox");
+        assert_eq!(theme.border_active, ratatui::style::Color::Rgb(0x83, 0xa5, 0x98));
+        assert_eq!(theme.diff_added, Theme::dark().diff_added);
+        assert_eq!(theme.badge_info, Theme::dark().badge_info);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings.iter().any(|w| w.contains("'diff_added'")), "{warnings:?}");
+        assert!(warnings.iter().any(|w| w.contains("unknown color 'sparkle'")), "{warnings:?}");
+        assert_eq!(Theme::find("gruvbox", &config.custom_themes).unwrap().1, *theme);
+
+        let table: toml::Table = toml::from_str("themes = \"gruvbox\"\n").unwrap();
+        assert_eq!(Config::parse_table(&table).1.len(), 1);
+    }
+
+    #[test]
+    fn reload_reads_changed_config_and_rejects_invalid_values() {
+        let dir = std::env::temp_dir().join(format!("airev-reload-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let local = dir.join("local.toml");
+
+        std::fs::write(&path, DEFAULT_CONFIG).unwrap();
+        assert_eq!(reload_config(&path, &local).unwrap(), Config::default());
+
+        let edited = with_theme(DEFAULT_CONFIG, "light")
+            .replace("scroll_lines = 3", "scroll_lines = 5")
+            .replace("layout = \"auto\"", "layout = \"vertical\"");
+        std::fs::write(&path, edited).unwrap();
+        let config = reload_config(&path, &local).unwrap();
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert_eq!(config.scroll_lines, 5);
+        assert_eq!(config.layout, Some(LayoutOrientation::Vertical));
+
+        std::fs::write(&path, "scroll_lines = 0\ntheme = \"neon\"\nsyntax_theme = \"neon\"\n")
+            .unwrap();
+        let err = reload_config(&path, &local).unwrap_err();
+        assert!(err.contains("scroll_lines"), "{err}");
+        assert!(err.contains("'syntax_theme': expected one of"), "{err}");
+        assert!(err.contains("unknown theme 'neon'"), "{err}");
+
+        std::fs::write(&path, "scroll_lines = \n").unwrap();
+        assert!(reload_config(&path, &local).unwrap_err().starts_with("parse error"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn local_config_wins_key_by_key() {
+        let dir = std::env::temp_dir().join(format!("airev-local-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, local) = (dir.join("config.toml"), dir.join("local.toml"));
+        std::fs::write(
+            &path,
+            "theme = \"light\"\nscroll_lines = 5\n[themes.mine]\nbadge_info = \"#000000\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &local,
+            "theme = \"mine\"\ndefault_diff_mode = \"staged\"\npanel_widths = [30, 40, 30]\n\
+             [themes.mine]\nborder_active = \"#ffffff\"\n",
+        )
+        .unwrap();
+
+        let config = reload_config(&path, &local).unwrap();
+        assert_eq!(config.theme.as_deref(), Some("mine"));
+        assert_eq!(config.scroll_lines, 5);
+        assert_eq!(config.default_diff_mode, DiffMode::Staged);
+        assert_eq!(config.panel_widths, Some([30, 40, 30]));
+        let (_, mine) = &config.custom_themes[0];
+        assert_eq!(mine.border_active, ratatui::style::Color::Rgb(255, 255, 255));
+        assert_eq!(mine.badge_info, ratatui::style::Color::Rgb(0, 0, 0));
+
+        std::fs::write(&local, "panel_widths = [30, 40, 40]\n").unwrap();
+        assert!(reload_config(&path, &local).unwrap_err().contains("panel_widths"));
+        std::fs::write(&local, "theme = \n").unwrap();
+        assert!(reload_config(&path, &local).unwrap_err().contains("local.toml"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn frame_intervals_are_clamped() {
+        let table: toml::Table = toml::from_str("render_ms = 5\ntick_ms = 1000\n").unwrap();
+        let (config, warnings) = Config::parse_table(&table);
+        assert_eq!((config.render_ms, config.tick_ms), (16, 1000));
+        assert_eq!(warnings, vec!["config key 'render_ms' clamped to 16".to_owned()]);
+
+        let table: toml::Table = toml::from_str("render_ms = \"fast\"\n").unwrap();
+        let (config, warnings) = Config::parse_table(&table);
+        assert_eq!(config.render_ms, 33);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn default_config_never_overwrites() {
+        let path = std::env::temp_dir()
+            .join(format!("airev-config-test-{}", std::process::id()))
+            .join("config.toml");
+        let _ = std::fs::remove_file(&path);
+
+        write_default_config(&path).unwrap();
+        std::fs::write(&path, "scroll_lines = 7\n").unwrap();
+        let err = write_default_config(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "scroll_lines = 7\n");
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,136 @@
+//! Opening the file under the cursor in an external editor (`e`).
+//!
+//! Without config `editor_command`, `$VISUAL` or `$EDITOR` is run as
+//! `EDITOR +LINE FILE` — the form vi, vim, nano, and emacs accept — with the
+//! TUI suspended until it exits.
+//!
+//! `editor_command` (or `--editor-command`) is a template instead, such as
+//! `code --goto {file}:{line}` or `subl {file}:{line}`. It is split on
+//! whitespace and `{file}` / `{line}` are filled in per word, so a path with
+//! spaces stays one argument. A template is meant for GUI editors: it is spawned
+//! detached and airev keeps running. Config `editor_suspend = true` hands it the
+//! terminal instead, for a terminal editor that needs its own template.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::app::AppState;
+
+/// A file and a 1-based line in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorTarget {
+    /// Path of the file, absolute when the diff comes from a repository.
+    pub file: PathBuf,
+    /// Line to put the editor's cursor on.
+    pub line: u32,
+}
+
+/// An editor command and how the event loop runs it.
+#[derive(Debug)]
+pub enum Launch {
+    /// Run on the terminal with the TUI suspended until it exits.
+    Suspended(Command),
+    /// Spawn in the background while the TUI keeps running.
+    Detached(Command),
+}
+
+/// Builds the editor command for `target`.
+///
+/// `template` is the configured `editor_command`; without one `editor` (the
+/// value of `$VISUAL` or `$EDITOR`, which may carry its own flags) is used.
+/// `suspend` only applies to a template.
+///
+/// # Errors
+///
+/// Returns a message for an empty template, or when there is neither a template
+/// nor an editor variable.
+pub fn launch(
+    template: Option<&str>,
+    editor: Option<&str>,
+    suspend: bool,
+    target: &EditorTarget,
+) -> Result<Launch, String> {
+    let line = target.line.to_string();
+    let Some(template) = template else {
+        let mut words = editor.unwrap_or_default().split_whitespace();
+        let program = words.next().ok_or("no editor: set $EDITOR or editor_command")?;
+        let mut command = Command::new(program);
+        command.args(words).arg(format!("+{line}")).arg(&target.file);
+        return Ok(Launch::Suspended(command));
+    };
+    let file = target.file.to_string_lossy();
+    let mut words = template
+        .split_whitespace()
+        .map(|word| word.replace("{file}", &file).replace("{line}", &line));
+    let program = words.next().ok_or("editor_command is empty")?;
+    let mut command = Command::new(program);
+    command.args(words);
+    Ok(if suspend { Launch::Suspended(command) } else { Launch::Detached(command) })
+}
+
+/// Builds the editor command for the cursor's file and line (see
+/// [`AppState::editor_target`]).
+///
+/// Repository paths are joined onto `repo_root`; a `--files` review passes
+/// `None` and its paths are used as given.
+///
+/// # Errors
+///
+/// Returns a message when there is no file to open or no editor (see [`launch`]).
+pub fn launch_for(state: &AppState, repo_root: Option<&str>) -> Result<Launch, String> {
+    let (file, line) = state.editor_target().ok_or("no file to open")?;
+    let target = EditorTarget { file: Path::new(repo_root.unwrap_or("")).join(file), line };
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).ok();
+    let template = state.editor_override.as_deref().or(state.editor_command.as_deref());
+    launch(template, editor.as_deref(), state.editor_suspend, &target)
+}
+
+/// Spawns `command` with its standard streams closed, so it cannot draw over the
+/// TUI, and reaps it on a background thread when it exits.
+///
+/// # Errors
+///
+/// Returns the spawn error, e.g. when the program is not on `PATH`.
+pub fn spawn_detached(mut command: Command) -> std::io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(launch: &Launch) -> Vec<String> {
+        let command = match launch {
+            Launch::Suspended(c) | Launch::Detached(c) => c,
+        };
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn template_fills_each_word_and_env_editor_gets_plus_line() {
+        let target = EditorTarget { file: PathBuf::from("/repo/my file.rs"), line: 42 };
+
+        let gui = launch(Some("code --goto {file}:{line}"), Some("vim"), false, &target).unwrap();
+        assert!(matches!(gui, Launch::Detached(_)));
+        assert_eq!(argv(&gui), ["code", "--goto", "/repo/my file.rs:42"]);
+
+        let suspended = launch(Some("hx {file}:{line}"), None, true, &target).unwrap();
+        assert!(matches!(suspended, Launch::Suspended(_)));
+
+        let env = launch(None, Some("nvim -p"), false, &target).unwrap();
+        assert!(matches!(env, Launch::Suspended(_)));
+        assert_eq!(argv(&env), ["nvim", "-p", "+42", "/repo/my file.rs"]);
+
+        assert!(launch(None, None, false, &target).is_err());
+        assert!(launch(Some("  "), Some("vim"), false, &target).is_err());
+    }
+}
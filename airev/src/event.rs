@@ -5,8 +5,10 @@
 //! main loop receives from this channel and dispatches accordingly.
 //!
 //! Two independent intervals drive the render and logic cycles:
-//! - **Render interval** (33 ms ≈ 30 FPS) — triggers a `terminal.draw()` call.
-//! - **Tick interval** (250 ms = 4 Hz) — triggers application-state updates.
+//! - **Render interval** (config `render_ms`, default 33 ms ≈ 30 FPS) — triggers a
+//!   `terminal.draw()` call.
+//! - **Tick interval** (config `tick_ms`, default 250 ms = 4 Hz) — triggers
+//!   application-state updates.
 //!
 //! Keeping them independent allows tuning render frequency (e.g., drop to 20 FPS
 //! on battery) without affecting logic frequency, and vice-versa.
@@ -15,6 +17,7 @@ use crossterm::event::{Event, EventStream, KeyEvent, KeyEventKind, MouseEvent};
 use futures::{FutureExt, StreamExt};
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::interval;
 
 /// All events the application can receive from any source.
@@ -33,20 +36,61 @@ pub enum AppEvent {
     Key(KeyEvent),
     /// A mouse event from the terminal (click, scroll, move).
     Mouse(MouseEvent),
-    /// Terminal was resized to (columns, rows).
-    Resize(u16, u16),
+    /// Terminal was resized; the next draw picks up the new size.
+    Resize,
     /// Logic tick for state updates (4 Hz / 250 ms).
     Tick,
     /// Render tick — triggers a `terminal.draw()` call (≈30 FPS / 33 ms).
     Render,
-    /// A watched file changed on disk.
+    /// Files in the work tree changed on disk (debounced; see `watcher`).
     FileChanged,
     /// Result from the git background thread.
     GitResult(Box<crate::git::types::GitResultPayload>),
+    /// The git background thread panicked.
+    GitWorkerStopped {
+        /// Generation of the worker that stopped (see `AsyncGit::respawn`).
+        generation: u64,
+        /// The panic message.
+        reason: String,
+    },
+    /// The git worker's answer to `GitRequest::ResolveRange`.
+    RangeResolved {
+        /// Generation of the worker that answered.
+        generation: u64,
+        /// The `(from, to)` refs that were checked.
+        range: (String, String),
+        /// `Err` names the ref that does not resolve to a commit.
+        result: Result<(), String>,
+    },
+    /// The git worker's answer to `GitRequest::ListRefs`.
+    RefsListed {
+        /// Generation of the worker that answered.
+        generation: u64,
+        /// `HEAD`, then local branches, remote branches, and tags by short name.
+        names: Vec<String>,
+    },
+    /// The git worker's answer to `GitRequest::Blame`.
+    BlameLoaded {
+        /// Generation of the worker that answered.
+        generation: u64,
+        /// The commit that was blamed at.
+        rev: String,
+        /// The file that was blamed.
+        path: String,
+        /// The requested lines, or git's error.
+        result: Result<Vec<(u32, crate::git::types::BlameTag)>, String>,
+    },
+    /// The git worker's answer to `GitRequest::WriteNote`.
+    NoteWritten {
+        /// Generation of the worker that answered.
+        generation: u64,
+        /// The notes ref written to.
+        notes_ref: String,
+        /// The commit the note is on, or git's error.
+        result: Result<String, String>,
+    },
     /// Result from the database background task.
     DbResult(Box<DbResultPayload>),
-    /// Quit signal (from `q` key or SIGTERM).
-    Quit,
 }
 
 /// Typed results from async DB operations, sent back over the event channel.
@@ -55,14 +99,29 @@ pub enum AppEvent {
 /// variants pointer-sized on the channel regardless of payload size.
 #[derive(Debug)]
 pub enum DbResultPayload {
-    /// A session was loaded (resumed) from the database.
-    SessionLoaded(airev_core::types::Session),
-    /// A new session was created in the database.
-    SessionCreated(airev_core::types::Session),
-    /// File review states loaded for the current session.
-    FileReviewStateLoaded(Vec<(String, bool)>),
     /// A file's reviewed state was toggled.
     ReviewToggled { file_path: String, reviewed: bool },
+    /// Toggling that file's reviewed state failed; carries the state it had before
+    /// the optimistic toggle and the error message.
+    ReviewToggleFailed { file_path: String, reviewed: bool, message: String },
+    /// Several files were marked reviewed in one transaction (`D`).
+    FilesMarkedReviewed(Vec<String>),
+    /// Marking those files reviewed failed; carries them and the error message.
+    MarkReviewedFailed { file_paths: Vec<String>, message: String },
+    /// A comment was inserted; carries the stored row with its generated id.
+    CommentSaved(airev_core::types::Comment),
+    /// A comment insert failed; carries the error message. The draft is kept.
+    CommentSaveFailed(String),
+    /// A fire-and-forget write (a session setting, a comment's resolved state)
+    /// failed; carries a message naming what was not saved.
+    SaveFailed(String),
+    /// The startup session load finished (see `session::spawn_open_session`).
+    SessionOpened(Box<crate::session::OpenedSession>),
+    /// The startup session load failed; carries the error message.
+    SessionFailed(String),
+    /// The session's comments were written as Markdown (`E`); carries the
+    /// report's path, or the error message.
+    CommentsExported(Result<String, String>),
 }
 
 /// Holds the sender and receiver ends of the unified event channel.
@@ -81,7 +140,7 @@ impl EventHandler {
     /// Creates a new `EventHandler` with a fresh unbounded channel.
     ///
     /// Unbounded is appropriate here because the producer side (terminal events
-    /// + timers) generates events at a bounded hardware rate, and the consumer
+    /// and timers) generates events at a bounded hardware rate, and the consumer
     /// (main loop) always keeps up. If backpressure ever becomes a concern in a
     /// future phase, swap to a bounded channel at that point.
     pub fn new() -> Self {
@@ -112,10 +171,23 @@ impl Default for EventHandler {
 /// - Send errors are silently ignored (`let _ = tx.send(…)`) — if the
 ///   receiver has been dropped, the task simply exits on the next loop
 ///   iteration when it tries to send.
-pub fn spawn_event_task(tx: mpsc::UnboundedSender<AppEvent>) {
+///
+/// Returns the task's handle so the event loop can abort it while a terminal
+/// editor owns the terminal (see `tui::suspend`), then spawn a new one.
+///
+/// # Arguments
+///
+/// * `tx`        — sender the events are delivered on
+/// * `render_ms` — milliseconds between `Render` events (config `render_ms`)
+/// * `tick_ms`   — milliseconds between `Tick` events (config `tick_ms`)
+pub fn spawn_event_task(
+    tx: mpsc::UnboundedSender<AppEvent>,
+    render_ms: u64,
+    tick_ms: u64,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let mut tick_interval = interval(Duration::from_millis(250));
-        let mut render_interval = interval(Duration::from_millis(33));
+        let mut tick_interval = interval(Duration::from_millis(tick_ms));
+        let mut render_interval = interval(Duration::from_millis(render_ms));
         let mut reader = EventStream::new();
 
         loop {
@@ -132,13 +204,11 @@ pub fn spawn_event_task(tx: mpsc::UnboundedSender<AppEvent>) {
                 }
                 maybe_event = crossterm_event => {
                     match maybe_event {
-                        Some(Ok(Event::Key(key))) => {
-                            if key.kind == KeyEventKind::Press {
-                                let _ = tx.send(AppEvent::Key(key));
-                            }
+                        Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                            let _ = tx.send(AppEvent::Key(key));
                         }
-                        Some(Ok(Event::Resize(w, h))) => {
-                            let _ = tx.send(AppEvent::Resize(w, h));
+                        Some(Ok(Event::Resize(_, _))) => {
+                            let _ = tx.send(AppEvent::Resize);
                         }
                         Some(Ok(Event::Mouse(mouse))) => {
                             let _ = tx.send(AppEvent::Mouse(mouse));
@@ -148,5 +218,5 @@ pub fn spawn_event_task(tx: mpsc::UnboundedSender<AppEvent>) {
                 }
             }
         }
-    });
+    })
 }
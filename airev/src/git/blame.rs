@@ -0,0 +1,105 @@
+//! Blame for the annotation column (`a`): which commit last touched each line
+//! on the old side of the diff.
+//!
+//! Context and removed lines exist in the old file, so they are blamed at the
+//! old side's commit: `HEAD` for the unstaged and staged modes, `main` for the
+//! branch comparison, and `FROM` for a range. The unstaged old side is really the
+//! index, so a line that is only staged shows the commit it had in `HEAD`.
+//!
+//! Blame is slow on long histories, so the worker only blames the lines the app
+//! asks for — the ones in view — and the app caches the answers per file.
+
+use std::path::Path;
+
+use git2::{BlameOptions, Repository};
+
+use crate::git::types::BlameTag;
+
+/// Blames lines `first..=last` (1-based) of `path` as of commit `rev`.
+///
+/// Returns one `(line, tag)` per line in the range that the file has.
+///
+/// # Errors
+///
+/// Returns git's error when `rev` does not name a commit or `path` is not in it
+/// (e.g. the old name of a renamed file differs).
+pub fn blame_lines(
+    repo: &Repository,
+    rev: &str,
+    path: &str,
+    first: u32,
+    last: u32,
+) -> Result<Vec<(u32, BlameTag)>, git2::Error> {
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    let mut opts = BlameOptions::new();
+    opts.newest_commit(commit.id()).min_line(first as usize).max_line(last as usize);
+    let blame = repo.blame_file(Path::new(path), Some(&mut opts))?;
+
+    let mut tags = Vec::new();
+    for hunk in blame.iter() {
+        let start = hunk.final_start_line() as u32;
+        let end = start + hunk.lines_in_hunk() as u32;
+        let sha = hunk.final_commit_id().to_string();
+        let tag = BlameTag {
+            sha: sha[..7.min(sha.len())].to_owned(),
+            initials: initials(hunk.final_signature().name().unwrap_or_default()),
+        };
+        tags.extend((start.max(first)..end.min(last + 1)).map(|line| (line, tag.clone())));
+    }
+    Ok(tags)
+}
+
+/// Two-letter initials for an author name: the first letters of its first two
+/// words (`Jane Doe` → `JD`), or of a single word (`jdoe` → `JD`).
+pub fn initials(name: &str) -> String {
+    let mut words = name.split_whitespace();
+    let letters: String = match (words.next(), words.next()) {
+        (Some(first), Some(second)) => {
+            first.chars().take(1).chain(second.chars().take(1)).collect()
+        }
+        (Some(only), None) => only.chars().take(2).collect(),
+        _ => "??".to_owned(),
+    };
+    letters.to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::init_test_repo;
+
+    #[test]
+    fn lines_are_tagged_with_the_commit_that_last_changed_them() {
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+        let commit = |content: &str, author: &str| {
+            std::fs::write(dir.join("lib.rs"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("lib.rs")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            index.write().unwrap();
+            let sig = git2::Signature::now(author, "dev@example.com").unwrap();
+            let parents: Vec<git2::Commit> =
+                repo.head().ok().and_then(|h| h.peel_to_commit().ok()).into_iter().collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, "change", &tree, &parents).unwrap().to_string()
+        };
+        let first = commit("a\nb\nc\n", "Jane Doe");
+        let second = commit("a\nB\nc\n", "rob");
+
+        let tags = blame_lines(&repo, "HEAD", "lib.rs", 2, 3).unwrap();
+        let expect = |sha: &str, initials: &str| BlameTag {
+            sha: sha[..7].to_owned(),
+            initials: initials.to_owned(),
+        };
+        assert_eq!(tags, [(2, expect(&second, "RO")), (3, expect(&first, "JD"))]);
+        assert!(blame_lines(&repo, "HEAD", "missing.rs", 1, 1).is_err());
+    }
+
+    #[test]
+    fn initials_take_two_words_or_two_letters() {
+        assert_eq!(initials("Jane Q. Doe"), "JQ");
+        assert_eq!(initials("jdoe"), "JD");
+        assert_eq!(initials(""), "??");
+    }
+}
@@ -0,0 +1,228 @@
+//! Optional on-disk cache of syntect-highlighted diff lines.
+//!
+//! Highlighting is the slowest part of building a `GitResultPayload`, and on a
+//! relaunch most files are unchanged. The cache stores each file's highlighted
+//! lines keyed by `(file_path, blob ids, highlight style)` so an unchanged file is
+//! rebuilt from disk instead of re-running syntect.
+//!
+//! The key uses the old and new blob oids from the diff delta. When either oid is
+//! unknown (e.g. an unhashed working-tree file) a SHA-1 of the file's hunk text is
+//! used instead, so a changed file always misses. Entries are evicted
+//! least-recently-used once `max_entries` is exceeded.
+//!
+//! The cache is owned by the git worker thread; it is loaded once at spawn and
+//! written back after any request that added entries. I/O errors are ignored —
+//! a missing or corrupt cache file just means a cold start.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use git2::{ObjectType, Oid};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use serde::{Deserialize, Serialize};
+
+use crate::git::types::{FileSummary, OwnedDiffHunk};
+
+/// A single styled span in serializable form.
+///
+/// Colors use ratatui's `Display` / `FromStr` round-trip (`"#rrggbb"`, `"Red"`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSpan {
+    text: String,
+    fg: Option<String>,
+    bg: Option<String>,
+    modifiers: u16,
+}
+
+/// The highlighted output for one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    lines: Vec<Vec<CachedSpan>>,
+    /// Hunk header offsets relative to the first line of this file.
+    hunk_offsets: Vec<usize>,
+    /// Monotonic use counter for LRU eviction.
+    last_used: u64,
+}
+
+/// On-disk format: all entries plus the use counter.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    clock: u64,
+    entries: HashMap<String, CachedFile>,
+}
+
+/// LRU cache of highlighted files, persisted as JSON.
+pub struct HighlightCache {
+    path: PathBuf,
+    max_entries: usize,
+    data: CacheFile,
+    dirty: bool,
+}
+
+impl HighlightCache {
+    /// Loads the cache from `path`, starting empty if it is missing or unreadable.
+    pub fn load(path: PathBuf, max_entries: usize) -> Self {
+        let data = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self { path, max_entries, data, dirty: false }
+    }
+
+    /// Returns the cached lines and relative hunk offsets for `key`, if present.
+    pub fn get(&mut self, key: &str) -> Option<(Vec<Line<'static>>, Vec<usize>)> {
+        self.data.clock += 1;
+        let clock = self.data.clock;
+        let entry = self.data.entries.get_mut(key)?;
+        entry.last_used = clock;
+        let lines = entry.lines.iter().map(|spans| decode_line(spans)).collect();
+        Some((lines, entry.hunk_offsets.clone()))
+    }
+
+    /// Stores the highlighted output for `key`, evicting the least-recently-used
+    /// entries beyond `max_entries`.
+    pub fn insert(&mut self, key: String, lines: &[Line<'static>], hunk_offsets: &[usize]) {
+        self.data.clock += 1;
+        let entry = CachedFile {
+            lines: lines.iter().map(encode_line).collect(),
+            hunk_offsets: hunk_offsets.to_vec(),
+            last_used: self.data.clock,
+        };
+        self.data.entries.insert(key, entry);
+        while self.data.entries.len() > self.max_entries {
+            let Some(oldest) = self
+                .data
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.data.entries.remove(&oldest);
+        }
+        self.dirty = true;
+    }
+
+    /// Writes the cache back to disk if entries were added since the last save.
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        if let Ok(bytes) = serde_json::to_vec(&self.data) {
+            let _ = std::fs::write(&self.path, bytes);
+        }
+        self.dirty = false;
+    }
+}
+
+/// Builds the cache key for one file of a diff.
+///
+/// Uses the delta's `(old, new)` blob ids when both are known; otherwise hashes
+/// the file's hunk text. `style` identifies how the lines were highlighted (the
+/// syntect theme name and word-diff granularity) and the diff options that
+/// shape the hunks.
+pub fn cache_key(
+    file: &FileSummary,
+    blobs: (Oid, Oid),
+    hunks: &[OwnedDiffHunk],
+    style: &str,
+) -> String {
+    let (old, new) = blobs;
+    let content = if old.is_zero() || new.is_zero() {
+        let mut text = String::new();
+        for hunk in hunks {
+            text.push_str(&hunk.header);
+            for line in &hunk.lines {
+                text.push(line.origin);
+                text.push_str(&line.content);
+            }
+        }
+        Oid::hash_object(ObjectType::Blob, text.as_bytes())
+            .map(|oid| format!("text:{oid}"))
+            .unwrap_or_default()
+    } else {
+        format!("{old}..{new}")
+    };
+    format!("{}\u{0}{}\u{0}{}", file.path, content, style)
+}
+
+/// Converts a rendered line into its serializable form.
+fn encode_line(line: &Line<'static>) -> Vec<CachedSpan> {
+    line.spans
+        .iter()
+        .map(|span| CachedSpan {
+            text: span.content.to_string(),
+            fg: span.style.fg.map(|c| c.to_string()),
+            bg: span.style.bg.map(|c| c.to_string()),
+            modifiers: span.style.add_modifier.bits(),
+        })
+        .collect()
+}
+
+/// Rebuilds a rendered line from its serialized form.
+fn decode_line(spans: &[CachedSpan]) -> Line<'static> {
+    let parse = |c: &Option<String>| c.as_deref().and_then(|s| Color::from_str(s).ok());
+    Line::from(
+        spans
+            .iter()
+            .map(|s| {
+                let mut style =
+                    Style::default().add_modifier(Modifier::from_bits_truncate(s.modifiers));
+                if let Some(fg) = parse(&s.fg) {
+                    style = style.fg(fg);
+                }
+                if let Some(bg) = parse(&s.bg) {
+                    style = style.bg(bg);
+                }
+                Span::styled(s.text.clone(), style)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("airev-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trips_styles_through_disk() {
+        let path = temp_path("hl-roundtrip");
+        let line = Line::from(vec![
+            Span::styled("+ ", Style::default().fg(Color::Green)),
+            Span::styled(
+                "fn main()",
+                Style::default().fg(Color::Rgb(1, 2, 3)).add_modifier(Modifier::BOLD),
+            ),
+        ]);
+
+        let mut cache = HighlightCache::load(path.clone(), 10);
+        cache.insert("k".to_owned(), std::slice::from_ref(&line), &[0]);
+        cache.save();
+
+        let mut reloaded = HighlightCache::load(path.clone(), 10);
+        let (lines, offsets) = reloaded.get("k").expect("entry should persist");
+        assert_eq!(lines, vec![line]);
+        assert_eq!(offsets, vec![0]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entry() {
+        let mut cache = HighlightCache::load(temp_path("hl-lru"), 2);
+        cache.insert("a".to_owned(), &[], &[]);
+        cache.insert("b".to_owned(), &[], &[]);
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_owned(), &[], &[]);
+
+        assert!(cache.get("b").is_none(), "b was least recently used");
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+    }
+}
@@ -0,0 +1,177 @@
+//! Diffs two loose files without git, for `airev --files OLD NEW`.
+//!
+//! `similar` produces the hunks directly as `OwnedDiffHunk`s, which then go
+//! through the same highlighting and line mapping as a git diff, so the panels
+//! cannot tell the difference. The file list holds a single synthetic entry
+//! named after the new file.
+
+use std::path::Path;
+use std::time::Instant;
+
+use airev_core::highlight::{highlight_unified_diff, syntax_name, WordDiff};
+use similar::{ChangeTag, TextDiff};
+
+use crate::git::types::{
+    DiffMode, DiffStatus, DiffTiming, FileSummary, GitResultPayload, OwnedDiffHunk,
+    OwnedDiffLine,
+};
+use crate::git::moved::mark_moved;
+use crate::git::worker::{file_ext, hunks_to_unified, line_refs_for_hunks};
+
+/// Lines of unchanged context around each change, as in `git diff`.
+const CONTEXT_LINES: usize = 3;
+
+/// Reads both files and builds a `DiffMode::Files` payload comparing them.
+///
+/// # Errors
+///
+/// Returns the I/O error if either file cannot be read as UTF-8 text.
+pub fn diff_file_pair(
+    old_path: &Path,
+    new_path: &Path,
+    word_diff: WordDiff,
+    syntax_theme: &str,
+    color_moved: bool,
+) -> std::io::Result<GitResultPayload> {
+    let old_text = std::fs::read_to_string(old_path)?;
+    let new_text = std::fs::read_to_string(new_path)?;
+    let path = new_path.to_string_lossy();
+    Ok(build_payload(&path, &old_text, &new_text, word_diff, syntax_theme, color_moved))
+}
+
+/// Builds the payload for one synthetic file entry named `path`.
+///
+/// With `color_moved`, blocks moved within the file are marked (see
+/// [`crate::git::moved`]).
+fn build_payload(
+    path: &str,
+    old_text: &str,
+    new_text: &str,
+    word_diff: WordDiff,
+    syntax_theme: &str,
+    color_moved: bool,
+) -> GitResultPayload {
+    let diff_start = Instant::now();
+    let hunks = text_hunks(old_text, new_text);
+    let (added, removed) = hunks.iter().flat_map(|h| &h.lines).fold((0, 0), |(a, r), dl| {
+        match dl.origin {
+            '+' => (a + 1, r),
+            '-' => (a, r + 1),
+            _ => (a, r),
+        }
+    });
+    let diff_ms = diff_start.elapsed().as_millis() as u64;
+    let highlight_start = Instant::now();
+    let (mut highlighted_lines, hunk_offsets) = highlight_unified_diff(
+        &hunks_to_unified(&hunks),
+        file_ext(path),
+        word_diff,
+        syntax_theme,
+    );
+    if color_moved {
+        mark_moved(&mut highlighted_lines, &hunks);
+    }
+    let line_refs = line_refs_for_hunks(0, 0, &hunks);
+    let highlight_ms = highlight_start.elapsed().as_millis() as u64;
+
+    GitResultPayload {
+        generation: 0,
+        mode: DiffMode::Files,
+        status: DiffStatus::Loaded,
+        hunks,
+        files: vec![FileSummary {
+            path: path.to_owned(),
+            status: 'M',
+            added,
+            removed,
+            generated: false,
+            binary: false,
+            language: syntax_name(file_ext(path)),
+        }],
+        highlighted_lines,
+        hunk_offsets,
+        file_line_offsets: vec![0],
+        line_refs,
+        timing: DiffTiming { diff_ms, highlight_ms },
+        base: None,
+    }
+}
+
+/// Converts a line diff of the two texts into owned hunks.
+///
+/// Line numbers are 1-based like git2's. A change on a final line without a
+/// trailing newline is followed by an end-of-file marker line using git2's
+/// origins (`'='` context, `'<'` removed, `'>'` added).
+fn text_hunks(old_text: &str, new_text: &str) -> Vec<OwnedDiffHunk> {
+    let diff = TextDiff::from_lines(old_text, new_text);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(CONTEXT_LINES);
+
+    unified
+        .iter_hunks()
+        .map(|hunk| {
+            let (old_start, new_start) = hunk
+                .ops()
+                .first()
+                .map(|op| (op.old_range().start as u32 + 1, op.new_range().start as u32 + 1))
+                .unwrap_or((0, 0));
+            let mut lines = Vec::new();
+            for change in hunk.iter_changes() {
+                let (origin, eof_origin) = match change.tag() {
+                    ChangeTag::Equal => (' ', '='),
+                    ChangeTag::Delete => ('-', '<'),
+                    ChangeTag::Insert => ('+', '>'),
+                };
+                let old_lineno = change.old_index().map(|i| i as u32 + 1);
+                let new_lineno = change.new_index().map(|i| i as u32 + 1);
+                lines.push(OwnedDiffLine {
+                    origin,
+                    content: change.value().to_owned(),
+                    old_lineno,
+                    new_lineno,
+                });
+                if change.missing_newline() {
+                    lines.push(OwnedDiffLine {
+                        origin: eof_origin,
+                        content: "\n\\ No newline at end of file\n".to_owned(),
+                        old_lineno: None,
+                        new_lineno: None,
+                    });
+                }
+            }
+            OwnedDiffHunk { header: format!("{}\n", hunk.header()), old_start, new_start, lines }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use airev_core::highlight::SYNTECT_THEME;
+
+    #[test]
+    fn file_pair_diff_becomes_one_synthetic_file() {
+        let (old, new) = ("a\nb\nc\n", "a\nB\nc\nd");
+        let payload = build_payload("notes.txt", old, new, WordDiff::Word, SYNTECT_THEME, false);
+
+        assert_eq!(payload.files.len(), 1);
+        assert_eq!(payload.files[0].path, "notes.txt");
+        assert_eq!((payload.files[0].added, payload.files[0].removed), (2, 1));
+        assert_eq!(payload.hunks.len(), 1);
+        assert_eq!(payload.hunks[0].header, "@@ -1,3 +1,4 @@\n");
+        assert_eq!(payload.line_refs.len(), payload.highlighted_lines.len());
+
+        let origins: Vec<char> = payload.hunks[0].lines.iter().map(|l| l.origin).collect();
+        assert_eq!(origins, vec![' ', '-', '+', ' ', '+', '>']);
+        let added = &payload.hunks[0].lines[4];
+        assert_eq!((added.old_lineno, added.new_lineno), (None, Some(4)));
+    }
+
+    #[test]
+    fn identical_files_have_no_hunks() {
+        let same = "fn main() {}\n";
+        let payload = build_payload("same.rs", same, same, WordDiff::Word, SYNTECT_THEME, false);
+        assert!(payload.hunks.is_empty());
+        assert!(payload.highlighted_lines.is_empty());
+    }
+}
@@ -2,15 +2,67 @@
 //!
 //! The background thread owns `git2::Repository` for its lifetime. All requests
 //! are sent via a `crossbeam_channel` sender; results arrive as `AppEvent::GitResult`.
+//! If the thread panics, the panic is caught and reported as
+//! `AppEvent::GitWorkerStopped` so the UI can offer a restart instead of hanging.
 
+pub mod blame;
+pub mod highlight_cache;
+pub mod loose;
+pub mod moved;
+pub mod notes;
+pub mod payload_cache;
+pub mod remote;
+pub mod report;
 pub mod types;
 pub mod worker;
 
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
 use crossbeam_channel::{unbounded, Sender};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::event::AppEvent;
-use crate::git::types::GitRequest;
+use crate::git::types::{GitRequest, WorkerOptions};
+
+/// Name given to the git worker thread.
+///
+/// The panic hook in `tui.rs` checks for it so a worker panic does not tear down
+/// the terminal while the UI keeps running.
+pub const WORKER_THREAD_NAME: &str = "airev-git";
+
+/// Finds the repository containing the current directory.
+///
+/// Walks parent directories from cwd and returns the working directory (or the
+/// `.git` directory for a bare repository).
+///
+/// # Errors
+///
+/// Returns git2's error when no repository contains cwd or it cannot be opened.
+pub fn discover_repo_path() -> Result<String, git2::Error> {
+    let repo = git2::Repository::discover(".")?;
+    let path = repo.workdir().unwrap_or_else(|| repo.path());
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Creates an empty repository in a fresh temporary directory, for tests.
+///
+/// The directory is deleted when the returned `TempDir` drops, including when
+/// the test panics before reaching its end.
+#[cfg(test)]
+pub(crate) fn init_test_repo() -> (tempfile::TempDir, git2::Repository) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    (dir, repo)
+}
+
+/// Returns the full SHA of `HEAD` in the repository at `path`, or `None` when it
+/// cannot be resolved (e.g. an unborn branch).
+pub fn head_sha(path: &str) -> Option<String> {
+    let repo = git2::Repository::open(path).ok()?;
+    let oid = repo.head().ok()?.peel_to_commit().ok()?.id();
+    Some(oid.to_string())
+}
 
 /// Facade for the git background thread.
 ///
@@ -19,25 +71,93 @@ use crate::git::types::GitRequest;
 pub struct AsyncGit {
     /// Send work requests to the background thread via this sender.
     pub request_tx: Sender<GitRequest>,
+    /// Spawn generation of the current worker; incremented by `respawn`.
+    ///
+    /// Results and stop notices carry the generation of the worker that sent
+    /// them so the main thread can ignore anything from a replaced worker.
+    pub generation: u64,
+    /// Event channel handed to each spawned worker.
+    event_tx: UnboundedSender<AppEvent>,
+    /// Worker settings reused when respawning.
+    options: WorkerOptions,
 }
 
 impl AsyncGit {
     /// Spawns the background thread and returns the AsyncGit handle.
     ///
     /// The `event_tx` is cloned and captured by the thread; results arrive as
-    /// `AppEvent::GitResult` on the main event channel.
-    pub fn new(event_tx: UnboundedSender<AppEvent>, repo_path: String) -> Self {
-        let (request_tx, request_rx) = unbounded::<GitRequest>();
-        std::thread::spawn(move || {
-            worker::git_worker_loop(repo_path, request_rx, event_tx);
-        });
-        Self { request_tx }
+    /// `AppEvent::GitResult` on the main event channel. `options` carries the
+    /// config-derived worker settings.
+    pub fn new(
+        event_tx: UnboundedSender<AppEvent>,
+        repo_path: String,
+        options: WorkerOptions,
+    ) -> Self {
+        Self::spawn(event_tx, repo_path, options, 0)
+    }
+
+    /// Replaces the worker with a fresh one opened on `repo_path`.
+    ///
+    /// Drops this handle's sender and returns a new handle with the next
+    /// generation. The old worker exits once every clone of its sender is gone
+    /// (callers must replace `AppState::git_tx` too); any result it still sends
+    /// carries the old generation and is ignored. Used both for switching
+    /// repositories and for recovering from a crashed worker.
+    pub fn respawn(self, repo_path: String) -> Self {
+        let Self { request_tx, generation, event_tx, options } = self;
+        drop(request_tx);
+        Self::spawn(event_tx, repo_path, options, generation + 1)
     }
 
-    /// Sends a diff load request to the worker thread.
+    /// Spawns a worker thread for `generation`.
     ///
-    /// Non-blocking. Returns false if the worker thread has exited (channel closed).
-    pub fn load_diff(&self, request: GitRequest) -> bool {
-        self.request_tx.send(request).is_ok()
+    /// A panic inside the worker loop is caught at the thread boundary and sent as
+    /// `AppEvent::GitWorkerStopped` carrying the panic message. The request channel
+    /// closes with the thread, so later sends on `request_tx` fail.
+    fn spawn(
+        event_tx: UnboundedSender<AppEvent>,
+        repo_path: String,
+        options: WorkerOptions,
+        generation: u64,
+    ) -> Self {
+        let (request_tx, request_rx) = unbounded::<GitRequest>();
+        let thread_tx = event_tx.clone();
+        let thread_options = options.clone();
+        // If the OS refuses to spawn the thread, the receiver is dropped with the
+        // closure and the first send reports the worker as stopped.
+        let _ = std::thread::Builder::new()
+            .name(WORKER_THREAD_NAME.to_owned())
+            .spawn(move || {
+                let loop_tx = thread_tx.clone();
+                let result = catch_unwind(AssertUnwindSafe(move || {
+                    worker::git_worker_loop(
+                        repo_path,
+                        request_rx,
+                        loop_tx,
+                        thread_options,
+                        generation,
+                    );
+                }));
+                if let Err(panic) = result {
+                    let _ = thread_tx.send(AppEvent::GitWorkerStopped {
+                        generation,
+                        reason: panic_message(&*panic),
+                    });
+                }
+            });
+        Self { request_tx, generation, event_tx, options }
+    }
+}
+
+/// Extracts a readable message from a caught panic payload.
+///
+/// `panic!` payloads are `&str` or `String`; anything else gets a generic message.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        (*s).to_owned()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "worker thread panicked".to_owned()
     }
 }
@@ -0,0 +1,194 @@
+//! Moved-block detection, an approximation of `git diff --color-moved`.
+//!
+//! Within one file, a removed line and an added line with the same content
+//! (leading and trailing whitespace ignored) are move candidates. A run of
+//! consecutive candidates on the same side of a hunk is drawn as moved — its
+//! `-`/`+` marker turns blue instead of red/green — once it holds at least
+//! [`MIN_MOVED_LINES`] lines with letters or digits in them, so a few stray `}`
+//! or blank lines never count as a move on their own.
+//!
+//! Limits of the heuristic:
+//!
+//! - Lines are matched one by one through a content set, not as whole blocks:
+//!   a run whose lines came from different places, or in a different order,
+//!   still counts as moved.
+//! - Only moves within one file are found; code moved to another file shows as
+//!   removed and added.
+//! - Indentation is ignored, so a block that was only re-indented (e.g. wrapped
+//!   in a new `if`) is shown as moved.
+//! - Generated files are folded to their headers and never checked.
+//!
+//! Enabled by config `color_moved`.
+
+use std::collections::HashSet;
+
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+
+use crate::git::types::{OwnedDiffHunk, OwnedDiffLine};
+
+/// Minimum number of non-trivial lines in a run for it to count as moved.
+pub const MIN_MOVED_LINES: usize = 3;
+
+/// Color of the `-`/`+` marker on a moved line.
+const MOVED_COLOR: Color = Color::Blue;
+
+/// Returns, for each line the highlighter renders for `hunks` (one header per
+/// hunk, then one line per `OwnedDiffLine`), whether it is part of a moved block.
+pub fn moved_flags(hunks: &[OwnedDiffHunk]) -> Vec<bool> {
+    let mut removed = HashSet::new();
+    let mut added = HashSet::new();
+    for line in hunks.iter().flat_map(|h| &h.lines) {
+        match line.origin {
+            '-' => removed.insert(key(line)),
+            '+' => added.insert(key(line)),
+            _ => false,
+        };
+    }
+
+    let mut flags = Vec::new();
+    for hunk in hunks {
+        flags.push(false);
+        let mut run_start = flags.len();
+        let mut run_origin = None;
+        let mut significant = 0;
+        for line in &hunk.lines {
+            let content = key(line);
+            let candidate = match line.origin {
+                '-' => added.contains(content),
+                '+' => removed.contains(content),
+                _ => false,
+            };
+            if !candidate || run_origin != Some(line.origin) {
+                close_run(&mut flags[run_start..], significant);
+                run_start = flags.len();
+                run_origin = candidate.then_some(line.origin);
+                significant = 0;
+            }
+            if candidate && content.chars().any(char::is_alphanumeric) {
+                significant += 1;
+            }
+            flags.push(candidate);
+        }
+        close_run(&mut flags[run_start..], significant);
+    }
+    flags
+}
+
+/// The content two lines are matched by: the text without surrounding whitespace.
+fn key(line: &OwnedDiffLine) -> &str {
+    line.content.trim()
+}
+
+/// Clears a finished run's flags unless it is long enough to be a move.
+fn close_run(run: &mut [bool], significant: usize) {
+    if significant < MIN_MOVED_LINES {
+        run.fill(false);
+    }
+}
+
+/// Recolors the `-`/`+` marker of every moved line in `lines`, the highlighter's
+/// output for `hunks`.
+pub fn mark_moved(lines: &mut [Line<'static>], hunks: &[OwnedDiffHunk]) {
+    for (line, moved) in lines.iter_mut().zip(moved_flags(hunks)) {
+        if let (true, Some(marker)) = (moved, line.spans.first_mut()) {
+            marker.style = Style::default().fg(MOVED_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::loose::diff_file_pair;
+    use airev_core::highlight::{WordDiff, SYNTECT_THEME};
+
+    /// `helper` moves below `parse`, which also gains a line.
+    const OLD: &str = "\
+use std::fmt;
+
+fn helper(n: u8) -> u8 {
+    let doubled = n * 2;
+    doubled + 1
+}
+
+fn parse(input: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for word in input.split(' ') {
+        out.push(word.to_owned());
+    }
+    out
+}
+";
+    const NEW: &str = "\
+use std::fmt;
+
+fn parse(input: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for word in input.split(' ') {
+        out.push(word.to_owned());
+    }
+    out.sort();
+    out
+}
+
+fn helper(n: u8) -> u8 {
+    let doubled = n * 2;
+    doubled + 1
+}
+";
+
+    /// Writes `old` and `new` to a temp dir and diffs them as `airev --files` does.
+    fn diff_fixture(name: &str, old: &str, new: &str) -> crate::git::types::GitResultPayload {
+        let dir =
+            std::env::temp_dir().join(format!("airev-moved-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (old_path, new_path) = (dir.join("old.rs"), dir.join("new.rs"));
+        std::fs::write(&old_path, old).unwrap();
+        std::fs::write(&new_path, new).unwrap();
+        let payload =
+            diff_file_pair(&old_path, &new_path, WordDiff::Off, SYNTECT_THEME, true).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        payload
+    }
+
+    #[test]
+    fn moved_function_is_flagged_and_edits_are_not() {
+        let payload = diff_fixture("fn", OLD, NEW);
+        let flags = moved_flags(&payload.hunks);
+        let rendered: Vec<Option<&OwnedDiffLine>> = payload
+            .hunks
+            .iter()
+            .flat_map(|h| std::iter::once(None).chain(h.lines.iter().map(Some)))
+            .collect();
+        assert_eq!(flags.len(), rendered.len());
+        assert_eq!(flags.len(), payload.line_refs.len());
+
+        let flagged = |origin: char, text: &str| {
+            rendered.iter().zip(&flags).any(|(line, &moved)| {
+                moved && line.is_some_and(|l| l.origin == origin && l.content.trim() == text)
+            })
+        };
+        for text in ["fn helper(n: u8) -> u8 {", "let doubled = n * 2;", "doubled + 1", "}"] {
+            assert!(flagged('-', text), "{text}");
+            assert!(flagged('+', text), "{text}");
+        }
+        assert!(!flagged('+', "out.sort();"));
+
+        let marker = |i: usize| payload.highlighted_lines[i].spans[0].style.fg;
+        for (i, &moved) in flags.iter().enumerate() {
+            match payload.line_refs[i].origin {
+                '-' | '+' if moved => assert_eq!(marker(i), Some(MOVED_COLOR)),
+                '-' => assert_eq!(marker(i), Some(Color::Red)),
+                '+' => assert_eq!(marker(i), Some(Color::Green)),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn short_matching_runs_are_not_moves() {
+        let payload = diff_fixture("short", "a\n}\n\nb\n", "b\n}\n\na\n");
+        assert!(moved_flags(&payload.hunks).iter().all(|&f| !f));
+    }
+}
@@ -0,0 +1,64 @@
+//! Saving the review summary as a git note on `HEAD` (`:note`).
+//!
+//! Notes live under their own ref (config `note_ref`, `refs/notes/airev` by
+//! default), so they stay out of `git log` unless asked for and can be shared
+//! with `git push origin refs/notes/airev`. Nothing is written except on the
+//! explicit command.
+
+use git2::{Oid, Repository, Signature};
+
+/// Writes `text` as the note on `HEAD` under `notes_ref` and returns `HEAD`'s id.
+///
+/// An existing note is appended to, after a blank line, unless `replace` is set.
+/// The note is signed with the repository's configured user, or `airev` when
+/// there is none.
+///
+/// # Errors
+///
+/// Returns git's error when `HEAD` does not name a commit, `notes_ref` is not a
+/// valid reference name, or the note cannot be written.
+pub fn write_note(
+    repo: &Repository,
+    notes_ref: &str,
+    text: &str,
+    replace: bool,
+) -> Result<Oid, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let existing = match repo.find_note(Some(notes_ref), head) {
+        Ok(note) if !replace => note.message().map(str::to_owned),
+        _ => None,
+    };
+    let message = match existing {
+        Some(existing) => format!("{}\n\n{text}", existing.trim_end()),
+        None => text.to_owned(),
+    };
+    let signature = repo.signature().or_else(|_| Signature::now("airev", "airev@localhost"))?;
+    repo.note(&signature, &signature, Some(notes_ref), head, &message, true)?;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::init_test_repo;
+
+    #[test]
+    fn note_is_readable_back_and_appended_or_replaced() {
+        let (_dir, repo) = init_test_repo();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let sig = Signature::now("airev", "airev@example.com").unwrap();
+        let head = repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
+        let read = || {
+            let note = repo.find_note(Some("refs/notes/airev"), head).unwrap();
+            note.message().unwrap().to_owned()
+        };
+
+        assert_eq!(write_note(&repo, "refs/notes/airev", "first\n", false).unwrap(), head);
+        assert_eq!(read(), "first\n");
+        write_note(&repo, "refs/notes/airev", "second\n", false).unwrap();
+        assert_eq!(read(), "first\n\nsecond\n");
+        write_note(&repo, "refs/notes/airev", "third\n", true).unwrap();
+        assert_eq!(read(), "third\n");
+        assert!(repo.find_note(None, head).is_err(), "the default notes ref is untouched");
+    }
+}
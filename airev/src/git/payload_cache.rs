@@ -0,0 +1,101 @@
+//! In-memory cache of whole diff results, so flipping back to a mode whose diff
+//! has not changed skips highlighting entirely.
+//!
+//! Entries are keyed by a hash of the request (mode and range refs), the
+//! `linguist-generated` flags, and the diff's patch text. Hashing the patch is a
+//! single pass over git's output, far cheaper than running syntect over it, and any
+//! edit to the diffed content changes the key. The cache lives in the git worker
+//! thread and is dropped with it; it is never written to disk (see
+//! `highlight_cache` for the persistent per-file cache).
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use git2::{Diff, DiffFormat};
+
+use crate::git::types::{DiffMode, GitResultPayload};
+
+/// Diff results kept; Tab cycles through at most this many modes.
+const MAX_ENTRIES: usize = 6;
+
+/// Most-recently-used-first list of computed payloads.
+#[derive(Default)]
+pub struct PayloadCache {
+    entries: VecDeque<(u64, GitResultPayload)>,
+}
+
+impl PayloadCache {
+    /// Returns a copy of the payload stored under `key`, marking it most recently used.
+    pub fn get(&mut self, key: u64) -> Option<GitResultPayload> {
+        let index = self.entries.iter().position(|(k, _)| *k == key)?;
+        let entry = self.entries.remove(index)?;
+        let payload = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(payload)
+    }
+
+    /// Stores `payload` under `key`, evicting the least recently used entry beyond
+    /// [`MAX_ENTRIES`].
+    pub fn insert(&mut self, key: u64, payload: &GitResultPayload) {
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.push_front((key, payload.clone()));
+        self.entries.truncate(MAX_ENTRIES);
+    }
+}
+
+/// Builds the cache key for `diff`, computed for `mode` (and `range`, for a
+/// commit range) with `generated` as its `linguist-generated` flags.
+///
+/// Returns `None` if git fails to print the patch; the result is then not cached.
+pub fn payload_key(
+    mode: DiffMode,
+    range: Option<(&str, &str)>,
+    generated: &[bool],
+    diff: &Diff<'_>,
+) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    (mode, range, generated).hash(&mut hasher);
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        line.origin().hash(&mut hasher);
+        line.content().hash(&mut hasher);
+        true
+    })
+    .ok()?;
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::types::{DiffStatus, DiffTiming};
+
+    fn payload(base: &str) -> GitResultPayload {
+        GitResultPayload {
+            generation: 0,
+            mode: DiffMode::Unstaged,
+            status: DiffStatus::Loaded,
+            hunks: Vec::new(),
+            files: Vec::new(),
+            highlighted_lines: Vec::new(),
+            hunk_offsets: Vec::new(),
+            file_line_offsets: Vec::new(),
+            line_refs: Vec::new(),
+            timing: DiffTiming::default(),
+            base: Some(base.to_owned()),
+        }
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_payload() {
+        let mut cache = PayloadCache::default();
+        for key in 0..MAX_ENTRIES as u64 {
+            cache.insert(key, &payload(&key.to_string()));
+        }
+        assert_eq!(cache.get(0).and_then(|p| p.base).as_deref(), Some("0"));
+        cache.insert(99, &payload("99"));
+        assert!(cache.get(1).is_none(), "1 was least recently used");
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(99).is_some());
+    }
+}
@@ -0,0 +1,171 @@
+//! Machine-readable diff summary for `airev json`.
+//!
+//! Serializes the same file and hunk metadata the TUI shows, without any
+//! highlighting, so CI scripts can ask questions like "does this change touch
+//! file X?". The serde field names below are the JSON schema:
+//!
+//! ```json
+//! {
+//!   "mode": "unstaged",
+//!   "files": [
+//!     {
+//!       "path": "src/main.rs",
+//!       "status": "M",
+//!       "added": 3,
+//!       "removed": 1,
+//!       "hunks": [
+//!         { "header": "@@ -10,4 +10,6 @@ fn main() {", "old_start": 10, "new_start": 10 }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `mode` is one of `unstaged`, `staged`, `branch`, or `range`. `status` is the
+//! same character shown in the file list: `M`, `A`, `D`, or `R`.
+
+use serde::Serialize;
+
+use crate::git::types::{DiffMode, FileSummary, OwnedDiffHunk};
+
+/// Top-level JSON document printed by `airev json`.
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    /// Diff mode that produced the report.
+    pub mode: &'static str,
+    /// Changed files in diff order.
+    pub files: Vec<FileReport>,
+}
+
+/// One changed file and its hunks.
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    /// Repository-relative path.
+    pub path: String,
+    /// Status character: `M`, `A`, `D`, or `R`.
+    pub status: char,
+    /// Number of added lines.
+    pub added: usize,
+    /// Number of removed lines.
+    pub removed: usize,
+    /// Hunks belonging to this file, in order.
+    pub hunks: Vec<HunkReport>,
+}
+
+/// Header metadata for one `@@` hunk.
+#[derive(Debug, Serialize)]
+pub struct HunkReport {
+    /// The `@@ ... @@` header line without its trailing newline.
+    pub header: String,
+    /// Starting line number in the old file.
+    pub old_start: u32,
+    /// Starting line number in the new file.
+    pub new_start: u32,
+}
+
+impl DiffReport {
+    /// Builds a report from the worker's extracted files and hunks.
+    ///
+    /// `file_hunk_starts[i]` is the index into `hunks` of file `i`'s first hunk,
+    /// as returned by the worker's hunk extraction.
+    pub fn new(
+        mode: DiffMode,
+        files: &[FileSummary],
+        hunks: &[OwnedDiffHunk],
+        file_hunk_starts: &[usize],
+    ) -> Self {
+        let files = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                let start = file_hunk_starts.get(i).copied().unwrap_or(hunks.len());
+                let end = file_hunk_starts.get(i + 1).copied().unwrap_or(hunks.len());
+                FileReport {
+                    path: file.path.clone(),
+                    status: file.status,
+                    added: file.added,
+                    removed: file.removed,
+                    hunks: hunks[start..end]
+                        .iter()
+                        .map(|h| HunkReport {
+                            header: h.header.trim_end_matches('\n').to_owned(),
+                            old_start: h.old_start,
+                            new_start: h.new_start,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        Self { mode: mode_name(mode), files }
+    }
+}
+
+/// JSON name for a diff mode.
+fn mode_name(mode: DiffMode) -> &'static str {
+    match mode {
+        DiffMode::Unstaged => "unstaged",
+        DiffMode::Staged => "staged",
+        DiffMode::BranchComparison => "branch",
+        DiffMode::CommitRange => "range",
+        DiffMode::Stash => "stash",
+        DiffMode::Files => "files",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, status: char, added: usize, removed: usize) -> FileSummary {
+        FileSummary {
+            path: path.to_owned(),
+            status,
+            added,
+            removed,
+            generated: false,
+            binary: false,
+            language: "plain",
+        }
+    }
+
+    fn hunk(header: &str, old_start: u32, new_start: u32) -> OwnedDiffHunk {
+        OwnedDiffHunk { header: header.to_owned(), old_start, new_start, lines: Vec::new() }
+    }
+
+    #[test]
+    fn report_groups_hunks_by_file_with_documented_field_names() {
+        let files = vec![
+            file("a.rs", 'M', 2, 1),
+            file("b.rs", 'A', 4, 0),
+        ];
+        let hunks = vec![
+            hunk("@@ -1,2 +1,3 @@\n", 1, 1),
+            hunk("@@ -20 +21 @@ fn x\n", 20, 21),
+            hunk("@@ -0,0 +1,4 @@\n", 0, 1),
+        ];
+        let report = DiffReport::new(DiffMode::Staged, &files, &hunks, &[0, 2]);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "mode": "staged",
+                "files": [
+                    {
+                        "path": "a.rs", "status": "M", "added": 2, "removed": 1,
+                        "hunks": [
+                            { "header": "@@ -1,2 +1,3 @@", "old_start": 1, "new_start": 1 },
+                            { "header": "@@ -20 +21 @@ fn x", "old_start": 20, "new_start": 21 }
+                        ]
+                    },
+                    {
+                        "path": "b.rs", "status": "A", "added": 4, "removed": 0,
+                        "hunks": [
+                            { "header": "@@ -0,0 +1,4 @@", "old_start": 0, "new_start": 1 }
+                        ]
+                    }
+                ]
+            })
+        );
+    }
+}
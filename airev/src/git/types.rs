@@ -40,6 +40,48 @@ pub struct OwnedDiffHunk {
     pub lines: Vec<OwnedDiffLine>,
 }
 
+/// Returns a stable identity for `hunk` that does not depend on its position in
+/// the diff.
+///
+/// Hashes the header together with `old_start`/`new_start`, so per-hunk state
+/// (scroll position, folds) follows the hunk when files are added, removed, or
+/// reordered between refreshes. Uses git's blob hash, which is stable across
+/// runs and Rust versions, so keys can be persisted.
+pub fn hunk_key(hunk: &OwnedDiffHunk) -> String {
+    let text = format!("{} {} {}", hunk.old_start, hunk.new_start, hunk.header.trim_end());
+    git2::Oid::hash_object(git2::ObjectType::Blob, text.as_bytes())
+        .map(|oid| oid.to_string())
+        .unwrap_or(text)
+}
+
+/// Source position of one rendered diff line.
+///
+/// `GitResultPayload::line_refs[i]` describes `highlighted_lines[i]`, linking the
+/// rendered line back to the file, hunk, and line numbers it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRef {
+    /// Index into `GitResultPayload::files` of the file this line belongs to.
+    pub file_index: usize,
+    /// Index into `GitResultPayload::hunks` (and `hunk_offsets`) of the hunk.
+    pub hunk_index: usize,
+    /// Origin character as in `OwnedDiffLine::origin`; `'H'` marks the hunk
+    /// header line, which has no line numbers.
+    pub origin: char,
+    /// Line number in the old (pre-patch) file, if applicable.
+    pub old_lineno: Option<u32>,
+    /// Line number in the new (post-patch) file, if applicable.
+    pub new_lineno: Option<u32>,
+}
+
+/// The commit that last changed an old-side line, for the blame column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameTag {
+    /// The commit's SHA, shortened to 7 characters.
+    pub sha: String,
+    /// The commit author's initials (see `git::blame::initials`).
+    pub initials: String,
+}
+
 /// Per-file statistics for the file-list panel.
 ///
 /// Aggregates the status character and line-count deltas for a single
@@ -54,13 +96,23 @@ pub struct FileSummary {
     pub added: usize,
     /// Number of lines removed from this file.
     pub removed: usize,
+    /// Marked `linguist-generated` in `.gitattributes`: listed dimmed, its hunks
+    /// folded when it first appears (see `AppState::folded_hunks`), and
+    /// auto-marked reviewed.
+    pub generated: bool,
+    /// git found binary content on either side: listed with a `[bin]` tag and shown
+    /// in the diff as a single placeholder hunk instead of its content.
+    pub binary: bool,
+    /// Name of the syntax the file is highlighted with (see
+    /// `airev_core::highlight::syntax_name`), shown in the diff panel title.
+    pub language: &'static str,
 }
 
-/// The four diff modes supported by airev.
+/// The diff modes supported by airev.
 ///
 /// Controls which git comparison the background thread performs when
 /// loading diff data. The default is `Unstaged` (working directory vs index).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum DiffMode {
     /// Working directory vs index (`git diff`).
     #[default]
@@ -69,8 +121,99 @@ pub enum DiffMode {
     Staged,
     /// Arbitrary commit range (`git diff A..B`).
     CommitRange,
-    /// Branch comparison (`git diff main..HEAD`).
+    /// Branch comparison (`git diff BASE..HEAD`, config `base_branch`).
     BranchComparison,
+    /// Most recent stash entry against the commit it was made on
+    /// (`git stash show -p`). Empty when nothing is stashed.
+    Stash,
+    /// Two loose files given with `--files`, diffed without git.
+    Files,
+}
+
+impl DiffMode {
+    /// The name used for this mode in the config file and the database
+    /// (`unstaged`, `staged`, `branch`), or `None` for modes that cannot be
+    /// requested on their own at startup.
+    pub fn config_name(self) -> Option<&'static str> {
+        match self {
+            DiffMode::Unstaged => Some("unstaged"),
+            DiffMode::Staged => Some("staged"),
+            DiffMode::BranchComparison => Some("branch"),
+            DiffMode::CommitRange | DiffMode::Stash | DiffMode::Files => None,
+        }
+    }
+
+    /// Parses a name produced by [`DiffMode::config_name`].
+    pub fn from_config_name(name: &str) -> Option<Self> {
+        match name {
+            "unstaged" => Some(DiffMode::Unstaged),
+            "staged" => Some(DiffMode::Staged),
+            "branch" => Some(DiffMode::BranchComparison),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of the most recent diff request, used to pick the empty-panel message.
+///
+/// An empty `Loaded` diff means the mode legitimately has no changes (e.g. a
+/// clean working tree); `NoRepo` and `Error` mean no diff could be computed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DiffStatus {
+    /// No result has been received yet.
+    #[default]
+    Pending,
+    /// The diff was computed successfully (it may contain zero files).
+    Loaded,
+    /// No git repository was found at startup.
+    NoRepo,
+    /// git2 failed to compute the diff; carries the error message.
+    Error(String),
+}
+
+/// Wall-clock time the worker spent producing one `GitResultPayload`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffTiming {
+    /// Milliseconds spent computing the diff and extracting hunks and files.
+    pub diff_ms: u64,
+    /// Milliseconds spent syntax-highlighting (including highlight cache lookups).
+    pub highlight_ms: u64,
+}
+
+/// Most context lines a diff can be shown with (config `context_lines`, `+`).
+pub const MAX_CONTEXT_LINES: u32 = 15;
+
+/// Settings handed to the git worker thread when it is spawned.
+///
+/// The worker has no access to `AppState` or the config, so anything it needs
+/// from the user's configuration travels in this struct.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerOptions {
+    /// On-disk highlight cache file. `None` disables the cache.
+    pub highlight_cache_path: Option<std::path::PathBuf>,
+    /// Maximum number of per-file entries kept in the highlight cache.
+    pub highlight_cache_max_entries: usize,
+    /// Inline emphasis granularity for paired removed/added lines.
+    pub word_diff: airev_core::highlight::WordDiff,
+    /// Bundled syntect theme that colors code in the diff (config
+    /// `syntax_theme`). Unknown or empty names use the default theme.
+    pub syntax_theme: String,
+    /// Read `linguist-generated` from `.gitattributes` and fold generated files.
+    pub detect_generated: bool,
+    /// Mark blocks moved within a file (see `git::moved`).
+    pub color_moved: bool,
+    /// Highlight each file's full old and new text, so syntax state from outside
+    /// a hunk carries into it (config `full_file_highlight`).
+    pub full_file_highlight: bool,
+    /// Ignore whitespace changes when diffing (`w`). Starts off; the app changes it
+    /// with `GitRequest::SetIgnoreWhitespace`.
+    pub ignore_whitespace: bool,
+    /// Unchanged lines shown around each change (config `context_lines`). The app
+    /// changes it with `GitRequest::SetContextLines` (`-` / `+`).
+    pub context_lines: u32,
+    /// Ref the branch comparison is taken against (config `base_branch`), before
+    /// the `main` / `master` fallbacks.
+    pub base_branch: String,
 }
 
 /// Commands sent from the main thread to the git background worker thread.
@@ -88,6 +231,45 @@ pub enum GitRequest {
         /// The ending ref (newer commit or branch tip).
         to: String,
     },
+    /// Check that both refs name commits, without loading a diff. Answered
+    /// with `AppEvent::RangeResolved` instead of a `GitResult`.
+    ResolveRange {
+        /// The starting ref.
+        from: String,
+        /// The ending ref.
+        to: String,
+    },
+    /// List the repository's branch and tag names for completion at the picker
+    /// prompt. Answered with `AppEvent::RefsListed` instead of a `GitResult`.
+    ListRefs,
+    /// Blame lines `first..=last` of `path` as of commit `rev`. Answered with
+    /// `AppEvent::BlameLoaded` instead of a `GitResult`.
+    Blame {
+        /// The old side's commit.
+        rev: String,
+        /// Repository-relative path of the file.
+        path: String,
+        /// First old-side line to blame, 1-based.
+        first: u32,
+        /// Last old-side line to blame, inclusive.
+        last: u32,
+    },
+    /// Write `text` as the git note on `HEAD` under `notes_ref` (`:note`).
+    /// Answered with `AppEvent::NoteWritten` instead of a `GitResult`.
+    WriteNote {
+        /// Notes reference, e.g. `refs/notes/airev`.
+        notes_ref: String,
+        /// The note's text.
+        text: String,
+        /// Replace an existing note instead of appending to it.
+        replace: bool,
+    },
+    /// Ignore (or stop ignoring) whitespace in the diffs that follow (`w`).
+    /// Nothing is sent back; the app re-requests its diff afterwards.
+    SetIgnoreWhitespace(bool),
+    /// Show this many context lines in the diffs that follow (`-` / `+`).
+    /// Nothing is sent back; the app re-requests its diff afterwards.
+    SetContextLines(u32),
 }
 
 /// Result payload sent from the git background thread back to the main thread.
@@ -98,11 +280,18 @@ pub enum GitRequest {
 ///
 /// `highlighted_lines` uses `'static` lifetime so lines can be stored directly
 /// in `AppState` without arena allocation or re-rendering on each frame.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct GitResultPayload {
+    /// Generation of the worker that produced this payload (see `AsyncGit::respawn`).
+    ///
+    /// Stamped by `git_worker_loop`; the main thread drops payloads whose
+    /// generation no longer matches the live worker.
+    pub generation: u64,
     /// The diff mode that was requested.
     pub mode: DiffMode,
+    /// Whether the diff was computed (`Loaded`) or failed (`Error`).
+    pub status: DiffStatus,
     /// All diff hunks from the comparison, in file order.
     pub hunks: Vec<OwnedDiffHunk>,
     /// Per-file statistics for the file-list panel.
@@ -122,4 +311,11 @@ pub struct GitResultPayload {
     /// appears in `highlighted_lines`. Used by `jump_to_selected_file()` to scroll
     /// the diff panel to the correct position.
     pub file_line_offsets: Vec<usize>,
+    /// Source position of each line, parallel to `highlighted_lines`.
+    pub line_refs: Vec<LineRef>,
+    /// Time spent on the diff and on highlighting it.
+    pub timing: DiffTiming,
+    /// The ref a `BranchComparison` was taken against, after any fallback from
+    /// `base_branch`; `None` for other modes and failed diffs.
+    pub base: Option<String>,
 }
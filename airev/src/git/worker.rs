@@ -3,145 +3,620 @@
 //! git2::Repository is !Send — it must be opened inside the thread, not passed in.
 //! All communication is via channels: GitRequest in, AppEvent::GitResult out.
 
-use std::sync::LazyLock;
+use std::time::Instant;
 
+use airev_core::highlight::{
+    highlight_unified_diff_with_sources, preload_syntax_assets, syntax_name, FileSources,
+};
 use crossbeam_channel::Receiver;
-use git2::{Delta, Diff, DiffOptions, Repository};
-use ratatui::style::{Color, Modifier, Style};
-use ratatui::text::{Line, Span};
-use similar::{ChangeTag, TextDiff};
-use syntect::easy::HighlightLines;
-use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
+use git2::{AttrCheckFlags, AttrValue, Delta, Diff, DiffOptions, Repository};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::event::AppEvent;
+use crate::git::blame::blame_lines;
+use crate::git::highlight_cache::{cache_key, HighlightCache};
+use crate::git::moved::mark_moved;
+use crate::git::notes::write_note;
+use crate::git::payload_cache::{payload_key, PayloadCache};
+use crate::git::report::DiffReport;
 use crate::git::types::{
-    DiffMode, FileSummary, GitRequest, GitResultPayload, OwnedDiffHunk, OwnedDiffLine,
+    DiffMode, DiffStatus, DiffTiming, FileSummary, GitRequest, GitResultPayload, LineRef,
+    OwnedDiffHunk, OwnedDiffLine, WorkerOptions,
 };
 
-static PS: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
-static TS: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
-
 /// Entry point for the background thread that owns the git Repository.
 ///
 /// Opens the Repository at `path` and loops over incoming `GitRequest` messages
 /// until the channel is closed (sender dropped). Results are sent back via `event_tx`
-/// as `AppEvent::GitResult`.
+/// as `AppEvent::GitResult`, stamped with `generation`. When `options` enables it,
+/// the highlight cache is loaded here and saved after every request; recent
+/// results are always kept in memory (see `git::payload_cache`).
+/// If the repository cannot be opened, `AppEvent::GitWorkerStopped` is sent and
+/// the thread exits.
 pub fn git_worker_loop(
     path: String,
     rx: Receiver<GitRequest>,
     event_tx: UnboundedSender<AppEvent>,
+    mut options: WorkerOptions,
+    generation: u64,
 ) {
-    // Eagerly initialize LazyLock statics to avoid first-request latency.
-    let _ = &*PS;
-    let _ = &*TS;
+    // Eagerly load syntect assets to avoid first-request latency.
+    preload_syntax_assets();
 
     let repo = match Repository::open(&path) {
         Ok(r) => r,
-        Err(_) => {
+        Err(e) => {
+            // Reported like a crash so a retry on a vanished path does not sit on
+            // "Computing diff..." forever.
+            let _ = event_tx.send(AppEvent::GitWorkerStopped {
+                generation,
+                reason: format!("cannot open repository at {path}: {}", e.message()),
+            });
             return;
         }
     };
 
+    let mut cache = options
+        .highlight_cache_path
+        .clone()
+        .map(|p| HighlightCache::load(p, options.highlight_cache_max_entries));
+    let mut payloads = PayloadCache::default();
+
     for request in rx {
-        let payload = handle_request(&repo, request);
+        let request = match request {
+            GitRequest::ResolveRange { from, to } => {
+                let result = resolve_range(&repo, &from, &to);
+                let range = (from, to);
+                let _ = event_tx.send(AppEvent::RangeResolved { generation, range, result });
+                continue;
+            }
+            GitRequest::ListRefs => {
+                let names = list_ref_names(&repo);
+                let _ = event_tx.send(AppEvent::RefsListed { generation, names });
+                continue;
+            }
+            GitRequest::Blame { rev, path, first, last } => {
+                let result = blame_lines(&repo, &rev, &path, first, last)
+                    .map_err(|e| e.message().to_owned());
+                let _ = event_tx.send(AppEvent::BlameLoaded { generation, rev, path, result });
+                continue;
+            }
+            GitRequest::WriteNote { notes_ref, text, replace } => {
+                let result = write_note(&repo, &notes_ref, &text, replace)
+                    .map(|oid| oid.to_string())
+                    .map_err(|e| e.message().to_owned());
+                let _ = event_tx.send(AppEvent::NoteWritten { generation, notes_ref, result });
+                continue;
+            }
+            GitRequest::SetIgnoreWhitespace(ignore) => {
+                options.ignore_whitespace = ignore;
+                continue;
+            }
+            GitRequest::SetContextLines(lines) => {
+                options.context_lines = lines;
+                continue;
+            }
+            request => request,
+        };
+        let mut payload =
+            handle_request(&repo, request, cache.as_mut(), &mut payloads, &options);
+        payload.generation = generation;
         let _ = event_tx.send(AppEvent::GitResult(Box::new(payload)));
+        if let Some(cache) = cache.as_mut() {
+            cache.save();
+        }
     }
 }
 
 /// Dispatches a GitRequest to the appropriate git2 operation and returns the payload.
 ///
-/// On git2 errors, returns an empty payload for graceful degradation.
-fn handle_request(repo: &Repository, request: GitRequest) -> GitResultPayload {
-    let (mode, diff_result) = match request {
-        GitRequest::LoadDiff(mode) => (mode, get_diff_for_mode(repo, mode)),
-        GitRequest::LoadDiffRange { from, to } => {
-            (DiffMode::CommitRange, get_diff_for_range(repo, &from, &to))
-        }
-    };
+/// On git2 errors, returns an empty payload carrying `DiffStatus::Error` so the
+/// diff panel can show the message instead of a generic placeholder. With
+/// `options.detect_generated` set, each delta is checked for `linguist-generated`
+/// before highlighting, and with `options.full_file_highlight` both sides of
+/// each file are loaded (see [`file_sources`]). A branch comparison reports the
+/// base it resolved to. A diff whose request, generated flags, and patch text match an entry in
+/// `payloads` reuses that entry's payload instead of being highlighted again.
+fn handle_request(
+    repo: &Repository,
+    request: GitRequest,
+    cache: Option<&mut HighlightCache>,
+    payloads: &mut PayloadCache,
+    options: &WorkerOptions,
+) -> GitResultPayload {
+    let started = Instant::now();
+    let base_branch = options.base_branch.as_str();
+    let (mode, diff_result) =
+        resolve_request(repo, &request, base_branch, &mut diff_options(options));
 
     match diff_result {
-        Ok(diff) => process_diff(mode, &diff),
-        Err(_) => GitResultPayload {
+        Ok(diff) => {
+            let generated = if options.detect_generated {
+                generated_flags(repo, &diff)
+            } else {
+                Vec::new()
+            };
+            let range = match &request {
+                GitRequest::LoadDiffRange { from, to } => Some((from.as_str(), to.as_str())),
+                _ => None,
+            };
+            let key = payload_key(mode, range, &generated, &diff);
+            let resolve_time = started.elapsed();
+            let mut payload = match key.and_then(|k| payloads.get(k)) {
+                Some(mut hit) => {
+                    hit.timing = DiffTiming::default();
+                    hit
+                }
+                None => {
+                    let sources = if options.full_file_highlight {
+                        file_sources(repo, &diff)
+                    } else {
+                        Vec::new()
+                    };
+                    let fresh = process_diff(mode, &diff, cache, options, &generated, &sources);
+                    if let Some(k) = key {
+                        payloads.insert(k, &fresh);
+                    }
+                    fresh
+                }
+            };
+            payload.timing.diff_ms += resolve_time.as_millis() as u64;
+            if mode == DiffMode::BranchComparison {
+                payload.base = resolve_base(repo, base_branch).ok().map(|(name, _)| name);
+            }
+            payload
+        }
+        Err(e) => GitResultPayload {
+            generation: 0,
             mode,
+            status: DiffStatus::Error(e.message().to_owned()),
             hunks: Vec::new(),
             files: Vec::new(),
             highlighted_lines: Vec::new(),
             hunk_offsets: Vec::new(),
             file_line_offsets: Vec::new(),
+            line_refs: Vec::new(),
+            timing: DiffTiming::default(),
+            base: None,
         },
     }
 }
 
-/// Obtains a git2::Diff for simple diff modes (Unstaged, Staged, BranchComparison).
+/// Computes the diff for `request` synchronously and summarizes it for `airev json`.
 ///
-/// Returns git2::Error on any failure (repo missing HEAD, no branch named "main", etc.).
-fn get_diff_for_mode(repo: &Repository, mode: DiffMode) -> Result<Diff<'_>, git2::Error> {
-    match mode {
-        DiffMode::Unstaged => {
-            let mut opts = DiffOptions::new();
-            repo.diff_index_to_workdir(None, Some(&mut opts))
+/// Opens the repository at `path` on the calling thread and skips highlighting
+/// entirely; only file summaries and hunk headers are extracted. A branch
+/// comparison is taken against `base_branch`, as in the TUI.
+pub fn diff_report(
+    path: &str,
+    request: GitRequest,
+    base_branch: &str,
+) -> Result<DiffReport, git2::Error> {
+    let repo = Repository::open(path)?;
+    let (mode, diff_result) =
+        resolve_request(&repo, &request, base_branch, &mut DiffOptions::new());
+    let diff = diff_result?;
+    let (hunks, file_hunk_starts) = extract_hunks(&diff);
+    let files = extract_files(&diff);
+    Ok(DiffReport::new(mode, &files, &hunks, &file_hunk_starts))
+}
+
+/// Returns, for each delta in `diff`, whether `.gitattributes` marks its path
+/// `linguist-generated` (set, or set to `true`).
+///
+/// Attributes are read from the working tree first, then the index, so a
+/// `.gitattributes` edit takes effect on the next refresh without staging it.
+fn generated_flags(repo: &Repository, diff: &Diff<'_>) -> Vec<bool> {
+    diff.deltas()
+        .map(|d| {
+            let path = d.new_file().path().or_else(|| d.old_file().path());
+            path.and_then(|p| {
+                repo.get_attr(p, "linguist-generated", AttrCheckFlags::FILE_THEN_INDEX).ok()
+            })
+            .is_some_and(|v| match AttrValue::from_string(v) {
+                AttrValue::True => true,
+                AttrValue::String(s) => s.eq_ignore_ascii_case("true"),
+                _ => false,
+            })
+        })
+        .collect()
+}
+
+/// Full old and new text of each delta in `diff`, for highlighting whole files.
+///
+/// A side is read from its blob when git knows the blob; otherwise the new side
+/// is read from the working tree (unstaged changes). Missing sides, binary blobs,
+/// and non-UTF-8 text are `None`.
+fn file_sources(repo: &Repository, diff: &Diff<'_>) -> Vec<(Option<String>, Option<String>)> {
+    let blob_text = |id: git2::Oid| -> Option<String> {
+        if id.is_zero() {
+            return None;
+        }
+        let blob = repo.find_blob(id).ok()?;
+        if blob.is_binary() {
+            return None;
         }
+        String::from_utf8(blob.content().to_vec()).ok()
+    };
+    diff.deltas()
+        .map(|d| {
+            let old = blob_text(d.old_file().id());
+            let new = blob_text(d.new_file().id()).or_else(|| {
+                let path = d.new_file().path()?;
+                std::fs::read_to_string(repo.workdir()?.join(path)).ok()
+            });
+            (old, new)
+        })
+        .collect()
+}
+
+/// The git2 diff options for `options`: whitespace handling and context lines.
+fn diff_options(options: &WorkerOptions) -> DiffOptions {
+    let mut opts = DiffOptions::new();
+    opts.ignore_whitespace(options.ignore_whitespace)
+        .ignore_whitespace_eol(options.ignore_whitespace)
+        .context_lines(options.context_lines);
+    opts
+}
+
+/// Maps a request to its diff mode and the git2::Diff it asks for, computed
+/// with `opts`; a branch comparison is taken against `base_branch`.
+fn resolve_request<'a>(
+    repo: &'a Repository,
+    request: &GitRequest,
+    base_branch: &str,
+    opts: &mut DiffOptions,
+) -> (DiffMode, Result<Diff<'a>, git2::Error>) {
+    match request {
+        GitRequest::LoadDiff(mode) => (*mode, get_diff_for_mode(repo, *mode, base_branch, opts)),
+        GitRequest::LoadDiffRange { from, to } => {
+            (DiffMode::CommitRange, get_diff_for_range(repo, from, to, opts))
+        }
+        // Answered by the worker loop before a diff is computed.
+        GitRequest::ResolveRange { .. }
+        | GitRequest::ListRefs
+        | GitRequest::Blame { .. }
+        | GitRequest::WriteNote { .. }
+        | GitRequest::SetIgnoreWhitespace(_)
+        | GitRequest::SetContextLines(_) => {
+            (DiffMode::CommitRange, Err(git2::Error::from_str("request loads no diff")))
+        }
+    }
+}
+
+/// Checks that `from` and `to` both name commits, allowing the `C^` of a root
+/// commit `C` (see [`range_base`]).
+///
+/// The error names the ref that failed, with git's message, e.g.
+/// `v9.9: revspec 'v9.9' not found`.
+fn resolve_range(repo: &Repository, from: &str, to: &str) -> Result<(), String> {
+    range_base(repo, from, to).map_err(|e| format!("{from}: {}", e.message()))?;
+    repo.revparse_single(to)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| format!("{to}: {}", e.message()))?;
+    Ok(())
+}
+
+/// The commit a `from..to` range starts at, or `None` when `from` is `C^` for a
+/// root commit `C`: that single-commit range is taken against the empty tree.
+fn range_base<'a>(
+    repo: &'a Repository,
+    from: &str,
+    to: &str,
+) -> Result<Option<git2::Commit<'a>>, git2::Error> {
+    let err = match repo.revparse_single(from).and_then(|o| o.peel_to_commit()) {
+        Ok(commit) => return Ok(Some(commit)),
+        Err(e) => e,
+    };
+    let is_root = |spec: &str| {
+        repo.revparse_single(spec)
+            .and_then(|o| o.peel_to_commit())
+            .is_ok_and(|c| c.parent_count() == 0)
+    };
+    match from.strip_suffix('^') {
+        Some(parent) if parent == to && is_root(to) => Ok(None),
+        _ => Err(err),
+    }
+}
+
+/// Lists `HEAD` and the short names of the repository's direct references:
+/// local branches, then remote branches, then tags, each sorted. Symbolic refs
+/// such as `origin/HEAD` are left out. A failed listing gives just `HEAD`.
+fn list_ref_names(repo: &Repository) -> Vec<String> {
+    let mut refs: Vec<(u8, String)> = repo
+        .references()
+        .map(|refs| {
+            refs.flatten()
+                .filter(|r| r.kind() == Some(git2::ReferenceType::Direct))
+                .filter_map(|r| {
+                    let rank = match r.name()? {
+                        name if name.starts_with("refs/heads/") => 0,
+                        name if name.starts_with("refs/remotes/") => 1,
+                        name if name.starts_with("refs/tags/") => 2,
+                        _ => return None,
+                    };
+                    Some((rank, r.shorthand()?.to_owned()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    refs.sort();
+    std::iter::once("HEAD".to_owned()).chain(refs.into_iter().map(|(_, name)| name)).collect()
+}
+
+/// Obtains a git2::Diff for simple diff modes (Unstaged, Staged, BranchComparison,
+/// Stash) with `opts`. The branch comparison is against `base_branch` (see
+/// [`resolve_base`]); without a stash, Stash is an empty diff.
+///
+/// Returns git2::Error on any failure (repo missing HEAD, no base branch, etc.).
+fn get_diff_for_mode<'a>(
+    repo: &'a Repository,
+    mode: DiffMode,
+    base_branch: &str,
+    opts: &mut DiffOptions,
+) -> Result<Diff<'a>, git2::Error> {
+    match mode {
+        DiffMode::Unstaged => repo.diff_index_to_workdir(None, Some(opts)),
         DiffMode::Staged => {
             let head_commit = repo.head()?.peel_to_commit()?;
             let head_tree = head_commit.tree()?;
-            let mut opts = DiffOptions::new();
-            repo.diff_tree_to_index(Some(&head_tree), None, Some(&mut opts))
+            repo.diff_tree_to_index(Some(&head_tree), None, Some(opts))
         }
         DiffMode::BranchComparison => {
-            let base_obj = repo.revparse_single("main")?;
-            let base_commit = base_obj.peel_to_commit()?;
+            let (_, base_commit) = resolve_base(repo, base_branch)?;
             let base_tree = base_commit.tree()?;
             let head_commit = repo.head()?.peel_to_commit()?;
             let head_tree = head_commit.tree()?;
-            let mut opts = DiffOptions::new();
-            repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut opts))
+            repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(opts))
+        }
+        DiffMode::Stash => {
+            let stash = match repo.revparse_single("stash@{0}") {
+                Ok(obj) => obj.peel_to_commit()?,
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                    return repo.diff_tree_to_tree(None, None, Some(opts));
+                }
+                Err(e) => return Err(e),
+            };
+            let base_tree = stash.parent(0)?.tree()?;
+            repo.diff_tree_to_tree(Some(&base_tree), Some(&stash.tree()?), Some(opts))
         }
         DiffMode::CommitRange => {
             // CommitRange requires explicit refs; LoadDiff(CommitRange) is a no-op.
             Err(git2::Error::from_str("CommitRange requires LoadDiffRange"))
         }
+        DiffMode::Files => {
+            // Loose-file diffs are built by `git::loose`, never by the worker.
+            Err(git2::Error::from_str("Files mode is not backed by the repository"))
+        }
     }
 }
 
-/// Resolves two ref strings to trees and diffs them.
+/// Branches tried, in order, when the configured base branch does not resolve.
+const FALLBACK_BASES: [&str; 2] = ["main", "master"];
+
+/// Resolves the base of a branch comparison to its name and commit: `base_branch`
+/// if it names a commit, else the first of [`FALLBACK_BASES`] that does.
+///
+/// Returns git's error for `base_branch` when none of them resolve.
+fn resolve_base<'a>(
+    repo: &'a Repository,
+    base_branch: &str,
+) -> Result<(String, git2::Commit<'a>), git2::Error> {
+    let configured = repo.revparse_single(base_branch).and_then(|o| o.peel_to_commit());
+    let err = match configured {
+        Ok(commit) => return Ok((base_branch.to_owned(), commit)),
+        Err(e) => e,
+    };
+    FALLBACK_BASES
+        .iter()
+        .filter(|&&name| name != base_branch)
+        .find_map(|&name| {
+            let commit = repo.revparse_single(name).and_then(|o| o.peel_to_commit()).ok()?;
+            Some((name.to_owned(), commit))
+        })
+        .ok_or(err)
+}
+
+/// Resolves two ref strings to trees and diffs them with `opts`. A root commit's
+/// `C^..C` is diffed against the empty tree, so every file shows as added.
 ///
 /// Returns git2::Error if either ref cannot be resolved or tree-walking fails.
 fn get_diff_for_range<'a>(
     repo: &'a Repository,
     from: &str,
     to: &str,
+    opts: &mut DiffOptions,
 ) -> Result<Diff<'a>, git2::Error> {
-    let old_obj = repo.revparse_single(from)?;
-    let old_commit = old_obj.peel_to_commit()?;
-    let old_tree = old_commit.tree()?;
+    let old_tree = range_base(repo, from, to)?.map(|c| c.tree()).transpose()?;
 
     let new_obj = repo.revparse_single(to)?;
     let new_commit = new_obj.peel_to_commit()?;
     let new_tree = new_commit.tree()?;
 
-    let mut opts = DiffOptions::new();
-    repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut opts))
+    repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), Some(opts))
 }
 
 /// Extracts hunks + files from a Diff and builds highlighted lines.
 ///
-/// Orchestrates extract_hunks, extract_files, and highlight_hunks into the final payload.
-/// `file_hunk_starts` from `extract_hunks` is mapped through `hunk_offsets` to produce
-/// `file_line_offsets` — the line index in `highlighted_lines` where each file begins.
-fn process_diff(mode: DiffMode, diff: &Diff<'_>) -> GitResultPayload {
+/// Each file is highlighted on its own, from the highlight cache when its key
+/// (theme, `word_diff`, whitespace, context) is cached, and from the full old /
+/// new text in `sources` when that is present. Binary files get a placeholder
+/// hunk; moved blocks are marked after caching. `timing` excludes computing the
+/// diff itself.
+fn process_diff(
+    mode: DiffMode,
+    diff: &Diff<'_>,
+    mut cache: Option<&mut HighlightCache>,
+    options: &WorkerOptions,
+    generated: &[bool],
+    sources: &[(Option<String>, Option<String>)],
+) -> GitResultPayload {
+    let word_diff = options.word_diff;
+    let extract_start = Instant::now();
     let (hunks, file_hunk_starts) = extract_hunks(diff);
-    let files = extract_files(diff);
-    let ext = files.first().map(|f| file_ext(&f.path)).unwrap_or("txt");
-    let (highlighted_lines, hunk_offsets) = highlight_hunks(&hunks, ext);
+    let mut files = extract_files(diff);
+    for (file, &flag) in files.iter_mut().zip(generated) {
+        file.generated = flag;
+    }
+    let (hunks, file_hunk_starts) = with_binary_placeholders(&files, hunks, &file_hunk_starts);
+    let blob_ids: Vec<(git2::Oid, git2::Oid)> =
+        diff.deltas().map(|d| (d.old_file().id(), d.new_file().id())).collect();
+    let syntax_paths: Vec<String> = diff.deltas().map(|d| syntax_path(&d)).collect();
+    let diff_ms = extract_start.elapsed().as_millis() as u64;
+    let highlight_start = Instant::now();
 
-    let file_line_offsets: Vec<usize> = file_hunk_starts
-        .iter()
-        .map(|&hunk_idx| hunk_offsets.get(hunk_idx).copied().unwrap_or(0))
-        .collect();
+    let mut highlighted_lines: Vec<Line<'static>> = Vec::new();
+    let mut hunk_offsets: Vec<usize> = Vec::new();
+    let mut file_line_offsets: Vec<usize> = Vec::with_capacity(files.len());
+    let mut line_refs: Vec<LineRef> = Vec::new();
+    let ws = if options.ignore_whitespace { "/ws" } else { "" };
+    let full = if options.full_file_highlight { "/full" } else { "" };
+    let style = format!(
+        "{}/{}{ws}{full}/u{}",
+        options.syntax_theme,
+        word_diff.as_str(),
+        options.context_lines
+    );
+
+    for (i, file) in files.iter().enumerate() {
+        let start = file_hunk_starts.get(i).copied().unwrap_or(hunks.len());
+        let end = file_hunk_starts.get(i + 1).copied().unwrap_or(hunks.len());
+        let file_hunks = &hunks[start..end];
+        let base = highlighted_lines.len();
+        file_line_offsets.push(base);
+
+        if file.binary {
+            hunk_offsets.push(base);
+            highlighted_lines.push(Line::styled(
+                BINARY_PLACEHOLDER,
+                Style::default().fg(Color::DarkGray),
+            ));
+            line_refs.extend(line_refs_for_hunks(i, start, file_hunks));
+            continue;
+        }
+
+        let key = cache.as_ref().map(|_| {
+            let blobs =
+                blob_ids.get(i).copied().unwrap_or((git2::Oid::zero(), git2::Oid::zero()));
+            cache_key(file, blobs, file_hunks, &style)
+        });
+        let cached = match (cache.as_deref_mut(), key.as_deref()) {
+            (Some(c), Some(k)) => c.get(k),
+            _ => None,
+        };
+        let (mut lines, offsets) = match cached {
+            Some(hit) => hit,
+            None => {
+                let path = syntax_paths.get(i).map_or(file.path.as_str(), String::as_str);
+                let unified = hunks_to_unified(file_hunks);
+                let (old, new) = sources.get(i).map_or((None, None), |(old, new)| {
+                    (old.as_deref(), new.as_deref())
+                });
+                let fresh = highlight_unified_diff_with_sources(
+                    &unified,
+                    file_ext(path),
+                    word_diff,
+                    &options.syntax_theme,
+                    FileSources { old, new },
+                );
+                if let (Some(c), Some(k)) = (cache.as_deref_mut(), key) {
+                    c.insert(k, &fresh.0, &fresh.1);
+                }
+                fresh
+            }
+        };
+
+        if options.color_moved {
+            mark_moved(&mut lines, file_hunks);
+        }
+        hunk_offsets.extend(offsets.into_iter().map(|o| o + base));
+        highlighted_lines.extend(lines);
+        line_refs.extend(line_refs_for_hunks(i, start, file_hunks));
+    }
+
+    GitResultPayload {
+        generation: 0,
+        mode,
+        status: DiffStatus::Loaded,
+        hunks,
+        files,
+        highlighted_lines,
+        hunk_offsets,
+        file_line_offsets,
+        line_refs,
+        timing: DiffTiming { diff_ms, highlight_ms: highlight_start.elapsed().as_millis() as u64 },
+        base: None,
+    }
+}
+
+/// Header of the hunk that stands in for a binary file's content.
+const BINARY_PLACEHOLDER: &str = "Binary file (not shown)";
+
+/// Gives each binary file in `files` a single empty hunk headed
+/// [`BINARY_PLACEHOLDER`], so it has a line in the diff to jump to, navigate
+/// by, and comment on.
+///
+/// Returns the hunks and per-file starts in the same shape as `extract_hunks`.
+fn with_binary_placeholders(
+    files: &[FileSummary],
+    hunks: Vec<OwnedDiffHunk>,
+    file_hunk_starts: &[usize],
+) -> (Vec<OwnedDiffHunk>, Vec<usize>) {
+    if !files.iter().any(|f| f.binary) {
+        return (hunks, file_hunk_starts.to_vec());
+    }
+    let total = hunks.len();
+    let mut hunks = hunks.into_iter();
+    let mut out = Vec::with_capacity(total);
+    let mut starts = Vec::with_capacity(files.len());
+    for (i, file) in files.iter().enumerate() {
+        let start = file_hunk_starts.get(i).copied().unwrap_or(total);
+        let end = file_hunk_starts.get(i + 1).copied().unwrap_or(total);
+        starts.push(out.len());
+        out.extend(hunks.by_ref().take(end.saturating_sub(start)));
+        if file.binary && start == end {
+            out.push(OwnedDiffHunk {
+                header: BINARY_PLACEHOLDER.to_owned(),
+                old_start: 0,
+                new_start: 0,
+                lines: Vec::new(),
+            });
+        }
+    }
+    (out, starts)
+}
 
-    GitResultPayload { mode, hunks, files, highlighted_lines, hunk_offsets, file_line_offsets }
+/// Builds the `LineRef`s for one file's hunks, in rendered-line order.
+///
+/// The highlighter emits one header line per hunk followed by exactly one line
+/// per `OwnedDiffLine`, so the refs line up index-for-index with its output.
+/// `hunk_base` is the global index of the file's first hunk.
+pub fn line_refs_for_hunks(
+    file_index: usize,
+    hunk_base: usize,
+    hunks: &[OwnedDiffHunk],
+) -> Vec<LineRef> {
+    let mut refs = Vec::new();
+    for (i, hunk) in hunks.iter().enumerate() {
+        let hunk_index = hunk_base + i;
+        refs.push(LineRef {
+            file_index,
+            hunk_index,
+            origin: 'H',
+            old_lineno: None,
+            new_lineno: None,
+        });
+        refs.extend(hunk.lines.iter().map(|dl| LineRef {
+            file_index,
+            hunk_index,
+            origin: dl.origin,
+            old_lineno: dl.old_lineno,
+            new_lineno: dl.new_lineno,
+        }));
+    }
+    refs
 }
 
 /// Walks diff hunks and lines, converting to owned types for cross-thread transfer.
@@ -198,6 +673,9 @@ fn extract_hunks(diff: &Diff<'_>) -> (Vec<OwnedDiffHunk>, Vec<usize>) {
 
 /// Collects per-file status info and real added/removed line counts from diff deltas.
 ///
+/// Binary deltas are flagged from `DiffDelta::flags`; git reports no lines for
+/// them, so their counts stay zero.
+///
 /// Uses `diff.foreach()` with all four callbacks so that line-origin characters
 /// (`'+'` / `'-'`) are counted per file in a single pass. The `file_cb` callback
 /// fires once per delta (file) in order, so `files.last_mut()` in the line callback
@@ -221,7 +699,16 @@ fn extract_files(diff: &Diff<'_>) -> Vec<FileSummary> {
                 Delta::Renamed => 'R',
                 _ => 'M',
             };
-            files.borrow_mut().push(FileSummary { path, status, added: 0, removed: 0 });
+            let language = syntax_name(file_ext(&syntax_path(&delta)));
+            files.borrow_mut().push(FileSummary {
+                path,
+                status,
+                added: 0,
+                removed: 0,
+                generated: false,
+                binary: delta.flags().is_binary(),
+                language,
+            });
             true
         },
         None,
@@ -242,199 +729,399 @@ fn extract_files(diff: &Diff<'_>) -> Vec<FileSummary> {
     files.into_inner()
 }
 
-/// Converts a syntect (Style, &str) pair to an owned ratatui Span.
+/// Serializes a file's hunks back to unified diff text for the highlighter.
 ///
-/// Rebuilds color and modifier fields from syntect types into ratatui types to
-/// avoid the type mismatch between ratatui::style::Style and ratatui::prelude::Style
-/// that arises from syntect-tui using a different ratatui crate split.
-fn syntect_to_span(style: syntect::highlighting::Style, content: &str) -> Span<'static> {
-    use syntect::highlighting::Color as SC;
-    let to_color = |c: SC| -> Option<Color> {
-        if c.a > 0 { Some(Color::Rgb(c.r, c.g, c.b)) } else { None }
-    };
-    let mut ratatui_style = Style::default();
-    if let Some(fg) = to_color(style.foreground) {
-        ratatui_style = ratatui_style.fg(fg);
-    }
-    if let Some(bg) = to_color(style.background) {
-        ratatui_style = ratatui_style.bg(bg);
-    }
-    if style.font_style.contains(syntect::highlighting::FontStyle::BOLD) {
-        ratatui_style = ratatui_style.add_modifier(Modifier::BOLD);
-    }
-    if style.font_style.contains(syntect::highlighting::FontStyle::ITALIC) {
-        ratatui_style = ratatui_style.add_modifier(Modifier::ITALIC);
-    }
-    if style.font_style.contains(syntect::highlighting::FontStyle::UNDERLINE) {
-        ratatui_style = ratatui_style.add_modifier(Modifier::UNDERLINED);
+/// git2 reports line content without the origin character, so it is prepended
+/// here. The end-of-file newline markers (`'='`, `'<'`, `'>'`) become the usual
+/// `\ No newline at end of file` line.
+pub fn hunks_to_unified(hunks: &[OwnedDiffHunk]) -> String {
+    let mut text = String::new();
+    for hunk in hunks {
+        text.push_str(hunk.header.trim_end_matches('\n'));
+        text.push('\n');
+        for dl in &hunk.lines {
+            match dl.origin {
+                '+' | '-' | ' ' => {
+                    text.push(dl.origin);
+                    text.push_str(dl.content.trim_end_matches('\n'));
+                    text.push('\n');
+                }
+                _ => text.push_str("\\ No newline at end of file\n"),
+            }
+        }
     }
-    Span::styled(content.to_owned(), ratatui_style)
+    text
 }
 
-/// Builds syntect-highlighted spans for a single line of code.
+/// Returns the path whose extension selects the syntax for a delta's hunks.
 ///
-/// Returns owned `Vec<Span<'static>>`. Falls back to a plain unstyled span on error.
-fn build_syntect_spans(
-    code: &str,
-    h: &mut HighlightLines,
-    ps: &SyntaxSet,
-) -> Vec<Span<'static>> {
-    let ranges = h.highlight_line(code, ps).unwrap_or_default();
-    let spans: Vec<Span<'static>> =
-        ranges.into_iter().map(|(style, text)| syntect_to_span(style, text)).collect();
-    if spans.is_empty() {
-        vec![Span::raw(code.to_owned())]
-    } else {
-        spans
-    }
+/// A deleted file only has old content, so the old path is used; every other
+/// delta (including additions, whose old side is empty) uses the new path.
+fn syntax_path(delta: &git2::DiffDelta<'_>) -> String {
+    let file = match delta.status() {
+        Delta::Deleted => delta.old_file(),
+        _ => delta.new_file(),
+    };
+    file.path().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default()
 }
 
-/// Computes word-level diff spans for a removed/added line pair.
+/// Extracts the file extension from a repository-relative path.
 ///
-/// Returns two parallel Vecs of spans: old_line spans and new_line spans.
-/// Changed words are rendered bold; unchanged words use the base diff color.
-fn word_diff_spans(
-    old_line: &str,
-    new_line: &str,
-) -> (Vec<Span<'static>>, Vec<Span<'static>>) {
-    let diff = TextDiff::from_words(old_line, new_line);
-    let mut old_spans: Vec<Span<'static>> = Vec::new();
-    let mut new_spans: Vec<Span<'static>> = Vec::new();
-
-    for op in diff.ops() {
-        for change in diff.iter_inline_changes(op) {
-            for (emphasized, value) in change.iter_strings_lossy() {
-                let text = value.into_owned();
-                match change.tag() {
-                    ChangeTag::Delete => {
-                        let style = if emphasized {
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
-                        } else {
-                            Style::default().fg(Color::Red)
-                        };
-                        old_spans.push(Span::styled(text, style));
-                    }
-                    ChangeTag::Insert => {
-                        let style = if emphasized {
-                            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
-                        } else {
-                            Style::default().fg(Color::Green)
-                        };
-                        new_spans.push(Span::styled(text, style));
-                    }
-                    ChangeTag::Equal => {
-                        let span =
-                            Span::styled(text.clone(), Style::default().fg(Color::DarkGray));
-                        old_spans.push(span.clone());
-                        new_spans.push(span);
-                    }
-                }
+/// Returns "txt" if the path has no extension.
+pub fn file_ext(path: &str) -> &str {
+    path.rsplit('.').next().unwrap_or("txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::init_test_repo;
+    use airev_core::highlight::{highlight_unified_diff, WordDiff, SYNTECT_THEME};
+
+    fn line(origin: char, content: &str, old: Option<u32>, new: Option<u32>) -> OwnedDiffLine {
+        OwnedDiffLine { origin, content: content.to_owned(), old_lineno: old, new_lineno: new }
+    }
+
+    fn sample_hunks() -> Vec<OwnedDiffHunk> {
+        vec![
+            OwnedDiffHunk {
+                header: "@@ -1,2 +1,2 @@\n".to_owned(),
+                old_start: 1,
+                new_start: 1,
+                lines: vec![
+                    line(' ', "fn main() {\n", Some(1), Some(1)),
+                    line('-', "    old();\n", Some(2), None),
+                    line('+', "    new();\n", None, Some(2)),
+                ],
+            },
+            OwnedDiffHunk {
+                header: "@@ -9 +9,2 @@\n".to_owned(),
+                old_start: 9,
+                new_start: 9,
+                lines: vec![
+                    line(' ', "}\n", Some(9), Some(9)),
+                    line('+', "// end", None, Some(10)),
+                    line('>', "\n\\ No newline at end of file\n", None, None),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn line_refs_track_hunks_and_line_numbers() {
+        let refs = line_refs_for_hunks(3, 5, &sample_hunks());
+
+        assert_eq!(refs.len(), 8);
+        let header =
+            LineRef { file_index: 3, hunk_index: 5, origin: 'H', old_lineno: None, new_lineno: None };
+        assert_eq!(refs[0], header);
+        assert_eq!((refs[2].origin, refs[2].old_lineno), ('-', Some(2)));
+        assert_eq!((refs[3].origin, refs[3].new_lineno), ('+', Some(2)));
+        assert_eq!((refs[4].origin, refs[4].hunk_index), ('H', 6));
+        assert_eq!(refs[6].new_lineno, Some(10));
+    }
+
+    #[test]
+    fn deleted_and_added_files_pick_syntax_from_surviving_path() {
+        use std::path::Path;
+
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+
+        // Commit old.rs, then stage its deletion alongside a brand-new new.py.
+        std::fs::write(dir.join("old.rs"), "fn main() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("old.rs")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("airev", "airev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
+
+        std::fs::write(dir.join("new.py"), "x = 1\n").unwrap();
+        index.remove_path(Path::new("old.rs")).unwrap();
+        index.add_path(Path::new("new.py")).unwrap();
+        index.write().unwrap();
+
+        let diff =
+            get_diff_for_mode(&repo, DiffMode::Staged, "main", &mut DiffOptions::new()).unwrap();
+        let picked: Vec<(Delta, String)> = diff
+            .deltas()
+            .map(|d| (d.status(), file_ext(&syntax_path(&d)).to_owned()))
+            .collect();
+        assert_eq!(
+            picked,
+            vec![(Delta::Added, "py".to_owned()), (Delta::Deleted, "rs".to_owned())]
+        );
+    }
+
+    #[test]
+    fn unopenable_repository_stops_the_worker_with_its_path() {
+        let dir = std::env::temp_dir().join(format!("airev-missing-{}", std::process::id()));
+        let path = dir.to_string_lossy().into_owned();
+        let (_request_tx, request_rx) = crossbeam_channel::unbounded();
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        git_worker_loop(path.clone(), request_rx, event_tx, WorkerOptions::default(), 7);
+
+        match event_rx.try_recv() {
+            Ok(AppEvent::GitWorkerStopped { generation, reason }) => {
+                assert_eq!(generation, 7);
+                assert!(reason.starts_with(&format!("cannot open repository at {path}")));
             }
+            other => panic!("unexpected event: {other:?}"),
         }
     }
-    (old_spans, new_spans)
-}
 
-/// Converts a slice of OwnedDiffHunk into highlighted ratatui Lines.
-///
-/// Applies syntect syntax highlighting and word-level diff emphasis for
-/// consecutive -/+ line pairs. Returns the lines and the hunk-header offsets.
-fn highlight_hunks(hunks: &[OwnedDiffHunk], ext: &str) -> (Vec<Line<'static>>, Vec<usize>) {
-    let theme = TS.themes.get("base16-ocean.dark").or_else(|| TS.themes.values().next());
-    let syntax = PS.find_syntax_by_extension(ext).unwrap_or_else(|| PS.find_syntax_plain_text());
+    #[test]
+    fn resolve_range_names_the_ref_that_fails() {
+        let (_dir, repo) = init_test_repo();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("airev", "airev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
 
-    let mut highlighted_lines: Vec<Line<'static>> = Vec::new();
-    let mut hunk_offsets: Vec<usize> = Vec::new();
+        assert_eq!(resolve_range(&repo, "HEAD", "HEAD"), Ok(()));
+        let err = resolve_range(&repo, "HEAD", "v9.9").unwrap_err();
+        assert!(err.starts_with("v9.9: "), "{err}");
+        // The root commit has no parent: its `C^..C` range is taken against the
+        // empty tree, but `C^` on its own still fails.
+        assert_eq!(resolve_range(&repo, "HEAD^", "HEAD"), Ok(()));
+        assert!(resolve_range(&repo, "HEAD^", "main").unwrap_err().starts_with("HEAD^: "));
+    }
 
-    for hunk in hunks {
-        // Record hunk header position and emit a styled header line.
-        hunk_offsets.push(highlighted_lines.len());
-        let header_span = Span::styled(hunk.header.trim_end().to_owned(), Style::default().fg(Color::Cyan));
-        highlighted_lines.push(Line::from(vec![header_span]));
-
-        // Fresh highlighter per hunk for simplicity (safe, predictable state).
-        let mut h = match theme {
-            Some(t) => HighlightLines::new(syntax, t),
-            None => {
-                emit_plain_hunk_lines(&hunk.lines, &mut highlighted_lines);
-                continue;
-            }
-        };
+    #[test]
+    fn root_commit_is_diffed_against_the_empty_tree() {
+        use std::path::Path;
 
-        let mut pending_removed: Option<(String, Vec<Span<'static>>)> = None;
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+        let sig = git2::Signature::now("t", "t@example.com").unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn a() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("lib.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
 
-        for dl in &hunk.lines {
-            let origin = dl.origin;
-            let content = &dl.content;
-            let code =
-                if content.starts_with(['+', '-', ' ']) { &content[1..] } else { content };
-            let code = code.trim_end_matches('\n');
-            let base_spans = build_syntect_spans(code, &mut h, &PS);
-
-            match origin {
-                '-' => {
-                    if let Some((_, spans)) = pending_removed.take() {
-                        highlighted_lines.push(Line::from(spans));
-                    }
-                    let mut s = vec![Span::styled("- ", Style::default().fg(Color::Red))];
-                    s.extend(base_spans);
-                    pending_removed = Some((code.to_owned(), s));
-                }
-                '+' => {
-                    if let Some((old_code, _)) = pending_removed.take() {
-                        let (old_word, new_word) = word_diff_spans(&old_code, code);
-                        let mut old_s = vec![Span::styled("- ", Style::default().fg(Color::Red))];
-                        old_s.extend(old_word);
-                        highlighted_lines.push(Line::from(old_s));
-                        let mut new_s =
-                            vec![Span::styled("+ ", Style::default().fg(Color::Green))];
-                        new_s.extend(new_word);
-                        highlighted_lines.push(Line::from(new_s));
-                    } else {
-                        let mut s =
-                            vec![Span::styled("+ ", Style::default().fg(Color::Green))];
-                        s.extend(base_spans);
-                        highlighted_lines.push(Line::from(s));
-                    }
-                }
-                _ => {
-                    if let Some((_, spans)) = pending_removed.take() {
-                        highlighted_lines.push(Line::from(spans));
-                    }
-                    let prefix = format!("{origin} ");
-                    let mut s = vec![Span::styled(prefix, Style::default().fg(Color::DarkGray))];
-                    s.extend(base_spans);
-                    highlighted_lines.push(Line::from(s));
-                }
-            }
+        let diff = get_diff_for_range(&repo, "HEAD^", "HEAD", &mut DiffOptions::new()).unwrap();
+        let files = extract_files(&diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "lib.rs");
+        assert_eq!(files[0].added, 1);
+        assert!(get_diff_for_range(&repo, "HEAD~2", "HEAD", &mut DiffOptions::new()).is_err());
+    }
+
+    #[test]
+    fn ref_names_list_branches_before_tags() {
+        let (_dir, repo) = init_test_repo();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("airev", "airev@example.com").unwrap();
+        let oid = repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[]).unwrap();
+        let commit = repo.find_commit(oid).unwrap();
+        repo.branch("feature/x", &commit, false).unwrap();
+        repo.tag_lightweight("v1.0", commit.as_object(), false).unwrap();
+        repo.reference("refs/remotes/origin/main", oid, false, "test").unwrap();
+        repo.reference_symbolic("refs/remotes/origin/HEAD", "refs/remotes/origin/main", false, "")
+            .unwrap();
+
+        assert_eq!(list_ref_names(&repo), ["HEAD", "feature/x", "main", "origin/main", "v1.0"]);
+    }
+
+    #[test]
+    fn linguist_generated_files_are_flagged_with_their_hunks_intact() {
+        use std::path::Path;
+
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+
+        std::fs::write(dir.join(".gitattributes"), "schema.gen.rs linguist-generated\n").unwrap();
+        std::fs::write(dir.join("schema.gen.rs"), "pub const A: u8 = 1;\n").unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn a() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        for path in [".gitattributes", "schema.gen.rs", "lib.rs"] {
+            index.add_path(Path::new(path)).unwrap();
         }
-        // Flush any trailing unpaired removed line.
-        if let Some((_, spans)) = pending_removed.take() {
-            highlighted_lines.push(Line::from(spans));
+        index.write().unwrap();
+
+        std::fs::write(dir.join("schema.gen.rs"), "pub const A: u8 = 2;\npub const B: u8 = 3;\n")
+            .unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn b() {}\n").unwrap();
+
+        let diff =
+            get_diff_for_mode(&repo, DiffMode::Unstaged, "main", &mut DiffOptions::new()).unwrap();
+        let flags = generated_flags(&repo, &diff);
+        let options =
+            WorkerOptions { word_diff: WordDiff::Off, detect_generated: true, ..Default::default() };
+        let payload = process_diff(DiffMode::Unstaged, &diff, None, &options, &flags, &[]);
+
+        let flagged: Vec<(&str, bool)> =
+            payload.files.iter().map(|f| (f.path.as_str(), f.generated)).collect();
+        assert_eq!(flagged, vec![("lib.rs", false), ("schema.gen.rs", true)]);
+        assert_eq!(payload.files[0].language, "Rust");
+
+        // The generated file ships its whole hunk; folding it is up to the app.
+        let generated_start = payload.file_line_offsets[1];
+        let origins: Vec<char> =
+            payload.line_refs[generated_start..].iter().map(|r| r.origin).collect();
+        assert_eq!(origins, ['H', '-', '+', '+']);
+        assert_eq!(payload.line_refs.len(), payload.highlighted_lines.len());
+        assert_eq!(payload.hunk_offsets.last(), Some(&generated_start));
+        assert_eq!((payload.files[1].added, payload.files[1].removed), (2, 1));
+    }
+
+    #[test]
+    fn binary_files_get_a_placeholder_hunk() {
+        use std::path::Path;
+
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+        std::fs::write(dir.join("icon.png"), b"\x89PNG\0\x01").unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn a() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        for path in ["icon.png", "lib.rs"] {
+            index.add_path(Path::new(path)).unwrap();
         }
+        index.write().unwrap();
+        std::fs::write(dir.join("icon.png"), b"\x89PNG\0\x02").unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn b() {}\n").unwrap();
+
+        let diff =
+            get_diff_for_mode(&repo, DiffMode::Unstaged, "main", &mut DiffOptions::new()).unwrap();
+        let options = WorkerOptions { word_diff: WordDiff::Off, ..Default::default() };
+        let payload = process_diff(DiffMode::Unstaged, &diff, None, &options, &[], &[]);
+
+        let binary: Vec<(&str, bool)> =
+            payload.files.iter().map(|f| (f.path.as_str(), f.binary)).collect();
+        assert_eq!(binary, vec![("icon.png", true), ("lib.rs", false)]);
+        assert_eq!(payload.hunks[0].header, BINARY_PLACEHOLDER);
+        assert_eq!(payload.hunk_offsets[..2], [0, 1]);
+        assert_eq!(payload.file_line_offsets, vec![0, 1]);
+        assert_eq!(payload.line_refs.len(), payload.highlighted_lines.len());
+        assert_eq!((payload.line_refs[0].origin, payload.line_refs[1].hunk_index), ('H', 1));
     }
 
-    (highlighted_lines, hunk_offsets)
-}
+    #[test]
+    fn base_branch_falls_back_to_master() {
+        let (_dir, repo) = init_test_repo();
+        let sig = git2::Signature::now("t", "t@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let oid = repo.commit(None, &sig, &sig, "init", &tree, &[]).unwrap();
+        let commit = repo.find_commit(oid).unwrap();
+        assert!(resolve_base(&repo, "trunk").is_err());
 
-/// Emits plain (non-syntect) lines for a hunk when no theme is available.
-///
-/// Fallback path used when ThemeSet contains no themes (unusual but possible).
-fn emit_plain_hunk_lines(lines: &[OwnedDiffLine], out: &mut Vec<Line<'static>>) {
-    for dl in lines {
-        let color = match dl.origin {
-            '+' => Color::Green,
-            '-' => Color::Red,
-            _ => Color::DarkGray,
+        repo.branch("master", &commit, false).unwrap();
+        repo.branch("trunk", &commit, false).unwrap();
+        let name = |base| resolve_base(&repo, base).map(|(name, _)| name).unwrap();
+        assert_eq!(name("trunk"), "trunk");
+        assert_eq!(name("develop"), "master");
+        assert_eq!(name("main"), "master");
+    }
+
+    #[test]
+    fn stash_mode_diffs_the_latest_stash() {
+        use std::path::Path;
+
+        let (tmp, mut repo) = init_test_repo();
+        let dir = tmp.path();
+        let sig = git2::Signature::now("t", "t@example.com").unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn a() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("lib.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
+        drop(tree);
+
+        let stash_files = |repo: &Repository| {
+            let diff =
+                get_diff_for_mode(repo, DiffMode::Stash, "main", &mut DiffOptions::new()).unwrap();
+            extract_files(&diff).into_iter().map(|f| f.path).collect::<Vec<_>>()
         };
-        let text = format!("{} {}", dl.origin, dl.content.trim_end_matches('\n'));
-        out.push(Line::from(vec![Span::styled(text, Style::default().fg(color))]));
+        assert!(stash_files(&repo).is_empty());
+
+        std::fs::write(dir.join("lib.rs"), "fn b() {}\n").unwrap();
+        repo.stash_save(&sig, "wip", None).unwrap();
+        assert_eq!(stash_files(&repo), vec!["lib.rs".to_owned()]);
     }
-}
 
-/// Extracts the file extension from a repository-relative path.
-///
-/// Returns "txt" if the path has no extension.
-fn file_ext(path: &str) -> &str {
-    path.rsplit('.').next().unwrap_or("txt")
+    #[test]
+    fn whitespace_only_changes_vanish_when_ignored() {
+        use std::path::Path;
+
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+        std::fs::write(dir.join("lib.rs"), "fn a() {\n    1\n}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("lib.rs")).unwrap();
+        index.write().unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn a() {\n\t1  \n}\n").unwrap();
+
+        let request = GitRequest::LoadDiff(DiffMode::Unstaged);
+        let hunks = |ignore_whitespace| {
+            let options =
+                WorkerOptions { ignore_whitespace, context_lines: 3, ..Default::default() };
+            let (_, diff) = resolve_request(&repo, &request, "main", &mut diff_options(&options));
+            let diff = diff.unwrap();
+            extract_hunks(&diff).0
+        };
+        assert_eq!(hunks(false).len(), 1);
+        assert!(hunks(true).is_empty());
+    }
+
+    #[test]
+    fn line_refs_are_parallel_to_highlighted_lines() {
+        let hunks = sample_hunks();
+        let unified = hunks_to_unified(&hunks);
+        let (lines, offsets) =
+            highlight_unified_diff(&unified, "rs", WordDiff::Word, SYNTECT_THEME);
+        let refs = line_refs_for_hunks(0, 0, &hunks);
+
+        assert_eq!(refs.len(), lines.len());
+        for offset in offsets {
+            assert_eq!(refs[offset].origin, 'H');
+        }
+    }
+
+    /// Cold vs. warm highlight cache on a 200-file diff: the first run highlights
+    /// every file, the second (a fresh `HighlightCache::load`, as on the next
+    /// launch) reads them all back from disk. Run by hand:
+    /// `cargo test --release -p airev -- --ignored bench_ --nocapture`.
+    #[test]
+    #[ignore = "benchmark; prints timings"]
+    fn bench_highlight_cache_cold_vs_warm() {
+        use std::path::Path;
+
+        let (tmp, repo) = init_test_repo();
+        let dir = tmp.path();
+        let source = |tag: &str| -> String {
+            (0..200).map(|n| format!("fn f{n}() -> u32 {{ let x = {n}; x + {tag} }}\n")).collect()
+        };
+        let mut index = repo.index().unwrap();
+        for file in 0..200 {
+            let name = format!("f{file}.rs");
+            std::fs::write(dir.join(&name), source("1")).unwrap();
+            index.add_path(Path::new(&name)).unwrap();
+        }
+        index.write().unwrap();
+        for file in 0..200 {
+            std::fs::write(dir.join(format!("f{file}.rs")), source("2")).unwrap();
+        }
+
+        let cache_path = dir.join("highlight-cache.json");
+        let options = WorkerOptions { highlight_cache_max_entries: 1000, ..Default::default() };
+        let run = || {
+            let diff = get_diff_for_mode(&repo, DiffMode::Unstaged, "main", &mut DiffOptions::new())
+                .unwrap();
+            // Loading the cache is part of the warm start's first paint.
+            let started = Instant::now();
+            let mut cache = HighlightCache::load(cache_path.clone(), 1000);
+            let payload =
+                process_diff(DiffMode::Unstaged, &diff, Some(&mut cache), &options, &[], &[]);
+            let elapsed = started.elapsed();
+            cache.save();
+            (elapsed, payload.highlighted_lines)
+        };
+        let (cold, cold_lines) = run();
+        let (warm, warm_lines) = run();
+        assert_eq!(cold_lines, warm_lines);
+        println!("{} lines: cold {cold:?}, warm {warm:?}", cold_lines.len());
+    }
 }
@@ -0,0 +1,542 @@
+//! Remappable Normal-mode key bindings.
+//!
+//! A [`KeyMap`] binds each [`Action`] to a list of [`Key`]s. The defaults are
+//! the built-in keys; the config's `[keybindings]` table replaces them per
+//! action (see [`KeyMap::from_table`]):
+//!
+//! ```toml
+//! [keybindings]
+//! scroll_down = ["j", "down"]
+//! next_hunk = "ctrl-n"
+//! ```
+//!
+//! Several actions may share a key when they apply in different places: `f`
+//! filters paths in the file list and cycles the comment filter elsewhere.
+//! `ui::keybindings` tries the actions bound to a key in [`Action::ALL`] order
+//! and runs the first that applies. Count digits and the `g` / `z` prefixes of
+//! two-key sequences are fixed, as are the keys of the other modes.
+
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A Normal-mode command that can be bound to keys. Each variant is named
+/// after its `[keybindings]` key (see [`Action::name`]); its default keys are
+/// noted in parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Scroll down one line, or select the next comment in the comments panel (`j`).
+    ScrollDown,
+    /// Scroll up one line, or select the previous comment in the comments panel (`k`).
+    ScrollUp,
+    /// Jump to the bottom of the focused panel (`G`).
+    ScrollBottom,
+    /// Scroll half a page down (`Ctrl-d`).
+    HalfPageDown,
+    /// Scroll half a page up (`Ctrl-u`).
+    HalfPageUp,
+    /// Scroll a full page down (`Ctrl-f`).
+    PageDown,
+    /// Scroll a full page up (`Ctrl-b`).
+    PageUp,
+    /// Jump the diff to the file selected in the file list (`Enter`, `l`).
+    JumpToFile,
+    /// Toggle the selected file's reviewed mark (`r`).
+    ToggleReviewed,
+    /// Start fuzzy-filtering the file list by path (`f` in the file list).
+    FilterFiles,
+    /// Mark every file under the selected file's directory reviewed (`D`).
+    MarkDirectoryReviewed,
+    /// Switch to the next diff mode (`Tab`).
+    CycleDiffMode,
+    /// Move panel focus left (`H`).
+    FocusLeft,
+    /// Move panel focus right (`L`).
+    FocusRight,
+    /// Select the previous file (`{`).
+    PrevFile,
+    /// Select the next file (`}`).
+    NextFile,
+    /// Move the diff cursor to the previous hunk (`[`).
+    PrevHunk,
+    /// Move the diff cursor to the next hunk (`]`).
+    NextHunk,
+    /// Cycle old only / new only / both for the `-`/`+` pair at the cursor (`o`).
+    TogglePairView,
+    /// Show or hide the blame column for old-side lines (`a`).
+    ToggleBlame,
+    /// Start or stop ignoring whitespace changes (`w`).
+    ToggleWhitespace,
+    /// Show one fewer context line around each change (`-`).
+    FewerContext,
+    /// Show one more context line around each change (`+`).
+    MoreContext,
+    /// Show or hide the line-number gutter (`#`).
+    ToggleLineNumbers,
+    /// Fold or unfold the hunk whose header is under the cursor (`Enter`).
+    ToggleFold,
+    /// Resolve or reopen the selected comment (`x` in the comments panel).
+    ToggleResolved,
+    /// Hide or show resolved comments (`X`).
+    HideResolved,
+    /// Dismiss the drifted-HEAD banner (`x`).
+    DismissBanner,
+    /// Restart a stopped git worker, or retry an unavailable repository (`R`).
+    RestartWorker,
+    /// Comment on the selected file, or on the hunk whose header is under the cursor (`c`).
+    Comment,
+    /// Comment on the hunk under the diff cursor (`C`).
+    CommentHunk,
+    /// Comment on the diff cursor line (`i`).
+    CommentLine,
+    /// Export the session's comments as Markdown (`E`).
+    ExportComments,
+    /// Cycle the comment filter: all, critical and major, critical (`f`).
+    CycleCommentFilter,
+    /// Show only one severity of comments, cycling through them (`!`).
+    CycleSeverityFilter,
+    /// Hide or show both side panels (`F`).
+    ToggleDiffOnly,
+    /// Shrink the diff panel in favour of the side panels (`<`).
+    ShrinkDiff,
+    /// Grow the diff panel at the side panels' expense (`>`).
+    GrowDiff,
+    /// Open the `:` command line (`:`).
+    Command,
+    /// Start a `/` search of the diff text (`/`).
+    Search,
+    /// Move to the next search match (`n`).
+    SearchNext,
+    /// Move to the previous search match (`N`).
+    SearchPrev,
+    /// Open the cursor's file at its line in the external editor (`e`).
+    OpenEditor,
+    /// Copy `path:line` of the cursor line (`y`).
+    CopyReference,
+    /// Copy a GitHub permalink to the cursor line (`Y`).
+    CopyPermalink,
+    /// Open the diff-mode picker (`m`).
+    PickMode,
+    /// Switch to the next built-in color theme (`T`).
+    CycleTheme,
+    /// Open the help overlay (`?`).
+    Help,
+    /// Open the debug overlay with the git worker's timing (`F12`).
+    DebugOverlay,
+    /// Quit, confirming first if a comment draft is unsaved (`q`, `Esc`).
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order the actions sharing a key are tried: the
+    /// file-list actions come before the ones they shadow there, and resolving a
+    /// comment before dismissing the drift banner.
+    pub const ALL: [Action; 50] = [
+        Action::ScrollDown,
+        Action::ScrollUp,
+        Action::ScrollBottom,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::PageDown,
+        Action::PageUp,
+        Action::JumpToFile,
+        Action::ToggleReviewed,
+        Action::FilterFiles,
+        Action::MarkDirectoryReviewed,
+        Action::CycleDiffMode,
+        Action::FocusLeft,
+        Action::FocusRight,
+        Action::PrevFile,
+        Action::NextFile,
+        Action::PrevHunk,
+        Action::NextHunk,
+        Action::TogglePairView,
+        Action::ToggleBlame,
+        Action::ToggleWhitespace,
+        Action::FewerContext,
+        Action::MoreContext,
+        Action::ToggleLineNumbers,
+        Action::ToggleFold,
+        Action::ToggleResolved,
+        Action::HideResolved,
+        Action::DismissBanner,
+        Action::RestartWorker,
+        Action::Comment,
+        Action::CommentHunk,
+        Action::CommentLine,
+        Action::ExportComments,
+        Action::CycleCommentFilter,
+        Action::CycleSeverityFilter,
+        Action::ToggleDiffOnly,
+        Action::ShrinkDiff,
+        Action::GrowDiff,
+        Action::Command,
+        Action::Search,
+        Action::SearchNext,
+        Action::SearchPrev,
+        Action::OpenEditor,
+        Action::CopyReference,
+        Action::CopyPermalink,
+        Action::PickMode,
+        Action::CycleTheme,
+        Action::Help,
+        Action::DebugOverlay,
+        Action::Quit,
+    ];
+
+    /// The action's key in the `[keybindings]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollBottom => "scroll_bottom",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::JumpToFile => "jump_to_file",
+            Action::ToggleReviewed => "toggle_reviewed",
+            Action::FilterFiles => "filter_files",
+            Action::MarkDirectoryReviewed => "mark_directory_reviewed",
+            Action::CycleDiffMode => "cycle_diff_mode",
+            Action::FocusLeft => "focus_left",
+            Action::FocusRight => "focus_right",
+            Action::PrevFile => "prev_file",
+            Action::NextFile => "next_file",
+            Action::PrevHunk => "prev_hunk",
+            Action::NextHunk => "next_hunk",
+            Action::TogglePairView => "toggle_pair_view",
+            Action::ToggleBlame => "toggle_blame",
+            Action::ToggleWhitespace => "toggle_whitespace",
+            Action::FewerContext => "fewer_context",
+            Action::MoreContext => "more_context",
+            Action::ToggleLineNumbers => "toggle_line_numbers",
+            Action::ToggleFold => "toggle_fold",
+            Action::ToggleResolved => "toggle_resolved",
+            Action::HideResolved => "hide_resolved",
+            Action::DismissBanner => "dismiss_banner",
+            Action::RestartWorker => "restart_worker",
+            Action::Comment => "comment",
+            Action::CommentHunk => "comment_hunk",
+            Action::CommentLine => "comment_line",
+            Action::ExportComments => "export_comments",
+            Action::CycleCommentFilter => "cycle_comment_filter",
+            Action::CycleSeverityFilter => "cycle_severity_filter",
+            Action::ToggleDiffOnly => "toggle_diff_only",
+            Action::ShrinkDiff => "shrink_diff",
+            Action::GrowDiff => "grow_diff",
+            Action::Command => "command",
+            Action::Search => "search",
+            Action::SearchNext => "search_next",
+            Action::SearchPrev => "search_prev",
+            Action::OpenEditor => "open_editor",
+            Action::CopyReference => "copy_reference",
+            Action::CopyPermalink => "copy_permalink",
+            Action::PickMode => "pick_mode",
+            Action::CycleTheme => "cycle_theme",
+            Action::Help => "help",
+            Action::DebugOverlay => "debug_overlay",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// Looks up an action by its [`Action::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// The built-in keys for the action.
+    fn default_keys(self) -> Vec<Key> {
+        match self {
+            Action::ScrollDown => vec![Key::char('j')],
+            Action::ScrollUp => vec![Key::char('k')],
+            Action::ScrollBottom => vec![Key::char('G')],
+            Action::HalfPageDown => vec![Key::ctrl('d')],
+            Action::HalfPageUp => vec![Key::ctrl('u')],
+            Action::PageDown => vec![Key::ctrl('f')],
+            Action::PageUp => vec![Key::ctrl('b')],
+            Action::JumpToFile => vec![Key::plain(KeyCode::Enter), Key::char('l')],
+            Action::ToggleReviewed => vec![Key::char('r')],
+            Action::FilterFiles => vec![Key::char('f')],
+            Action::MarkDirectoryReviewed => vec![Key::char('D')],
+            Action::CycleDiffMode => vec![Key::plain(KeyCode::Tab)],
+            Action::FocusLeft => vec![Key::char('H')],
+            Action::FocusRight => vec![Key::char('L')],
+            Action::PrevFile => vec![Key::char('{')],
+            Action::NextFile => vec![Key::char('}')],
+            Action::PrevHunk => vec![Key::char('[')],
+            Action::NextHunk => vec![Key::char(']')],
+            Action::TogglePairView => vec![Key::char('o')],
+            Action::ToggleBlame => vec![Key::char('a')],
+            Action::ToggleWhitespace => vec![Key::char('w')],
+            Action::FewerContext => vec![Key::char('-')],
+            Action::MoreContext => vec![Key::char('+')],
+            Action::ToggleLineNumbers => vec![Key::char('#')],
+            Action::ToggleFold => vec![Key::plain(KeyCode::Enter)],
+            Action::ToggleResolved => vec![Key::char('x')],
+            Action::HideResolved => vec![Key::char('X')],
+            Action::DismissBanner => vec![Key::char('x')],
+            Action::RestartWorker => vec![Key::char('R')],
+            Action::Comment => vec![Key::char('c')],
+            Action::CommentHunk => vec![Key::char('C')],
+            Action::CommentLine => vec![Key::char('i')],
+            Action::ExportComments => vec![Key::char('E')],
+            Action::CycleCommentFilter => vec![Key::char('f')],
+            Action::CycleSeverityFilter => vec![Key::char('!')],
+            Action::ToggleDiffOnly => vec![Key::char('F')],
+            Action::ShrinkDiff => vec![Key::char('<')],
+            Action::GrowDiff => vec![Key::char('>')],
+            Action::Command => vec![Key::char(':')],
+            Action::Search => vec![Key::char('/')],
+            Action::SearchNext => vec![Key::char('n')],
+            Action::SearchPrev => vec![Key::char('N')],
+            Action::OpenEditor => vec![Key::char('e')],
+            Action::CopyReference => vec![Key::char('y')],
+            Action::CopyPermalink => vec![Key::char('Y')],
+            Action::PickMode => vec![Key::char('m')],
+            Action::CycleTheme => vec![Key::char('T')],
+            Action::Help => vec![Key::char('?')],
+            Action::DebugOverlay => vec![Key::plain(KeyCode::F(12))],
+            Action::Quit => vec![Key::char('q'), Key::plain(KeyCode::Esc)],
+        }
+    }
+}
+
+/// A key with its Ctrl / Alt modifiers.
+///
+/// Shift is not stored: it is part of the character (`G`), and terminals
+/// disagree on whether they also report it as a modifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    /// The key itself.
+    pub code: KeyCode,
+    /// `CONTROL` and / or `ALT`, or `NONE`.
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// An unmodified key.
+    const fn plain(code: KeyCode) -> Self {
+        Self { code, modifiers: KeyModifiers::NONE }
+    }
+
+    /// An unmodified character key.
+    const fn char(c: char) -> Self {
+        Self::plain(KeyCode::Char(c))
+    }
+
+    /// A character key with Ctrl held.
+    const fn ctrl(c: char) -> Self {
+        Self { code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL }
+    }
+
+    /// Parses a key as written in the config: a single character (`"j"`, `"G"`,
+    /// `"-"`), or a name (`"enter"`, `"esc"`, `"tab"`, `"backtab"`, `"space"`,
+    /// `"backspace"`, `"delete"`, `"up"`, `"down"`, `"left"`, `"right"`,
+    /// `"home"`, `"end"`, `"pageup"`, `"pagedown"`, `"f1"`..`"f12"`), each
+    /// optionally prefixed with `ctrl-` and / or `alt-`. Names and modifiers are
+    /// case-insensitive; single characters are not.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        // A trailing `-` is the key itself (`"-"`, `"ctrl--"`), not a separator.
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                _ => return None,
+            };
+            rest = key;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => {
+                    let n = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok());
+                    KeyCode::F(n.filter(|n| (1..=12).contains(n))?)
+                }
+            },
+        };
+        Some(Self { code, modifiers })
+    }
+
+    /// Whether a received key event is this key, ignoring Shift.
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        self.code == event.code
+            && self.modifiers == event.modifiers.difference(KeyModifiers::SHIFT)
+    }
+}
+
+impl fmt::Display for Key {
+    /// Formats the key as the help overlay shows it: `j`, `Ctrl-d`, `Enter`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => f.write_str("Shift-Tab"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// The keys bound to every [`Action`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    /// One entry per action, in [`Action::ALL`] order.
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for KeyMap {
+    /// The built-in bindings.
+    fn default() -> Self {
+        let bindings = Action::ALL.into_iter().map(|a| (a, a.default_keys())).collect();
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    /// Builds the key map from the config's `[keybindings]` table.
+    ///
+    /// Each key names an action and holds a key or a list of keys (an empty
+    /// list unbinds the action), replacing its defaults. A key bound this way
+    /// is also taken from the actions still on their defaults, so binding
+    /// `Ctrl-d` to `scroll_down` does not leave it on `half_page_down` too.
+    /// Unknown actions and unparseable keys are noted in `warnings` and keep
+    /// the defaults.
+    pub fn from_table(table: &toml::Table, warnings: &mut Vec<String>) -> Self {
+        let mut overrides: Vec<(Action, Vec<Key>)> = Vec::new();
+        for (name, value) in table {
+            let Some(action) = Action::from_name(name) else {
+                warnings.push(format!("ignoring unknown keybinding action '{name}'"));
+                continue;
+            };
+            let keys: Option<Vec<Key>> = match value {
+                toml::Value::String(s) => Key::parse(s).map(|key| vec![key]),
+                toml::Value::Array(a) => {
+                    a.iter().map(|v| v.as_str().and_then(Key::parse)).collect()
+                }
+                _ => None,
+            };
+            match keys {
+                Some(keys) => overrides.push((action, keys)),
+                None => warnings.push(format!(
+                    "ignoring keybinding '{name}': expected a key such as \"j\", \
+                     \"ctrl-d\", or \"enter\", or a list of them"
+                )),
+            }
+        }
+        let mut map = Self::default();
+        for (action, keys) in &mut map.bindings {
+            match overrides.iter().find(|(overridden, _)| *overridden == *action) {
+                Some((_, bound)) => *keys = bound.clone(),
+                None => {
+                    keys.retain(|key| !overrides.iter().any(|(_, bound)| bound.contains(key)))
+                }
+            }
+        }
+        map
+    }
+
+    /// The actions bound to the received key, in [`Action::ALL`] order.
+    pub fn actions_for(&self, event: &KeyEvent) -> Vec<Action> {
+        self.bindings
+            .iter()
+            .filter(|(_, keys)| keys.iter().any(|key| key.matches(event)))
+            .map(|(action, _)| *action)
+            .collect()
+    }
+
+    /// The keys bound to `action`, for the help overlay: `"q / Esc"`, or
+    /// `"(unbound)"` when there are none.
+    pub fn label(&self, action: Action) -> String {
+        let keys = self
+            .bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default();
+        if keys.is_empty() {
+            return "(unbound)".to_owned();
+        }
+        keys.iter().map(Key::to_string).collect::<Vec<_>>().join(" / ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn keys_parse_and_display() {
+        assert_eq!(Key::parse("j"), Some(Key::char('j')));
+        assert_eq!(Key::parse("-"), Some(Key::char('-')));
+        assert_eq!(Key::parse("Ctrl-D"), Some(Key::ctrl('D')));
+        assert_eq!(Key::parse("ctrl--").map(|k| k.to_string()).as_deref(), Some("Ctrl--"));
+        assert_eq!(Key::parse("alt-enter").map(|k| k.to_string()).as_deref(), Some("Alt-Enter"));
+        assert_eq!(Key::parse("f12"), Some(Key::plain(KeyCode::F(12))));
+        assert_eq!(Key::parse("f13"), None);
+        assert_eq!(Key::parse("hyper-x"), None);
+        assert_eq!(Key::parse(""), None);
+
+        // Shift arrives with capitals on some terminals; it never changes the match.
+        assert!(Key::char('G').matches(&event(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert!(!Key::char('j').matches(&event(KeyCode::Char('j'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn configured_keys_replace_defaults_and_leave_other_actions() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            scroll_down = ["ctrl-d", "down"]
+            search = []
+            no_such_action = "x"
+            next_hunk = "ctrl-nope"
+            "#,
+        )
+        .unwrap();
+        let mut warnings = Vec::new();
+        let map = KeyMap::from_table(&table, &mut warnings);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+
+        let ctrl_d = event(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(map.actions_for(&ctrl_d), vec![Action::ScrollDown]);
+        assert_eq!(map.label(Action::ScrollDown), "Ctrl-d / Down");
+        assert_eq!(map.label(Action::HalfPageDown), "(unbound)");
+        assert_eq!(map.label(Action::Search), "(unbound)");
+        assert_eq!(map.label(Action::NextHunk), "]");
+        assert!(map.actions_for(&event(KeyCode::Char('j'), KeyModifiers::NONE)).is_empty());
+
+        // Keys shared by default stay shared; the dispatcher picks by context.
+        let f = event(KeyCode::Char('f'), KeyModifiers::NONE);
+        assert_eq!(map.actions_for(&f), vec![Action::FilterFiles, Action::CycleCommentFilter]);
+    }
+}
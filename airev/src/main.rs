@@ -1,21 +1,29 @@
 //! airev — AI-assisted code review TUI.
 //!
-//! Entry point for the `airev` binary. Wires together the terminal lifecycle
-//! (`tui`), unified event bus (`event`), placeholder UI (`ui`), theme system
-//! (`theme`), and the shared WAL-mode SQLite database (`airev-core`).
+//! Entry point for the `airev` binary. Parses arguments (`cli`) — `airev json`
+//! prints the diff and exits without a TUI — then wires together the terminal
+//! lifecycle (`tui`), unified event bus (`event`), placeholder UI (`ui`), user config
+//! (`config`), theme system (`theme`), and the shared WAL-mode SQLite database
+//! (`airev-core`).
 //!
 //! # Startup sequence (order matters — see RESEARCH.md Pitfall 6)
 //!
-//! 1. Load theme from XDG config — read-only, safe before terminal init.
+//! 1. Load config and theme from XDG config — read-only, safe before terminal init.
 //! 2. `install_panic_hook()` — installed first so it is the innermost hook.
 //!    Restores the terminal before the panic message prints.
-//! 3. `register_sigterm()` — returns `Arc<AtomicBool>` polled in the event loop.
-//! 4. `init_tui()` — enters alternate screen and enables raw mode.
+//! 3. `register_sigterm()` — returns `Arc<AtomicBool>` polled in the event loop;
+//!    `register_sighup()` likewise, for reloading the config file.
+//! 4. `init_tui()` — enables raw mode and enters the alternate screen (or an
+//!    inline viewport with `alt_screen = false` / `--no-alt-screen`).
 //! 5. Create event channel, `spawn_event_task()`, and store `event_tx` in AppState.
 //! 6. Discover git repository (needed for session detection in Step 7).
-//! 7. `create_dir_all(".airev")` + `open_db()` + `detect_or_create_session()` +
-//!    `load_file_review_state()` — all before first frame (no loading spinner).
-//! 8. Spawn AsyncGit background thread.
+//! 7. `create_dir_all(".airev")` + `open_db()`, then `session::spawn_open_session()`
+//!    detects the session and loads its review state and comments in the
+//!    background while the first frame shows "opening session…". A DB that cannot
+//!    be opened is shown in the startup error overlay and the review continues
+//!    without persistence.
+//! 8. Start the work-tree `watcher` and spawn the AsyncGit background thread, or
+//!    show the precomputed `--files` diff.
 //!
 //! # Safety
 //!
@@ -25,129 +33,246 @@
 //! and reach `restore_tui()` after `break`. The panic hook covers unexpected panics.
 
 mod app;
+mod cli;
+mod config;
+mod editor;
 mod event;
 mod git;
+mod keymap;
+mod session;
 mod theme;
 mod tui;
 mod ui;
+mod watcher;
 
 use std::sync::atomic::Ordering;
 
-use ui::keybindings::{handle_key, handle_mouse, KeyAction};
-
-/// Returns the path to the airev config file.
-///
-/// Prefers `$XDG_CONFIG_HOME/airev/config.toml`; falls back to
-/// `~/.config/airev/config.toml` when the env var is absent.
-fn config_path() -> std::path::PathBuf {
-    let base = std::env::var("XDG_CONFIG_HOME")
-        .ok()
-        .map(std::path::PathBuf::from)
-        .or_else(|| {
-            std::env::var("HOME")
-                .ok()
-                .map(|h| std::path::PathBuf::from(h).join(".config"))
-        })
-        .unwrap_or_else(|| std::path::PathBuf::from(".config"));
-    base.join("airev").join("config.toml")
-}
+use ui::keybindings::{handle_key, handle_mouse, persist_diff_range, persist_reviewed, KeyAction};
 
-/// Loads the theme name from `~/.config/airev/config.toml`.
-///
-/// Returns `"dark"` if the file does not exist, cannot be parsed, or has no
-/// `theme` key. Never panics — config errors are soft failures printed to stderr.
-fn load_theme_name() -> String {
-    let path = config_path();
-    let raw = match std::fs::read_to_string(&path) {
-        Ok(s) => s,
-        Err(_) => return "catppuccin-mocha".to_owned(),
-    };
-    let table: toml::Table = match toml::from_str(&raw) {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("airev: config parse error in {:?}: {}", path, e);
-            return "catppuccin-mocha".to_owned();
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let started = std::time::Instant::now();
+    // Step 0: load config — read-only, safe before terminal init. A repo-local
+    // .airev/config.toml is layered over the global file.
+    let config = config::load_config();
+
+    // Headless subcommands run before anything touches the terminal. A `--files`
+    // pair is read and diffed here too, so an unreadable file fails before the TUI.
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("airev: {msg}\n\n{}", cli::USAGE);
+            std::process::exit(2);
         }
     };
-    table
-        .get("theme")
-        .and_then(|v| v.as_str())
-        .unwrap_or("catppuccin-mocha")
-        .to_owned()
-}
+    let alt_screen = config.alt_screen && !args.no_alt_screen;
+    let mut file_pair: Option<(String, git::types::GitResultPayload)> = None;
+    let mut cli_range: Option<(String, String)> = None;
+    match args.command {
+        cli::Command::Tui => {}
+        cli::Command::Range { from, to } => cli_range = Some((from, to)),
+        cli::Command::Files { old, new } => {
+            let (word_diff, theme) = (config.word_diff, &config.syntax_theme);
+            match git::loose::diff_file_pair(&old, &new, word_diff, theme, config.color_moved) {
+                Ok(payload) => {
+                    let args = format!("{} {}", old.display(), new.display());
+                    file_pair = Some((args, payload));
+                }
+                Err(e) => {
+                    eprintln!("airev: cannot diff {} and {}: {e}", old.display(), new.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        cli::Command::Json(request) => {
+            std::process::exit(cli::run_json(request, &config.base_branch))
+        }
+        cli::Command::Help => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+    }
 
-#[tokio::main]
-async fn main() -> std::io::Result<()> {
-    // Step 0: load theme from config — read-only, safe before terminal init.
-    let theme = theme::Theme::from_name(&load_theme_name());
-    let mut state = app::AppState::default();
+    // Pick the theme from config — read-only, safe before terminal init.
+    // Without an explicit theme, follow the terminal background from COLORFGBG.
+    // RGB colors are approximated unless the terminal announces truecolor.
+    let theme = match config.theme.as_deref() {
+        Some(name) => theme::Theme::from_name(name, &config.custom_themes),
+        None => theme::Theme::from_background(std::env::var("COLORFGBG").ok().as_deref()),
+    };
+    let truecolor = config.force_truecolor || theme::supports_truecolor();
+    let shown_theme = theme.clone().for_terminal(truecolor);
+    if shown_theme != theme {
+        eprintln!(
+            "airev: COLORTERM does not report truecolor, approximating theme colors \
+             (set force_truecolor = true to keep them)"
+        );
+    }
+    let mut state = app::AppState {
+        theme: shown_theme,
+        truecolor,
+        theme_name: config
+            .theme
+            .as_deref()
+            .and_then(|name| theme::Theme::find(name, &config.custom_themes))
+            .map(|(name, _)| name),
+        custom_themes: config.custom_themes.clone(),
+        wheel_scroll_lines: config.scroll_lines,
+        natural_scroll: config.natural_scroll,
+        diff_mode: config.default_diff_mode,
+        max_diff_width: config.max_diff_width,
+        diff_only: args.diff_only,
+        pending_jump: args.file.map(|path| (path, args.line)),
+        editor_command: config.editor_command.clone(),
+        editor_override: args.editor_command,
+        editor_suspend: config.editor_suspend,
+        show_quit_summary: config.quit_summary,
+        cursor_line: config.cursor_line,
+        note_ref: config.note_ref.clone(),
+        note_replace: config.note_replace,
+        keymap: config.keybindings.clone(),
+        layout_orientation: config.layout,
+        status_segments: config.status_segments.clone(),
+        auto_refresh_secs: config.auto_refresh_secs,
+        context_lines: config.context_lines,
+        base_branch: config.base_branch.clone(),
+        ..app::AppState::default()
+    };
+    if let Some([left, center, right]) = config.panel_widths {
+        if let Err(e) = state.set_panel_percentages(left, center, right) {
+            eprintln!("airev: ignoring config key 'panel_widths': {e}");
+        }
+    }
 
     // Step 1: panic hook installed first — innermost hook restores terminal.
     tui::install_panic_hook();
 
-    // Step 2: SIGTERM flag — polled in the 50ms heartbeat arm below.
+    // Step 2: SIGTERM flag — polled in the 50ms heartbeat arm below. SIGHUP
+    // (config reload) is polled there too.
     let term_flag = tui::register_sigterm();
+    let reload_flag = tui::register_sighup();
 
-    // Step 3: enter alternate screen and raw mode.
-    let mut terminal = tui::init_tui()?;
+    // Step 3: enter raw mode and the alternate screen (or an inline viewport).
+    let mut terminal = tui::init_tui(alt_screen)?;
 
     // Step 4: create event channel and spawn the background event task.
     let handler = event::EventHandler::new();
     // Store event_tx in AppState so keybindings.rs can send DB results back.
     state.event_tx = Some(handler.tx.clone());
-    event::spawn_event_task(handler.tx.clone());
+    let (render_ms, tick_ms) = (config.render_ms, config.tick_ms);
+    let mut event_task = event::spawn_event_task(handler.tx.clone(), render_ms, tick_ms);
     let mut rx = handler.rx;
 
     // Step 5: discover git repository (needed for session detection in Step 6).
-    // Walk parent directories from cwd. If no repo found, diff panel shows placeholder.
-    let maybe_repo_path: Option<String> = git2::Repository::discover(".")
-        .ok()
-        .and_then(|r| {
-            r.workdir()
-                .or_else(|| Some(r.path()))
-                .map(|p| p.to_string_lossy().into_owned())
-        });
+    // Walk parent directories from cwd. A failed probe opens the repo-error overlay
+    // straight away instead of leaving a blank diff panel.
+    // A `--files` review never touches the repository.
+    let maybe_repo_path: Option<String> = match &file_pair {
+        Some(_) => None,
+        None => match git::discover_repo_path() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                state.push_startup_error(app::StartupError {
+                    title: "Cannot open repository".to_owned(),
+                    message: format!("not a git repository: {}\n\n{}", cwd.display(), e.message()),
+                    continue_label: "continue without git (airev --files OLD NEW)",
+                });
+                None
+            }
+        },
+    };
     let repo_path_for_session = maybe_repo_path.as_deref().unwrap_or(".");
+    state.web_url = maybe_repo_path.as_deref().and_then(git::remote::origin_web_url);
 
-    // Step 6: open DB, detect/create session, load review state — all before first frame.
-    // Requirements: "no loading spinner; all reads complete before the first frame."
-    std::fs::create_dir_all(".airev")?;
-    let db_conn = airev_core::db::open_db(".airev/reviews.db")
-        .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-
-    // Detect or create session for this repo + diff mode combination.
-    let diff_mode_str = format!("{:?}", state.diff_mode);
-    let session = airev_core::db::detect_or_create_session(
-        &db_conn,
-        repo_path_for_session,
-        &diff_mode_str,
-        "",
-    )
-    .await
-    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-
-    let review_states = airev_core::db::load_file_review_state(&db_conn, &session.id)
-        .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-
-    state.db_conn = Some(db_conn);
-    state.file_review_states = review_states.into_iter().collect();
-    state.session = Some(session);
+    // Step 6: open DB, then load the session in the background (see `session`).
+    // A DB that cannot be opened (e.g. a read-only directory) is reported in the
+    // startup error overlay; the review then runs without persistence.
+    let db_conn = match std::fs::create_dir_all(".airev") {
+        Ok(()) => airev_core::db::open_db(".airev/reviews.db")
+            .await
+            .map_err(|e| format!("{e}\n\n{}", e.hint())),
+        Err(e) => Err(format!("cannot create .airev/: {e}")),
+    };
+    let db_conn = match db_conn {
+        Ok(conn) => Some(conn),
+        Err(message) => {
+            state.push_startup_error(app::StartupError {
+                title: "Cannot open review database".to_owned(),
+                message: format!(".airev/reviews.db: {message}"),
+                continue_label: "continue without saving comments or review state",
+            });
+            None
+        }
+    };
+
+    // A `--files` review gets its own session per file pair, keyed by the two paths,
+    // and a `--range` review the session of that range.
+    if file_pair.is_some() {
+        state.diff_mode = git::types::DiffMode::Files;
+    }
+    let range_args = cli_range.as_ref().map(|(from, to)| format!("{from}..{to}"));
+    if cli_range.is_some() {
+        state.diff_mode = git::types::DiffMode::CommitRange;
+        state.diff_range = cli_range;
+    }
+
+    // Session detection runs on a task so the first frame is not held up by a
+    // slow disk; the session is applied when its `DbResult` arrives.
+    if let Some(db_conn) = db_conn {
+        let request = session::SessionRequest {
+            repo_path: repo_path_for_session.to_owned(),
+            default_mode: state.diff_mode,
+            diff_args: match (&file_pair, &range_args) {
+                (Some((args, _)), _) => args.clone(),
+                (None, Some(range)) => range.clone(),
+                (None, None) => String::new(),
+            },
+            remember_mode: file_pair.is_none() && range_args.is_none(),
+            head_sha: maybe_repo_path.as_deref().and_then(git::head_sha),
+        };
+        session::spawn_open_session(db_conn.clone(), request, handler.tx.clone());
+        state.db_conn = Some(db_conn);
+        state.session_loading = true;
+    }
 
     // Step 7: spawn AsyncGit background thread and request initial diff.
-    let maybe_git: Option<crate::git::AsyncGit> = maybe_repo_path.map(|path| {
-        let git = crate::git::AsyncGit::new(handler.tx.clone(), path);
-        // Send the initial diff request immediately so the panel populates at startup.
-        state.diff_loading = true;
-        git.load_diff(crate::git::types::GitRequest::LoadDiff(
-            crate::git::types::DiffMode::Unstaged,
-        ));
-        git
+    let worker_options = crate::git::types::WorkerOptions {
+        highlight_cache_path: config
+            .highlight_cache
+            .then(|| std::path::PathBuf::from(".airev/highlight-cache.json")),
+        highlight_cache_max_entries: config.highlight_cache_max_entries,
+        word_diff: config.word_diff,
+        syntax_theme: config.syntax_theme.clone(),
+        detect_generated: config.detect_generated,
+        color_moved: config.color_moved,
+        full_file_highlight: config.full_file_highlight,
+        ignore_whitespace: false,
+        context_lines: config.context_lines,
+        base_branch: config.base_branch.clone(),
+    };
+    let mut maybe_git: Option<crate::git::AsyncGit> = maybe_repo_path.clone().map(|path| {
+        crate::git::AsyncGit::new(handler.tx.clone(), path, worker_options)
+    });
+
+    // Refresh the diff when the work tree changes. Without a watcher (e.g. the
+    // inotify limit is reached) the auto-refresh timer still picks changes up.
+    let _watcher = maybe_repo_path.as_deref().and_then(|path| {
+        watcher::spawn_watcher(std::path::Path::new(path), handler.tx.clone())
+            .map_err(|e| state.push_notification(format!("file watcher unavailable: {e}")))
+            .ok()
     });
 
-    // Store the request sender in AppState so keybindings.rs can send requests.
-    state.git_tx = maybe_git.as_ref().map(|g| g.request_tx.clone());
+    // Store the request sender in AppState so keybindings.rs can send requests,
+    // and send the initial diff request so the panel populates at startup.
+    match (maybe_git.as_ref(), file_pair) {
+        (Some(git), _) => state.attach_git_worker(git),
+        (None, Some((_, payload))) => state.apply_git_result(payload),
+        (None, None) => state.diff_status = crate::git::types::DiffStatus::NoRepo,
+    }
+
+    // Only a quit key prints the summary; SIGTERM and a closed channel do not.
+    let mut quit_by_key = false;
 
     // Event loop — exits only via `break`, never via `?`.
     // This guarantees `restore_tui()` is always reached after the loop.
@@ -161,38 +286,140 @@ async fn main() -> std::io::Result<()> {
                 if term_flag.load(Ordering::Relaxed) {
                     break 'event_loop;
                 }
+                // A config that fails to reload leaves the running settings untouched.
+                if reload_flag.swap(false, Ordering::Relaxed) {
+                    let local = std::path::Path::new(config::LOCAL_CONFIG_PATH);
+                    match config::reload_config(&config::config_path(), local) {
+                        Ok(config) => state.apply_config(&config),
+                        Err(message) => state.push_startup_error(app::StartupError {
+                            title: "Cannot reload config".to_owned(),
+                            message,
+                            continue_label: "keep the previous settings",
+                        }),
+                    }
+                    handler.tx.send(event::AppEvent::Render).ok();
+                }
             }
             maybe_event = rx.recv() => {
                 match maybe_event {
                     Some(event::AppEvent::Render) => {
                         // Exactly one draw() call per Render event — never elsewhere.
-                        terminal.draw(|frame| ui::render(frame, &mut state, &theme))?;
+                        terminal.draw(|frame| ui::render(frame, &mut state))?;
+                        // Startup-to-first-frame, shown in the debug overlay.
+                        state.first_frame_ms.get_or_insert(started.elapsed().as_millis() as u64);
+                        // Blame is fetched for the lines this draw showed.
+                        state.request_visible_blame();
                     }
                     Some(event::AppEvent::Key(key)) => {
                         match handle_key(key, &mut state) {
-                            KeyAction::Quit => break 'event_loop,
+                            KeyAction::Quit => {
+                                quit_by_key = true;
+                                break 'event_loop;
+                            }
+                            KeyAction::RestartGitWorker => {
+                                if let (Some(git), Some(path)) =
+                                    (maybe_git.take(), maybe_repo_path.clone())
+                                {
+                                    let git = git.respawn(path);
+                                    state.attach_git_worker(&git);
+                                    maybe_git = Some(git);
+                                }
+                            }
+                            KeyAction::OpenEditor => {
+                                match editor::launch_for(&state, maybe_repo_path.as_deref()) {
+                                    Err(message) => state.push_notification(message),
+                                    Ok(editor::Launch::Detached(command)) => {
+                                        if let Err(e) = editor::spawn_detached(command) {
+                                            let message = format!("cannot start editor: {e}");
+                                            state.push_notification(message);
+                                        }
+                                    }
+                                    Ok(editor::Launch::Suspended(mut command)) => {
+                                        // The event task must not read the editor's keys.
+                                        event_task.abort();
+                                        let _ = (&mut event_task).await;
+                                        let Ok((tui, status)) =
+                                            tui::suspend(alt_screen, || command.status())
+                                        else {
+                                            break 'event_loop;
+                                        };
+                                        terminal = tui;
+                                        event_task = event::spawn_event_task(
+                                            handler.tx.clone(),
+                                            render_ms,
+                                            tick_ms,
+                                        );
+                                        let message = match status {
+                                            Ok(status) if !status.success() => {
+                                                format!("editor exited with {status}")
+                                            }
+                                            Ok(_) => String::new(),
+                                            Err(e) => format!("cannot start editor: {e}"),
+                                        };
+                                        if !message.is_empty() {
+                                            state.push_notification(message);
+                                        }
+                                    }
+                                }
+                            }
                             KeyAction::Continue => {}
                         }
                     }
                     Some(event::AppEvent::Mouse(mouse)) => {
                         handle_mouse(mouse, &mut state);
                     }
-                    Some(event::AppEvent::Resize(_, _)) => {
+                    Some(event::AppEvent::Tick) => {
+                        state.on_tick(std::time::Instant::now());
+                    }
+                    Some(event::AppEvent::FileChanged) => {
+                        state.refresh_diff();
+                    }
+                    Some(event::AppEvent::Resize) => {
                         // Force an immediate redraw after a terminal resize so the new
                         // layout is computed without waiting for the next 100ms tick.
                         handler.tx.send(event::AppEvent::Render).ok();
                     }
                     Some(event::AppEvent::GitResult(payload)) => {
                         state.apply_git_result(*payload);
+                        let generated = state.auto_review_generated();
+                        persist_reviewed(&state, generated);
                         // Trigger immediate redraw after diff data arrives.
                         handler.tx.send(event::AppEvent::Render).ok();
                     }
+                    // A notice from a replaced worker says nothing about the live one.
+                    Some(event::AppEvent::GitWorkerStopped { generation, reason })
+                        if generation == state.git_generation =>
+                    {
+                        state.mark_git_worker_stopped(reason);
+                        handler.tx.send(event::AppEvent::Render).ok();
+                    }
+                    Some(event::AppEvent::RangeResolved { generation, range, result }) => {
+                        if state.apply_range_resolved(generation, range, result) {
+                            persist_diff_range(&state);
+                        }
+                        handler.tx.send(event::AppEvent::Render).ok();
+                    }
+                    Some(event::AppEvent::BlameLoaded { generation, rev, path, result }) => {
+                        state.apply_blame(generation, &rev, &path, result);
+                        handler.tx.send(event::AppEvent::Render).ok();
+                    }
+                    Some(event::AppEvent::NoteWritten { generation, notes_ref, result }) => {
+                        state.apply_note_written(generation, &notes_ref, result);
+                        handler.tx.send(event::AppEvent::Render).ok();
+                    }
+                    Some(event::AppEvent::RefsListed { generation, names }) => {
+                        state.apply_ref_names(generation, names);
+                        handler.tx.send(event::AppEvent::Render).ok();
+                    }
                     Some(event::AppEvent::DbResult(payload)) => {
                         state.apply_db_result(*payload);
+                        // Generated files seen before the session loaded are saved now.
+                        let generated = state.auto_review_generated();
+                        persist_reviewed(&state, generated);
                         // Trigger immediate redraw after DB state change.
                         handler.tx.send(event::AppEvent::Render).ok();
                     }
-                    Some(event::AppEvent::Quit) | None => break 'event_loop,
+                    None => break 'event_loop,
                     _ => {}
                 }
                 // Check SIGTERM after every event too, not just on the heartbeat,
@@ -208,5 +435,28 @@ async fn main() -> std::io::Result<()> {
     // Called unconditionally — covers normal quit, 'q' key, SIGTERM, and
     // channel close. The panic hook handles the panic path separately.
     tui::restore_tui()?;
+
+    // The panel widths, however they were reached, reopen with the session.
+    if let (Some(conn), Some(session)) = (&state.db_conn, &state.session) {
+        let widths = [state.left_pct, state.center_pct, state.right_pct];
+        if let Err(e) = airev_core::db::save_layout(conn, &session.id, widths).await {
+            eprintln!("airev: could not save panel widths: {e}");
+        }
+    }
+
+    // A theme picked with `:theme` / `T` becomes the configured one.
+    if let (true, Some(name)) = (state.theme_changed, state.theme_name.as_deref()) {
+        let path = config::config_path();
+        match config::save_theme(&path, name) {
+            Ok(()) => eprintln!("airev: saved theme '{name}' to {}", path.display()),
+            Err(e) => eprintln!("airev: could not save theme to {}: {e}", path.display()),
+        }
+    }
+
+    if quit_by_key && state.show_quit_summary {
+        if let Some(summary) = state.quit_summary() {
+            println!("{summary}");
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,152 @@
+//! Startup loading of the review session.
+//!
+//! Session detection runs on a spawned task so the first frame is drawn without
+//! waiting on the database. The result comes back as
+//! `DbResultPayload::SessionOpened` (or `SessionFailed`) and is applied by
+//! `AppState::apply_db_result`; until then `AppState::session_loading` holds back
+//! every key that would act on the session.
+
+use airev_core::types::Session;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_rusqlite::Connection;
+
+use crate::app::drift_message;
+use crate::event::{AppEvent, DbResultPayload};
+use crate::git::types::DiffMode;
+
+/// What to open a session for, gathered in `main.rs` before the task is spawned.
+#[derive(Debug, Clone)]
+pub struct SessionRequest {
+    /// Repository path the session is keyed by (`"."` without a repository).
+    pub repo_path: String,
+    /// Mode used when the repository has no remembered mode (config
+    /// `default_diff_mode`), or always for a `--files` or `--range` review.
+    pub default_mode: DiffMode,
+    /// Extra session key: the two paths of a `--files` review, the `FROM..TO` of
+    /// a `--range` review, otherwise empty.
+    pub diff_args: String,
+    /// Look up the repository's last-used diff mode. `false` for `--files` and
+    /// `--range`.
+    pub remember_mode: bool,
+    /// Current `HEAD`, compared against the one the session was started from.
+    pub head_sha: Option<String>,
+}
+
+/// Everything loaded for the review session, sent to the main loop in one piece.
+#[derive(Debug)]
+pub struct OpenedSession {
+    /// The resumed or newly created session.
+    pub session: Session,
+    /// Diff mode the session belongs to: the remembered one or `default_mode`.
+    pub diff_mode: DiffMode,
+    /// `(from, to)` when the remembered mode is a commit range.
+    pub diff_range: Option<(String, String)>,
+    /// The repository's recently shown ranges, most recent first, offered as
+    /// history at the picker prompt. Empty for a `--files` review.
+    pub recent_ranges: Vec<(String, String)>,
+    /// Per-file reviewed flags for the session.
+    pub review_states: Vec<(String, bool)>,
+    /// The session's comments.
+    pub comments: Vec<airev_core::types::Comment>,
+    /// Banner text when `HEAD` moved since the session started.
+    pub drift_warning: Option<String>,
+    /// `[left, center, right]` panel percentages saved when the session was
+    /// last quit, if any.
+    pub panel_widths: Option<[u16; 3]>,
+}
+
+/// Spawns the session load and sends its outcome back as a `DbResult`.
+pub fn spawn_open_session(
+    conn: Connection,
+    request: SessionRequest,
+    tx: UnboundedSender<AppEvent>,
+) {
+    tokio::spawn(async move {
+        let payload = match open_session(&conn, request).await {
+            Ok(opened) => DbResultPayload::SessionOpened(Box::new(opened)),
+            Err(e) => DbResultPayload::SessionFailed(e.to_string()),
+        };
+        let _ = tx.send(AppEvent::DbResult(Box::new(payload)));
+    });
+}
+
+/// Picks the diff mode, detects or creates the session for it, records or checks
+/// its `HEAD`, and loads its review state, comments, and panel widths.
+///
+/// A remembered commit range (picked with `m` or `:range`) reopens as that
+/// range, in a session keyed by its `FROM..TO` as `diff_args`.
+///
+/// A session without a recorded `HEAD` adopts the current one; a resumed session
+/// whose recorded `HEAD` differs gets a drift warning.
+///
+/// # Errors
+///
+/// Returns the first `tokio_rusqlite::Error` from any of the queries.
+async fn open_session(
+    conn: &Connection,
+    request: SessionRequest,
+) -> Result<OpenedSession, tokio_rusqlite::Error> {
+    let remembered = if request.remember_mode {
+        airev_core::db::get_last_diff_mode(conn, &request.repo_path).await?
+    } else {
+        None
+    };
+    let (remembered, diff_range) = match remembered.as_deref() {
+        Some("range") => {
+            let range = airev_core::db::get_last_diff_range(conn, &request.repo_path).await?;
+            match range.as_deref().and_then(split_range) {
+                Some(range) => (Some(DiffMode::CommitRange), Some(range)),
+                None => (None, None),
+            }
+        }
+        name => (name.and_then(DiffMode::from_config_name), None),
+    };
+    let recent_ranges = if request.default_mode != DiffMode::Files {
+        let recent = airev_core::db::get_recent_diff_args(conn, &request.repo_path).await?;
+        recent.iter().filter_map(|args| split_range(args)).collect()
+    } else {
+        Vec::new()
+    };
+    let diff_mode = remembered.unwrap_or(request.default_mode);
+    let diff_args = match &diff_range {
+        Some((from, to)) => format!("{from}..{to}"),
+        None => request.diff_args,
+    };
+
+    let session = airev_core::db::detect_or_create_session(
+        conn,
+        &request.repo_path,
+        &format!("{diff_mode:?}"),
+        &diff_args,
+    )
+    .await?;
+
+    let mut drift_warning = None;
+    if let Some(head) = request.head_sha {
+        if session.head_sha.is_empty() {
+            airev_core::db::set_session_head(conn, &session.id, &head).await?;
+        } else if session.head_sha != head {
+            drift_warning = Some(drift_message(&session.head_sha, &head));
+        }
+    }
+
+    let review_states = airev_core::db::load_file_review_state(conn, &session.id).await?;
+    let comments = airev_core::db::load_comments(conn, &session.id).await?;
+    let panel_widths = airev_core::db::load_layout(conn, &session.id).await?;
+
+    Ok(OpenedSession {
+        session,
+        diff_mode,
+        diff_range,
+        recent_ranges,
+        review_states,
+        comments,
+        drift_warning,
+        panel_widths,
+    })
+}
+
+/// Splits stored `FROM..TO` diff args into `(from, to)`.
+fn split_range(args: &str) -> Option<(String, String)> {
+    args.split_once("..").map(|(from, to)| (from.to_owned(), to.to_owned()))
+}
@@ -1,22 +1,61 @@
 //! Color theme system for airev.
 //!
 //! A `Theme` holds named `ratatui::style::Color` fields covering every UI surface
-//! airev renders. Two built-in themes are provided:
+//! airev renders. Three built-in themes are provided:
 //!
 //! - `dark` — uses ANSI 16 colors (`Color::Reset`, `Color::DarkGray`, etc.) so it
 //!   works on any terminal including 256-color SSH sessions with no truecolor support.
+//! - `light` — Solarized Light palette in RGB, for light terminal backgrounds.
 //! - `catppuccin_mocha` — Catppuccin Mocha palette in RGB; requires truecolor.
 //!
+//! On a terminal whose `COLORTERM` does not report truecolor (see
+//! [`supports_truecolor`]), RGB colors are approximated from the xterm
+//! 256-color palette by [`Theme::for_terminal`]; config `force_truecolor`
+//! skips the check.
+//!
+//! When no theme is configured, [`Theme::from_background`] picks `light` or
+//! `catppuccin-mocha` from the `COLORFGBG` environment variable.
+//!
+//! Custom themes are `[themes.NAME]` tables in the config, read by
+//! [`Theme::from_table`]: each key names a `Theme` field and holds a `"#rrggbb"`
+//! color. A custom name is looked up before the built-ins (see [`Theme::find`]).
+//!
 //! Phase 1 only uses `border_active` and `border_inactive`. All other fields are
 //! defined now so Phase 2+ can use them without a schema change.
 
 use ratatui::style::Color;
 
+/// Names of the built-in themes, in the order `T` cycles through them.
+pub const BUILTIN_THEMES: [&str; 3] = ["catppuccin-mocha", "dark", "light"];
+
+/// Names of every `Theme` field, as used in `[themes.NAME]` tables.
+const COLOR_KEYS: [&str; 19] = [
+    "border_active",
+    "border_inactive",
+    "diff_added",
+    "diff_removed",
+    "diff_context",
+    "diff_hunk_header",
+    "cursor_line",
+    "file_added",
+    "file_removed",
+    "file_modified",
+    "badge_critical",
+    "badge_major",
+    "badge_minor",
+    "badge_info",
+    "status_bar_bg",
+    "status_bar_fg",
+    "status_mode_normal",
+    "status_mode_insert",
+    "background",
+];
+
 /// All color values used across airev's UI surfaces.
 ///
 /// Every field is a `ratatui::style::Color`. Callers use `theme.field` directly
 /// inside `Style::default().fg(theme.border_active)`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Theme {
     // Panel borders
     /// Border color for the currently focused panel.
@@ -34,6 +73,11 @@ pub struct Theme {
     /// Color for hunk header lines (`@@ ... @@`).
     pub diff_hunk_header: Color,
 
+    // Cursor rows
+    /// Background of the current row in the focused panel (config `cursor_line`).
+    /// Only the background is set, so syntax colors show through.
+    pub cursor_line: Color,
+
     // File tree
     /// File status: newly added.
     pub file_added: Color,
@@ -82,6 +126,8 @@ impl Theme {
             diff_context: Color::Reset,
             diff_hunk_header: Color::Cyan,
 
+            cursor_line: Color::DarkGray,
+
             file_added: Color::Green,
             file_removed: Color::Red,
             file_modified: Color::Yellow,
@@ -100,11 +146,66 @@ impl Theme {
         }
     }
 
+    /// Returns the built-in light theme, a Solarized Light palette in RGB.
+    ///
+    /// Foregrounds are Solarized's dark content tones rather than `Reset`,
+    /// `White`, or `DarkGray`, which wash out on a light background. Added and
+    /// removed lines use green and red a shade darker than Solarized's own, for
+    /// contrast against white as well as `base3`.
+    ///
+    /// Palette source: <https://ethanschoonover.com/solarized/>.
+    pub fn light() -> Self {
+        // Solarized palette (light-background subset)
+        let base01 = Color::Rgb(88, 110, 117);  // #586e75
+        let base00 = Color::Rgb(101, 123, 131); // #657b83
+        let base1 = Color::Rgb(147, 161, 161);  // #93a1a1
+        let base2 = Color::Rgb(238, 232, 213);  // #eee8d5
+        let base3 = Color::Rgb(253, 246, 227);  // #fdf6e3
+        let yellow = Color::Rgb(181, 137, 0);   // #b58900
+        let orange = Color::Rgb(203, 75, 22);   // #cb4b16
+        let red = Color::Rgb(220, 50, 47);      // #dc322f
+        let magenta = Color::Rgb(211, 54, 130); // #d33682
+        let violet = Color::Rgb(108, 113, 196); // #6c71c4
+        let blue = Color::Rgb(38, 139, 210);    // #268bd2
+        let cyan = Color::Rgb(42, 161, 152);    // #2aa198
+        let green = Color::Rgb(95, 110, 0);     // #5f6e00, darkened #859900
+        let dark_red = Color::Rgb(176, 32, 30); // #b0201e, darkened #dc322f
+
+        Self {
+            border_active: blue,
+            border_inactive: base1,
+
+            diff_added: green,
+            diff_removed: dark_red,
+            diff_context: base00,
+            diff_hunk_header: cyan,
+
+            cursor_line: base2,
+
+            file_added: green,
+            file_removed: dark_red,
+            file_modified: yellow,
+
+            badge_critical: red,
+            badge_major: orange,
+            badge_minor: violet,
+            badge_info: base01,
+
+            status_bar_bg: base2,
+            status_bar_fg: base01,
+            status_mode_normal: blue,
+            status_mode_insert: magenta,
+
+            background: base3,
+        }
+    }
+
     /// Returns the Catppuccin Mocha theme using RGB truecolor values.
     ///
-    /// Requires a truecolor terminal. Falls back gracefully in ratatui — colors
-    /// degrade to the nearest ANSI 256-color approximation on non-truecolor terms,
-    /// but visual fidelity is reduced. Use `dark()` on SSH or 256-color terminals.
+    /// Meant for truecolor terminals. ratatui sends RGB colors as-is, so on other
+    /// terminals the theme must go through [`Theme::for_terminal`], which
+    /// approximates them from the 256-color palette with reduced fidelity. Use
+    /// `dark()` for the terminal's own colors.
     ///
     /// Palette source: <https://github.com/catppuccin/catppuccin> Mocha variant.
     pub fn catppuccin_mocha() -> Self {
@@ -116,6 +217,7 @@ impl Theme {
         let teal = Color::Rgb(148, 226, 213);     // #94e2d5
         let lavender = Color::Rgb(180, 190, 254); // #b4befe
         let overlay1 = Color::Rgb(127, 132, 156); // #7f849c
+        let surface0 = Color::Rgb(49, 50, 68);   // #313244
         let surface1 = Color::Rgb(69, 71, 90);   // #45475a
         let base = Color::Rgb(30, 30, 46);        // #1e1e2e
         let text = Color::Rgb(205, 214, 244);     // #cdd6f4
@@ -130,6 +232,8 @@ impl Theme {
             diff_context: text,
             diff_hunk_header: teal,
 
+            cursor_line: surface0,
+
             file_added: green,
             file_removed: red,
             file_modified: yellow,
@@ -148,7 +252,8 @@ impl Theme {
         }
     }
 
-    /// Resolves a theme name string to the corresponding built-in theme.
+    /// Resolves a theme name string to a custom theme from `custom`, or else the
+    /// corresponding built-in theme.
     ///
     /// Unknown names fall back to `dark()` so a typo in config never prevents
     /// startup. The fallback is logged to stderr (not a hard error).
@@ -156,17 +261,242 @@ impl Theme {
     /// # Arguments
     ///
     /// * `name` — theme name from config, e.g. `"dark"` or `"catppuccin-mocha"`.
-    pub fn from_name(name: &str) -> Self {
-        match name {
-            "catppuccin-mocha" | "catppuccin_mocha" => Self::catppuccin_mocha(),
-            "dark" => Self::dark(),
-            other => {
+    /// * `custom` — the config's `[themes.NAME]` tables, by name.
+    pub fn from_name(name: &str, custom: &[(String, Theme)]) -> Self {
+        match Self::find(name, custom) {
+            Some((_, theme)) => theme,
+            None => {
                 eprintln!(
                     "airev: unknown theme '{}', falling back to 'dark'",
-                    other
+                    name
                 );
                 Self::dark()
             }
         }
     }
+
+    /// Looks up a built-in theme without logging, returning its canonical name
+    /// (one of [`BUILTIN_THEMES`]) with it. `None` for unknown names.
+    ///
+    /// Used by the `:theme` command, which runs while stderr is the TUI.
+    pub fn builtin(name: &str) -> Option<(&'static str, Self)> {
+        match name {
+            "catppuccin-mocha" | "catppuccin_mocha" => {
+                Some(("catppuccin-mocha", Self::catppuccin_mocha()))
+            }
+            "dark" => Some(("dark", Self::dark())),
+            "light" => Some(("light", Self::light())),
+            _ => None,
+        }
+    }
+
+    /// Looks up `name` among the custom themes, then the built-ins, without
+    /// logging. Returns the canonical name with the theme.
+    pub fn find(name: &str, custom: &[(String, Theme)]) -> Option<(String, Self)> {
+        match custom.iter().find(|(custom_name, _)| custom_name == name) {
+            Some((name, theme)) => Some((name.clone(), theme.clone())),
+            None => Self::builtin(name).map(|(name, theme)| (name.to_owned(), theme)),
+        }
+    }
+
+    /// Builds the custom theme `name` from its `[themes.NAME]` config table.
+    ///
+    /// Each key names a field (`border_active`, `diff_added`, ...) and holds a
+    /// `"#rrggbb"` hex string. Fields left out, unknown keys, and malformed
+    /// colors keep the `dark()` color; the last two are noted in `warnings`.
+    pub fn from_table(name: &str, table: &toml::Table, warnings: &mut Vec<String>) -> Self {
+        let mut theme = Self::dark();
+        for (key, value) in table {
+            let Some(field) = theme.color_mut(key) else {
+                warnings.push(format!("ignoring unknown color '{key}' in theme '{name}'"));
+                continue;
+            };
+            match value.as_str().and_then(parse_hex_color) {
+                Some(color) => *field = color,
+                None => warnings.push(format!(
+                    "ignoring color '{key}' in theme '{name}': expected \"#rrggbb\""
+                )),
+            }
+        }
+        theme
+    }
+
+    /// This theme as shown on a terminal with (`truecolor`) or without 24-bit
+    /// color: without it, every RGB color becomes the nearest xterm 256-color
+    /// palette entry. ANSI colors are kept.
+    pub fn for_terminal(mut self, truecolor: bool) -> Self {
+        if truecolor {
+            return self;
+        }
+        for key in COLOR_KEYS {
+            if let Some(color) = self.color_mut(key) {
+                if let Color::Rgb(r, g, b) = *color {
+                    *color = Color::Indexed(nearest_ansi256(r, g, b));
+                }
+            }
+        }
+        self
+    }
+
+    /// The field named `key`, for [`Theme::from_table`].
+    fn color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        Some(match key {
+            "border_active" => &mut self.border_active,
+            "border_inactive" => &mut self.border_inactive,
+            "diff_added" => &mut self.diff_added,
+            "diff_removed" => &mut self.diff_removed,
+            "diff_context" => &mut self.diff_context,
+            "diff_hunk_header" => &mut self.diff_hunk_header,
+            "cursor_line" => &mut self.cursor_line,
+            "file_added" => &mut self.file_added,
+            "file_removed" => &mut self.file_removed,
+            "file_modified" => &mut self.file_modified,
+            "badge_critical" => &mut self.badge_critical,
+            "badge_major" => &mut self.badge_major,
+            "badge_minor" => &mut self.badge_minor,
+            "badge_info" => &mut self.badge_info,
+            "status_bar_bg" => &mut self.status_bar_bg,
+            "status_bar_fg" => &mut self.status_bar_fg,
+            "status_mode_normal" => &mut self.status_mode_normal,
+            "status_mode_insert" => &mut self.status_mode_insert,
+            "background" => &mut self.background,
+            _ => return None,
+        })
+    }
+
+    /// Picks a theme from the terminal background reported in `COLORFGBG`.
+    ///
+    /// `colorfgbg` is the variable's value (`None` when unset). A light background
+    /// selects `light()`; a dark, missing, or unparseable one selects
+    /// `catppuccin_mocha()`. The choice and its reason are logged to stderr.
+    pub fn from_background(colorfgbg: Option<&str>) -> Self {
+        match colorfgbg.and_then(background_is_light) {
+            Some(true) => {
+                eprintln!("airev: COLORFGBG reports a light background, using 'light' theme");
+                Self::light()
+            }
+            Some(false) => {
+                eprintln!(
+                    "airev: COLORFGBG reports a dark background, using 'catppuccin-mocha' theme"
+                );
+                Self::catppuccin_mocha()
+            }
+            None => {
+                eprintln!(
+                    "airev: terminal background unknown (COLORFGBG unset or unparseable), \
+                     using 'catppuccin-mocha' theme"
+                );
+                Self::catppuccin_mocha()
+            }
+        }
+    }
+
+    /// Same choice as [`Theme::from_background`], without logging. Used on a
+    /// config reload, when stderr is the TUI.
+    pub fn for_background(colorfgbg: Option<&str>) -> Self {
+        match colorfgbg.and_then(background_is_light) {
+            Some(true) => Self::light(),
+            Some(false) | None => Self::catppuccin_mocha(),
+        }
+    }
+}
+
+/// Whether the terminal displays 24-bit color, going by `COLORTERM`.
+pub fn supports_truecolor() -> bool {
+    colorterm_is_truecolor(std::env::var("COLORTERM").ok().as_deref())
+}
+
+/// Returns whether a `COLORTERM` value announces 24-bit color: `truecolor` or
+/// `24bit`, in any case. Unset means no.
+fn colorterm_is_truecolor(colorterm: Option<&str>) -> bool {
+    colorterm.is_some_and(|v| {
+        let v = v.trim();
+        v.eq_ignore_ascii_case("truecolor") || v.eq_ignore_ascii_case("24bit")
+    })
+}
+
+/// Channel values of the six steps of the xterm 6x6x6 color cube (indices 16..=231).
+const CUBE_STEPS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The xterm 256-color palette index closest to `(r, g, b)`: the nearest entry
+/// of the color cube or of the 24-step gray ramp (232..=255), by squared
+/// distance. The 16 ANSI colors are skipped since terminals redefine them.
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    let step = |c: u8| {
+        (0..CUBE_STEPS.len()).min_by_key(|&i| (i32::from(CUBE_STEPS[i]) - i32::from(c)).abs())
+    };
+    let (ri, gi, bi) = (step(r).unwrap_or(0), step(g).unwrap_or(0), step(b).unwrap_or(0));
+    let cube = (CUBE_STEPS[ri], CUBE_STEPS[gi], CUBE_STEPS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_step;
+    let gray = (gray_level, gray_level, gray_level);
+
+    if distance(gray) < distance(cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Parses a `"#rrggbb"` hex color. Anything else, including color names, is
+/// `None`.
+fn parse_hex_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Returns whether a `COLORFGBG` value (`"fg;bg"` or `"fg;default;bg"`) names a
+/// light background.
+///
+/// The background is the last field, an ANSI color index: `7` (white) and
+/// `9..=15` (bright colors) are light, `0..=6` and `8` are dark. Anything else
+/// (e.g. `default`) returns `None`.
+fn background_is_light(colorfgbg: &str) -> Option<bool> {
+    let bg: u8 = colorfgbg.rsplit(';').next()?.trim().parse().ok()?;
+    match bg {
+        7 | 9..=15 => Some(true),
+        0..=6 | 8 => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colorterm_announces_truecolor() {
+        assert!(colorterm_is_truecolor(Some("truecolor")));
+        assert!(colorterm_is_truecolor(Some("24BIT")));
+        assert!(!colorterm_is_truecolor(Some("256color")));
+        assert!(!colorterm_is_truecolor(None));
+    }
+
+    #[test]
+    fn rgb_colors_are_quantized_without_truecolor() {
+        assert_eq!(nearest_ansi256(0, 0, 0), 16);
+        assert_eq!(nearest_ansi256(255, 255, 255), 231);
+        assert_eq!(nearest_ansi256(255, 0, 0), 196);
+        assert_eq!(nearest_ansi256(128, 128, 128), 244);
+
+        let mocha = Theme::catppuccin_mocha();
+        assert_eq!(mocha.clone().for_terminal(true), mocha);
+        let quantized = mocha.for_terminal(false);
+        for key in COLOR_KEYS {
+            let color = *quantized.clone().color_mut(key).expect("every key names a field");
+            assert!(matches!(color, Color::Indexed(_)), "{key} is {color:?}");
+        }
+        assert_eq!(Theme::dark().for_terminal(false), Theme::dark());
+    }
 }
@@ -6,19 +6,29 @@
 //! the TUI renders entirely to stderr. This lets both processes share the same terminal
 //! session: the editor's extension reads stdout from `airev-mcp` while the human user
 //! sees the TUI on stderr. It also means shell pipelines (`airev | …`) remain clean.
+//!
+//! **Alternate screen vs inline.**
+//! By default the TUI runs on the alternate screen, so the shell's scrollback is
+//! untouched and vanishes back into view on exit. With `alt_screen = false` (or
+//! `--no-alt-screen`) it renders into an inline viewport on the normal screen
+//! instead: earlier shell history stays reachable (e.g. in tmux copy-mode) and the
+//! last frame is left behind on exit, at the cost of every full-height redraw
+//! scrolling the previous contents up into the scrollback.
 
+use crossterm::cursor::Show;
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use ratatui::backend::CrosstermBackend;
-use ratatui::Terminal;
-use signal_hook::consts::SIGTERM;
+use ratatui::{Terminal, TerminalOptions, Viewport};
+use signal_hook::consts::{SIGHUP, SIGTERM};
 use signal_hook::flag::register;
-use std::io::{stderr, BufWriter, Stderr};
+use std::io::{stderr, BufWriter, Stderr, Write};
 use std::panic;
-use std::sync::{atomic::AtomicBool, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// The terminal type used by airev — CrosstermBackend over a buffered stderr writer.
 ///
@@ -26,24 +36,41 @@ use std::sync::{atomic::AtomicBool, Arc};
 /// reducing flicker on high-frequency draws (30 FPS render interval).
 pub type Tui = Terminal<CrosstermBackend<BufWriter<Stderr>>>;
 
+/// Whether [`init_tui`] entered the alternate screen.
+///
+/// Read by [`restore_tui`], which the panic hook calls without any context.
+static ALT_SCREEN: AtomicBool = AtomicBool::new(true);
+
 /// Initialise the terminal for TUI rendering.
 ///
-/// Creates a `CrosstermBackend` backed by a `BufWriter<Stderr>`, enables raw mode,
-/// and enters the alternate screen. Call [`restore_tui`] at every exit path.
+/// Creates a `CrosstermBackend` backed by a `BufWriter<Stderr>` and enables raw
+/// mode. With `alt_screen` it enters the alternate screen; otherwise it uses an
+/// inline viewport as tall as the terminal (see the module docs for the
+/// tradeoff). Call [`restore_tui`] at every exit path.
 ///
 /// # Errors
 ///
 /// Returns `Err` if `enable_raw_mode`, `execute!`, or `Terminal::new` fails.
-pub fn init_tui() -> std::io::Result<Tui> {
+pub fn init_tui(alt_screen: bool) -> std::io::Result<Tui> {
     let mut out = BufWriter::new(stderr());
+    ALT_SCREEN.store(alt_screen, Ordering::Relaxed);
     enable_raw_mode()?;
-    execute!(out, EnterAlternateScreen, EnableMouseCapture)?;
-    Terminal::new(CrosstermBackend::new(out))
+    if alt_screen {
+        execute!(out, EnterAlternateScreen, EnableMouseCapture)?;
+        return Terminal::new(CrosstermBackend::new(out));
+    }
+    execute!(out, EnableMouseCapture)?;
+    let (_, rows) = crossterm::terminal::size()?;
+    Terminal::with_options(
+        CrosstermBackend::new(out),
+        TerminalOptions { viewport: Viewport::Inline(rows) },
+    )
 }
 
 /// Restore the terminal to its pre-TUI state.
 ///
-/// Disables raw mode and leaves the alternate screen. This function is idempotent
+/// Disables raw mode and leaves the alternate screen, or in inline mode shows the
+/// cursor again and moves below the last frame. This function is idempotent
 /// and must be called at every exit path — including the panic hook — because
 /// ratatui 0.30 does NOT auto-restore the terminal on `Drop` (see GitHub #2087).
 ///
@@ -53,10 +80,68 @@ pub fn init_tui() -> std::io::Result<Tui> {
 /// hook should use `let _ = restore_tui();` and ignore the error (best-effort only).
 pub fn restore_tui() -> std::io::Result<()> {
     disable_raw_mode()?;
-    execute!(stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+    if ALT_SCREEN.load(Ordering::Relaxed) {
+        execute!(stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+    } else {
+        execute!(stderr(), DisableMouseCapture, Show)?;
+        eprintln!();
+    }
     Ok(())
 }
 
+/// Hands the terminal to `run` (e.g. a terminal editor) and takes it back.
+///
+/// Restores the terminal, calls `run`, then initialises a fresh [`Tui`] with the
+/// same `alt_screen` setting and clears it so the next frame redraws in full.
+/// Stop the event task first: it would otherwise read the keys meant for `run`.
+///
+/// # Errors
+///
+/// Returns `Err` if the terminal cannot be restored or initialised again.
+pub fn suspend<T>(alt_screen: bool, run: impl FnOnce() -> T) -> std::io::Result<(Tui, T)> {
+    restore_tui()?;
+    let result = run();
+    let mut terminal = init_tui(alt_screen)?;
+    terminal.clear()?;
+    Ok((terminal, result))
+}
+
+/// Copies `text` to the system clipboard with an OSC 52 escape sequence.
+///
+/// The terminal does the copying, so it works over ssh without a clipboard
+/// tool, but only in terminals that support OSC 52 (inside tmux, with
+/// `set-clipboard on`). Nothing reports whether the terminal accepted it.
+///
+/// # Errors
+///
+/// Returns `Err` if writing to the terminal fails.
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let mut out = stderr();
+    write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    out.flush()
+}
+
+/// Standard base64 with padding, as OSC 52 expects.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (u32::from(b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
 /// Install a panic hook that restores the terminal before printing the panic message.
 ///
 /// Must be called **before** [`init_tui`]. Chains onto any previously installed hook
@@ -64,9 +149,16 @@ pub fn restore_tui() -> std::io::Result<()> {
 /// terminal is restored. Without this hook, a panic leaves the terminal in raw mode
 /// with the alternate screen active, making the panic message invisible and the
 /// shell unusable until the user types `reset`.
+///
+/// Panics on the git worker thread are skipped entirely: the worker catches them
+/// and the UI keeps running, so restoring the terminal (or printing over the TUI)
+/// would leave the screen broken.
 pub fn install_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
+        if std::thread::current().name() == Some(crate::git::WORKER_THREAD_NAME) {
+            return;
+        }
         // Restore terminal first so the panic message is readable.
         // Errors from restore_tui() are intentionally ignored here —
         // we're already in a panic, best-effort cleanup only.
@@ -78,8 +170,8 @@ pub fn install_panic_hook() {
 /// Register a SIGTERM handler that sets an `AtomicBool` flag.
 ///
 /// Returns an `Arc<AtomicBool>` that transitions from `false` to `true` when
-/// the process receives SIGTERM. Poll this flag in the main event loop — the
-/// event task sends `AppEvent::Quit` when the flag is set (see `event.rs`).
+/// the process receives SIGTERM. The main event loop polls this flag on every
+/// event and on its heartbeat, and exits when it is set (see `main.rs`).
 ///
 /// # Panics
 ///
@@ -92,3 +184,41 @@ pub fn register_sigterm() -> Arc<AtomicBool> {
     register(SIGTERM, Arc::clone(&term)).expect("Failed to register SIGTERM handler");
     term
 }
+
+/// Register a SIGHUP handler that sets an `AtomicBool` flag.
+///
+/// The event loop swaps the flag back to `false` and reloads the config file
+/// (see `config::reload_config`), so `kill -HUP` applies edits without a restart.
+///
+/// # Panics
+///
+/// Panics if the OS refuses to register the signal handler, like
+/// [`register_sigterm`].
+pub fn register_sighup() -> Arc<AtomicBool> {
+    let hup = Arc::new(AtomicBool::new(false));
+    register(SIGHUP, Arc::clone(&hup)).expect("Failed to register SIGHUP handler");
+    hup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_rfc_4648_test_vectors() {
+        // RFC 4648 §10, covering no padding, one `=`, and two `=`.
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in vectors {
+            assert_eq!(base64(input.as_bytes()), expected, "{input:?}");
+        }
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=", "the last two alphabet entries");
+    }
+}
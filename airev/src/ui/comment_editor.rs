@@ -0,0 +1,143 @@
+//! Comment composer and severity/type picker overlays for airev.
+//!
+//! Both overlays follow the help overlay pattern: a `Clear` widget erases the
+//! area first, then a bordered `Paragraph` is drawn on top inside the same
+//! `terminal.draw()` closure. The composer is shown in `Mode::Insert`; the
+//! quick-pick is shown in `Mode::CommentTag` after the body has been typed.
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Clear, Paragraph, Wrap},
+};
+
+use airev_core::types::{CommentType, SEVERITIES};
+
+use crate::app::AppState;
+use crate::theme::Theme;
+
+/// Renders the comment composer as a small modal near the bottom of the screen.
+///
+/// Shows the target file (or line, or hunk) in the title and the current draft followed
+/// by a block cursor. After a failed save, the error is shown along the bottom
+/// border. Skipped on terminals too small to hold the box.
+///
+/// # Arguments
+///
+/// * `frame` — current render frame
+/// * `state` — app state supplying `comment_draft`, the selected file, and `theme`
+pub fn render_comment_composer(frame: &mut Frame, state: &AppState) {
+    let theme = &state.theme;
+    let Some(area) = overlay_area(frame.area(), 6) else {
+        return;
+    };
+    frame.render_widget(Clear, area);
+
+    let target = match (&state.draft_line, &state.draft_hunk) {
+        (Some((path, line)), _) => format!("{path}:{line}"),
+        (None, Some((path, _))) => format!("{path} (hunk)"),
+        (None, None) => state.current_file_path().unwrap_or("?").to_owned(),
+    };
+    let title = format!(" Comment on {target}  — Enter to tag, Ctrl-s save, Esc discard ");
+    let mut block = Block::bordered()
+        .title(title)
+        .border_style(Style::default().fg(theme.border_active));
+    if let Some(error) = &state.comment_error {
+        block = block.title_bottom(Line::styled(
+            format!(" cannot save: {error} "),
+            Style::default().fg(theme.badge_critical),
+        ));
+    }
+
+    let text = Line::from(vec![
+        Span::raw(state.comment_draft.clone()),
+        Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+    ]);
+
+    frame.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+/// Renders the severity/type quick-pick overlay.
+///
+/// Lists the four severities with their `1`–`4` keys and badge colors, and all
+/// comment types with the current choice highlighted. The selected severity is
+/// shown bold and reversed.
+///
+/// # Arguments
+///
+/// * `frame` — current render frame
+/// * `state` — app state supplying `draft_severity`, `draft_type`, and `theme` (whose
+///   `badge_*` colors mark the severities)
+pub fn render_comment_tag_picker(frame: &mut Frame, state: &AppState) {
+    let theme = &state.theme;
+    let Some(area) = overlay_area(frame.area(), 7) else {
+        return;
+    };
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .title(" Tag comment  — 1-4 severity, j/k type, Enter save, Esc back ")
+        .border_style(Style::default().fg(theme.border_active));
+
+    let mut severity_spans = vec![Span::raw("Severity: ")];
+    for (i, name) in SEVERITIES.iter().enumerate() {
+        let mut style = Style::default().fg(severity_color(name, theme));
+        if i == state.draft_severity {
+            style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        }
+        severity_spans.push(Span::styled(format!(" {} {} ", i + 1, name), style));
+        severity_spans.push(Span::raw(" "));
+    }
+
+    let mut type_spans = vec![Span::raw("Type:     ")];
+    for t in CommentType::ALL {
+        let style = if t == state.draft_type {
+            Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        type_spans.push(Span::styled(format!(" {} ", t.as_str()), style));
+        type_spans.push(Span::raw(" "));
+    }
+
+    let text = Text::from(vec![
+        Line::from(severity_spans),
+        Line::from(""),
+        Line::from(type_spans),
+    ]);
+
+    frame.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+/// Returns the badge color for a severity string.
+///
+/// Unknown strings use `badge_info`.
+pub fn severity_color(severity: &str, theme: &Theme) -> Color {
+    match severity {
+        "critical" => theme.badge_critical,
+        "major" => theme.badge_major,
+        "minor" => theme.badge_minor,
+        _ => theme.badge_info,
+    }
+}
+
+/// Computes a horizontally centred overlay `height` rows tall, anchored near the bottom.
+///
+/// Returns `None` when the terminal is narrower than 40 columns or shorter than
+/// `height + 2` rows, to avoid zero-size `Rect`s.
+fn overlay_area(screen: Rect, height: u16) -> Option<Rect> {
+    if screen.width < 40 || screen.height < height + 2 {
+        return None;
+    }
+    let centered = screen.centered(Constraint::Percentage(70), Constraint::Length(height));
+    // Sit just above the status bar rather than in the vertical middle.
+    Some(Rect { y: screen.height - height - 2, ..centered })
+}
@@ -0,0 +1,351 @@
+//! Comments panel renderer for airev.
+//!
+//! Renders the right panel from `AppState.comments`. Each comment is a header row
+//! (severity badge, type, file and line) followed by its body, word-wrapped to the
+//! panel's inner width. Wrapping is done here rather than by `Paragraph::wrap` so
+//! the row offset of every comment is known exactly — the renderer caches those
+//! offsets in `state.comment_row_offsets` and uses them to scroll the selected
+//! comment into view. Comments hidden by `state.comment_filter` (or resolved ones
+//! under `state.hide_resolved`) are skipped and the active filter is shown in the
+//! panel title. Resolved comments are drawn dimmed.
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style, Stylize as _},
+    text::{Line, Span, Text},
+    widgets::Paragraph,
+};
+
+use airev_core::types::{Comment, CommentFilter, Severity};
+
+use crate::app::{AppState, PanelFocus};
+use crate::theme::Theme;
+use crate::ui::comment_editor::severity_color;
+use crate::ui::layout::{
+    cursor_line_style, inner_rect, panel_block, render_scrollbar, scroll_title,
+};
+
+/// Renders the comments panel with the selected comment highlighted.
+///
+/// When `state.comments_follow_selection` is set, adjusts `state.comments_scroll`
+/// so the selected comment's rows are fully visible (or its header is at the top
+/// if it is taller than the viewport), then clears the flag. The title and a
+/// scrollbar show the scroll position once the wrapped comments overflow the panel.
+///
+/// # Arguments
+///
+/// * `frame` — current render frame
+/// * `area` — the `Rect` for the right panel (includes borders)
+/// * `focus` — current panel focus (determines border style)
+/// * `state` — mutable app state (row offsets and scroll are written back)
+pub fn render_comments(
+    frame: &mut Frame,
+    area: Rect,
+    focus: PanelFocus,
+    state: &mut AppState,
+) {
+    // Cloned so the scroll helpers below can borrow `state` mutably.
+    let theme = &state.theme.clone();
+    let is_focused = focus == PanelFocus::Comments;
+    let visible = state.visible_comment_indices();
+    let title = comments_title(
+        state.comments.len(),
+        visible.len(),
+        state.comment_filter,
+        state.severity_filter,
+        state.hide_resolved,
+    );
+    let inner = inner_rect(area);
+
+    if visible.is_empty() {
+        frame.render_widget(panel_block(&title, is_focused, theme), area);
+        state.comment_row_offsets.clear();
+        let msg = if state.comments.is_empty() {
+            "  No comments yet — press c to add one"
+        } else if state.hide_resolved
+            && state.comment_filter == CommentFilter::All
+            && state.severity_filter.is_none()
+        {
+            "  Every comment is resolved — press X to show them"
+        } else {
+            "  No comments match the filter — press f to change it"
+        };
+        let placeholder = Line::from(msg).fg(theme.diff_context);
+        frame.render_widget(Paragraph::new(placeholder), inner);
+        return;
+    }
+
+    let width = inner.width.max(1) as usize;
+    let cursor_style = cursor_line_style(state, is_focused);
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    state.comment_row_offsets.clear();
+
+    for i in visible {
+        let comment = &state.comments[i];
+        state.comment_row_offsets.push((lines.len(), i));
+        let resolved = comment.resolved_at.is_some();
+        let dim = |line: Line<'static>| {
+            if resolved { line.add_modifier(Modifier::DIM) } else { line }
+        };
+        let header = dim(comment_header(comment, theme));
+        lines.push(if i == state.selected_comment {
+            mark_selected(header, cursor_style, width)
+        } else {
+            header
+        });
+        for row in wrap_text(&comment.body, width.saturating_sub(2)) {
+            lines.push(dim(Line::from(format!("  {row}"))));
+        }
+        lines.push(Line::from(""));
+    }
+
+    if state.comments_follow_selection {
+        scroll_selection_into_view(state, lines.len(), inner.height as usize);
+        state.comments_follow_selection = false;
+    }
+
+    // Clamp so scrolling past the end (e.g. G sets u16::MAX) keeps the last page visible.
+    let max_scroll = lines.len().saturating_sub(inner.height as usize);
+    state.comments_scroll = state.comments_scroll.min(max_scroll.min(u16::MAX as usize) as u16);
+
+    // The title needs the wrapped row count, so the block is drawn only now.
+    let title = scroll_title(
+        &title,
+        state.comments_scroll as usize,
+        lines.len(),
+        inner.height as usize,
+    );
+    frame.render_widget(panel_block(&title, is_focused, theme), area);
+
+    let total = lines.len();
+    let paragraph = Paragraph::new(Text::from(lines)).scroll((state.comments_scroll, 0));
+    frame.render_widget(paragraph, inner);
+    let scroll = state.comments_scroll as usize;
+    render_scrollbar(frame, inner, scroll, total, inner.height as usize);
+}
+
+/// Returns the index into `state.comments` of the comment rendered at `row`
+/// (relative to the top of the scrolled content), using the offsets cached by
+/// the last render.
+///
+/// Returns `None` when no comments are rendered.
+pub fn comment_at_row(state: &AppState, row: usize) -> Option<usize> {
+    let pos = state.comment_row_offsets.partition_point(|&(start, _)| start <= row);
+    state.comment_row_offsets.get(pos.saturating_sub(1)).map(|&(_, idx)| idx)
+}
+
+/// Adjusts `comments_scroll` so the selected comment is visible.
+///
+/// The comment spans from its cached offset up to the next rendered comment's
+/// offset (or `total_rows`). If it is already fully visible, or the selected
+/// comment is filtered out, the scroll is unchanged.
+fn scroll_selection_into_view(state: &mut AppState, total_rows: usize, viewport: usize) {
+    let offsets = &state.comment_row_offsets;
+    let Some(pos) = offsets.iter().position(|&(_, idx)| idx == state.selected_comment) else {
+        return;
+    };
+    let start = offsets[pos].0;
+    let end = offsets.get(pos + 1).map(|&(row, _)| row).unwrap_or(total_rows);
+    let scroll = state.comments_scroll as usize;
+
+    let new_scroll = if start < scroll || end - start > viewport {
+        start
+    } else if end > scroll + viewport {
+        end - viewport
+    } else {
+        scroll
+    };
+    state.comments_scroll = new_scroll.min(u16::MAX as usize) as u16;
+}
+
+/// Builds the panel title: `Comments (N)`, or `Comments (shown/N) [filters]` when
+/// a severity filter is active or resolved comments are hidden.
+fn comments_title(
+    total: usize,
+    shown: usize,
+    filter: CommentFilter,
+    severity: Option<Severity>,
+    hide_resolved: bool,
+) -> String {
+    let mut labels = Vec::new();
+    match filter {
+        CommentFilter::All => {}
+        CommentFilter::MajorAndAbove => labels.push("critical+major"),
+        CommentFilter::CriticalOnly => labels.push("critical"),
+    }
+    if let Some(severity) = severity {
+        labels.push(severity.as_str());
+    }
+    if hide_resolved {
+        labels.push("unresolved");
+    }
+    match total {
+        _ if !labels.is_empty() => format!("Comments ({shown}/{total}) [{}]", labels.join(", ")),
+        0 => "Comments".to_owned(),
+        _ => format!("Comments ({total})"),
+    }
+}
+
+/// Builds the header row for a comment: `[severity] type  path:line`, or
+/// `path (hunk)` for a hunk-level comment, followed by `resolved` once it is.
+fn comment_header(comment: &Comment, theme: &Theme) -> Line<'static> {
+    let location = match (comment.line_number, comment.hunk_offset) {
+        (Some(n), _) => format!("{}:{}", comment.file_path, n),
+        (None, Some(_)) => format!("{} (hunk)", comment.file_path),
+        (None, None) => comment.file_path.clone(),
+    };
+    let resolved = if comment.resolved_at.is_some() { "  resolved" } else { "" };
+    Line::from(vec![
+        Span::styled(
+            format!("[{}]", comment.severity),
+            Style::default()
+                .fg(severity_color(&comment.severity, theme))
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" {}  ", comment.comment_type)),
+        Span::styled(location, Style::default().fg(theme.border_inactive)),
+        Span::raw(resolved),
+    ])
+}
+
+/// Marks the selected comment's header as a cursor row: reversed, or padded to
+/// the panel `width` on the `cursor_line` background when `cursor_style` is set.
+fn mark_selected(
+    header: Line<'static>,
+    cursor_style: Option<Style>,
+    width: usize,
+) -> Line<'static> {
+    let Some(style) = cursor_style else {
+        return header.add_modifier(Modifier::REVERSED);
+    };
+    let pad = width.saturating_sub(header.width());
+    let mut header = header.patch_style(style);
+    header.spans.push(Span::raw(" ".repeat(pad)));
+    header
+}
+
+/// Word-wraps `text` to `width` columns, breaking overlong words.
+///
+/// Explicit newlines in `text` are preserved. Always returns at least one row
+/// so an empty body still occupies a line.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut current = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            // Hard-break words that cannot fit on a row by themselves.
+            while word.chars().count() > width {
+                if !current.is_empty() {
+                    rows.push(std::mem::take(&mut current));
+                }
+                let split =
+                    word.char_indices().nth(width).map(|(i, _)| i).unwrap_or(word.len());
+                rows.push(word[..split].to_owned());
+                word = &word[split..];
+            }
+            if word.is_empty() {
+                continue;
+            }
+            let word_len = word.chars().count();
+            let needed = if current.is_empty() {
+                word_len
+            } else {
+                current.chars().count() + 1 + word_len
+            };
+            if needed > width {
+                rows.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        rows.push(current);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    /// A comment on `line` of `a.rs` whose body wraps to three rows at width 20.
+    fn comment(line: i64) -> Comment {
+        Comment {
+            id: line.to_string(),
+            session_id: "s".to_owned(),
+            file_path: "a.rs".to_owned(),
+            line_number: Some(line),
+            hunk_offset: None,
+            comment_type: "concern".to_owned(),
+            severity: "minor".to_owned(),
+            body: "one two three four five six seven eight".to_owned(),
+            created_at: 0,
+            resolved_at: None,
+            thread_id: None,
+        }
+    }
+
+    fn render(terminal: &mut Terminal<TestBackend>, state: &mut AppState) {
+        terminal
+            .draw(|frame| {
+                let area = frame.area();
+                render_comments(frame, area, PanelFocus::Comments, state);
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn scroll_follows_selection_past_the_viewport() {
+        // 22 columns leave 20 inside the borders; 12 rows leave a 10-row viewport.
+        let mut terminal = Terminal::new(TestBackend::new(22, 12)).unwrap();
+        let mut state =
+            AppState { comments: (1..=6).map(comment).collect(), ..AppState::default() };
+        render(&mut terminal, &mut state);
+        let rows: Vec<usize> = state.comment_row_offsets.iter().map(|&(row, _)| row).collect();
+        assert_eq!(rows, vec![0, 5, 10, 15, 20, 25]);
+        assert_eq!(state.comments_scroll, 0);
+
+        // Comment 3 spans rows 15..20: scrolled just far enough to show it whole.
+        state.select_comment(3);
+        render(&mut terminal, &mut state);
+        assert_eq!(state.comments_scroll, 10);
+
+        // Scrolling away by hand is kept until the selection changes again.
+        state.comments_scroll = 0;
+        render(&mut terminal, &mut state);
+        assert_eq!(state.comments_scroll, 0);
+
+        // Moving back above the viewport puts the selection's header at the top.
+        state.comments_scroll = 20;
+        state.prev_comment();
+        render(&mut terminal, &mut state);
+        assert_eq!(state.comments_scroll, 10);
+    }
+
+    #[test]
+    fn header_shows_severity_type_and_location() {
+        let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+        let mut state = AppState { comments: vec![comment(4)], ..AppState::default() };
+        render(&mut terminal, &mut state);
+        let header: String = (1..39)
+            .map(|x| terminal.backend().buffer()[(x, 1)].symbol().to_owned())
+            .collect();
+        assert_eq!(header.trim_end(), "[minor] concern  a.rs:4");
+    }
+
+    #[test]
+    fn empty_panel_shows_the_placeholder() {
+        let mut terminal = Terminal::new(TestBackend::new(50, 4)).unwrap();
+        let mut state = AppState::default();
+        render(&mut terminal, &mut state);
+        let row: String = (1..49)
+            .map(|x| terminal.backend().buffer()[(x, 1)].symbol().to_owned())
+            .collect();
+        assert_eq!(row.trim(), "No comments yet — press c to add one");
+    }
+}
@@ -0,0 +1,53 @@
+//! Debug overlay for airev.
+//!
+//! Shown in `Mode::DebugOverlay` (`F12`): the git worker's timing for the diff
+//! on screen and the startup-to-first-frame time, so a "large diff is slow"
+//! report can say whether diffing or highlighting took the time. Follows the
+//! help overlay pattern: `Clear`, then a bordered `Paragraph`.
+
+use ratatui::{
+    Frame,
+    layout::Constraint,
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph},
+};
+
+use crate::app::{AppState, SLOW_HIGHLIGHT_MS};
+
+/// Renders the timing diagnostics as a small centred modal.
+///
+/// Skipped on terminals narrower than 40 columns, like the other overlays.
+///
+/// # Arguments
+///
+/// * `frame` — current render frame
+/// * `state` — app state supplying `diff_timing`, `first_frame_ms`, and `theme`
+pub fn render_debug_overlay(frame: &mut Frame, state: &AppState) {
+    if frame.area().width < 40 {
+        return;
+    }
+    let area = frame
+        .area()
+        .centered(Constraint::Length(40), Constraint::Length(7));
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .title(" Debug ")
+        .border_style(Style::default().fg(state.theme.border_active));
+
+    let timing = state.diff_timing;
+    let slow = if timing.highlight_ms > SLOW_HIGHLIGHT_MS { "  (slow)" } else { "" };
+    let lines = vec![
+        Line::from(format!("Diff          {} ms", timing.diff_ms)),
+        Line::from(format!("Highlight     {} ms{slow}", timing.highlight_ms)),
+        Line::from(match state.first_frame_ms {
+            Some(ms) => format!("First frame   {ms} ms"),
+            None => "First frame   -".to_owned(),
+        }),
+        Line::from(""),
+        Line::styled("any key closes", Style::default().fg(Color::DarkGray)),
+    ];
+
+    frame.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
+}
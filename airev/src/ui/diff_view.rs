@@ -6,48 +6,65 @@
 
 use ratatui::{
     Frame,
-    text::Line,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
     widgets::{List, ListItem},
 };
 
-use crate::app::{AppState, PanelFocus};
+use airev_core::types::Severity;
+
+use crate::app::{AppState, PairSide, PanelFocus};
+use crate::git::types::{DiffMode, DiffStatus};
 use crate::theme::Theme;
-use crate::ui::layout::{inner_rect, panel_block};
+use crate::ui::comment_editor::severity_color;
+use crate::ui::layout::{
+    cursor_line_style, inner_rect, panel_block, render_scrollbar, scroll_title,
+};
 
 /// Renders the diff centre panel using virtual List scrolling.
 ///
 /// Only the visible window of `state.diff_lines` is materialized into ListItems per frame.
-/// If `state.diff_lines` is empty, shows a "No diff loaded" placeholder.
+/// The line at `state.diff_cursor` is drawn reversed when it is in view, or on the
+/// `cursor_line` background while the panel is focused (see [`cursor_line_style`]). While
+/// the `o` pair toggle is active, the other side of the cursor's `-`/`+` block is dimmed.
+/// Hunk headers get a `(+N -M)` line-count annotation, and folded ones a `[+N lines]`
+/// count of the rows they hide. With the blame column on (`a`), each old-side line starts
+/// with its commit (see [`blame_span`]), and with the gutter on (`#`), with its line
+/// number (see [`line_number_span`]). A severity filter (`!`) marks commented lines (see
+/// [`severity_mark_span`]). If `state.diff_lines` is empty, shows a status-specific
+/// placeholder (see [`empty_diff_message`]). The title names the syntax of the file under
+/// the cursor (`Diff — Rust`, or `Diff — plain`) and shows the scroll position when the
+/// diff is taller than the panel (see [`scroll_title`]), as does a scrollbar on its right
+/// edge (see [`render_scrollbar`]).
 ///
 /// # Arguments
 ///
 /// * `frame` — current render frame
 /// * `area` — the `Rect` for the centre panel (includes borders)
 /// * `focus` — current panel focus (determines border style)
-/// * `state` — read-only app state supplying `diff_lines`, `diff_scroll`, and `diff_loading`
-/// * `theme` — active color theme
+/// * `state` — read-only app state supplying `diff_lines`, `diff_scroll`, `diff_loading`,
+///   and `theme`
 pub fn render_diff(
     frame: &mut Frame,
     area: ratatui::layout::Rect,
     focus: PanelFocus,
     state: &AppState,
-    theme: &Theme,
 ) {
+    let theme = &state.theme;
     let is_focused = focus == PanelFocus::Diff;
-    let block = panel_block("Diff", is_focused, theme);
     let inner = inner_rect(area);
     let viewport_height = inner.height as usize;
+    let label = match state.cursor_file() {
+        Some(file) => format!("Diff — {}", file.language),
+        None => "Diff".to_owned(),
+    };
+    let title = scroll_title(&label, state.diff_scroll, state.diff_lines.len(), viewport_height);
+    let block = panel_block(&title, is_focused, theme);
 
     frame.render_widget(block, area);
 
     if state.diff_lines.is_empty() {
-        // Show placeholder when no diff is loaded yet.
-        let msg = if state.diff_loading {
-            "Computing diff..."
-        } else {
-            "No diff loaded. Start in a git repository."
-        };
-        let items = vec![ListItem::new(Line::raw(msg))];
+        let items = vec![ListItem::new(Line::raw(empty_diff_message(state)))];
         let list = List::new(items);
         frame.render_widget(list, inner);
         return;
@@ -57,11 +74,174 @@ pub fn render_diff(
     let visible_start = state.diff_scroll.min(total.saturating_sub(1));
     let visible_end = (visible_start + viewport_height).min(total);
 
+    let pair_view = state.active_pair_view();
+    let cursor_style = cursor_line_style(state, is_focused)
+        .unwrap_or_else(|| Style::default().add_modifier(Modifier::REVERSED));
     let items: Vec<ListItem> = state.diff_lines[visible_start..visible_end]
         .iter()
-        .map(|l| ListItem::new(l.clone()))
+        .enumerate()
+        .map(|(i, l)| {
+            let idx = visible_start + i;
+            let dimmed = pair_view.as_ref().is_some_and(|(side, block)| {
+                let keep = if *side == PairSide::Old { '-' } else { '+' };
+                block.contains(&idx) && state.line_refs.get(idx).is_some_and(|r| r.origin != keep)
+            });
+            let header_counts = state
+                .line_refs
+                .get(idx)
+                .filter(|r| r.origin == 'H')
+                .and_then(|r| state.hunk_line_counts.get(r.hunk_index));
+            let mut line = match header_counts {
+                Some(&counts) => with_hunk_counts(l, counts, theme),
+                None if dimmed => dim_line(l),
+                None => l.clone(),
+            };
+            if let Some(hidden) = folded_rows(state, idx) {
+                let generated = state
+                    .line_refs
+                    .get(idx)
+                    .and_then(|r| state.file_summaries.get(r.file_index))
+                    .is_some_and(|f| f.generated);
+                let summary = if generated {
+                    format!(" [generated, +{hidden} lines]")
+                } else {
+                    format!(" [+{hidden} lines]")
+                };
+                line.spans.push(Span::styled(summary, Style::default().fg(theme.diff_context)));
+            }
+            if state.show_line_numbers {
+                line.spans.insert(0, line_number_span(state, idx));
+            }
+            if let Some(severity) = state.severity_filter {
+                line.spans.insert(0, severity_mark_span(state, severity, idx));
+            }
+            if state.blame_visible {
+                line.spans.insert(0, blame_span(state, idx));
+            }
+            let item = ListItem::new(line);
+            if idx == state.diff_cursor {
+                item.style(cursor_style)
+            } else {
+                item
+            }
+        })
         .collect();
 
     let list = List::new(items);
     frame.render_widget(list, inner);
+    render_scrollbar(frame, inner, visible_start, total, viewport_height);
+}
+
+/// Number of rows hidden under row `idx` when it is the header of a folded hunk
+/// (see [`AppState::toggle_hunk_fold`]).
+fn folded_rows(state: &AppState, idx: usize) -> Option<usize> {
+    let at = state.line_refs.get(idx).filter(|r| r.origin == 'H')?;
+    state.collapsed_hunks.get(&at.hunk_index).map(|(lines, _)| lines.len())
+}
+
+/// Returns the hunk header `line` followed by `(+added -removed)` in the theme's
+/// diff colours, leaving the raw `@@` text untouched. Headers with no changed
+/// lines (binary placeholders) are returned as-is.
+fn with_hunk_counts(
+    line: &Line<'static>,
+    (added, removed): (usize, usize),
+    theme: &Theme,
+) -> Line<'static> {
+    let mut line = line.clone();
+    if added == 0 && removed == 0 {
+        return line;
+    }
+    let dim = Style::default().fg(Color::DarkGray);
+    line.spans.extend([
+        Span::styled(" (", dim),
+        Span::styled(format!("+{added}"), Style::default().fg(theme.diff_added)),
+        Span::styled(" ", dim),
+        Span::styled(format!("-{removed}"), Style::default().fg(theme.diff_removed)),
+        Span::styled(")", dim),
+    ]);
+    line
+}
+
+/// Width of the blame column: a 7-character SHA, initials, and two spaces.
+const BLAME_WIDTH: usize = 11;
+
+/// Colors the blame column cycles through, picked per commit.
+const BLAME_COLORS: [Color; 6] =
+    [Color::Cyan, Color::Magenta, Color::Yellow, Color::Blue, Color::LightGreen, Color::LightRed];
+
+/// Returns the blame column for diff line `index`: the short SHA and author
+/// initials, in a color derived from the SHA so lines from one commit group
+/// visually. Blank for added lines, headers, and lines not blamed yet.
+fn blame_span(state: &AppState, index: usize) -> Span<'static> {
+    let Some(tag) = state.blame_tag(index) else {
+        return Span::raw(" ".repeat(BLAME_WIDTH));
+    };
+    let hash = tag.sha.bytes().fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b.into()));
+    let color = BLAME_COLORS[hash % BLAME_COLORS.len()];
+    Span::styled(format!("{} {:<2} ", tag.sha, tag.initials), Style::default().fg(color))
+}
+
+/// Returns the gutter for diff line `index`, like ` 120 │ `: the new-side line
+/// number, or the old side for a removed line, right-aligned to
+/// `line_number_width`. Blank for headers.
+fn line_number_span(state: &AppState, index: usize) -> Span<'static> {
+    let width = state.line_number_width;
+    let number = state.line_refs.get(index).and_then(|r| match r.origin {
+        '-' => r.old_lineno,
+        _ => r.new_lineno,
+    });
+    let text = match number {
+        Some(n) => format!(" {n:>width$} │ "),
+        None => format!(" {:width$} │ ", ""),
+    };
+    Span::styled(text, Style::default().fg(Color::DarkGray))
+}
+
+/// Returns the severity mark for diff line `index` while a severity filter is
+/// set: a bar in the severity's badge color when a comment of that severity is
+/// on the line (see [`AppState::line_has_visible_comment`]), blank otherwise.
+fn severity_mark_span(state: &AppState, severity: Severity, index: usize) -> Span<'static> {
+    if !state.line_has_visible_comment(index) {
+        return Span::raw("  ");
+    }
+    let color = severity_color(severity.as_str(), &state.theme);
+    Span::styled("▌ ", Style::default().fg(color))
+}
+
+/// Returns `line` with every span recoloured dark gray and its background dropped,
+/// for the hidden side of the pair toggle.
+fn dim_line(line: &Line<'static>) -> Line<'static> {
+    let spans = line.spans.iter().map(|s| s.clone().style(Style::default().fg(Color::DarkGray)));
+    Line::from(spans.collect::<Vec<_>>())
+}
+
+/// Chooses the placeholder shown when there are no diff lines to render.
+///
+/// Distinguishes a stopped git worker, an in-flight request, a missing
+/// repository, a git error, and a mode that legitimately has no changes (e.g.
+/// "Working tree clean" for `Unstaged`), so an empty diff never reads like a failure.
+pub fn empty_diff_message(state: &AppState) -> String {
+    if let Some(reason) = &state.git_worker_error {
+        return format!("Git worker stopped: {reason} — press R to restart.");
+    }
+    if state.diff_loading {
+        return "Computing diff...".to_owned();
+    }
+    match &state.diff_status {
+        DiffStatus::Pending => "No diff loaded.".to_owned(),
+        DiffStatus::NoRepo => {
+            "Not a git repository. Start airev inside a git work tree, \
+             or run `airev --files OLD NEW` to diff two files."
+                .to_owned()
+        }
+        DiffStatus::Error(msg) => format!("Could not compute diff: {msg}"),
+        DiffStatus::Loaded => match state.diff_mode {
+            DiffMode::Unstaged => "Working tree clean — no unstaged changes.".to_owned(),
+            DiffMode::Staged => "Nothing staged.".to_owned(),
+            DiffMode::BranchComparison => "No changes relative to the base branch.".to_owned(),
+            DiffMode::CommitRange => "No changes in this commit range.".to_owned(),
+            DiffMode::Stash => "Nothing stashed.".to_owned(),
+            DiffMode::Files => "The two files are identical.".to_owned(),
+        },
+    }
 }
@@ -1,25 +1,33 @@
 //! File list panel renderer for airev.
 //!
 //! Renders the left file-list panel from AppState.file_summaries. Each entry shows
-//! a status badge (M/A/D/R), filename, and +N/-N change counts. When file_summaries
-//! is empty, shows a "No files" placeholder matching the diff loading state.
+//! a status badge (M/A/D/R), filename, and +N/-N change counts, or a `[bin]` tag
+//! for binary files. Files marked `linguist-generated` are drawn dimmed. When
+//! file_summaries is empty, shows a "No files" placeholder matching the diff
+//! loading state. While a filter is typed (`f`) or set, only the matching files
+//! are listed and the title shows the query with the match count.
 
 use ratatui::{
     Frame,
     style::{Color, Style},
     text::{Line, Span},
-    widgets::{List, ListItem},
+    widgets::{List, ListItem, ListState},
 };
 
-use crate::app::{AppState, PanelFocus};
+use crate::app::{AppState, Mode, PanelFocus};
 use crate::git::types::FileSummary;
 use crate::theme::Theme;
-use crate::ui::layout::panel_block;
+use crate::ui::layout::{cursor_line_style, inner_rect, panel_block};
 
 /// Renders the file-list left panel from `AppState.file_summaries`.
 ///
-/// Uses `render_stateful_widget` so the ListState selection highlight is applied.
-/// File count is shown in the panel title block (e.g., "Files (12)").
+/// Like the diff panel, only the visible window of `file_summaries` is
+/// materialized into ListItems (see [`AppState::file_list_window`]), so a diff
+/// touching thousands of files costs no more per frame than a small one. The
+/// window is drawn with its own `ListState` for the selection highlight. File
+/// count is shown in the panel title block (e.g., "Files (12)"), or with a
+/// filter the matches out of all files and the query (e.g., "Files 3/12 /view").
+/// Rows are mapped to files with [`AppState::file_at_row`].
 ///
 /// # Arguments
 ///
@@ -27,17 +35,22 @@ use crate::ui::layout::panel_block;
 /// * `area` — the `Rect` for the left panel (includes borders)
 /// * `focus` — current panel focus (determines border style)
 /// * `state` — mutable app state providing `file_summaries`, `diff_loading`, and `file_list_state`
-/// * `theme` — active color theme
 pub fn render_file_list(
     frame: &mut Frame,
     area: ratatui::layout::Rect,
     focus: PanelFocus,
     state: &mut AppState,
-    theme: &Theme,
 ) {
+    let window = state.file_list_window(inner_rect(area).height as usize);
+    let theme = &state.theme;
     let is_focused = focus == PanelFocus::FileList;
     let file_count = state.file_summaries.len();
-    let title = if file_count > 0 {
+    let filtering = state.mode == Mode::FileFilter;
+    let title = if filtering || !state.file_filter.is_empty() {
+        let cursor = if filtering { "_" } else { "" };
+        let shown = state.file_list_len();
+        format!("Files {shown}/{file_count} /{}{cursor}", state.file_filter)
+    } else if file_count > 0 {
         format!("Files ({})", file_count)
     } else {
         "Files".to_owned()
@@ -47,25 +60,33 @@ pub fn render_file_list(
     let items: Vec<ListItem> = if state.file_summaries.is_empty() {
         let msg = if state.diff_loading { "Loading..." } else { "No files" };
         vec![ListItem::new(Line::raw(msg))]
+    } else if state.file_list_len() == 0 {
+        vec![ListItem::new(Line::raw("No matches"))]
     } else {
-        state.file_summaries.iter().map(|f| {
+        window.clone().filter_map(|row| state.file_at_row(row)).map(|idx| {
+            let f = &state.file_summaries[idx];
             let reviewed = state.file_review_states.get(&f.path).copied().unwrap_or(false);
             file_summary_item(f, reviewed, theme)
         }).collect()
     };
 
-    let list = List::new(items)
-        .block(block)
-        .highlight_style(Style::default().fg(theme.border_active));
+    // The focused list shades the selected row; otherwise its text is recolored.
+    let highlight = cursor_line_style(state, is_focused)
+        .unwrap_or_else(|| Style::default().fg(theme.border_active));
+    let list = List::new(items).block(block).highlight_style(highlight);
 
-    frame.render_stateful_widget(list, area, &mut state.file_list_state);
+    let selected = state.file_list_state.selected().and_then(|s| s.checked_sub(window.start));
+    let mut window_state = ListState::default().with_selected(selected);
+    frame.render_stateful_widget(list, area, &mut window_state);
 }
 
 /// Converts a FileSummary into a styled ListItem.
 ///
 /// Format: `[x] [M] src/main.rs  +42 -7` when reviewed, `[ ] [M] src/...` when not.
 /// Badge colors: M=Yellow, A=Green, D=Red, R=Cyan.
-/// Review mark colors: reviewed=Green, unreviewed=DarkGray.
+/// Review mark colors: reviewed=Green, unreviewed=DarkGray. Generated files draw
+/// the badge and path in DarkGray too, so hand-written changes stand out. Binary
+/// files have no line counts and end in a Magenta `[bin]` tag instead.
 fn file_summary_item(f: &FileSummary, reviewed: bool, _theme: &Theme) -> ListItem<'static> {
     let review_mark = if reviewed {
         Span::styled("[x] ", Style::default().fg(Color::Green))
@@ -73,6 +94,7 @@ fn file_summary_item(f: &FileSummary, reviewed: bool, _theme: &Theme) -> ListIte
         Span::styled("[ ] ", Style::default().fg(Color::DarkGray))
     };
     let badge_color = match f.status {
+        _ if f.generated => Color::DarkGray,
         'A' => Color::Green,
         'D' => Color::Red,
         'R' => Color::Cyan,
@@ -89,8 +111,14 @@ fn file_summary_item(f: &FileSummary, reviewed: bool, _theme: &Theme) -> ListIte
     } else {
         f.path.clone()
     };
-    let path_span = Span::raw(path_display);
-    let counts = if f.added > 0 || f.removed > 0 {
+    let path_span = if f.generated {
+        Span::styled(path_display, Style::default().fg(Color::DarkGray))
+    } else {
+        Span::raw(path_display)
+    };
+    let counts = if f.binary {
+        Span::styled("  [bin]", Style::default().fg(Color::Magenta))
+    } else if f.added > 0 || f.removed > 0 {
         Span::styled(
             format!("  +{} -{}", f.added, f.removed),
             Style::default().fg(Color::DarkGray),
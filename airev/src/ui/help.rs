@@ -13,14 +13,15 @@ use ratatui::{
     widgets::{Block, Clear, Paragraph, Wrap},
 };
 
+use crate::keymap::{Action, KeyMap};
 use crate::theme::Theme;
 
 /// Renders the help overlay as a centred modal on top of the 3-panel layout.
 ///
 /// Erases the overlay area with `Clear`, then draws a bordered `Block` titled
 /// `" Help  — j/k scroll, ? or Esc to dismiss "` and a `Paragraph` containing all
-/// keybinding descriptions. The paragraph scrolls vertically by `help_scroll` rows,
-/// enabling navigation of long help text on short terminals.
+/// keybinding descriptions. The paragraph scrolls vertically by `help_scroll`
+/// rows, enabling navigation of long help text on short terminals.
 ///
 /// If the terminal is narrower than 60 columns the overlay is skipped to avoid
 /// a zero-height `Rect` panic (Pitfall 6 from Phase 2 research).
@@ -29,8 +30,9 @@ use crate::theme::Theme;
 ///
 /// * `frame` — current render frame provided by `terminal.draw()`
 /// * `theme` — active color theme (supplies `border_active` for the modal border)
+/// * `keymap` — active key bindings, shown for the remappable keys
 /// * `help_scroll` — vertical scroll offset; j/k in HelpOverlay mode mutate this field
-pub fn render_help_overlay(frame: &mut Frame, theme: &Theme, help_scroll: u16) {
+pub fn render_help_overlay(frame: &mut Frame, theme: &Theme, keymap: &KeyMap, help_scroll: u16) {
     // Guard: skip on very narrow terminals to prevent zero-height Rect (Pitfall 6).
     if frame.area().width < 60 {
         return;
@@ -47,7 +49,7 @@ pub fn render_help_overlay(frame: &mut Frame, theme: &Theme, help_scroll: u16) {
         .title(" Help  — j/k scroll, ? or Esc to dismiss ")
         .border_style(ratatui::style::Style::default().fg(theme.border_active));
 
-    let help_text = build_help_text();
+    let help_text = build_help_text(keymap);
 
     frame.render_widget(
         Paragraph::new(help_text)
@@ -60,33 +62,106 @@ pub fn render_help_overlay(frame: &mut Frame, theme: &Theme, help_scroll: u16) {
 
 /// Builds the help text as a multi-line `Text` value.
 ///
-/// Returns all keybinding descriptions grouped by section.  No color styling is
-/// applied to the body text — theme coloring for help content is reserved for
-/// Phase 5+ polish.
-fn build_help_text() -> Text<'static> {
+/// Returns all keybinding descriptions grouped by section, with the remappable
+/// keys taken from `keymap`.  No color styling is applied to the body text — theme
+/// coloring for help content is reserved for Phase 5+ polish.
+fn build_help_text(keymap: &KeyMap) -> Text<'static> {
+    let bound = |actions: &[Action], text: &str| {
+        let keys: Vec<String> = actions.iter().map(|&action| keymap.label(action)).collect();
+        entry(&keys.join(" / "), text)
+    };
     Text::from(vec![
         Line::from("Navigation"),
-        Line::from("  j / k         Scroll down / up one line"),
-        Line::from("  g / G         Jump to top / bottom"),
-        Line::from("  Ctrl-d / u    Scroll half page down / up"),
-        Line::from("  Ctrl-f / b    Scroll full page down / up"),
-        Line::from("  H / L         Move panel focus left / right"),
+        bound(&[Action::ScrollDown, Action::ScrollUp], "Scroll down / up one line"),
+        entry(
+            &format!("gg / {}", keymap.label(Action::ScrollBottom)),
+            "Jump to top / bottom (g waits for its second key)",
+        ),
+        bound(&[Action::HalfPageDown, Action::HalfPageUp], "Scroll half page down / up"),
+        bound(&[Action::PageDown, Action::PageUp], "Scroll full page down / up"),
+        bound(&[Action::FocusLeft, Action::FocusRight], "Move panel focus left / right"),
+        entry("5j / 3] ...", "A count repeats line / page scrolls and file / hunk moves"),
         Line::from(""),
         Line::from("File List"),
-        Line::from("  { / }         Previous / next file"),
-        Line::from("  Enter / l     Jump to selected file in diff view"),
-        Line::from("  r             Toggle file reviewed (file list)"),
+        bound(&[Action::PrevFile, Action::NextFile], "Previous / next file"),
+        bound(&[Action::JumpToFile], "Jump to selected file in diff view"),
+        bound(&[Action::ToggleReviewed], "Toggle file reviewed (file list)"),
+        bound(&[Action::MarkDirectoryReviewed], "Mark directory reviewed (file list)"),
+        bound(&[Action::FilterFiles], "Fuzzy-filter paths; Enter keeps, Esc clears (file list)"),
         Line::from(""),
         Line::from("Diff View"),
-        Line::from("  [ / ]         Previous / next hunk"),
-        Line::from("  < / >         Shrink / grow diff panel by 5%"),
+        bound(&[Action::PrevHunk, Action::NextHunk], "Previous / next hunk"),
+        entry("za", "Fold / unfold the hunk at the cursor"),
+        bound(&[Action::ToggleFold], "Fold / unfold the hunk (on its header)"),
+        bound(&[Action::Search], "Search the diff text (case-insensitive)"),
+        bound(&[Action::SearchNext, Action::SearchPrev], "Next / previous search match"),
+        bound(&[Action::ShrinkDiff, Action::GrowDiff], "Shrink / grow diff panel by 5%"),
+        entry(":layout L C R", "Set panel widths in percent (sum 100)"),
+        bound(&[Action::ToggleDiffOnly], "Diff only: hide / show both side panels"),
+        entry(":theme NAME", "Switch theme (built-in or a config [themes.NAME])"),
+        bound(&[Action::CycleTheme], "Cycle themes (saved to config on quit)"),
+        bound(&[Action::TogglePairView], "Old only / new only / both for the -/+ pair at cursor"),
+        bound(&[Action::ToggleBlame], "Blame column: commit and author of old-side lines"),
+        bound(&[Action::ToggleWhitespace], "Ignore whitespace changes ([ws] in the status bar)"),
+        bound(
+            &[Action::FewerContext, Action::MoreContext],
+            "Fewer / more context lines (0-15, saved per session)",
+        ),
+        bound(&[Action::ToggleLineNumbers], "Line-number gutter (new side, old side for removed)"),
+        bound(&[Action::OpenEditor], "Open file at cursor line in $EDITOR / editor_command"),
+        bound(
+            &[Action::CopyReference, Action::CopyPermalink],
+            "Copy path:line / GitHub permalink of the cursor line",
+        ),
         Line::from(""),
-        Line::from("Diff Mode  (Tab cycles through all modes)"),
-        Line::from("  Unstaged  ->  Staged  ->  Branch vs main  ->  Commit Range"),
+        Line::from("Comments"),
+        bound(&[Action::Comment], "Comment on the selected file (or hunk header)"),
+        bound(&[Action::CommentHunk], "Comment on the hunk under the diff cursor"),
+        bound(&[Action::CommentLine], "Comment on the diff cursor line"),
+        bound(&[Action::ExportComments], "Export all comments to .airev/review-<time>.md"),
+        entry("Enter", "Finish typing, then pick severity / type"),
+        entry("Ctrl-s", "Save the draft with the current severity / type"),
+        entry("1-4", "Severity: critical / major / minor / info"),
+        entry("j / k", "Cycle comment type (default: concern)"),
+        entry("Esc", "Back / discard draft"),
+        bound(
+            &[Action::ScrollDown, Action::ScrollUp],
+            "Select next / previous comment (comments panel)",
+        ),
+        entry("gn / gp", "Select next / previous comment (any panel)"),
+        bound(&[Action::ToggleResolved], "Resolve / reopen the selected comment (comments panel)"),
+        bound(&[Action::HideResolved], "Hide / show resolved comments"),
+        bound(
+            &[Action::CycleCommentFilter],
+            "Filter: all -> critical+major -> critical (not file list)",
+        ),
+        bound(
+            &[Action::CycleSeverityFilter],
+            "Only one severity: critical -> major -> minor -> info -> all (marked in diff)",
+        ),
+        Line::from(""),
+        Line::from(format!(
+            "Diff Mode  ({} cycles through all modes)",
+            keymap.label(Action::CycleDiffMode)
+        )),
+        Line::from("  Unstaged  ->  Staged  ->  Branch vs base  ->  Stash  ->  Commit Range"),
+        bound(&[Action::PickMode], "Pick a mode, or type a range / commit / stash (Tab completes)"),
+        entry(":range A..B", "Load the diff between two refs (remembered per repo)"),
         Line::from(""),
         Line::from("General"),
-        Line::from("  j / k         Scroll this help overlay"),
-        Line::from("  ?             Open / close this help overlay"),
-        Line::from("  q / Esc       Quit (confirms if unsaved comments exist)"),
+        entry("j / k", "Scroll this help overlay"),
+        bound(&[Action::Help], "Open this help overlay (? or Esc closes it)"),
+        bound(&[Action::DebugOverlay], "Debug overlay: git worker diff / highlight timing"),
+        bound(&[Action::RestartWorker], "Restart the git worker (stopped / repo unavailable)"),
+        bound(&[Action::DismissBanner], "Dismiss the drifted-HEAD banner, adopting the new HEAD"),
+        entry(":note", "Save the review summary as a git note on HEAD"),
+        bound(&[Action::Quit], "Quit (confirms if unsaved comments exist)"),
+        Line::from("  Keys other than counts, prefixes, and : commands are remappable in the"),
+        Line::from("  config's [keybindings] table."),
     ])
 }
+
+/// One help line: `keys` padded to a 13-column key column, then `text`.
+fn entry(keys: &str, text: &str) -> Line<'static> {
+    Line::from(format!("  {keys:<13} {text}"))
+}
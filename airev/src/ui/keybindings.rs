@@ -2,15 +2,23 @@
 //!
 //! Translates raw crossterm `KeyEvent`s into `AppState` mutations and returns a
 //! `KeyAction` telling the event loop whether to continue or quit.  The dispatcher
-//! branches first on `state.mode` so that HelpOverlay, ConfirmQuit, Insert, and Normal
-//! all have isolated handler functions.
+//! branches first on `state.mode` so that HelpOverlay, ConfirmQuit, Insert, CommentTag,
+//! StartupError, and Normal all have isolated handler functions.
+
+use std::time::Instant;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::layout::Position;
+use tokio::sync::mpsc::UnboundedSender;
+
+use airev_core::types::{Comment, SEVERITIES};
 
 use crate::app::{AppState, Mode, PanelFocus};
 use crate::event::{AppEvent, DbResultPayload};
-use crate::git::types::{DiffMode, GitRequest};
+use crate::git::types::DiffMode;
+use crate::keymap::Action;
+use crate::ui::comments::comment_at_row;
+use crate::ui::layout::inner_rect;
 
 /// Control-flow signal returned from the key dispatcher.
 ///
@@ -22,6 +30,10 @@ pub enum KeyAction {
     Continue,
     /// Exit cleanly (no unsaved state — the caller may skip the confirm dialog).
     Quit,
+    /// Respawn the git worker thread after it stopped (`R`).
+    RestartGitWorker,
+    /// Open the cursor's file in the external editor (`e`; see `editor`).
+    OpenEditor,
 }
 
 /// Dispatches a key event to the handler matching the current mode.
@@ -37,9 +49,19 @@ pub enum KeyAction {
 pub fn handle_key(key: KeyEvent, state: &mut AppState) -> KeyAction {
     match state.mode {
         Mode::HelpOverlay => handle_help(key, state),
+        Mode::DebugOverlay => {
+            state.mode = Mode::Normal;
+            KeyAction::Continue
+        }
         Mode::ConfirmQuit => handle_confirm_quit(key, state),
         Mode::Normal => handle_normal(key, state),
         Mode::Insert => handle_insert(key, state),
+        Mode::CommentTag => handle_comment_tag(key, state),
+        Mode::StartupError => handle_startup_error(key, state),
+        Mode::Command => handle_command(key, state),
+        Mode::Search => handle_search(key, state),
+        Mode::FileFilter => handle_file_filter(key, state),
+        Mode::DiffModePicker => handle_mode_picker(key, state),
     }
 }
 
@@ -49,106 +71,266 @@ pub fn handle_key(key: KeyEvent, state: &mut AppState) -> KeyAction {
 
 /// Handles a key event while in Normal mode.
 ///
-/// Delegates scroll keys to `handle_scroll_key`, file-list-specific keys to
-/// `handle_file_list_key`, and handles focus, panel resize, hunk navigation,
-/// and mode transitions inline.
+/// Digits typed first build a count for the next motion, as in vim; `g` and `z`
+/// start a two-key sequence finished by `handle_key_sequence`. The key itself
+/// goes to `handle_normal_key`, which maps it to actions through `state.keymap`
+/// and runs them with `handle_action`.
 ///
 /// # Arguments
 ///
 /// * `key`   — the raw crossterm key event
 /// * `state` — mutable reference to all UI state
 fn handle_normal(key: KeyEvent, state: &mut AppState) -> KeyAction {
-    // Try scroll keys first (j/k/g/G/Ctrl-d/u/f/b).
-    if let Some(action) = handle_scroll_key(key, state) {
-        return action;
-    }
-    // Try file-list-specific keys (Enter, l, Tab) when focused on the file list.
-    if let Some(action) = handle_file_list_key(key, state) {
-        return action;
+    // Digits build a count for the next motion (`20j`, `5]`); `0` only continues one.
+    if let (KeyCode::Char(ch @ '0'..='9'), None) = (key.code, state.pending_key) {
+        if ch != '0' || state.pending_count.is_some() {
+            state.push_count_digit(ch.to_digit(10).unwrap_or(0));
+            return KeyAction::Continue;
+        }
     }
+    let action = handle_normal_key(key, state);
+    // A motion took the count; any other key drops it.
+    state.pending_count = None;
+    action
+}
 
-    match key.code {
-        // Panel focus
-        KeyCode::Char('H') => {
-            state.focus = state.focus.prev();
-            KeyAction::Continue
+/// Handles a Normal-mode key other than a count digit (see [`handle_normal`]).
+///
+/// The key is looked up in `state.keymap`; the actions bound to it are tried in
+/// [`Action::ALL`] order until one applies (see [`handle_action`]). Motions
+/// that take a count read it from `state.pending_count`.
+fn handle_normal_key(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    // Complete a pending two-key sequence (gg / gn / gp / za) before anything else.
+    if let Some(prefix) = state.pending_key.take() {
+        return handle_key_sequence(prefix, key, state);
+    }
+    let actions = state.keymap.actions_for(&key);
+    // Until the startup session has loaded, everything but quit and help would
+    // either need the session or be overwritten when it arrives.
+    if state.session_loading && !actions.iter().any(|a| matches!(a, Action::Quit | Action::Help)) {
+        state.push_notification("opening session…".to_owned());
+        return KeyAction::Continue;
+    }
+    // Prefix keys: resolved by handle_key_sequence on the next keypress.
+    if let KeyCode::Char(prefix @ ('g' | 'z')) = key.code {
+        if key.modifiers.difference(KeyModifiers::SHIFT).is_empty() {
+            state.pending_key = Some(prefix);
+            return KeyAction::Continue;
         }
-        KeyCode::Char('L') => {
-            state.focus = state.focus.next();
-            KeyAction::Continue
+    }
+    for action in actions {
+        if let Some(result) = handle_action(action, state) {
+            return result;
         }
+    }
+    KeyAction::Continue
+}
+
+/// Runs a Normal-mode action, trying the scroll and file-list actions first.
+///
+/// Returns `None` when the action does not apply in the current state (e.g.
+/// `ToggleFold` away from a hunk header), so the next action bound to the same
+/// key can be tried.
+///
+/// # Arguments
+///
+/// * `action` — an action bound to the received key
+/// * `state`  — mutable reference to all UI state
+fn handle_action(action: Action, state: &mut AppState) -> Option<KeyAction> {
+    if let Some(result) = handle_scroll_action(action, state) {
+        return Some(result);
+    }
+    if let Some(result) = handle_file_list_action(action, state) {
+        return Some(result);
+    }
+
+    match action {
+        // Panel focus
+        Action::FocusLeft => state.focus_prev(),
+        Action::FocusRight => state.focus_next(),
 
         // File list navigation
-        KeyCode::Char('{') => { state.prev_file(); KeyAction::Continue }
-        KeyCode::Char('}') => { state.next_file(); KeyAction::Continue }
+        Action::PrevFile => state.prev_file(),
+        Action::NextFile => state.next_file(),
 
         // Hunk navigation — calls real AppState methods wired in Phase 3 Plan 02.
-        KeyCode::Char('[') => { state.prev_hunk(); KeyAction::Continue }
-        KeyCode::Char(']') => { state.next_hunk(); KeyAction::Continue }
+        Action::PrevHunk => state.prev_hunk(),
+        Action::NextHunk => state.next_hunk(),
+
+        // Show only the old or new side of the -/+ pair at the cursor.
+        Action::TogglePairView => state.toggle_pair_view(),
+
+        // Show or hide the blame column of old-side lines.
+        Action::ToggleBlame => state.toggle_blame(),
+
+        // Ignore whitespace changes, or stop ignoring them, and reload the diff.
+        Action::ToggleWhitespace => state.toggle_ignore_whitespace(),
+
+        // Show fewer or more context lines around each change.
+        Action::FewerContext => handle_change_context_lines(state, -1),
+        Action::MoreContext => handle_change_context_lines(state, 1),
+
+        // Show or hide the line-number gutter.
+        Action::ToggleLineNumbers => state.toggle_line_numbers(),
+
+        // Fold or unfold the hunk whose header is under the diff cursor (`za` works anywhere).
+        Action::ToggleFold if state.focus == PanelFocus::Diff && state.cursor_on_hunk_header() => {
+            state.toggle_hunk_fold();
+        }
+
+        // Resolve or reopen the selected comment (comments panel only).
+        Action::ToggleResolved if state.focus == PanelFocus::Comments => {
+            handle_toggle_resolved(state);
+        }
+        Action::HideResolved => state.toggle_hide_resolved(),
+
+        // Dismiss the drift banner, adopting the current HEAD.
+        Action::DismissBanner if state.drift_warning.is_some() => handle_dismiss_drift(state),
+
+        // Restart the git worker after it stopped or the repository became unavailable.
+        Action::RestartWorker
+            if state.git_worker_error.is_some() || state.repo_unavailable.is_some() =>
+        {
+            return Some(KeyAction::RestartGitWorker);
+        }
+
+        // Start composing a comment on the selected file, or on the hunk when the
+        // diff cursor sits on its header. `CommentHunk` comments on the cursor's hunk anywhere.
+        Action::Comment if state.focus == PanelFocus::Diff && state.cursor_on_hunk_header() => {
+            state.begin_hunk_comment();
+        }
+        Action::Comment => state.begin_comment(),
+        Action::CommentHunk => state.begin_hunk_comment(),
+        Action::CommentLine if state.focus == PanelFocus::Diff => state.begin_line_comment(),
+
+        // Write every comment in the session to a Markdown report.
+        Action::ExportComments => handle_export_comments(state),
+
+        // Cycle the comment severity filter (`f` in the file list filters files).
+        Action::CycleCommentFilter => handle_cycle_comment_filter(state),
+        Action::CycleSeverityFilter => state.cycle_severity_filter(),
+
+        // Hide or show both side panels.
+        Action::ToggleDiffOnly => handle_toggle_diff_only(state),
 
         // Diff panel resize
-        KeyCode::Char('<') => { state.shrink_diff_panel(); KeyAction::Continue }
-        KeyCode::Char('>') => { state.grow_diff_panel(); KeyAction::Continue }
+        Action::ShrinkDiff => state.shrink_diff_panel(),
+        Action::GrowDiff => state.grow_diff_panel(),
+        Action::Command => state.begin_command(),
+
+        // Search the diff text; repeat the last search forward / backward.
+        Action::Search => state.begin_search(),
+        Action::SearchNext => state.search_next(true),
+        Action::SearchPrev => state.search_next(false),
+
+        // Open the cursor's file and line in the external editor.
+        Action::OpenEditor => return Some(KeyAction::OpenEditor),
+
+        // Copy `path:line`, or a GitHub permalink, for the cursor line.
+        Action::CopyReference => handle_copy_reference(state, false),
+        Action::CopyPermalink => handle_copy_reference(state, true),
+
+        // Pick the diff mode from a list, including a range, commit, or stash.
+        Action::PickMode if state.diff_mode == DiffMode::Files => {
+            state.push_notification("a --files review has no other diff modes".to_owned());
+        }
+        Action::PickMode => state.begin_mode_picker(),
+
+        // Cycle the built-in color themes.
+        Action::CycleTheme => state.cycle_theme(),
 
         // Help overlay
-        KeyCode::Char('?') => {
+        Action::Help => {
             state.help_scroll = 0;
             state.mode = Mode::HelpOverlay;
-            KeyAction::Continue
         }
+        Action::DebugOverlay => state.mode = Mode::DebugOverlay,
 
         // Quit / confirm-quit
-        KeyCode::Char('q') | KeyCode::Esc => {
-            if state.has_unsaved_comments {
-                state.mode = Mode::ConfirmQuit;
-                KeyAction::Continue
-            } else {
-                KeyAction::Quit
-            }
-        }
+        Action::Quit if state.has_unsaved_comments => state.mode = Mode::ConfirmQuit,
+        Action::Quit => return Some(KeyAction::Quit),
 
-        _ => KeyAction::Continue,
+        _ => return None,
     }
+    Some(KeyAction::Continue)
 }
 
-/// Handles file-list-specific keys in Normal mode: Enter, l (jump), Tab (mode cycle).
+/// Completes a two-key Normal-mode sequence started by `prefix`.
 ///
-/// Returns `Some(KeyAction)` when the key was consumed, `None` when the key
-/// should fall through to the rest of the Normal handler.
+/// `gg` scrolls the focused panel to the top; `gn` / `gp` select the next /
+/// previous comment from any panel; `za` folds or unfolds the hunk under the
+/// diff cursor. Any other follow-up key cancels the prefix and is dropped.
 ///
 /// # Arguments
 ///
-/// * `key`   — the raw crossterm key event
-/// * `state` — mutable reference to all UI state
-fn handle_file_list_key(key: KeyEvent, state: &mut AppState) -> Option<KeyAction> {
-    match key.code {
-        // Enter and l both jump to the selected file when the file list is focused.
-        KeyCode::Enter
-        | KeyCode::Char('l') if state.focus == PanelFocus::FileList => {
+/// * `prefix` — the first key of the sequence (`g` or `z`)
+/// * `key`    — the second key
+/// * `state`  — mutable reference to all UI state
+fn handle_key_sequence(prefix: char, key: KeyEvent, state: &mut AppState) -> KeyAction {
+    match (prefix, key.code) {
+        ('g', KeyCode::Char('g')) => state.scroll_top(),
+        ('g', KeyCode::Char('n')) => state.next_comment(),
+        ('g', KeyCode::Char('p')) => state.prev_comment(),
+        ('z', KeyCode::Char('a')) => state.toggle_hunk_fold(),
+        _ => {}
+    }
+    KeyAction::Continue
+}
+
+/// Runs the file-list actions: jump, toggle reviewed, filter, mark directory
+/// reviewed (file list focus only), and the diff mode cycle (any focus).
+///
+/// Returns `Some(KeyAction)` when the action was handled, `None` when it is not
+/// a file-list action or does not apply to the focused panel.
+///
+/// # Arguments
+///
+/// * `action` — an action bound to the received key
+/// * `state`  — mutable reference to all UI state
+fn handle_file_list_action(action: Action, state: &mut AppState) -> Option<KeyAction> {
+    let file_list = state.focus == PanelFocus::FileList;
+    match action {
+        // Jump to the selected file when the file list is focused.
+        Action::JumpToFile if file_list => {
             state.jump_to_selected_file();
             Some(KeyAction::Continue)
         }
 
-        // Toggle file reviewed state (r key, file list panel only).
-        KeyCode::Char('r') if state.focus == PanelFocus::FileList => {
+        // Toggle file reviewed state (file list panel only).
+        Action::ToggleReviewed if file_list => {
             handle_toggle_review(state);
             Some(KeyAction::Continue)
         }
 
-        // Tab cycles the diff mode regardless of focused panel, then sends a new request.
-        KeyCode::Tab => {
+        // Filter the file list by a fuzzy path query (file list panel only).
+        Action::FilterFiles if file_list => {
+            state.begin_file_filter();
+            Some(KeyAction::Continue)
+        }
+
+        // Mark every file in the selected file's directory reviewed (file list panel only).
+        Action::MarkDirectoryReviewed if file_list => {
+            handle_mark_directory_reviewed(state);
+            Some(KeyAction::Continue)
+        }
+
+        // Cycle the diff mode regardless of focused panel, then send a new request.
+        // A `--files` diff has no repository behind it, so there is nothing to cycle to.
+        // Reaching the commit range before one was entered asks for its refs instead.
+        Action::CycleDiffMode if state.diff_mode != DiffMode::Files => {
             let next_mode = match state.diff_mode {
                 DiffMode::Unstaged => DiffMode::Staged,
                 DiffMode::Staged => DiffMode::BranchComparison,
-                DiffMode::BranchComparison => DiffMode::CommitRange,
-                DiffMode::CommitRange => DiffMode::Unstaged,
+                DiffMode::BranchComparison => DiffMode::Stash,
+                DiffMode::Stash => DiffMode::CommitRange,
+                DiffMode::CommitRange | DiffMode::Files => DiffMode::Unstaged,
             };
-            state.diff_mode = next_mode;
-            state.diff_loading = true;
-            state.diff_scroll = 0;
-            if let Some(ref tx) = state.git_tx {
-                let _ = tx.send(GitRequest::LoadDiff(next_mode));
+            if next_mode == DiffMode::CommitRange && state.diff_range.is_none() {
+                state.begin_range_prompt();
+                return Some(KeyAction::Continue);
             }
+            state.switch_diff_mode(next_mode);
+            handle_save_diff_mode(state);
             Some(KeyAction::Continue)
         }
 
@@ -193,60 +375,369 @@ fn handle_toggle_review(state: &mut AppState) {
                 )));
             }
             Err(e) => {
-                // Log to stderr (the terminal backend) — visible in debug but not
-                // disruptive in normal use since stderr IS the terminal.
-                eprintln!("airev: DB toggle error: {e}");
+                let _ = tx.send(AppEvent::DbResult(Box::new(
+                    DbResultPayload::ReviewToggleFailed {
+                        file_path,
+                        reviewed: current,
+                        message: e.to_string(),
+                    },
+                )));
             }
         }
     });
 }
 
-/// Handles scroll-related keys in Normal mode: j / k / g / G and Ctrl combos.
+/// Marks every unreviewed file under the selected file's directory as reviewed.
 ///
-/// Returns `Some(KeyAction)` when the key was consumed, `None` when the key
-/// should fall through to the rest of the Normal handler.
+/// Updates `file_review_states` optimistically, shows a toast with the count, and
+/// spawns one `batch_set_reviewed()` transaction whose result comes back as
+/// `DbResultPayload::FilesMarkedReviewed`. Does nothing if no DB connection, no
+/// session, or no file is selected.
+fn handle_mark_directory_reviewed(state: &mut AppState) {
+    if state.db_conn.is_none() || state.session.is_none() || state.event_tx.is_none() {
+        return;
+    }
+    let Some((dir, file_paths)) = state.unreviewed_files_in_selected_dir() else {
+        return;
+    };
+
+    let label = if dir.is_empty() { "the repository root".to_owned() } else { dir };
+    if file_paths.is_empty() {
+        state.push_notification(format!("all files in {label} already reviewed"));
+        return;
+    }
+    for path in &file_paths {
+        state.file_review_states.insert(path.clone(), true);
+    }
+    let noun = if file_paths.len() == 1 { "file" } else { "files" };
+    state.push_notification(format!("marked {} {noun} in {label} reviewed", file_paths.len()));
+    persist_reviewed(state, file_paths);
+}
+
+/// Resolves or reopens the selected comment and spawns an async DB task to
+/// save it with `set_comment_resolved()`.
 ///
-/// # Arguments
+/// Fire-and-forget like the filter save. Without a DB connection the change is
+/// kept for this run only.
+fn handle_toggle_resolved(state: &mut AppState) {
+    let Some((comment_id, resolved)) = state.toggle_comment_resolved(unix_secs()) else {
+        return;
+    };
+    let Some(conn) = state.db_conn.clone() else {
+        return;
+    };
+    let tx = state.event_tx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_comment_resolved(&conn, &comment_id, resolved).await {
+            report_save_failed(tx.as_ref(), "resolved state", e);
+        }
+    });
+}
+
+/// Reports a failed fire-and-forget write as `DbResultPayload::SaveFailed`, which
+/// shows it as a notification. Printing it would draw over the TUI, which owns
+/// the terminal. Does nothing without an event sender.
+fn report_save_failed(
+    tx: Option<&UnboundedSender<AppEvent>>,
+    what: &str,
+    error: impl std::fmt::Display,
+) {
+    if let Some(tx) = tx {
+        let message = format!("cannot save {what}: {error}");
+        let _ = tx.send(AppEvent::DbResult(Box::new(DbResultPayload::SaveFailed(message))));
+    }
+}
+
+/// The current time in whole seconds since the Unix epoch.
+fn unix_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Spawns a task writing the session's comments to `.airev/review-<unix time>.md`
+/// (see `db::export_comments_markdown`).
 ///
-/// * `key`   — the raw crossterm key event
-/// * `state` — mutable reference to all UI state
-fn handle_scroll_key(key: KeyEvent, state: &mut AppState) -> Option<KeyAction> {
-    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+/// The path written, or the error, comes back as
+/// `DbResultPayload::CommentsExported` and is shown as a toast. Without a DB
+/// connection or session, a toast says there is nothing to export.
+fn handle_export_comments(state: &mut AppState) {
+    let (Some(conn), Some(session), Some(tx)) =
+        (state.db_conn.as_ref(), state.session.as_ref(), state.event_tx.as_ref())
+    else {
+        state.push_notification("no review session to export".to_owned());
+        return;
+    };
+    let (conn, session_id, tx) = (conn.clone(), session.id.clone(), tx.clone());
+    let path = format!(".airev/review-{}.md", unix_secs());
 
-    match key.code {
-        KeyCode::Char('j') => {
-            state.scroll_down(1);
-            Some(KeyAction::Continue)
+    tokio::spawn(async move {
+        let result = match airev_core::db::export_comments_markdown(&conn, &session_id).await {
+            Ok(report) => match std::fs::write(&path, report) {
+                Ok(()) => Ok(path),
+                Err(e) => Err(format!("cannot write {path}: {e}")),
+            },
+            Err(e) => Err(format!("cannot export comments: {e}")),
+        };
+        let _ = tx.send(AppEvent::DbResult(Box::new(DbResultPayload::CommentsExported(result))));
+    });
+}
+
+/// Spawns one `batch_set_reviewed()` transaction marking `file_paths` reviewed.
+///
+/// The in-memory states must already be updated; the result comes back as
+/// `DbResultPayload::FilesMarkedReviewed`, or `MarkReviewedFailed`, which
+/// unmarks them again. Does nothing for an empty list or without a DB
+/// connection or session.
+pub fn persist_reviewed(state: &AppState, file_paths: Vec<String>) {
+    let (Some(conn), Some(session), Some(tx)) =
+        (state.db_conn.as_ref(), state.session.as_ref(), state.event_tx.as_ref())
+    else {
+        return;
+    };
+    if file_paths.is_empty() {
+        return;
+    }
+    let (conn, session_id, tx) = (conn.clone(), session.id.clone(), tx.clone());
+
+    tokio::spawn(async move {
+        let payload =
+            match airev_core::db::batch_set_reviewed(&conn, &session_id, file_paths.clone(), true)
+                .await
+            {
+                Ok(_) => DbResultPayload::FilesMarkedReviewed(file_paths),
+                Err(e) => DbResultPayload::MarkReviewedFailed { file_paths, message: e.to_string() },
+            };
+        let _ = tx.send(AppEvent::DbResult(Box::new(payload)));
+    });
+}
+
+/// Cycles the comment severity filter and spawns an async DB task to save it.
+///
+/// The filter applies immediately in memory; the save is fire-and-forget, with
+/// errors shown as a notification (see [`report_save_failed`]). Without a DB
+/// connection or session the filter still changes but is not persisted.
+fn handle_cycle_comment_filter(state: &mut AppState) {
+    state.cycle_comment_filter();
+
+    let (Some(conn), Some(session)) = (state.db_conn.as_ref(), state.session.as_mut()) else {
+        return;
+    };
+    let conn = conn.clone();
+    let session_id = session.id.clone();
+    let filter = state.comment_filter.as_str();
+    session.comment_filter = filter.to_owned();
+    let tx = state.event_tx.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_comment_filter(&conn, &session_id, filter).await {
+            report_save_failed(tx.as_ref(), "comment filter", e);
         }
-        KeyCode::Char('k') => {
-            state.scroll_up(1);
-            Some(KeyAction::Continue)
+    });
+}
+
+/// Toggles the diff-only layout and spawns an async DB task to save it for the
+/// session.
+///
+/// Fire-and-forget like the filter save. Without a DB connection or session the
+/// layout still changes but is not persisted.
+fn handle_toggle_diff_only(state: &mut AppState) {
+    state.toggle_diff_only();
+
+    let (Some(conn), Some(session)) = (state.db_conn.as_ref(), state.session.as_mut()) else {
+        return;
+    };
+    let conn = conn.clone();
+    let session_id = session.id.clone();
+    let diff_only = state.diff_only;
+    session.diff_only = diff_only;
+    let tx = state.event_tx.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_session_diff_only(&conn, &session_id, diff_only).await
+        {
+            report_save_failed(tx.as_ref(), "diff-only layout", e);
         }
-        KeyCode::Char('g') => {
-            state.scroll_top();
-            Some(KeyAction::Continue)
+    });
+}
+
+/// Changes the diff context by `delta` lines and spawns an async DB task to save
+/// the new count for the session.
+///
+/// Fire-and-forget like the diff-only save. Nothing is saved when the count did
+/// not change or there is no DB connection or session.
+fn handle_change_context_lines(state: &mut AppState, delta: i32) {
+    if !state.change_context_lines(delta) {
+        return;
+    }
+
+    let (Some(conn), Some(session)) = (state.db_conn.as_ref(), state.session.as_mut()) else {
+        return;
+    };
+    let conn = conn.clone();
+    let session_id = session.id.clone();
+    let lines = state.context_lines;
+    session.context_lines = Some(lines);
+    let tx = state.event_tx.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_session_context_lines(&conn, &session_id, lines).await
+        {
+            report_save_failed(tx.as_ref(), "context lines", e);
         }
-        KeyCode::Char('G') => {
-            state.scroll_bottom();
-            Some(KeyAction::Continue)
+    });
+}
+
+/// Copies a reference to the cursor line to the clipboard (OSC 52).
+///
+/// `y` copies `path:line`. `Y` copies a GitHub blob URL at the session's
+/// recorded `HEAD` when `origin` is a GitHub remote, and otherwise falls back to
+/// `path:line` with a toast saying why.
+fn handle_copy_reference(state: &mut AppState, permalink: bool) {
+    let Some((path, line)) = state.editor_target() else {
+        state.push_notification("no line to copy".to_owned());
+        return;
+    };
+    let sha = state.session.as_ref().map(|s| s.head_sha.as_str()).filter(|s| !s.is_empty());
+    let (text, note) = match (permalink, state.web_url.as_deref(), sha) {
+        (true, Some(url), Some(sha)) => (format!("{url}/blob/{sha}/{path}#L{line}"), ""),
+        (true, None, _) => (format!("{path}:{line}"), " (origin is not on GitHub)"),
+        (true, Some(_), None) => (format!("{path}:{line}"), " (no HEAD commit)"),
+        (false, _, _) => (format!("{path}:{line}"), ""),
+    };
+    match crate::tui::copy_to_clipboard(&text) {
+        Ok(()) => state.push_notification(format!("copied {text}{note}")),
+        Err(e) => state.push_notification(format!("cannot copy: {e}")),
+    }
+}
+
+/// Clears the drift banner and records the repository's current `HEAD` as the
+/// session's, so the banner does not come back on the next start.
+///
+/// Fire-and-forget like the other session settings. When `HEAD` cannot be
+/// resolved only the banner is cleared.
+fn handle_dismiss_drift(state: &mut AppState) {
+    state.drift_warning = None;
+    let Some(session) = state.session.as_mut() else {
+        return;
+    };
+    let Some(head) = crate::git::head_sha(&session.repo_path) else {
+        return;
+    };
+    session.head_sha = head.clone();
+    let Some(conn) = state.db_conn.clone() else {
+        return;
+    };
+    let session_id = session.id.clone();
+    let tx = state.event_tx.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_session_head(&conn, &session_id, &head).await {
+            report_save_failed(tx.as_ref(), "session HEAD", e);
         }
-        KeyCode::Char('d') if ctrl => {
-            state.half_page_down();
-            Some(KeyAction::Continue)
+    });
+}
+
+/// Saves the current diff mode as the repository's last-used mode.
+///
+/// Fire-and-forget like the filter save. Modes without a config name (commit
+/// range, `--files`) are not recorded, so the next launch falls back to the
+/// last simple mode. Does nothing without a DB connection or session.
+fn handle_save_diff_mode(state: &AppState) {
+    let (Some(conn), Some(session), Some(mode)) =
+        (state.db_conn.as_ref(), state.session.as_ref(), state.diff_mode.config_name())
+    else {
+        return;
+    };
+    let conn = conn.clone();
+    let repo_path = session.repo_path.clone();
+    let tx = state.event_tx.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_last_diff_mode(&conn, &repo_path, mode).await {
+            report_save_failed(tx.as_ref(), "diff mode", e);
         }
-        KeyCode::Char('u') if ctrl => {
-            state.half_page_up();
-            Some(KeyAction::Continue)
+    });
+}
+
+/// Saves the current commit range as the repository's last-shown diff, so the
+/// next launch reopens it (see `session`), and as its most recent range.
+///
+/// Fire-and-forget like the diff mode save.
+pub fn persist_diff_range(state: &AppState) {
+    let (Some(conn), Some(session), Some((from, to))) =
+        (state.db_conn.as_ref(), state.session.as_ref(), state.diff_range.as_ref())
+    else {
+        return;
+    };
+    let conn = conn.clone();
+    let repo_path = session.repo_path.clone();
+    let range = format!("{from}..{to}");
+    let tx = state.event_tx.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = airev_core::db::set_last_diff_range(&conn, &repo_path, &range).await {
+            report_save_failed(tx.as_ref(), "diff range", e);
         }
-        KeyCode::Char('f') if ctrl => {
-            state.full_page_down();
-            Some(KeyAction::Continue)
+        if let Err(e) = airev_core::db::push_recent_diff_args(&conn, &repo_path, &range).await {
+            report_save_failed(tx.as_ref(), "recent ranges", e);
         }
-        KeyCode::Char('b') if ctrl => {
-            state.full_page_up();
-            Some(KeyAction::Continue)
+    });
+}
+
+/// Runs the scroll actions: line, bottom, and half / full page scrolls.
+///
+/// With the comments panel focused and comments listed, the line scrolls move
+/// the comment selection instead; the page scrolls still scroll freely.
+///
+/// Returns `Some(KeyAction)` when the action was handled, `None` when it is not
+/// a scroll action. `gg` goes through `handle_key_sequence`.
+///
+/// # Arguments
+///
+/// * `action` — an action bound to the received key
+/// * `state`  — mutable reference to all UI state
+fn handle_scroll_action(action: Action, state: &mut AppState) -> Option<KeyAction> {
+    let selecting = state.focus == PanelFocus::Comments && !state.comments.is_empty();
+
+    match action {
+        Action::ScrollDown if selecting => state.next_comment(),
+        Action::ScrollUp if selecting => state.prev_comment(),
+        Action::ScrollDown => state.scroll_down(1),
+        Action::ScrollUp => state.scroll_up(1),
+        Action::ScrollBottom => state.scroll_bottom(),
+        Action::HalfPageDown => state.half_page_down(),
+        Action::HalfPageUp => state.half_page_up(),
+        Action::PageDown => state.full_page_down(),
+        Action::PageUp => state.full_page_up(),
+        _ => return None,
+    }
+    Some(KeyAction::Continue)
+}
+
+// ---------------------------------------------------------------------------
+// StartupError mode
+// ---------------------------------------------------------------------------
+
+/// Handles a key event while a startup error is shown.
+///
+/// `q` / `Esc` quit. `Enter` / `c` dismiss the error and continue in the degraded
+/// mode it describes (e.g. without git, or without saving review state); the
+/// next queued error, if any, is shown instead.
+///
+/// # Arguments
+///
+/// * `key`   — the raw crossterm key event
+/// * `state` — mutable reference to all UI state
+fn handle_startup_error(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => KeyAction::Quit,
+        KeyCode::Enter | KeyCode::Char('c') => {
+            state.dismiss_startup_error();
+            KeyAction::Continue
         }
-        _ => None,
+        _ => KeyAction::Continue,
     }
 }
 
@@ -317,25 +808,266 @@ fn handle_confirm_quit(key: KeyEvent, state: &mut AppState) -> KeyAction {
 // Insert mode
 // ---------------------------------------------------------------------------
 
-/// Handles a key event while in Insert mode (comment editing placeholder).
+/// Handles a key event while in Insert mode (comment body editing).
 ///
-/// `Esc` returns to Normal mode.  All other keys are passed through without
-/// mutation — comment text editing is wired in Phase 5.
+/// Printable characters append to `comment_draft` and `Backspace` removes the last
+/// character. `Enter` advances to the severity/type picker when the draft is
+/// non-blank, and `Ctrl-s` saves it straight away with the picker's current tags.
+/// `Esc` discards the draft and returns to Normal mode. Keys are ignored while a
+/// save is in flight.
 ///
 /// # Arguments
 ///
 /// * `key`   — the raw crossterm key event
 /// * `state` — mutable reference to all UI state
 fn handle_insert(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    if state.comment_saving {
+        return KeyAction::Continue;
+    }
     match key.code {
         KeyCode::Esc => {
-            state.mode = Mode::Normal;
+            state.clear_comment_draft();
+            KeyAction::Continue
+        }
+        KeyCode::Enter => {
+            if !state.comment_draft.trim().is_empty() {
+                state.mode = Mode::CommentTag;
+            }
+            KeyAction::Continue
+        }
+        KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            if !state.comment_draft.trim().is_empty() {
+                handle_save_comment(state);
+            }
+            KeyAction::Continue
+        }
+        KeyCode::Backspace => {
+            state.pop_draft_char();
+            KeyAction::Continue
+        }
+        KeyCode::Char(ch) => {
+            state.push_draft_char(ch);
             KeyAction::Continue
         }
         _ => KeyAction::Continue,
     }
 }
 
+// ---------------------------------------------------------------------------
+// Command mode
+// ---------------------------------------------------------------------------
+
+/// Handles a key event while a `:` command is being typed.
+///
+/// `Enter` runs the command, `Esc` (or `Backspace` on an empty line) cancels,
+/// and any other character is appended.
+///
+/// # Arguments
+///
+/// * `key`   — the raw crossterm key event
+/// * `state` — mutable reference to all UI state
+fn handle_command(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    match key.code {
+        KeyCode::Esc => state.cancel_command(),
+        KeyCode::Enter => state.run_command(),
+        KeyCode::Backspace if state.command_line.is_empty() => state.cancel_command(),
+        KeyCode::Backspace => {
+            state.command_line.pop();
+        }
+        KeyCode::Char(ch) => state.command_line.push(ch),
+        _ => {}
+    }
+    KeyAction::Continue
+}
+
+// ---------------------------------------------------------------------------
+// Search mode
+// ---------------------------------------------------------------------------
+
+/// Handles a key event while a `/` search is being typed.
+///
+/// `Enter` searches, `Esc` (or `Backspace` on an empty query) cancels, and any
+/// other character is appended.
+///
+/// # Arguments
+///
+/// * `key`   — the raw crossterm key event
+/// * `state` — mutable reference to all UI state
+fn handle_search(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    match key.code {
+        KeyCode::Esc => state.cancel_search(),
+        KeyCode::Enter => state.run_search(),
+        KeyCode::Backspace if state.search_query.is_empty() => state.cancel_search(),
+        KeyCode::Backspace => {
+            state.search_query.pop();
+        }
+        KeyCode::Char(ch) => state.search_query.push(ch),
+        _ => {}
+    }
+    KeyAction::Continue
+}
+
+// ---------------------------------------------------------------------------
+// FileFilter mode
+// ---------------------------------------------------------------------------
+
+/// Handles a key event while the file-list filter is being typed.
+///
+/// Characters and `Backspace` edit the query, narrowing the list as it is typed;
+/// `Up` / `Down` move the selection. `Enter` keeps the filter and `Esc` clears it.
+///
+/// # Arguments
+///
+/// * `key`   — the raw crossterm key event
+/// * `state` — mutable reference to all UI state
+fn handle_file_filter(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    match key.code {
+        KeyCode::Esc => state.clear_file_filter(),
+        KeyCode::Enter => state.accept_file_filter(),
+        KeyCode::Up => state.prev_file(),
+        KeyCode::Down => state.next_file(),
+        KeyCode::Backspace => state.edit_file_filter(None),
+        KeyCode::Char(ch) => state.edit_file_filter(Some(ch)),
+        _ => {}
+    }
+    KeyAction::Continue
+}
+
+// ---------------------------------------------------------------------------
+// DiffModePicker mode
+// ---------------------------------------------------------------------------
+
+/// Handles a key event while the diff-mode picker is open.
+///
+/// `j` / `k` (or the arrow keys) move the highlight, `Enter` picks, and `Esc`,
+/// `q`, or `m` close the picker. While refs are being typed for a range, commit,
+/// or stash, keys edit the prompt; `Tab` completes a branch or tag name,
+/// `Up` / `Down` step through recently used ranges, `Enter` submits the refs
+/// for resolving, and `Esc` returns to the list.
+///
+/// # Arguments
+///
+/// * `key`   — the raw crossterm key event
+/// * `state` — mutable reference to all UI state
+fn handle_mode_picker(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    if state.mode_pick_refs.is_some() {
+        match key.code {
+            KeyCode::Esc => state.cancel_mode_refs(),
+            KeyCode::Enter => state.submit_mode_refs(),
+            KeyCode::Tab => state.complete_ref(),
+            KeyCode::Up => state.step_ref_history(1),
+            KeyCode::Down => state.step_ref_history(-1),
+            KeyCode::Backspace => state.edit_mode_refs(None),
+            KeyCode::Char(ch) => state.edit_mode_refs(Some(ch)),
+            _ => {}
+        }
+        return KeyAction::Continue;
+    }
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => state.move_mode_pick(1),
+        KeyCode::Char('k') | KeyCode::Up => state.move_mode_pick(-1),
+        KeyCode::Enter => {
+            let chosen = state.choose_mode_pick();
+            if chosen.is_some() {
+                handle_save_diff_mode(state);
+            }
+        }
+        KeyCode::Esc | KeyCode::Char('q' | 'm') => state.mode = Mode::Normal,
+        _ => {}
+    }
+    KeyAction::Continue
+}
+
+// ---------------------------------------------------------------------------
+// CommentTag mode
+// ---------------------------------------------------------------------------
+
+/// Handles a key event while the severity/type quick-pick overlay is shown.
+///
+/// `1`–`4` pick critical / major / minor / info. `j` / `k` (or `Tab` /
+/// `BackTab`) cycle the comment type. `Enter` saves the comment; `Esc` returns
+/// to Insert mode with the draft intact. Keys are ignored while a save is in
+/// flight.
+///
+/// # Arguments
+///
+/// * `key`   — the raw crossterm key event
+/// * `state` — mutable reference to all UI state
+fn handle_comment_tag(key: KeyEvent, state: &mut AppState) -> KeyAction {
+    if state.comment_saving {
+        return KeyAction::Continue;
+    }
+    match key.code {
+        KeyCode::Char(ch @ '1'..='4') => {
+            state.draft_severity = ch as usize - '1' as usize;
+        }
+        KeyCode::Char('j') | KeyCode::Tab => state.cycle_draft_type(1),
+        KeyCode::Char('k') | KeyCode::BackTab => state.cycle_draft_type(-1),
+        KeyCode::Enter => handle_save_comment(state),
+        KeyCode::Esc => state.mode = Mode::Insert,
+        _ => {}
+    }
+    KeyAction::Continue
+}
+
+/// Spawns an async DB task that inserts the drafted comment for the selected file,
+/// or for the drafted line or hunk.
+///
+/// Builds a `Comment` from `comment_draft`, `draft_severity`, `draft_type`, and
+/// `draft_line` (which sets `line_number`) or `draft_hunk` (which sets
+/// `hunk_offset` and leaves `line_number` NULL), and sets `comment_saving`.
+/// The draft stays until the stored row (with its generated id) arrives back as
+/// `DbResultPayload::CommentSaved`; on `CommentSaveFailed` it is kept for a
+/// retry. Does nothing if a save is already in flight, or if no DB connection,
+/// no session, or no file is selected.
+fn handle_save_comment(state: &mut AppState) {
+    if state.comment_saving {
+        return;
+    }
+    let conn = match state.db_conn.as_ref() {
+        Some(c) => c.clone(),
+        None => return,
+    };
+    let session_id = match state.session.as_ref() {
+        Some(s) => s.id.clone(),
+        None => return,
+    };
+    let (file_path, line_number, hunk_offset) =
+        match (state.draft_line.clone(), state.draft_hunk.clone(), state.current_file_path()) {
+            (Some((path, line)), _, _) => (path, Some(line), None),
+            (None, Some((path, offset)), _) => (path, None, Some(offset)),
+            (None, None, Some(p)) => (p.to_owned(), None, None),
+            (None, None, None) => return,
+        };
+    let tx = match state.event_tx.as_ref() {
+        Some(t) => t.clone(),
+        None => return,
+    };
+
+    let comment = Comment {
+        id: String::new(),
+        session_id,
+        file_path,
+        line_number,
+        hunk_offset,
+        comment_type: state.draft_type.as_str().to_owned(),
+        severity: SEVERITIES[state.draft_severity].to_owned(),
+        body: state.comment_draft.trim().to_owned(),
+        created_at: 0,
+        resolved_at: None,
+        thread_id: None,
+    };
+    state.comment_saving = true;
+
+    tokio::spawn(async move {
+        let payload = match airev_core::db::insert_comment(&conn, comment).await {
+            Ok(saved) => DbResultPayload::CommentSaved(saved),
+            Err(e) => DbResultPayload::CommentSaveFailed(e.to_string()),
+        };
+        let _ = tx.send(AppEvent::DbResult(Box::new(payload)));
+    });
+}
+
 // ---------------------------------------------------------------------------
 // Mouse events
 // ---------------------------------------------------------------------------
@@ -343,28 +1075,41 @@ fn handle_insert(key: KeyEvent, state: &mut AppState) -> KeyAction {
 /// Handles a mouse event: click-to-focus and scroll-wheel.
 ///
 /// Left click on a panel sets focus to that panel. Scroll wheel up/down
-/// scrolls the focused panel by 3 lines (matching typical terminal scroll
-/// speed). Mouse events in HelpOverlay mode scroll the help overlay.
+/// scrolls the panel under the mouse cursor by `wheel_scroll_lines` rows
+/// (config `scroll_lines`, default 3) without moving focus. Mouse events in HelpOverlay
+/// mode scroll the help overlay; the StartupError overlay and the diff-mode picker
+/// ignore the mouse.
 ///
 /// # Arguments
 ///
 /// * `mouse` — the crossterm mouse event
 /// * `state` — mutable reference to all UI state
 pub fn handle_mouse(mouse: MouseEvent, state: &mut AppState) -> KeyAction {
+    if matches!(state.mode, Mode::StartupError | Mode::DiffModePicker) {
+        return KeyAction::Continue;
+    }
     match mouse.kind {
         MouseEventKind::Down(MouseButton::Left) => {
             handle_mouse_click(mouse.column, mouse.row, state)
         }
-        MouseEventKind::ScrollUp => handle_mouse_scroll_up(state),
-        MouseEventKind::ScrollDown => handle_mouse_scroll_down(state),
+        MouseEventKind::ScrollUp => handle_mouse_scroll(mouse.column, mouse.row, true, state),
+        MouseEventKind::ScrollDown => handle_mouse_scroll(mouse.column, mouse.row, false, state),
         _ => KeyAction::Continue,
     }
 }
 
+/// Maximum interval between two clicks on the same cell for them to count as a
+/// double-click.
+const DOUBLE_CLICK_MS: u128 = 400;
+
 /// Sets panel focus based on the clicked screen position.
 ///
 /// Checks each cached panel rect in `state.panel_rects`. Panels with zero width
-/// are skipped so collapsed panels cannot receive focus via click.
+/// are skipped so collapsed panels cannot receive focus via click. A click on a
+/// file-list row selects that file; a double-click on the same row also jumps to
+/// it in the diff, like `Enter`. A click inside the diff panel also moves the
+/// diff cursor to the clicked line, and a click inside the comments panel selects
+/// the comment under the cursor.
 ///
 /// # Arguments
 ///
@@ -375,41 +1120,89 @@ fn handle_mouse_click(col: u16, row: u16, state: &mut AppState) -> KeyAction {
     let pos = Position { x: col, y: row };
     let [left, center, right] = state.panel_rects;
 
+    let now = Instant::now();
+    let is_double = match state.last_click {
+        Some((at, c, r)) => {
+            c == col && r == row && now.duration_since(at).as_millis() <= DOUBLE_CLICK_MS
+        }
+        None => false,
+    };
+    // A double-click consumes the pair so a third click starts a new sequence.
+    state.last_click = if is_double { None } else { Some((now, col, row)) };
+
     if left.width > 0 && left.contains(pos) {
         state.focus = PanelFocus::FileList;
+        let inner = inner_rect(left);
+        if inner.contains(pos) && state.click_file_row(row - inner.y) && is_double {
+            state.jump_to_selected_file();
+        }
     } else if center.contains(pos) {
         state.focus = PanelFocus::Diff;
+        // Clicks on the border fall outside the inner rect and only set focus.
+        let inner = inner_rect(center);
+        if inner.contains(pos) {
+            state.click_diff_row(row - inner.y);
+        }
     } else if right.width > 0 && right.contains(pos) {
         state.focus = PanelFocus::Comments;
+        let inner = inner_rect(right);
+        if inner.contains(pos) {
+            let row = (row - inner.y) as usize + state.comments_scroll as usize;
+            if let Some(idx) = comment_at_row(state, row) {
+                state.select_comment(idx);
+            }
+        }
     }
 
     KeyAction::Continue
 }
 
-/// Scrolls up by 3 lines. Scrolls the help overlay when in HelpOverlay mode.
-///
-/// # Arguments
+/// Returns the panel whose inner area (excluding borders) contains `(col, row)`.
 ///
-/// * `state` — mutable reference to all UI state
-fn handle_mouse_scroll_up(state: &mut AppState) -> KeyAction {
-    if state.mode == Mode::HelpOverlay {
-        state.help_scroll = state.help_scroll.saturating_sub(3);
-    } else {
-        state.scroll_up(3);
-    }
-    KeyAction::Continue
+/// Collapsed panels (zero width) never match. Returns `None` over borders, the
+/// status bar, or anywhere outside the panels.
+fn panel_at(col: u16, row: u16, state: &AppState) -> Option<PanelFocus> {
+    let pos = Position { x: col, y: row };
+    let [left, center, right] = state.panel_rects;
+    [
+        (left, PanelFocus::FileList),
+        (center, PanelFocus::Diff),
+        (right, PanelFocus::Comments),
+    ]
+    .into_iter()
+    .find(|(rect, _)| rect.width > 0 && inner_rect(*rect).contains(pos))
+    .map(|(_, panel)| panel)
 }
 
-/// Scrolls down by 3 lines. Scrolls the help overlay when in HelpOverlay mode.
+/// Scrolls in response to a mouse-wheel notch.
+///
+/// `up` is the physical wheel direction; it is inverted when `natural_scroll` is
+/// enabled. Scrolls `wheel_scroll_lines` rows of the help overlay when in
+/// HelpOverlay mode, otherwise of the panel under the cursor — falling back to
+/// the focused panel when the cursor is over a border.
 ///
 /// # Arguments
 ///
+/// * `col`   — column (x) of the mouse cursor
+/// * `row`   — row (y) of the mouse cursor
+/// * `up`    — `true` for a wheel-up notch
 /// * `state` — mutable reference to all UI state
-fn handle_mouse_scroll_down(state: &mut AppState) -> KeyAction {
+fn handle_mouse_scroll(col: u16, row: u16, up: bool, state: &mut AppState) -> KeyAction {
+    let lines = state.wheel_scroll_lines;
+    let up = up != state.natural_scroll;
     if state.mode == Mode::HelpOverlay {
-        state.help_scroll = state.help_scroll.saturating_add(3);
+        state.help_scroll = if up {
+            state.help_scroll.saturating_sub(lines)
+        } else {
+            state.help_scroll.saturating_add(lines)
+        };
     } else {
-        state.scroll_down(3);
+        let panel = panel_at(col, row, state).unwrap_or(state.focus);
+        if up {
+            state.scroll_panel_up(panel, lines);
+        } else {
+            state.scroll_panel_down(panel, lines);
+        }
     }
     KeyAction::Continue
 }
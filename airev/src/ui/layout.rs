@@ -19,6 +19,17 @@
 //! Unicode box-drawing characters automatically. Overlap is NOT used on the 80-119
 //! layout because `Length(0)` panels with overlap cause u16 underflow in ratatui's
 //! layout engine.
+//!
+//! On tall, narrow terminals (fewer columns than rows, e.g. 40×90) the panels are
+//! stacked top-to-bottom instead, with row breakpoints mirroring the column ones:
+//!
+//! | Terminal height | Panels visible |
+//! |-----------------|----------------|
+//! | `>= 36` rows    | File list + Diff + Comments, heights from the same percentages |
+//! | `24..=35` rows  | File list (25%) + Diff; comments hidden |
+//! | `< 24` rows     | Diff only |
+//!
+//! The config key `layout` forces either orientation.
 
 use ratatui::{
     Frame,
@@ -26,13 +37,46 @@ use ratatui::{
     style::{Color, Modifier, Style},
     symbols::merge::MergeStrategy,
     text::{Line, Span},
-    widgets::{Block, BorderType, Paragraph},
+    widgets::{Block, BorderType, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
 };
 
 use crate::app::{AppState, Mode};
 use crate::git::types::DiffMode;
+use crate::keymap::Action;
 use crate::theme::Theme;
 
+/// Direction in which the three panels are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutOrientation {
+    /// File list, diff, and comments side by side.
+    Horizontal,
+    /// File list, diff, and comments stacked top-to-bottom.
+    Vertical,
+}
+
+impl LayoutOrientation {
+    /// Parses a forced `layout` config value. Returns `None` for unknown strings
+    /// (including `"auto"`, which the config reader handles itself).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "horizontal" => Some(LayoutOrientation::Horizontal),
+            "vertical" => Some(LayoutOrientation::Vertical),
+            _ => None,
+        }
+    }
+
+    /// Picks the orientation for `area`: vertical when it has fewer columns than
+    /// rows. A cell is roughly twice as tall as it is wide, so that is a screen
+    /// about twice as tall as wide.
+    fn auto(area: Rect) -> Self {
+        if area.width < area.height {
+            LayoutOrientation::Vertical
+        } else {
+            LayoutOrientation::Horizontal
+        }
+    }
+}
+
 /// Returns `[left, center, right, status_bar]` panel `Rect`s for the current frame.
 ///
 /// Called inside `terminal.draw()` on every render. The returned slices are valid only
@@ -46,19 +90,36 @@ use crate::theme::Theme;
 /// | `80..=119` cols | File list (25%) + Diff (fill); comments panel hidden |
 /// | `< 80` cols    | Diff only; both side panels collapsed |
 ///
+/// In the 3-panel layout, a non-zero `state.max_diff_width` caps the diff
+/// panel's content width; the side panels absorb the remaining columns.
+///
+/// `state.diff_only` (`--diff-only` / `F`) forces the diff-only row of the table
+/// at every width and in either orientation.
+///
+/// With `LayoutOrientation::Vertical` (forced by `state.layout_orientation`, or
+/// picked automatically for tall, narrow terminals) the panels are stacked
+/// instead; see [`vertical_panels`]. Collapsed panels are then zero rows tall
+/// rather than zero columns wide.
+///
 /// # Arguments
 ///
-/// * `frame` — current render frame (provides `frame.area()` with live terminal size)
-/// * `state` — read-only app state supplying panel width percentages
-pub fn compute_layout(frame: &Frame, state: &AppState) -> [Rect; 4] {
-    let term_width = frame.area().width;
+/// * `area` — the frame area below any banner (see [`split_banner`])
+/// * `state` — read-only app state supplying panel width percentages and the diff width cap
+pub fn compute_layout(area: Rect, state: &AppState) -> [Rect; 4] {
+    let term_width = area.width;
 
     // Vertical split: main area (fills remaining height) + 1-row status bar.
     let [main_area, status_bar] =
-        frame.area().layout(&Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]));
+        area.layout(&Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]));
+
+    let orientation = state.layout_orientation.unwrap_or_else(|| LayoutOrientation::auto(area));
+    if orientation == LayoutOrientation::Vertical && !state.diff_only {
+        let [top, middle, bottom] = vertical_panels(main_area, state);
+        return [top, middle, bottom, status_bar];
+    }
 
     // Horizontal split: 3-breakpoint responsive layout.
-    let horizontal = if term_width >= 120 {
+    let horizontal = if term_width >= 120 && !state.diff_only {
         // Full 3-panel layout with border overlap for clean junctions.
         Layout::horizontal([
             Constraint::Percentage(state.left_pct),
@@ -66,7 +127,7 @@ pub fn compute_layout(frame: &Frame, state: &AppState) -> [Rect; 4] {
             Constraint::Percentage(state.right_pct),
         ])
         .spacing(Spacing::Overlap(1))
-    } else if term_width >= 80 {
+    } else if term_width >= 80 && !state.diff_only {
         // Medium width: show file list (25%) + diff, hide comments.
         // Do NOT use Spacing::Overlap here — Length(0) right panel + Overlap(1) causes
         // u16 underflow in ratatui's layout engine (0 - 1 wraps to u16::MAX).
@@ -76,7 +137,7 @@ pub fn compute_layout(frame: &Frame, state: &AppState) -> [Rect; 4] {
             Constraint::Length(0),
         ])
     } else {
-        // Narrow: diff-only layout. No overlap (same underflow risk applies).
+        // Narrow, or diff-only by request. No overlap (same underflow risk applies).
         Layout::horizontal([
             Constraint::Length(0),
             Constraint::Fill(1),
@@ -86,9 +147,87 @@ pub fn compute_layout(frame: &Frame, state: &AppState) -> [Rect; 4] {
 
     let [left, center, right] = main_area.layout(&horizontal);
 
+    // Ultrawide terminals: cap the diff content width and hand the excess to the
+    // side panels in proportion to their percentages. The +2 covers the borders.
+    let cap = state.max_diff_width.saturating_add(2);
+    if !state.diff_only && term_width >= 120 && state.max_diff_width > 0 && center.width > cap {
+        let capped = Layout::horizontal([
+            Constraint::Fill(state.left_pct.max(1)),
+            Constraint::Length(cap),
+            Constraint::Fill(state.right_pct.max(1)),
+        ])
+        .spacing(Spacing::Overlap(1));
+        let [left, center, right] = main_area.layout(&capped);
+        return [left, center, right, status_bar];
+    }
+
     [left, center, right, status_bar]
 }
 
+/// Stacks file list, diff, and comments top-to-bottom in `main_area`.
+///
+/// Uses the same percentages as the horizontal layout for the panel heights,
+/// and the row breakpoints from the module docs to collapse the side panels
+/// to zero height, without overlap for the same underflow reason.
+fn vertical_panels(main_area: Rect, state: &AppState) -> [Rect; 3] {
+    let vertical = if main_area.height >= 35 {
+        Layout::vertical([
+            Constraint::Percentage(state.left_pct),
+            Constraint::Percentage(state.center_pct),
+            // Fill rather than a percentage, so the overlapping borders leave
+            // no empty rows above the status bar.
+            Constraint::Fill(1),
+        ])
+        .spacing(Spacing::Overlap(1))
+    } else if main_area.height >= 23 {
+        Layout::vertical([Constraint::Percentage(25), Constraint::Fill(1), Constraint::Length(0)])
+    } else {
+        Layout::vertical([Constraint::Length(0), Constraint::Fill(1), Constraint::Length(0)])
+    };
+    main_area.layout(&vertical)
+}
+
+/// Returns the banner to show and its colour: the unavailable-repository
+/// warning takes precedence over the drift warning, which gets the dismiss key
+/// from `state.keymap` appended.
+fn banner(state: &AppState) -> Option<(String, Color)> {
+    if let Some(text) = &state.repo_unavailable {
+        return Some((text.clone(), Color::Red));
+    }
+    let text = state.drift_warning.as_deref()?;
+    let key = state.keymap.label(Action::DismissBanner);
+    Some((format!("{text}. {key} to dismiss and adopt the new HEAD"), Color::Yellow))
+}
+
+/// Splits a 1-row banner off the top of `area` while `state.repo_unavailable`
+/// or `state.drift_warning` is set.
+///
+/// Returns `(banner, rest)`; `banner` is empty when there is nothing to show.
+pub fn split_banner(area: Rect, state: &AppState) -> (Rect, Rect) {
+    if banner(state).is_none() {
+        return (Rect::default(), area);
+    }
+    let [banner, rest] =
+        area.layout(&Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]));
+    (banner, rest)
+}
+
+/// Renders the active banner in the row returned by [`split_banner`].
+pub fn render_banner(frame: &mut Frame, area: Rect, state: &AppState) {
+    let theme = &state.theme;
+    let Some((text, color)) = banner(state) else {
+        return;
+    };
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            text,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )))
+        .style(Style::default().bg(theme.status_bar_bg)),
+        area,
+    );
+}
+
 /// Returns the inner `Rect` of a panel after removing the 1-cell border on each side.
 ///
 /// Used to cache viewport heights in `AppState` before panels are rendered, so that
@@ -101,6 +240,48 @@ pub fn inner_rect(area: Rect) -> Rect {
     area.inner(Margin { vertical: 1, horizontal: 1 })
 }
 
+/// Returns a panel title with the scroll position appended, e.g. `Diff 320/710 [45%]`.
+///
+/// `scroll` is the first visible row, `total` the number of content rows, and
+/// `viewport` the number of rows that fit. The absolute figure is the 1-based
+/// first visible row; the percentage is how far through the scrollable range the
+/// view is (100% once the last row is on screen). Returns `base` unchanged when
+/// all content fits without scrolling.
+pub fn scroll_title(base: &str, scroll: usize, total: usize, viewport: usize) -> String {
+    if total <= viewport {
+        return base.to_owned();
+    }
+    let max_scroll = total - viewport;
+    let scroll = scroll.min(max_scroll);
+    let pct = scroll * 100 / max_scroll;
+    format!("{base} {}/{total} [{pct}%]", scroll + 1)
+}
+
+/// Draws a vertical scrollbar on the right edge of a panel's `inner` area, with
+/// the same `scroll` / `total` / `viewport` as [`scroll_title`]. Draws nothing
+/// when all content fits.
+pub fn render_scrollbar(
+    frame: &mut Frame,
+    inner: Rect,
+    scroll: usize,
+    total: usize,
+    viewport: usize,
+) {
+    if total <= viewport {
+        return;
+    }
+    // One position per first visible row, so the thumb reaches the bottom
+    // exactly when the last row is on screen.
+    let max_scroll = total - viewport;
+    let mut scrollbar_state = ScrollbarState::new(max_scroll + 1)
+        .position(scroll.min(max_scroll))
+        .viewport_content_length(viewport);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    frame.render_stateful_widget(scrollbar, inner, &mut scrollbar_state);
+}
+
 /// Builds a bordered `Block` for a panel.
 ///
 /// Applies `BorderType::Thick` when the panel is focused (distinct active border) and
@@ -128,67 +309,314 @@ pub fn panel_block<'a>(title: &'a str, is_focused: bool, theme: &'a Theme) -> Bl
         .merge_borders(MergeStrategy::Fuzzy)
 }
 
+/// Style for the current row of a panel with config `cursor_line` on: the theme's
+/// `cursor_line` background, leaving foreground (e.g. syntax) colors alone.
+///
+/// `None` when the panel is not focused or the option is off; the panel then
+/// marks its current row the usual way.
+pub fn cursor_line_style(state: &AppState, is_focused: bool) -> Option<Style> {
+    (is_focused && state.cursor_line).then(|| Style::default().bg(state.theme.cursor_line))
+}
+
+/// One piece of the status bar, selected and ordered by config `status_segments`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusSegment {
+    /// Mode indicator, `NORMAL` or `INSERT`.
+    Mode,
+    /// Active diff mode label, e.g. `UNSTAGED`, with a hint while diff-only.
+    DiffMode,
+    /// Changed file count and line totals, e.g. `12 files  +340 -87`.
+    Files,
+    /// Review progress, e.g. `3/12 reviewed`.
+    Reviewed,
+    /// Abbreviated session id.
+    Session,
+    /// Worker stopped hint, `Computing diff...`, or the newest notification.
+    /// Without it, notifications are shown in a box above the status bar.
+    Status,
+    /// Wall clock as `HH:MM UTC`.
+    Clock,
+}
+
+impl StatusSegment {
+    /// The segments shown when config `status_segments` is absent.
+    pub const DEFAULT: [StatusSegment; 5] = [
+        StatusSegment::Mode,
+        StatusSegment::DiffMode,
+        StatusSegment::Files,
+        StatusSegment::Session,
+        StatusSegment::Status,
+    ];
+
+    /// Parses a `status_segments` entry. Returns `None` for unknown names.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "mode" => Some(StatusSegment::Mode),
+            "diff_mode" => Some(StatusSegment::DiffMode),
+            "files" => Some(StatusSegment::Files),
+            "reviewed" => Some(StatusSegment::Reviewed),
+            "session" => Some(StatusSegment::Session),
+            "status" => Some(StatusSegment::Status),
+            "clock" => Some(StatusSegment::Clock),
+            _ => None,
+        }
+    }
+}
+
+/// Separator drawn between status bar segments.
+const SEGMENT_SEPARATOR: &str = "  |  ";
+
 /// Renders the 1-row status bar at the bottom of the terminal.
 ///
-/// Shows a mode indicator (`NORMAL` or `INSERT`), the active diff mode label
-/// (`UNSTAGED`, `STAGED`, `BRANCH`, or `RANGE`), a file count (e.g. `12 files`)
-/// when files are loaded, and a `Computing diff...` loading indicator when
-/// `state.diff_loading` is true.
+/// Draws the segments in `state.status_segments` in order (see [`StatusSegment`]),
+/// skipping those with nothing to show, such as `files` before a diff is loaded.
+/// When the bar is too narrow, segments are dropped from the right.
+///
+/// `HelpOverlay`, `DebugOverlay`, `ConfirmQuit`, and `StartupError` all display
+/// `NORMAL` because the underlying mode is `Normal` — the overlay is a transient
+/// visual layer, not a mode change.
+/// While a `:` command or `/` search is being typed, the bar shows it instead.
 ///
-/// `HelpOverlay` and `ConfirmQuit` both display `NORMAL` because the underlying
-/// mode is `Normal` — the overlay is a transient visual layer, not a mode change.
+/// Returns whether the newest notification was drawn; when it was not (no
+/// `status` segment, dropped for width, or replaced by a prompt or the loading
+/// notice), `ui::render` shows it in the notification box instead.
 ///
 /// # Arguments
 ///
 /// * `frame` — current render frame
 /// * `area` — the 1-row `Rect` returned by `compute_layout` (index 3)
-/// * `state` — read-only app state supplying the current mode, diff mode, loading flag,
-///   and file summaries
-/// * `theme` — active color theme (supplies status bar and mode indicator colors)
-pub fn render_status_bar(frame: &mut Frame, area: Rect, state: &AppState, theme: &Theme) {
-    let (mode_text, mode_fg) = match state.mode {
-        Mode::Insert => (" INSERT ", theme.status_mode_insert),
-        Mode::Normal | Mode::ConfirmQuit | Mode::HelpOverlay => {
-            (" NORMAL ", theme.status_mode_normal)
-        }
+/// * `state` — read-only app state supplying the segment list, their contents, and the
+///   `theme` (status bar and mode indicator colors)
+pub fn render_status_bar(frame: &mut Frame, area: Rect, state: &AppState) -> bool {
+    let theme = &state.theme;
+    let prompt = match state.mode {
+        Mode::Command => Some((':', &state.command_line)),
+        Mode::Search => Some(('/', &state.search_query)),
+        _ => None,
     };
+    if let Some((prefix, text)) = prompt {
+        frame.render_widget(
+            Paragraph::new(format!("{prefix}{text}"))
+                .style(Style::default().bg(theme.status_bar_bg).fg(theme.status_bar_fg)),
+            area,
+        );
+        return false;
+    }
+    let shows_notification = state.git_worker_error.is_none() && !state.diff_loading;
+    let mut notification_shown = false;
+    let mut spans: Vec<Span> = Vec::new();
+    let mut width = 0;
+    let segments = state.status_segments.iter().filter_map(|&s| {
+        status_segment(s, state, theme).map(|span| (s, span))
+    });
+    for (kind, segment) in segments {
+        let sep = if spans.is_empty() { 0 } else { SEGMENT_SEPARATOR.len() };
+        let seg_width = sep + segment.width();
+        if width + seg_width > area.width as usize {
+            break;
+        }
+        if sep > 0 {
+            spans.push(Span::raw(SEGMENT_SEPARATOR));
+        }
+        spans.push(segment);
+        width += seg_width;
+        notification_shown |= kind == StatusSegment::Status && shows_notification;
+    }
 
-    let diff_mode_label = match state.diff_mode {
-        DiffMode::Unstaged => "UNSTAGED",
-        DiffMode::Staged => "STAGED",
-        DiffMode::BranchComparison => "BRANCH",
-        DiffMode::CommitRange => "RANGE",
-    };
+    frame.render_widget(
+        Paragraph::new(Line::from(spans))
+            .style(Style::default().bg(theme.status_bar_bg).fg(theme.status_bar_fg)),
+        area,
+    );
+    notification_shown
+}
 
-    let mut spans = vec![
-        Span::styled(mode_text, Style::default().fg(mode_fg).add_modifier(Modifier::BOLD)),
-        Span::raw("  |  "),
-        Span::styled(diff_mode_label, Style::default().fg(Color::DarkGray)),
-    ];
+/// Builds the span for one status segment, or `None` when it has nothing to show.
+fn status_segment(
+    segment: StatusSegment,
+    state: &AppState,
+    theme: &Theme,
+) -> Option<Span<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    match segment {
+        StatusSegment::Mode => {
+            let (mode_text, mode_fg) = match state.mode {
+                Mode::Insert | Mode::CommentTag => (" INSERT ", theme.status_mode_insert),
+                Mode::Normal
+                | Mode::ConfirmQuit
+                | Mode::HelpOverlay
+                | Mode::DebugOverlay
+                | Mode::StartupError
+                | Mode::Command
+                | Mode::Search
+                | Mode::FileFilter
+                | Mode::DiffModePicker => (" NORMAL ", theme.status_mode_normal),
+            };
+            let style = Style::default().fg(mode_fg).add_modifier(Modifier::BOLD);
+            Some(Span::styled(mode_text, style))
+        }
+        StatusSegment::DiffMode => {
+            let label = match (state.diff_mode, &state.diff_range) {
+                (DiffMode::Unstaged, _) => "UNSTAGED".to_owned(),
+                (DiffMode::Staged, _) => "STAGED".to_owned(),
+                (DiffMode::BranchComparison, _) => format!("BRANCH vs {}", state.base_branch),
+                (DiffMode::CommitRange, Some((from, to))) => format!("RANGE {from}..{to}"),
+                (DiffMode::CommitRange, None) => "RANGE".to_owned(),
+                (DiffMode::Stash, _) => "STASH".to_owned(),
+                (DiffMode::Files, _) => "FILES".to_owned(),
+            };
+            let label = if state.ignore_whitespace { format!("{label} [ws]") } else { label };
+            let label = match state.severity_filter {
+                Some(severity) => format!("{label} [{} only]", severity.as_str()),
+                None => label,
+            };
+            if state.diff_only {
+                Some(Span::styled(format!("{label} · diff only (F for panels)"), dim))
+            } else {
+                Some(Span::styled(label, dim))
+            }
+        }
+        StatusSegment::Files if !state.file_summaries.is_empty() => {
+            let (added, removed) = state.diff_totals;
+            let files = state.file_summaries.len();
+            Some(Span::styled(format!("{files} files  +{added} -{removed}"), dim))
+        }
+        StatusSegment::Reviewed if !state.file_summaries.is_empty() => {
+            let reviewed = state.reviewed_count();
+            Some(Span::styled(format!("{reviewed}/{} reviewed", state.file_summaries.len()), dim))
+        }
+        StatusSegment::Session if state.session_loading => {
+            Some(Span::styled("opening session…", Style::default().fg(Color::Yellow)))
+        }
+        StatusSegment::Session => state
+            .session
+            .as_ref()
+            .map(|session| Span::styled(format!("Session: {}...", &session.id[..8]), dim)),
+        StatusSegment::Status => {
+            if state.git_worker_error.is_some() {
+                Some(Span::styled(
+                    "git worker stopped — press R to restart",
+                    Style::default().fg(Color::Red),
+                ))
+            } else if state.diff_loading {
+                Some(Span::styled("Computing diff...", Style::default().fg(Color::Yellow)))
+            } else {
+                state.latest_notification().map(|message| Span::styled(message.to_owned(), dim))
+            }
+        }
+        StatusSegment::Clock => {
+            let secs = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let minutes = secs / 60 % (24 * 60);
+            Some(Span::styled(format!("{:02}:{:02} UTC", minutes / 60, minutes % 60), dim))
+        }
+        StatusSegment::Files | StatusSegment::Reviewed => None,
+    }
+}
 
-    if !state.file_summaries.is_empty() {
-        spans.push(Span::raw("  |  "));
-        spans.push(Span::styled(
-            format!("{} files", state.file_summaries.len()),
-            Style::default().fg(Color::DarkGray),
-        ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::PanelFocus;
+    use crate::keymap::KeyMap;
+
+    #[test]
+    fn cursor_line_shades_only_the_focused_panel_and_keeps_foregrounds() {
+        let mut state = AppState::default();
+        let style = cursor_line_style(&state, true).unwrap();
+        assert_eq!((style.bg, style.fg), (Some(state.theme.cursor_line), None));
+        assert_eq!(cursor_line_style(&state, false), None);
+        state.cursor_line = false;
+        assert_eq!(cursor_line_style(&state, true), None);
     }
 
-    // Show session info when a session is active.
-    if let Some(ref session) = state.session {
-        spans.push(Span::raw("  |  "));
-        let session_label = format!("Session: {}...", &session.id[..8]);
-        spans.push(Span::styled(session_label, Style::default().fg(Color::DarkGray)));
+    #[test]
+    fn drift_banner_names_the_bound_dismiss_key() {
+        let table = toml::from_str::<toml::Table>(r#"dismiss_banner = "ctrl-x""#).unwrap();
+        let mut state = AppState {
+            drift_warning: Some("HEAD moved".to_owned()),
+            keymap: KeyMap::from_table(&table, &mut Vec::new()),
+            ..AppState::default()
+        };
+        let (text, _) = banner(&state).unwrap();
+        assert_eq!(text, "HEAD moved. Ctrl-x to dismiss and adopt the new HEAD");
+
+        state.repo_unavailable = Some("repository unavailable".to_owned());
+        assert_eq!(banner(&state).unwrap(), ("repository unavailable".to_owned(), Color::Red));
     }
 
-    if state.diff_loading {
-        spans.push(Span::raw("  |  "));
-        spans.push(Span::styled("Computing diff...", Style::default().fg(Color::Yellow)));
+    #[test]
+    fn tall_narrow_terminal_stacks_panels() {
+        let mut state = AppState::default();
+        let [left, center, right, status_bar] = compute_layout(Rect::new(0, 0, 40, 90), &state);
+
+        assert_eq!(status_bar, Rect::new(0, 89, 40, 1));
+        for panel in [left, center, right] {
+            assert_eq!((panel.x, panel.width), (0, 40), "stacked panels span the full width");
+        }
+        assert_eq!(left.y, 0);
+        assert_eq!(center.y, left.bottom() - 1, "adjacent borders overlap");
+        assert_eq!(right.y, center.bottom() - 1);
+        assert_eq!(right.bottom(), 89);
+        assert!(center.height > right.height && right.height > left.height);
+
+        state.panel_rects = [left, center, right];
+        state.focus = PanelFocus::FileList;
+        state.focus_next();
+        assert_eq!(state.focus, PanelFocus::Diff);
+        state.focus_next();
+        assert_eq!(state.focus, PanelFocus::Comments);
     }
 
-    frame.render_widget(
-        Paragraph::new(Line::from(spans))
-            .style(Style::default().bg(theme.status_bar_bg).fg(theme.status_bar_fg)),
-        area,
-    );
+    #[test]
+    fn short_vertical_layout_collapses_comments_by_height() {
+        let mut state = AppState {
+            layout_orientation: Some(LayoutOrientation::Vertical),
+            ..AppState::default()
+        };
+        let [left, center, right, _] = compute_layout(Rect::new(0, 0, 120, 30), &state);
+
+        assert_eq!((left.width, left.height), (120, 7));
+        assert_eq!(center.height, 22);
+        assert_eq!(right.height, 0);
+
+        state.panel_rects = [left, center, right];
+        assert_eq!(state.visible_panels(), vec![PanelFocus::FileList, PanelFocus::Diff]);
+    }
+
+    #[test]
+    fn status_segment_names_parse() {
+        assert_eq!(StatusSegment::parse("reviewed"), Some(StatusSegment::Reviewed));
+        assert_eq!(StatusSegment::parse("diff_mode"), Some(StatusSegment::DiffMode));
+        assert_eq!(StatusSegment::parse("battery"), None);
+    }
+
+    #[test]
+    fn diff_only_hides_side_panels_at_any_width() {
+        let mut state = AppState { diff_only: true, max_diff_width: 80, ..AppState::default() };
+        let [left, center, right, _] = compute_layout(Rect::new(0, 0, 200, 50), &state);
+        assert_eq!((left.width, center.width, right.width), (0, 200, 0));
+
+        state.layout_orientation = Some(LayoutOrientation::Vertical);
+        let [left, center, right, _] = compute_layout(Rect::new(0, 0, 200, 50), &state);
+        assert_eq!((left.width, center.width, right.width), (0, 200, 0));
+
+        state.panel_rects = [left, center, right];
+        state.focus = PanelFocus::Diff;
+        state.focus_next();
+        assert_eq!(state.focus, PanelFocus::Diff, "focus is pinned to the diff");
+    }
+
+    #[test]
+    fn forced_horizontal_ignores_terminal_shape() {
+        let state = AppState {
+            layout_orientation: Some(LayoutOrientation::Horizontal),
+            ..AppState::default()
+        };
+        let [left, center, right, _] = compute_layout(Rect::new(0, 0, 40, 90), &state);
+
+        assert_eq!((left.width, center.width, right.width), (0, 40, 0));
+        assert_eq!(center.height, 89);
+    }
 }
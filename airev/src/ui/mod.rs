@@ -4,27 +4,43 @@
 //! point called by the event loop's `terminal.draw()` closure.
 //!
 //! All layout arithmetic lives in `layout.rs`. Diff panel rendering lives in
-//! `diff_view.rs` and file-list rendering lives in `file_tree.rs`. The comments
-//! panel remains a Paragraph placeholder until Phase 5.
+//! `diff_view.rs`, file-list rendering lives in `file_tree.rs`, and the comments
+//! panel lives in `comments.rs`.
 
-mod layout;
+pub mod layout;
+pub mod comment_editor;
+pub mod comments;
+pub mod debug;
 pub mod diff_view;
 pub mod file_tree;
 pub mod help;
 pub mod keybindings;
+pub mod mode_picker;
+pub mod startup_error;
 
 use ratatui::{
     Frame,
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::Style,
     text::Line,
-    widgets::Paragraph,
+    widgets::{Block, Clear, Paragraph, Wrap},
 };
 
-use crate::app::{AppState, Mode, PanelFocus};
+use crate::app::{AppState, Mode};
 use crate::theme::Theme;
-use layout::{compute_layout, inner_rect, panel_block, render_status_bar};
+use layout::{compute_layout, inner_rect, render_banner, render_status_bar, split_banner};
+
+/// Minimum terminal width (columns) for the panel layout to be drawn.
+const MIN_WIDTH: u16 = 40;
+/// Minimum terminal height (rows) for the panel layout to be drawn.
+const MIN_HEIGHT: u16 = 10;
 
 /// Renders one complete frame: 3-panel layout, real diff/file-list content, and status bar.
 ///
+/// Below `MIN_WIDTH` x `MIN_HEIGHT` the layout is skipped entirely and only a
+/// centred "Terminal too small" message is drawn, so no panel ever receives a
+/// zero-size `Rect`. Normal rendering resumes on the next frame after a resize.
+///
 /// Called exactly once per `AppEvent::Render` inside `terminal.draw()`. This is the
 /// only location where `terminal.draw()` is called in the application — never call
 /// it from anywhere else.
@@ -36,13 +52,23 @@ use layout::{compute_layout, inner_rect, panel_block, render_status_bar};
 /// # Arguments
 ///
 /// * `frame` — current render frame provided by `terminal.draw()`
-/// * `state` — mutable reference to app state (viewport heights are cached here)
-/// * `theme` — active color theme
-pub fn render(frame: &mut Frame, state: &mut AppState, theme: &Theme) {
-    let [left, center, right, status_bar] = compute_layout(frame, state);
+/// * `state` — mutable reference to app state (viewport heights are cached here; the
+///   active theme is read from `state.theme`)
+pub fn render(frame: &mut Frame, state: &mut AppState) {
+    let area = frame.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        // Forget stale panel geometry so mouse hit-testing cannot target hidden panels.
+        state.panel_rects = [Rect::default(); 3];
+        render_too_small(frame, &state.theme);
+        return;
+    }
+
+    let (banner, body) = split_banner(area, state);
+    let [left, center, right, status_bar] = compute_layout(body, state);
 
     // Cache panel rects for mouse hit-testing (updated every frame before rendering).
-    state.panel_rects = [left, center, right];
+    // A resize past a breakpoint may have hidden the focused side panel.
+    state.apply_panel_rects([left, center, right]);
 
     // Cache viewport heights BEFORE rendering panels so they are available for the
     // next keypress cycle. Uses inner_rect() to strip the 1-cell border on each side.
@@ -53,57 +79,129 @@ pub fn render(frame: &mut Frame, state: &mut AppState, theme: &Theme) {
     let focus = state.focus;
 
     // Left panel: file list (skip rendering if collapsed)
-    if left.width > 0 {
-        file_tree::render_file_list(frame, left, focus, state, theme);
+    if !left.is_empty() {
+        file_tree::render_file_list(frame, left, focus, state);
     }
 
     // Centre panel: diff view (always visible)
-    diff_view::render_diff(frame, center, focus, state, theme);
+    diff_view::render_diff(frame, center, focus, state);
 
-    // Right panel: comments placeholder (skip rendering if collapsed; Phase 5 replaces)
-    if right.width > 0 {
-        render_comments(frame, right, focus, state, theme);
+    // Right panel: comments (skip rendering if collapsed)
+    if !right.is_empty() {
+        comments::render_comments(frame, right, focus, state);
     }
 
-    // Status bar: always visible, 1 row, shows current mode.
-    render_status_bar(frame, status_bar, state, theme);
+    // Status bar: always visible, 1 row, shows current mode and the newest
+    // notification. The others, and the newest if the bar had no room, stack in
+    // a box just above it.
+    let newest_shown = render_status_bar(frame, status_bar, state);
+    render_notifications(frame, body, status_bar, state, newest_shown);
+    // Banner: top row, while the repository is unavailable or HEAD has drifted.
+    render_banner(frame, banner, state);
 
-    // Help overlay: rendered after all panels so it sits on top.
-    // Clear is called inside render_help_overlay() to erase the background.
-    if state.mode == Mode::HelpOverlay {
-        help::render_help_overlay(frame, theme, state.help_scroll);
+    // Modal overlays (help, debug, comment composer, tag picker, mode picker, startup error):
+    // rendered after all panels so they sit on top. Each calls Clear to erase its background.
+    match state.mode {
+        Mode::HelpOverlay => {
+            help::render_help_overlay(frame, &state.theme, &state.keymap, state.help_scroll)
+        }
+        Mode::DebugOverlay => debug::render_debug_overlay(frame, state),
+        Mode::Insert => comment_editor::render_comment_composer(frame, state),
+        Mode::CommentTag => comment_editor::render_comment_tag_picker(frame, state),
+        Mode::StartupError => startup_error::render_startup_error(frame, state),
+        Mode::DiffModePicker => mode_picker::render_mode_picker(frame, state),
+        Mode::Normal | Mode::ConfirmQuit | Mode::Command | Mode::Search | Mode::FileFilter => {}
     }
 }
 
-/// Renders the comments panel with placeholder content.
-///
-/// Uses `Paragraph::scroll((comments_scroll, 0))` driven by the manual `u16` offset.
-/// Phase 5 replaces this placeholder with real comment thread data from SQLite.
-///
-/// # Arguments
-///
-/// * `frame` — current render frame
-/// * `area` — the `Rect` for the right panel (includes borders)
-/// * `focus` — current panel focus (determines border style)
-/// * `state` — app state supplying `comments_scroll`
-/// * `theme` — active color theme
-fn render_comments(
+/// Draws the live notifications in a bordered box at the bottom-right of `body`,
+/// just above `status_bar`, newest at the bottom. Skips the newest when the
+/// status bar already shows it (`newest_shown`). The box is cut to the body's
+/// width, and to its height by dropping the oldest.
+fn render_notifications(
     frame: &mut Frame,
-    area: ratatui::layout::Rect,
-    focus: PanelFocus,
+    body: Rect,
+    status_bar: Rect,
     state: &AppState,
-    theme: &Theme,
+    newest_shown: bool,
 ) {
-    use ratatui::style::Stylize as _;
-    let is_focused = focus == PanelFocus::Comments;
-    let block = panel_block("Comments", is_focused, theme);
-    let inner = inner_rect(area);
-
-    frame.render_widget(block, area);
-
-    let placeholder = ratatui::text::Text::from(vec![
-        Line::from("  (Phase 5 loads real comments)").fg(theme.diff_context),
-    ]);
-    let paragraph = Paragraph::new(placeholder).scroll((state.comments_scroll, 0));
-    frame.render_widget(paragraph, inner);
+    let pending = state.notifications.len().saturating_sub(usize::from(newest_shown));
+    let max_rows = status_bar.y.saturating_sub(body.y).saturating_sub(2) as usize;
+    let lines: Vec<Line> = state
+        .notifications
+        .iter()
+        .take(pending)
+        .skip(pending.saturating_sub(max_rows))
+        .map(|(message, _)| Line::from(format!(" {message}")))
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+    let widest = lines.iter().map(Line::width).max().unwrap_or(0);
+    let width = (widest as u16).saturating_add(3).min(body.width);
+    let height = lines.len() as u16 + 2;
+    let area = Rect {
+        x: body.right().saturating_sub(width),
+        y: status_bar.y.saturating_sub(height),
+        width,
+        height,
+    };
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::bordered().border_style(Style::default().fg(state.theme.border_active)),
+        ),
+        area,
+    );
+}
+
+/// Renders the "Terminal too small" notice centred in the whole frame.
+///
+/// Uses only the frame area, so it is safe at any size (including 0x0).
+fn render_too_small(frame: &mut Frame, theme: &Theme) {
+    let area = frame.area();
+    let msg = format!("Terminal too small (need ≥{MIN_WIDTH}×{MIN_HEIGHT})");
+    let [row] = area.layout(&Layout::vertical([Constraint::Length(1)]).flex(Flex::Center));
+    frame.render_widget(
+        Paragraph::new(msg)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(theme.border_active)),
+        row,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn screen_rows(terminal: &Terminal<TestBackend>) -> Vec<String> {
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn notifications_stack_above_a_narrow_status_bar() {
+        let mut state = AppState::default();
+        state.push_notification("comment saved".to_owned());
+        state.push_notification("comments exported".to_owned());
+
+        // 50 columns fit the status segment: it shows the newest, the box the rest.
+        let mut terminal = Terminal::new(TestBackend::new(50, 12)).unwrap();
+        terminal.draw(|frame| render(frame, &mut state)).unwrap();
+        let rows = screen_rows(&terminal);
+        assert!(rows[11].contains("comments exported"), "{rows:#?}");
+        assert!(rows[9].contains("│ comment saved │"), "{rows:#?}");
+
+        // At 40 the status segment is dropped, so both stack, newest at the bottom.
+        let mut terminal = Terminal::new(TestBackend::new(40, 12)).unwrap();
+        terminal.draw(|frame| render(frame, &mut state)).unwrap();
+        let rows = screen_rows(&terminal);
+        assert!(!rows[11].contains("comments"), "{rows:#?}");
+        assert!(rows[8].contains("│ comment saved"), "{rows:#?}");
+        assert!(rows[9].contains("│ comments exported │"), "{rows:#?}");
+    }
 }
@@ -0,0 +1,114 @@
+//! Diff-mode picker overlay for airev.
+//!
+//! Shown in `Mode::DiffModePicker` (`m`): the entries of `ModePick::ALL`, with the
+//! mode on screen marked and the highlighted row reversed, plus a ref prompt
+//! for range, commit, and stash that shows why refs were rejected and lists the
+//! branch and tag names `Tab` completes to, or where `Up` / `Down` are in the
+//! recently used ranges. Follows the help overlay pattern: `Clear`, then a
+//! bordered `Paragraph`.
+
+use ratatui::{
+    Frame,
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Clear, Paragraph},
+};
+
+use crate::app::{AppState, ModePick};
+
+/// Most completions listed under the ref prompt; the rest are counted.
+const MAX_COMPLETIONS: usize = 5;
+
+/// Renders the diff-mode picker as a centred modal.
+///
+/// Skipped on terminals narrower than 40 columns, like the other overlays.
+///
+/// # Arguments
+///
+/// * `frame` — current render frame
+/// * `state` — app state supplying `mode_pick`, `mode_pick_refs`,
+///   `mode_pick_error`, `pending_range`, the ref completions and history, and
+///   `theme`
+pub fn render_mode_picker(frame: &mut Frame, state: &AppState) {
+    let theme = &state.theme;
+    if frame.area().width < 40 {
+        return;
+    }
+    let completions = state.ref_completions();
+    // A lone completion that is already typed out needs no list.
+    let typed = state.ref_tab.is_none()
+        && completions.len() == 1
+        && state.mode_pick_refs.as_deref().is_some_and(|refs| refs.ends_with(completions[0]));
+    let completions = if typed { &[][..] } else { &completions[..] };
+    let listed = completions.len().min(MAX_COMPLETIONS);
+    let more = completions.len() > MAX_COMPLETIONS;
+    let height = ModePick::ALL.len() as u16 + 6 + listed as u16 + u16::from(more);
+    let area = frame.area().centered(Constraint::Length(48), Constraint::Length(height));
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .title(" Diff mode — j/k move, Enter pick, Esc close ")
+        .border_style(Style::default().fg(theme.border_active));
+
+    let dim = Style::default().fg(Color::DarkGray);
+    let current = state.current_mode_pick();
+    let mut lines: Vec<Line> = ModePick::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, &pick)| {
+            let (name, description) = pick.label();
+            let marker = if pick == current { "●" } else { " " };
+            let mut style = Style::default();
+            if i == state.mode_pick {
+                style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+            }
+            Line::from(vec![
+                Span::styled(format!(" {marker} {name:<9}"), style),
+                Span::styled(format!(" {description}"), dim),
+            ])
+        })
+        .collect();
+    lines.push(Line::from(""));
+    match &state.mode_pick_refs {
+        Some(refs) => {
+            lines.push(Line::from(vec![
+                Span::raw(" refs: "),
+                Span::styled(format!("{refs}▏"), Style::default().fg(theme.border_active)),
+            ]));
+            lines.push(match (&state.mode_pick_error, &state.pending_range) {
+                (Some(error), _) => {
+                    Line::styled(format!(" {error}"), Style::default().fg(theme.badge_critical))
+                }
+                (None, Some(_)) => Line::styled(" resolving…", dim),
+                (None, None) => match (&state.ref_history_pos, state.ref_history().len()) {
+                    (Some((i, _)), recent) => {
+                        Line::styled(format!(" recent {}/{recent}, Up/Down for more", i + 1), dim)
+                    }
+                    (None, 0) => Line::styled(" Enter load, Tab complete, Esc back", dim),
+                    (None, _) => Line::styled(" Enter load, Tab complete, Up recent", dim),
+                },
+            });
+            let selected = state.ref_tab.as_ref().map(|(_, i)| *i);
+            // Keep the name Tab filled in within the listed rows.
+            let first = selected.map_or(0, |i| (i + 1).saturating_sub(listed));
+            for (i, name) in completions.iter().enumerate().skip(first).take(listed) {
+                let style = if selected == Some(i) {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default().fg(theme.border_active)
+                };
+                lines.push(Line::styled(format!("   {name}"), style));
+            }
+            if more {
+                lines.push(Line::styled(
+                    format!("   … {} more", completions.len() - listed),
+                    dim,
+                ));
+            }
+        }
+        None => lines.push(Line::styled(" ● current mode", dim)),
+    }
+
+    frame.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
+}
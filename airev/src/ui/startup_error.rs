@@ -0,0 +1,56 @@
+//! Startup error overlay for airev.
+//!
+//! Shown in `Mode::StartupError` when a startup step in `main.rs` fails in a way
+//! airev can continue past — no git repository, or a review database that cannot
+//! be opened — so the user gets the reason immediately instead of a blank panel
+//! or silently lost review state. Follows the help overlay pattern: `Clear`, then
+//! a bordered `Paragraph`.
+
+use ratatui::{
+    Frame,
+    layout::Constraint,
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, Wrap},
+};
+
+use crate::app::AppState;
+
+/// Renders the first pending startup error as a centred modal with the quit /
+/// continue keys.
+///
+/// Skipped on terminals narrower than 40 columns, like the other overlays.
+///
+/// # Arguments
+///
+/// * `frame` — current render frame
+/// * `state` — app state supplying `startup_errors` and `theme`
+pub fn render_startup_error(frame: &mut Frame, state: &AppState) {
+    let theme = &state.theme;
+    let Some(error) = state.startup_errors.first() else {
+        return;
+    };
+    if frame.area().width < 40 {
+        return;
+    }
+    let area = frame
+        .area()
+        .centered(Constraint::Percentage(70), Constraint::Length(10));
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .title(format!(" {} ", error.title))
+        .border_style(Style::default().fg(theme.border_active));
+
+    let mut lines: Vec<Line> = error.message.lines().map(|l| Line::from(l.to_owned())).collect();
+    lines.push(Line::from(""));
+    lines.push(Line::styled(
+        format!("q / Esc  quit    Enter / c  {}", error.continue_label),
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(block).wrap(Wrap { trim: false }),
+        area,
+    );
+}
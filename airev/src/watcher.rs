@@ -0,0 +1,124 @@
+//! Filesystem watcher for airev.
+//!
+//! Watches the repository work tree with `notify` and turns each burst of
+//! changes into a single `AppEvent::FileChanged` once it has been quiet for
+//! [`DEBOUNCE`], so saving a file, or a checkout touching hundreds, refreshes
+//! the diff once. Changes under `.git/` and `.airev/` are ignored: git's own
+//! bookkeeping and airev's database writes would otherwise keep triggering
+//! refreshes. So are paths the repository's ignore rules exclude, such as a
+//! `target/` directory rewritten by every build, since they never show in a diff.
+
+use std::path::Path;
+use std::time::Duration;
+
+use git2::Repository;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::event::AppEvent;
+
+/// How long the work tree must stay unchanged before the diff is refreshed.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Directories whose changes never refresh the diff, wherever they appear
+/// below the watched root.
+const IGNORED_DIRS: [&str; 2] = [".git", ".airev"];
+
+/// Starts watching `root` recursively and spawns the task that debounces its
+/// changes into `AppEvent::FileChanged` on `tx`.
+///
+/// Watching stops when the returned watcher is dropped, so the caller keeps it
+/// alive for as long as refreshes are wanted. Ignore rules are read from the
+/// repository at `root`; if it cannot be opened only [`IGNORED_DIRS`] are skipped.
+///
+/// # Errors
+///
+/// Returns the `notify::Error` when the platform watcher cannot be created or
+/// `root` cannot be watched (e.g. the inotify watch limit is reached).
+pub fn spawn_watcher(
+    root: &Path,
+    tx: UnboundedSender<AppEvent>,
+) -> notify::Result<RecommendedWatcher> {
+    let (changed_tx, mut changed_rx) = mpsc::unbounded_channel();
+    let watched = root.to_path_buf();
+    let repo = Repository::open(root).ok();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        if result.is_ok_and(|event| is_relevant(&watched, repo.as_ref(), &event)) {
+            let _ = changed_tx.send(());
+        }
+    })?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+
+    tokio::spawn(async move {
+        while changed_rx.recv().await.is_some() {
+            // Swallow the rest of the burst until it has been quiet for DEBOUNCE.
+            loop {
+                match tokio::time::timeout(DEBOUNCE, changed_rx.recv()).await {
+                    Ok(Some(())) => {}
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+            if tx.send(AppEvent::FileChanged).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(watcher)
+}
+
+/// Whether `event` can change the diff: anything but a read, touching at least
+/// one path that is not ignored (see [`is_ignored`]).
+fn is_relevant(root: &Path, repo: Option<&Repository>, event: &Event) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event.paths.iter().any(|path| !is_ignored(root, repo, path))
+}
+
+/// Whether `path` lies in one of [`IGNORED_DIRS`] below `root`, or is excluded
+/// by `repo`'s ignore rules (`.gitignore`, `.git/info/exclude`, the global one).
+fn is_ignored(root: &Path, repo: Option<&Repository>, path: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .any(|part| IGNORED_DIRS.iter().any(|dir| part.as_os_str() == *dir))
+        || repo.is_some_and(|repo| repo.is_path_ignored(relative).unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_and_airev_directories_are_ignored() {
+        let root = Path::new("/work/repo");
+        assert!(is_ignored(root, None, Path::new("/work/repo/.git/index")));
+        assert!(is_ignored(root, None, Path::new("/work/repo/sub/.airev/reviews.db-wal")));
+        assert!(!is_ignored(root, None, Path::new("/work/repo/src/main.rs")));
+        assert!(!is_ignored(root, None, Path::new("/work/repo/.gitignore")));
+
+        let event = |kind, paths: &[&str]| Event {
+            kind,
+            paths: paths.iter().map(|p| root.join(p)).collect(),
+            attrs: Default::default(),
+        };
+        let modify = EventKind::Modify(notify::event::ModifyKind::Any);
+        assert!(is_relevant(root, None, &event(modify, &[".git/index", "src/lib.rs"])));
+        assert!(!is_relevant(root, None, &event(modify, &[".git/index"])));
+        let read = EventKind::Access(notify::event::AccessKind::Any);
+        assert!(!is_relevant(root, None, &event(read, &["src/lib.rs"])));
+    }
+
+    #[test]
+    fn gitignored_paths_are_ignored() {
+        let (tmp, repo) = crate::git::init_test_repo();
+        let root = tmp.path();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+
+        let ignored = |path: &str| is_ignored(root, Some(&repo), &root.join(path));
+        assert!(ignored("target/debug/airev"));
+        assert!(ignored("build.log"));
+        assert!(!ignored("src/main.rs"));
+        assert!(!ignored(".gitignore"));
+    }
+}